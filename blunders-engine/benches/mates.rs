@@ -3,8 +3,10 @@ use std::sync::{atomic::AtomicBool, Arc};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 use blunders_engine::coretypes::{Color::*, Move, Square::*};
+use blunders_engine::eval::EvalCache;
 use blunders_engine::fen::Fen;
-use blunders_engine::search::{self, History};
+use blunders_engine::moveorder::HistoryTable;
+use blunders_engine::search::{self, History, PruningParams, QuiescenceParams, SearchContext};
 use blunders_engine::timeman::Mode;
 use blunders_engine::*;
 
@@ -49,7 +51,14 @@ pub fn criterion_mates_3_sac_knight(c: &mut Criterion) {
                 black_box(mode),
                 black_box(history.clone()),
                 black_box(&tt),
-                black_box(stopper),
+                &mut SearchContext {
+                    eval_cache: &mut EvalCache::with_mb_and_zobrist(1, tt.zobrist_table().clone()),
+                    history_table: &mut HistoryTable::new(),
+                    q_params: QuiescenceParams::DEFAULT,
+                    pruning: PruningParams::DEFAULT,
+                    stopper: black_box(stopper),
+                    progress: &(),
+                },
             )
             .unwrap();
 
@@ -67,8 +76,14 @@ pub fn criterion_mates_3_sac_knight(c: &mut Criterion) {
                 black_box(mode),
                 black_box(history.clone()),
                 black_box(&tt),
-                black_box(stopper),
-                false,
+                &mut SearchContext {
+                    eval_cache: &mut EvalCache::with_mb_and_zobrist(1, tt.zobrist_table().clone()),
+                    history_table: &mut HistoryTable::new(),
+                    q_params: QuiescenceParams::DEFAULT,
+                    pruning: PruningParams::DEFAULT,
+                    stopper: black_box(stopper),
+                    progress: &(),
+                },
             );
 
             assert_eq!(result.leading(), lead);
@@ -117,7 +132,14 @@ pub fn criterion_mates_3_knights_and_bishop(c: &mut Criterion) {
                 black_box(mode),
                 black_box(history.clone()),
                 black_box(&tt),
-                black_box(stopper),
+                &mut SearchContext {
+                    eval_cache: &mut EvalCache::with_mb_and_zobrist(1, tt.zobrist_table().clone()),
+                    history_table: &mut HistoryTable::new(),
+                    q_params: QuiescenceParams::DEFAULT,
+                    pruning: PruningParams::DEFAULT,
+                    stopper: black_box(stopper),
+                    progress: &(),
+                },
             )
             .unwrap();
 
@@ -135,8 +157,14 @@ pub fn criterion_mates_3_knights_and_bishop(c: &mut Criterion) {
                 black_box(mode),
                 black_box(history.clone()),
                 black_box(&tt),
-                black_box(stopper),
-                false,
+                &mut SearchContext {
+                    eval_cache: &mut EvalCache::with_mb_and_zobrist(1, tt.zobrist_table().clone()),
+                    history_table: &mut HistoryTable::new(),
+                    q_params: QuiescenceParams::DEFAULT,
+                    pruning: PruningParams::DEFAULT,
+                    stopper: black_box(stopper),
+                    progress: &(),
+                },
             );
 
             assert_eq!(result.leading(), lead);
@@ -0,0 +1,45 @@
+//! Benchmarks for Transposition Table probes, with and without prefetching.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::prelude::*;
+
+use blunders_engine::coretypes::{Cp, Move, Square::*};
+use blunders_engine::transposition::{Entry, NodeKind, TranspositionTable};
+use blunders_engine::zobrist::HashKind;
+
+pub fn transposition_probe(c: &mut Criterion) {
+    // Setup
+    // Table is much larger than cache, so probes of random hashes are likely cache misses.
+    let tt = TranspositionTable::with_mb(256);
+    let mut rng = StdRng::seed_from_u64(0);
+    let best_move = Move::new(E2, E4, None);
+    let hashes: Vec<HashKind> = (0..4096).map(|_| rng.gen()).collect();
+    for hash in &hashes {
+        tt.replace(Entry::new(*hash, best_move, Cp(0), 1, NodeKind::Pv), 0);
+    }
+
+    // Benchmarks
+    c.bench_function("tt probe random hashes", |b| {
+        b.iter(|| {
+            for hash in &hashes {
+                black_box(tt.get(black_box(*hash)));
+            }
+        });
+    });
+
+    // Each probe is prefetched a few probes ahead of its use, as a search would between
+    // making a move and recursing into the child position.
+    c.bench_function("tt prefetch then probe random hashes", |b| {
+        b.iter(|| {
+            for (idx, hash) in hashes.iter().enumerate() {
+                if let Some(next_hash) = hashes.get(idx + 4) {
+                    tt.prefetch(black_box(*next_hash));
+                }
+                black_box(tt.get(black_box(*hash)));
+            }
+        });
+    });
+}
+
+criterion_group!(benches, transposition_probe);
+criterion_main!(benches);
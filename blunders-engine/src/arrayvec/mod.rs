@@ -31,3 +31,35 @@ pub fn append<T, const CAP: usize>(vec: &mut ArrayVec<T, CAP>, other: ArrayVec<T
         vec.push(item);
     }
 }
+
+/// Appends items of other to the ArrayVec until it is full, truncating the rest.
+/// Returns true if every item was appended.
+pub fn try_append<T, const CAP: usize>(
+    vec: &mut ArrayVec<T, CAP>,
+    other: ArrayVec<T, CAP>,
+) -> bool {
+    for item in other {
+        if vec.try_push(item).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_append_truncates_past_capacity() {
+        let mut vec: ArrayVec<u8, 4> = [1, 2, 3].iter().copied().collect();
+        let other: ArrayVec<u8, 4> = [4].iter().copied().collect();
+        assert!(try_append(&mut vec, other));
+        assert_eq!(vec[..], [1, 2, 3, 4]);
+
+        let mut vec: ArrayVec<u8, 4> = [1, 2, 3].iter().copied().collect();
+        let other: ArrayVec<u8, 4> = [4, 5, 6].iter().copied().collect();
+        assert!(!try_append(&mut vec, other));
+        assert_eq!(vec[..], [1, 2, 3, 4]);
+    }
+}
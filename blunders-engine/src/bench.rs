@@ -0,0 +1,132 @@
+//! Benchmark
+//!
+//! Searches each position of a built in suite to a fixed depth, reporting the total nodes
+//! searched and the nodes per second.
+//!
+//! Each search begins with a cleared transposition table that uses a seeded zobrist table,
+//! so the total node count is reproducible. A change that does not alter the total node count
+//! is unlikely to have changed search behavior, and the nodes per second measure its speed.
+
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+use crate::coretypes::PlyKind;
+use crate::fen::Fen;
+use crate::position::Position;
+use crate::search;
+use crate::transposition::TranspositionTable;
+use crate::zobrist::ZobristTable;
+
+/// Default depth each position of the benchmark is searched to.
+pub const BENCH_DEPTH: PlyKind = 5;
+
+/// Size of the transposition table used by the benchmark in Megabytes.
+const BENCH_TT_MB: usize = 16;
+
+/// Seed of the zobrist table used by the benchmark.
+const BENCH_SEED: u64 = 0;
+
+/// Positions searched by the benchmark, covering openings, middlegames, and endgames.
+pub const BENCH_FENS: [&str; 20] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r3k2r/2pb1ppp/2pp1q2/p7/1nP1B3/1P2P3/P2N1PPP/R2QK2R w KQkq a6 0 14",
+    "4rrk1/2p1b1p1/p1p3q1/4p3/2P2n1p/1P1NR2P/PB3PP1/3R1QK1 b - - 2 24",
+    "r3qbrk/6p1/2b2pPp/p3pP1Q/PpPpP2P/3P1B2/2PB3K/R5R1 w - - 16 42",
+    "6k1/1R3p2/6p1/2Bp3p/3P2q1/P7/1P2rQ1K/5R2 b - - 4 44",
+    "8/8/1p2k1p1/3p3p/1p1P1P1P/1P2PK2/8/8 w - - 3 54",
+    "7r/2p3k1/1p1p1qp1/1P1Bp3/p1P2r1P/P7/4R3/Q4RK1 w - - 0 36",
+    "r1bq1rk1/pp2b1pp/n1pp1n2/3P1p2/2P1p3/2N1P2N/PP2BPPP/R1BQ1RK1 b - - 2 10",
+    "3r3k/2r4p/1p1b3q/p4P2/P2Pp3/1B2P3/3BQ1RP/6K1 w - - 3 87",
+    "2r4r/1p4k1/1Pnp4/3Qb1pq/8/4BpPp/5P2/2RR1BK1 w - - 0 42",
+    "4q1bk/6b1/7p/p1p4p/PNPpP2P/KN4P1/3Q4/4R3 b - - 0 37",
+    "2q3r1/1r2pk2/pp3pp1/2pP3p/P1Pb1BbP/1P4Q1/R3NPP1/4R1K1 w - - 2 34",
+    "1r2r2k/1b4q1/pp5p/2pPp1p1/P3Pn2/1P1B1Q1P/2R3P1/4BR1K b - - 1 37",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 80",
+    "8/3k4/8/8/8/4B3/4KB2/2B5 w - - 0 1",
+];
+
+/// Totals of a benchmark run.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BenchResult {
+    pub positions: usize,
+    pub depth: PlyKind,
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Get average nodes per second of all searches.
+    pub fn nps(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        (self.nodes as f64 / secs).round()
+    }
+}
+
+impl Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "positions {} depth {} nodes {} time {} nps {}",
+            self.positions,
+            self.depth,
+            self.nodes,
+            self.elapsed.as_millis(),
+            self.nps()
+        )
+    }
+}
+
+/// Search every benchmark position to `depth`, which must be at least 1.
+pub fn bench(depth: PlyKind) -> BenchResult {
+    let mut tt =
+        TranspositionTable::with_mb_and_zobrist(BENCH_TT_MB, ZobristTable::with_seed(BENCH_SEED));
+    let mut nodes = 0;
+    let mut elapsed = Duration::ZERO;
+
+    for fen in BENCH_FENS {
+        let position = Position::parse_fen(fen).unwrap();
+        tt.clear();
+
+        let instant = Instant::now();
+        let search_result = search::search(position, depth, &tt);
+        elapsed += instant.elapsed();
+        nodes += search_result.nodes;
+    }
+
+    BenchResult {
+        positions: BENCH_FENS.len(),
+        depth,
+        nodes,
+        elapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench_positions_are_valid() {
+        for fen in BENCH_FENS {
+            let position = Position::parse_fen(fen).unwrap();
+            position.validate().unwrap();
+            assert!(!position.get_legal_moves().is_empty(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn bench_node_count_is_deterministic() {
+        // Update when a change is expected to alter search behavior.
+        const BENCH_DEPTH_3_NODES: u64 = 100_924;
+
+        let result = bench(3);
+        assert_eq!(result.positions, BENCH_FENS.len());
+        assert_eq!(result.nodes, bench(3).nodes);
+        assert_eq!(result.nodes, BENCH_DEPTH_3_NODES);
+    }
+}
@@ -92,6 +92,25 @@ impl Bitboard {
 }
 
 impl Bitboard {
+    /// Create a Bitboard with only the given square set.
+    pub const fn single(square: Square) -> Self {
+        Self(1u64 << square as u8)
+    }
+
+    /// Create a Bitboard with every given square set.
+    /// # Examples
+    /// ```rust
+    /// # use blunders_engine::bitboard::Bitboard;
+    /// # use blunders_engine::coretypes::Square::*;
+    /// let board = Bitboard::from_squares([E1, A1, H8]);
+    /// assert_eq!(board.squares(), vec![A1, E1, H8]);
+    /// ```
+    pub fn from_squares<I: IntoIterator<Item = Square>>(squares: I) -> Self {
+        let mut bb = Bitboard::EMPTY;
+        squares.into_iter().for_each(|square| bb.set_square(square));
+        bb
+    }
+
     /// Get copy of internal u64 representation of this Bitboard.
     #[inline(always)]
     pub const fn bits(&self) -> u64 {
@@ -165,6 +184,21 @@ impl Bitboard {
         Square::try_from(self.0.trailing_zeros() as u8).ok()
     }
 
+    /// Removes and returns the lowest square of bitboard, or None if bitboard has no squares.
+    #[inline(always)]
+    pub fn pop_lowest_square(&mut self) -> Option<Square> {
+        let maybe_square = self.get_lowest_square();
+        self.clear_lowest_square();
+        maybe_square
+    }
+
+    /// Returns an iterator over the squares of bitboard from lowest to highest,
+    /// without consuming or modifying bitboard.
+    #[inline(always)]
+    pub fn iter_squares(&self) -> BitboardSquareIterator {
+        BitboardSquareIterator { bb: *self }
+    }
+
     /// Remove all squares in other from self.
     #[inline(always)]
     pub fn remove(&mut self, other: &Bitboard) {
@@ -233,6 +267,44 @@ impl Bitboard {
         Self((self.0 >> 9) & Self::NOT_FILE_H.0)
     }
 
+    /// Returns new Bitboard with all squares flipped across the middle ranks (ex: D2 -> D7).
+    #[inline(always)]
+    pub const fn flip_vertical(&self) -> Self {
+        Self(self.0.swap_bytes())
+    }
+    /// Returns new Bitboard with all squares flipped across the middle files (ex: B4 -> G4).
+    #[inline(always)]
+    pub const fn flip_horizontal(&self) -> Self {
+        const K1: u64 = 0x5555555555555555;
+        const K2: u64 = 0x3333333333333333;
+        const K4: u64 = 0x0F0F0F0F0F0F0F0F;
+        let mut bits = self.0;
+        bits = ((bits >> 1) & K1) | ((bits & K1) << 1);
+        bits = ((bits >> 2) & K2) | ((bits & K2) << 2);
+        bits = ((bits >> 4) & K4) | ((bits & K4) << 4);
+        Self(bits)
+    }
+    /// Returns new Bitboard with all squares mirrored across the A1-H8 diagonal (ex: B1 -> A2).
+    #[inline(always)]
+    pub const fn mirror_diagonal(&self) -> Self {
+        const K1: u64 = 0x5500550055005500;
+        const K2: u64 = 0x3333000033330000;
+        const K4: u64 = 0x0F0F0F0F00000000;
+        let mut bits = self.0;
+        let mut t = K4 & (bits ^ (bits << 28));
+        bits ^= t ^ (t >> 28);
+        t = K2 & (bits ^ (bits << 14));
+        bits ^= t ^ (t >> 14);
+        t = K1 & (bits ^ (bits << 7));
+        bits ^= t ^ (t >> 7);
+        Self(bits)
+    }
+    /// Returns new Bitboard with all squares rotated 180 degrees around the center (ex: A1 -> H8).
+    #[inline(always)]
+    pub const fn rotate_180(&self) -> Self {
+        Self(self.0.reverse_bits())
+    }
+
     /// Returns a vector of all the Squares represented in the Bitboard.
     /// # Examples
     /// ```rust
@@ -254,13 +326,10 @@ impl Bitboard {
         let num_ones = self.count_squares() as usize;
         let mut vec = Vec::with_capacity(num_ones);
 
-        for _ in 0..num_ones {
-            let square_value = bits.0.trailing_zeros() as u8;
-            bits.clear_lowest_square();
-            let square = Square::try_from(square_value);
-            debug_assert!(square_value < 64u8 && square.is_ok());
-            vec.push(square.unwrap());
+        while let Some(square) = bits.pop_lowest_square() {
+            vec.push(square);
         }
+        debug_assert_eq!(vec.len(), num_ones);
         vec
     }
 }
@@ -395,9 +464,7 @@ pub struct BitboardSquareIterator {
 impl Iterator for BitboardSquareIterator {
     type Item = Square;
     fn next(&mut self) -> Option<Self::Item> {
-        let maybe_square = self.bb.get_lowest_square();
-        self.bb.clear_lowest_square();
-        maybe_square
+        self.bb.pop_lowest_square()
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         let size = self.bb.count_squares() as usize;
@@ -451,6 +518,23 @@ mod tests {
         assert!(bb.has_square(A3));
     }
 
+    #[test]
+    fn from_squares_and_back() {
+        let squares = [H8, A1, D4, A1, C7];
+        let bb = Bitboard::from_squares(squares.iter().copied());
+        assert_eq!(bb.count_squares(), 4);
+        assert_eq!(bb.squares(), vec![A1, D4, C7, H8]);
+        assert_eq!(bb, Bitboard::from(&squares[..]));
+        assert_eq!(Bitboard::from_squares(Vec::new()), Bitboard::EMPTY);
+
+        for square in Square::iter() {
+            let single = Bitboard::single(square);
+            assert_eq!(single, Bitboard::from(square));
+            assert_eq!(single.squares(), vec![square]);
+            assert_eq!(Bitboard::from_squares(single.squares()), single);
+        }
+    }
+
     #[test]
     fn to_north_west_south_east() {
         let a1 = Bitboard::from(Square::A1);
@@ -520,4 +604,40 @@ mod tests {
         let empty_vec: Vec<Square> = empty.into_iter().collect();
         assert_eq!(empty_vec.len(), 0);
     }
+
+    #[test]
+    fn iter_and_pop_squares() {
+        let mut bb = Bitboard::from([H8, A1, D4, B1].as_slice());
+        let squares: Vec<Square> = bb.iter_squares().collect();
+        assert_eq!(squares, vec![A1, B1, D4, H8]);
+        assert_eq!(bb.iter_squares().len(), 4);
+        assert_eq!(bb.count_squares(), 4);
+
+        assert_eq!(bb.pop_lowest_square(), Some(A1));
+        assert_eq!(bb.pop_lowest_square(), Some(B1));
+        assert_eq!(bb.pop_lowest_square(), Some(D4));
+        assert_eq!(bb.pop_lowest_square(), Some(H8));
+        assert_eq!(bb.pop_lowest_square(), None);
+        assert_eq!(bb, Bitboard::EMPTY);
+        assert_eq!(bb.iter_squares().next(), None);
+    }
+
+    #[test]
+    fn flip_mirror_rotate() {
+        let bb = Bitboard::from([A1, B2, C2, E5, H7, G8].as_slice());
+        assert_eq!(bb.flip_vertical().flip_vertical(), bb);
+        assert_eq!(bb.flip_horizontal().flip_horizontal(), bb);
+        assert_eq!(bb.mirror_diagonal().mirror_diagonal(), bb);
+        assert_eq!(bb.rotate_180().rotate_180(), bb);
+        assert_eq!(bb.rotate_180(), bb.flip_vertical().flip_horizontal());
+
+        let d2 = Bitboard::from(D2);
+        assert_eq!(d2.flip_vertical(), Bitboard::from(D7));
+        assert_eq!(d2.flip_horizontal(), Bitboard::from(E2));
+        assert_eq!(d2.mirror_diagonal(), Bitboard::from(B4));
+        assert_eq!(d2.rotate_180(), Bitboard::from(E7));
+        assert_eq!(Bitboard::RANK_1.flip_vertical(), Bitboard::RANK_8);
+        assert_eq!(Bitboard::FILE_A.flip_horizontal(), Bitboard::FILE_H);
+        assert_eq!(Bitboard::RANK_1.mirror_diagonal(), Bitboard::FILE_A);
+    }
 }
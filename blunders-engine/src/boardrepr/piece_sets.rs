@@ -5,7 +5,7 @@ use std::ops::{Index, IndexMut, Range};
 
 use crate::bitboard::Bitboard;
 use crate::boardrepr::Mailbox;
-use crate::coretypes::{Color, Piece, PieceKind, Square};
+use crate::coretypes::{Color, Cp, Piece, PieceKind, Square};
 use crate::coretypes::{Color::*, PieceKind::*};
 
 // These offset impls are used to index their corresponding place in PieceSets.
@@ -85,6 +85,24 @@ impl PieceSets {
         self[color].iter().fold(Bitboard::EMPTY, |acc, bb| acc | bb)
     }
 
+    /// Returns the number of pieces of a color and kind.
+    pub fn count(&self, color: Color, piece_kind: PieceKind) -> u32 {
+        self[(color, piece_kind)].count_squares()
+    }
+
+    /// Returns the summed centipawn value of a color's knights, bishops, rooks, and queens.
+    pub fn non_pawn_material(&self, color: Color) -> Cp {
+        [Knight, Bishop, Rook, Queen]
+            .iter()
+            .map(|&pk| pk.centipawns() * self.count(color, pk))
+            .fold(Cp::default(), |acc, value| acc + value)
+    }
+
+    /// Returns the summed centipawn value of all of a color's pieces, excluding the king.
+    pub fn total_material(&self, color: Color) -> Cp {
+        self.non_pawn_material(color) + Pawn.centipawns() * self.count(color, Pawn)
+    }
+
     /// Finds and returns the first piece found on target square, or None.
     pub fn on_square(&self, sq: Square) -> Option<Piece> {
         for player in Color::iter() {
@@ -107,6 +125,11 @@ impl PieceSets {
         None
     }
 
+    /// Returns true if every square of self holds the same piece as the same square of mailbox.
+    pub fn matches(&self, mailbox: &Mailbox) -> bool {
+        Square::iter().all(|sq| self.on_square(sq) == mailbox[sq])
+    }
+
     /// Returns pretty-printed chess board representation of Self.
     /// Uses Mailbox pretty.
     pub fn pretty(&self) -> String {
@@ -278,6 +301,8 @@ impl Display for PieceSets {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fen::Fen;
+    use crate::position::Position;
     use Square::*;
     #[test]
     fn piece_indexing() {
@@ -315,6 +340,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn count_and_material() {
+        let pieces = PieceSets::start_position();
+        for color in Color::iter() {
+            assert_eq!(pieces.count(color, Pawn), 8);
+            assert_eq!(pieces.count(color, Knight), 2);
+            assert_eq!(pieces.count(color, Queen), 1);
+            assert_eq!(
+                pieces.non_pawn_material(color),
+                Cp(2 * 305 + 2 * 310 + 2 * 510 + 950)
+            );
+            assert_eq!(pieces.total_material(color), Cp(3200 + 8 * 100));
+        }
+
+        let position = Position::parse_fen("4k3/2r5/8/2p5/8/8/PP6/3QK1N1 w - - 0 1").unwrap();
+        let pieces = position.pieces();
+        assert_eq!(pieces.count(White, Pawn), 2);
+        assert_eq!(pieces.count(Black, Bishop), 0);
+        assert_eq!(pieces.non_pawn_material(White), Cp(950 + 305));
+        assert_eq!(pieces.non_pawn_material(Black), Cp(510));
+        assert_eq!(pieces.total_material(White), Cp(1255 + 200));
+        assert_eq!(pieces.total_material(Black), Cp(610));
+    }
+
+    #[test]
+    fn mailbox_round_trip() {
+        let pieces = PieceSets::start_position();
+        let mailbox = Mailbox::from(&pieces);
+        assert_eq!(mailbox, Mailbox::start_position());
+        assert!(pieces.matches(&mailbox));
+        assert_eq!(PieceSets::from(&mailbox), pieces);
+
+        // Walk a line of moves, checking both representations agree after each.
+        let mut position = Position::parse_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        for idx in 0..20 {
+            let moves = position.get_legal_moves();
+            position.do_move(moves[idx * 7 % moves.len()]);
+
+            let pieces = *position.pieces();
+            let mailbox = Mailbox::from(&pieces);
+            assert!(pieces.matches(&mailbox));
+            assert_eq!(PieceSets::from(&mailbox), pieces);
+        }
+
+        let mut mailbox = Mailbox::start_position();
+        mailbox[E4] = mailbox[E2].take();
+        assert!(!PieceSets::start_position().matches(&mailbox));
+    }
+
     #[test]
     fn check_is_valid() {
         let mut set = PieceSets::start_position();
@@ -8,6 +8,9 @@ use std::ops::{Add, AddAssign, Mul, Neg, RangeInclusive, Sub};
 use std::ops::{BitOr, Not};
 use std::str::FromStr;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::error::{self, ErrorKind};
 
 ///////////////
@@ -24,9 +27,10 @@ pub const NUM_PIECE_KINDS: usize = 12;
 pub const MAX_MOVES: usize = 218;
 
 // The greatest depth reachable for the engine during search.
+// Nodes this many plies from the root are evaluated without searching deeper.
 // This also expresses the maximum number of moves than can be in a line.
 // This value may change or be removed at any time.
-pub const MAX_DEPTH: PlyKind = 40;
+pub const MAX_DEPTH: PlyKind = 64;
 
 // The greatest number of plies supported for the engine, 600 ply, or 300 moves.
 // Supports exceptionally long games of 300 moves. If a game goes longer than
@@ -48,11 +52,13 @@ pub type CpKind = i16;
 
 /// Centipawn, a common unit of measurement in chess, where 100 Centipawn == 1 Pawn.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[repr(transparent)]
 pub struct Cp(pub CpKind);
 
 /// Color can represent the color of a piece, or a player.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Color {
     White,
     Black,
@@ -61,6 +67,7 @@ pub enum Color {
 /// Enum variant order and discriminant are important.
 /// Must be contiguous and start from 0.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PieceKind {
     King,
     Pawn,
@@ -78,6 +85,7 @@ pub struct Piece {
 
 /// Observe Castling rights for a position.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Castling(u8);
 
 /// Castling Enum constants.
@@ -95,6 +103,12 @@ impl Castling {
     pub const ENUMERATIONS: usize = 16; // 16 possibilities for castling rights.
 }
 
+/// Starting files of the castling rooks for each castling right.
+/// In standard chess, kingside rooks start on the H file and queenside rooks on the A file.
+/// In Chess960 the rooks may start on any file, as long as the king is between them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CastlingFiles([File; 4]);
+
 /// Enum variant order and discriminant must be contiguous, start from 0, 
 /// and be in ascending order ABCDEFGH.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -113,6 +127,20 @@ pub enum Rank {
     R1, R2, R3, R4, R5, R6, R7, R8 = 7u8,
 }
 
+/// One of the 8 directions a ray may travel from a square on the board.
+/// North is towards Rank 8, and East is towards File H.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
 /// Square
 /// Every possible square on a chess board.
 /// The order of enums is important, as `Square::A1 as u8` corresponds to
@@ -120,6 +148,7 @@ pub enum Rank {
 /// WARNING: The exact ordering of enums is important for their discriminants.
 ///          Changing the discriminant of any variant is breaking.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[rustfmt::skip]
 #[repr(u8)]
 pub enum Square {
@@ -145,6 +174,7 @@ pub struct Move {
 
 /// Enum describing the kind of a move.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MoveKind {
     /// Move resulted in a capture.
     Capture(PieceKind),
@@ -158,6 +188,7 @@ pub enum MoveKind {
 
 /// MoveInfo contains extra properties of a move in context of an existing position.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MoveInfo {
     /// Original square of moving piece.
     pub(crate) from: Square,
@@ -268,6 +299,33 @@ impl Cp {
     pub fn is_legal(&self) -> bool {
         Cp::legal_range().contains(self)
     }
+
+    /// Returns the sum of Centipawn scores, saturating at the bounds of the legal range.
+    pub const fn saturating_add(self, rhs: Cp) -> Cp {
+        Cp::saturate(self.0.saturating_add(rhs.0))
+    }
+
+    /// Returns the difference of Centipawn scores, saturating at the bounds of the legal range.
+    pub const fn saturating_sub(self, rhs: Cp) -> Cp {
+        Cp::saturate(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Returns the negated Centipawn score, saturating at the bounds of the legal range.
+    /// Negating any legal score is exact, as MIN == -MAX.
+    pub const fn saturating_neg(self) -> Cp {
+        Cp::saturate(self.0.saturating_neg())
+    }
+
+    /// Clamps a raw value into the legal range.
+    const fn saturate(value: CpKind) -> Cp {
+        if value < Cp::MIN.0 {
+            Cp::MIN
+        } else if value > Cp::MAX.0 {
+            Cp::MAX
+        } else {
+            Cp(value)
+        }
+    }
 }
 
 impl Add for Cp {
@@ -638,6 +696,106 @@ impl FromStr for Castling {
     }
 }
 
+impl Castling {
+    /// Returns the kingside castling right for a color.
+    pub const fn king_side_of(color: Color) -> Self {
+        match color {
+            Color::White => Self::W_KING,
+            Color::Black => Self::B_KING,
+        }
+    }
+
+    /// Returns the queenside castling right for a color.
+    pub const fn queen_side_of(color: Color) -> Self {
+        match color {
+            Color::White => Self::W_QUEEN,
+            Color::Black => Self::B_QUEEN,
+        }
+    }
+}
+
+impl CastlingFiles {
+    /// Castling rook files of standard chess, H for kingside and A for queenside.
+    pub const STANDARD: CastlingFiles = CastlingFiles([File::H, File::A, File::H, File::A]);
+
+    /// Create new CastlingFiles where both players share the same rook files.
+    pub const fn new(king_side: File, queen_side: File) -> Self {
+        Self([king_side, queen_side, king_side, queen_side])
+    }
+
+    /// Returns true if these are the rook files of standard chess.
+    pub fn is_standard(&self) -> bool {
+        *self == Self::STANDARD
+    }
+
+    /// Index of a single castling right. Panics if given multiple or no rights.
+    fn index(right: Castling) -> usize {
+        match right {
+            Castling::W_KING => 0,
+            Castling::W_QUEEN => 1,
+            Castling::B_KING => 2,
+            Castling::B_QUEEN => 3,
+            _ => panic!("castling files require exactly one castling right"),
+        }
+    }
+
+    /// Returns the starting file of the rook for a single castling right.
+    pub fn rook_file(&self, right: Castling) -> File {
+        self.0[Self::index(right)]
+    }
+
+    /// Set the starting file of the rook for a single castling right.
+    pub fn set_rook_file(&mut self, right: Castling, file: File) {
+        self.0[Self::index(right)] = file;
+    }
+
+    /// Returns the starting square of the rook for a single castling right.
+    pub fn rook_square(&self, right: Castling) -> Square {
+        let rank = match right.has_any(Castling::W_SIDE) {
+            true => Rank::R1,
+            false => Rank::R8,
+        };
+        Square::from((self.rook_file(right), rank))
+    }
+
+    /// Returns the squares `(king_to, rook_from, rook_to)` of a castling move,
+    /// where the king moves from `from` towards `to`.
+    /// `to` may either be the king's destination (standard notation)
+    /// or the square of the castling rook (Chess960 notation).
+    /// The king always ends on the G or C file, and the rook on the F or D file.
+    pub fn castling_squares(&self, from: Square, to: Square) -> (Square, Square, Square) {
+        let rank = from.rank();
+        let color = match rank {
+            Rank::R1 => Color::White,
+            _ => Color::Black,
+        };
+
+        // The king is always between both rooks, so it moves towards the castling side.
+        if to.file() > from.file() {
+            let rook_from = self.rook_square(Castling::king_side_of(color));
+            (
+                Square::from((File::G, rank)),
+                rook_from,
+                Square::from((File::F, rank)),
+            )
+        } else {
+            let rook_from = self.rook_square(Castling::queen_side_of(color));
+            (
+                Square::from((File::C, rank)),
+                rook_from,
+                Square::from((File::D, rank)),
+            )
+        }
+    }
+}
+
+/// Defaults to the castling rook files of standard chess.
+impl Default for CastlingFiles {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
 impl File {
     /// Get the File after the current file, or None if at the end.
     pub const fn after(self) -> Option<Self> {
@@ -861,6 +1019,42 @@ impl Square {
     pub fn flip_rank(&self) -> Self {
         Self::from_idx((self.file(), self.rank().flip())).unwrap()
     }
+
+    /// Returns the number of king steps, or Chebyshev distance, between squares.
+    pub fn chebyshev_distance(self, other: Square) -> u8 {
+        let file_distance = self.file_u8().abs_diff(other.file_u8());
+        let rank_distance = self.rank_u8().abs_diff(other.rank_u8());
+        file_distance.max(rank_distance)
+    }
+
+    /// Returns the number of king steps without diagonals, or Manhattan distance, between squares.
+    pub fn manhattan_distance(self, other: Square) -> u8 {
+        let file_distance = self.file_u8().abs_diff(other.file_u8());
+        let rank_distance = self.rank_u8().abs_diff(other.rank_u8());
+        file_distance + rank_distance
+    }
+
+    /// Returns the direction of the ray from this square which passes through `other`,
+    /// or None if the squares are the same or do not share a rank, file, or diagonal.
+    pub fn direction_to(self, other: Square) -> Option<Direction> {
+        let file_delta = other.file_u8() as i8 - self.file_u8() as i8;
+        let rank_delta = other.rank_u8() as i8 - self.rank_u8() as i8;
+        let is_aligned = file_delta == 0 || rank_delta == 0 || file_delta.abs() == rank_delta.abs();
+        if self == other || !is_aligned {
+            return None;
+        }
+
+        match (file_delta.signum(), rank_delta.signum()) {
+            (0, 1) => Some(Direction::North),
+            (1, 1) => Some(Direction::NorthEast),
+            (1, 0) => Some(Direction::East),
+            (1, -1) => Some(Direction::SouthEast),
+            (0, -1) => Some(Direction::South),
+            (-1, -1) => Some(Direction::SouthWest),
+            (-1, 0) => Some(Direction::West),
+            _ => Some(Direction::NorthWest),
+        }
+    }
 }
 
 /// Square enum variants cover all u8 values from 0-63 inclusive.
@@ -954,6 +1148,45 @@ impl Move {
             promotion: None,
         }
     }
+
+    /// Pack this move into 16 bits in the same bit layout as Polyglot opening books.
+    /// Bits 0-5 hold the to square, bits 6-11 the from square, and bits 12-14 the promotion.
+    /// Promotions to Knight, Bishop, Rook, and Queen are 1 to 4 as in Polyglot, and the never
+    /// legal promotions to King and Pawn are 5 and 6.
+    ///
+    /// Castling does not match Polyglot. It is packed as given, such as e1g1 in standard chess,
+    /// while Polyglot packs castling as the king moving onto its own rook, such as e1h1.
+    /// Callers reading or writing Polyglot books must convert castling moves themselves.
+    pub const fn to_u16(&self) -> u16 {
+        let promotion: u16 = match self.promotion {
+            None => 0,
+            Some(PieceKind::Knight) => 1,
+            Some(PieceKind::Bishop) => 2,
+            Some(PieceKind::Rook) => 3,
+            Some(PieceKind::Queen) => 4,
+            Some(PieceKind::King) => 5,
+            Some(PieceKind::Pawn) => 6,
+        };
+        (self.to as u16) | (self.from as u16) << 6 | promotion << 12
+    }
+
+    /// Unpack a move packed by `Move::to_u16`. Castling is not converted from Polyglot form.
+    /// Returns None if the promotion bits or the unused highest bit are invalid.
+    pub fn from_u16(packed: u16) -> Option<Self> {
+        let to = Square::try_from((packed & 0x3F) as u8).ok()?;
+        let from = Square::try_from((packed >> 6 & 0x3F) as u8).ok()?;
+        let promotion = match packed >> 12 {
+            0 => None,
+            1 => Some(PieceKind::Knight),
+            2 => Some(PieceKind::Bishop),
+            3 => Some(PieceKind::Rook),
+            4 => Some(PieceKind::Queen),
+            5 => Some(PieceKind::King),
+            6 => Some(PieceKind::Pawn),
+            _ => return None,
+        };
+        Some(Self::new(from, to, promotion))
+    }
 }
 
 impl PartialEq<MoveInfo> for Move {
@@ -1009,6 +1242,22 @@ impl Display for Move {
     }
 }
 
+/// Move is serialized as its coordinate string, for example `e2e4`.
+#[cfg(feature = "serde")]
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl MoveInfo {
     pub const fn new(move_: Move, moved_piece_kind: PieceKind, move_kind: MoveKind) -> Self {
         Self {
@@ -1075,12 +1324,65 @@ mod tests {
     use PieceKind::*;
     use Square::*;
 
+    #[test]
+    fn square_distances() {
+        assert_eq!(A1.chebyshev_distance(H8), 7);
+        assert_eq!(A1.manhattan_distance(H8), 14);
+        assert_eq!(H8.chebyshev_distance(A1), 7);
+        assert_eq!(D4.chebyshev_distance(D4), 0);
+        assert_eq!(B2.chebyshev_distance(C7), 5);
+        assert_eq!(B2.manhattan_distance(C7), 6);
+
+        // Every neighbor of a square is one king step away.
+        for neighbor in [C4, C5, D5, E5, E4, E3, D3, C3] {
+            assert_eq!(D4.chebyshev_distance(neighbor), 1);
+            assert!(D4.manhattan_distance(neighbor) <= 2);
+        }
+    }
+
+    #[test]
+    fn square_direction_to() {
+        assert_eq!(A1.direction_to(H8), Some(Direction::NorthEast));
+        assert_eq!(H8.direction_to(A1), Some(Direction::SouthWest));
+        assert_eq!(D4.direction_to(D8), Some(Direction::North));
+        assert_eq!(D4.direction_to(A4), Some(Direction::West));
+        assert_eq!(D4.direction_to(H4), Some(Direction::East));
+        assert_eq!(D4.direction_to(D1), Some(Direction::South));
+        assert_eq!(D4.direction_to(A7), Some(Direction::NorthWest));
+        assert_eq!(D4.direction_to(G1), Some(Direction::SouthEast));
+
+        // Squares which do not share a line have no direction.
+        assert_eq!(D4.direction_to(D4), None);
+        assert_eq!(A1.direction_to(B3), None);
+        assert_eq!(G1.direction_to(F3), None);
+        assert_eq!(A1.direction_to(H7), None);
+    }
+
     #[test]
     fn logical_not_color() {
         assert_eq!(!Color::White, Color::Black);
         assert_eq!(!Color::Black, Color::White);
     }
 
+    #[test]
+    fn cp_saturating_arithmetic() {
+        assert_eq!(Cp(100).saturating_add(Cp(-30)), Cp(70));
+        assert_eq!(Cp(100).saturating_sub(Cp(30)), Cp(70));
+        assert_eq!(Cp(100).saturating_neg(), Cp(-100));
+
+        // Results saturate at the legal bounds, and never reach the illegal value.
+        assert_eq!(Cp::MAX.saturating_add(Cp(1)), Cp::MAX);
+        assert_eq!(Cp::CHECKMATE_MAX.saturating_add(Cp(200)), Cp::MAX);
+        assert_eq!(Cp::MIN.saturating_sub(Cp(1)), Cp::MIN);
+        assert_eq!(Cp::MIN.saturating_add(Cp::MIN), Cp::MIN);
+        assert_eq!(Cp::MAX.saturating_sub(Cp::MIN), Cp::MAX);
+        assert_eq!(Cp::MIN.saturating_neg(), Cp::MAX);
+        assert_eq!(Cp::MAX.saturating_neg(), Cp::MIN);
+        assert_eq!(Cp(CpKind::MIN).saturating_neg(), Cp::MAX);
+        assert_eq!(Cp::ILLEGAL.saturating_neg(), Cp::MIN);
+        assert!(Cp(CpKind::MAX).saturating_add(Cp(CpKind::MAX)).is_legal());
+    }
+
     #[test]
     fn cp_disjoint_ranges() {
         // Assert that Illegal is not in any legal range.
@@ -1347,4 +1649,23 @@ mod tests {
         assert_eq!(sq.increment_rank(), None);
         assert_eq!(sq.decrement_rank(), Some(D7));
     }
+
+    #[test]
+    fn move_u16_round_trip() {
+        for from in Square::iter() {
+            for to in Square::iter() {
+                let promotions = std::iter::once(None).chain(PieceKind::iter().map(Some));
+                for promotion in promotions {
+                    let move_ = Move::new(from, to, promotion);
+                    assert_eq!(Move::from_u16(move_.to_u16()), Some(move_));
+                }
+            }
+        }
+
+        // Polyglot encoding of e2e4 and e7e8q.
+        assert_eq!(Move::new(E2, E4, None).to_u16(), 0x031C);
+        assert_eq!(Move::new(E7, E8, Some(Queen)).to_u16(), 0x4D3C);
+        assert_eq!(Move::from_u16(0x7000), None);
+        assert_eq!(Move::from_u16(0x8000), None);
+    }
 }
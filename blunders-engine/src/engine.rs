@@ -2,15 +2,28 @@
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Sender};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
+use std::time::Duration;
 
+use crate::coretypes::{Cp, Move, PlyKind};
 use crate::error::{self, ErrorKind};
+use crate::eval::{EvalCache, EvalParams, EvalTrace};
+use crate::fen::Fen;
+use crate::moveorder::HistoryTable;
+use crate::perft;
 use crate::position::{Game, Position};
-use crate::search::{self, SearchResult};
-use crate::timeman::Mode;
+use crate::search::{
+    self, PruningParams, QuiescenceParams, SearchProgress, SearchResult, SharedContext,
+    EVAL_CACHE_MB,
+};
+use crate::timeman::{Mode, MOVE_OVERHEAD};
+use crate::zobrist::ZobristTable;
 use crate::TranspositionTable;
 
+/// Seed of the zobrist table of a deterministic engine.
+const DETERMINISTIC_SEED: u64 = 0;
+
 /// EngineBuilder allows for parameters of an Engine to be set and built once,
 /// avoiding repeating costly initialization steps of making then changing an Engine.
 ///
@@ -18,14 +31,59 @@ use crate::TranspositionTable;
 ///
 /// * `game`: Starting chess position
 /// * `transpositions_mb`: 1 megabytes
+/// * `eval_cache_mb`: 1 megabytes
 /// * `num_threads`: 1,
 /// * `debug`: true
+/// * `eval_params`: Default evaluation parameters
+/// * `quiescence_params`: Default quiescence parameters
+/// * `pruning_params`: Default pruning parameters, with every kind of pruning enabled
+/// * `easy_move`: true
+/// * `move_overhead`: 10 milliseconds
+/// * `deterministic`: false
+/// * `zobrist_seed`: None, randomly seeded
+/// * `clear_on_new_game`: true
+///
+/// Example:
+/// ```
+/// # use std::time::Duration;
+/// # use blunders_engine::coretypes::Cp;
+/// # use blunders_engine::eval::EvalParams;
+/// # use blunders_engine::search::QuiescenceParams;
+/// # use blunders_engine::EngineBuilder;
+/// let params = EvalParams {
+///     knight_mobility_cp: Cp(6),
+///     ..EvalParams::default()
+/// };
+/// let engine = EngineBuilder::new()
+///     .eval_params(params)
+///     .contempt(Cp(25))
+///     .move_overhead(Duration::from_millis(100))
+///     .quiescence_params(QuiescenceParams {
+///         depth: 6,
+///         check_plies: 1,
+///     })
+///     .build();
+///
+/// assert_eq!(engine.eval_params().knight_mobility_cp, Cp(6));
+/// assert_eq!(engine.eval_params().contempt_cp, Cp(25));
+/// assert_eq!(engine.move_overhead(), Duration::from_millis(100));
+/// assert_eq!(engine.quiescence_params().check_plies, 1);
+/// ```
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct EngineBuilder {
     game: Game,
     transpositions_mb: usize,
+    eval_cache_mb: usize,
     num_threads: usize,
     debug: bool,
+    eval_params: EvalParams,
+    quiescence_params: QuiescenceParams,
+    pruning_params: PruningParams,
+    easy_move: bool,
+    move_overhead: Duration,
+    deterministic: bool,
+    zobrist_seed: Option<u64>,
+    clear_on_new_game: bool,
 }
 
 impl EngineBuilder {
@@ -34,21 +92,52 @@ impl EngineBuilder {
         Self {
             game: Game::start_position(),
             transpositions_mb: 1,
+            eval_cache_mb: EVAL_CACHE_MB,
             num_threads: 1,
             debug: true,
+            eval_params: EvalParams::default(),
+            quiescence_params: QuiescenceParams::default(),
+            pruning_params: PruningParams::default(),
+            easy_move: true,
+            move_overhead: MOVE_OVERHEAD,
+            deterministic: false,
+            zobrist_seed: None,
+            clear_on_new_game: true,
         }
     }
 
     /// Create and return a new Engine.
     pub fn build(&self) -> Engine {
-        let tt = Arc::new(TranspositionTable::with_mb(self.transpositions_mb));
+        let seed = match self.deterministic {
+            true => Some(self.zobrist_seed.unwrap_or(DETERMINISTIC_SEED)),
+            false => self.zobrist_seed,
+        };
+        let tt = match seed {
+            Some(seed) => TranspositionTable::with_mb_and_zobrist(
+                self.transpositions_mb,
+                ZobristTable::with_seed(seed),
+            ),
+            None => TranspositionTable::with_mb(self.transpositions_mb),
+        };
+        let eval_cache =
+            EvalCache::with_mb_and_zobrist(self.eval_cache_mb, tt.zobrist_table().clone());
+        let tt = Arc::new(tt);
         let stopper = Arc::new(AtomicBool::new(false));
 
         Engine {
             game: self.game.clone(),
             tt,
+            history_table: Arc::new(Mutex::new(HistoryTable::new())),
+            eval_cache: Arc::new(Mutex::new(eval_cache)),
             stopper,
             debug: self.debug,
+            eval_params: self.eval_params,
+            quiescence_params: self.quiescence_params,
+            pruning_params: self.pruning_params,
+            easy_move: self.easy_move,
+            move_overhead: self.move_overhead,
+            deterministic: self.deterministic,
+            clear_on_new_game: self.clear_on_new_game,
             search_handle: None,
         }
     }
@@ -66,6 +155,7 @@ impl EngineBuilder {
     }
 
     /// Set the engine's initial search thread pool size.
+    /// A deterministic engine always searches with a single thread.
     pub fn threads(mut self, num_threads: usize) -> Self {
         self.num_threads = num_threads;
         self
@@ -77,11 +167,77 @@ impl EngineBuilder {
         self
     }
 
+    /// Set the engine's evaluation cache size in megabytes.
+    pub fn eval_cache_mb(mut self, eval_cache_mb: usize) -> Self {
+        self.eval_cache_mb = eval_cache_mb;
+        self
+    }
+
     /// Set whether the engine begins in debug mode.
     pub fn debug(mut self, debug: bool) -> Self {
         self.debug = debug;
         self
     }
+    /// Set whether timed and node limited searches may stop early when one move is clearly best.
+    /// Set whether timed searches may stop early when one move is clearly best.
+    pub fn easy_move(mut self, easy_move: bool) -> Self {
+        self.easy_move = easy_move;
+        self
+    }
+
+    /// Set the evaluation parameters used by the engine's searches.
+    pub fn eval_params(mut self, eval_params: EvalParams) -> Self {
+        self.eval_params = eval_params;
+        self
+    }
+
+    /// Set the quiescence search depth and checking plies used by the engine's searches.
+    pub fn quiescence_params(mut self, quiescence_params: QuiescenceParams) -> Self {
+        self.quiescence_params = quiescence_params;
+        self
+    }
+
+    /// Set which kinds of pruning are done near the horizon of the engine's searches.
+    pub fn pruning_params(mut self, pruning_params: PruningParams) -> Self {
+        self.pruning_params = pruning_params;
+        self
+    }
+
+    /// Set how much the engine avoids draws, overriding the contempt of its evaluation parameters.
+    pub fn contempt(mut self, contempt: Cp) -> Self {
+        self.eval_params.contempt_cp = contempt;
+        self
+    }
+
+    /// Set the expected time lost communicating each move, which timed searches leave unused.
+    pub fn move_overhead(mut self, move_overhead: Duration) -> Self {
+        self.move_overhead = move_overhead;
+        self
+    }
+
+    /// Set whether the engine's searches are reproducible, searching the same nodes every run.
+    /// A deterministic engine searches with a single thread, uses a fixed zobrist seed
+    /// if none is set, and stops searches only by depth or nodes. See `Mode::deterministic`.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        if deterministic {
+            self.num_threads = 1;
+        }
+        self
+    }
+
+    /// Set the seed of the engine's zobrist table, so position hashes are the same every run.
+    pub fn zobrist_seed(mut self, seed: u64) -> Self {
+        self.zobrist_seed = Some(seed);
+        self
+    }
+
+    /// Set whether the transposition table is cleared for a new game.
+    /// If not, entries from previous games are only aged out. See `Engine::new_game`.
+    pub fn clear_on_new_game(mut self, clear_on_new_game: bool) -> Self {
+        self.clear_on_new_game = clear_on_new_game;
+        self
+    }
 }
 
 /// Engine wraps up all parameters required for running any kind of search.
@@ -93,8 +249,17 @@ pub struct Engine {
     // Search fields
     game: Game,
     tt: Arc<TranspositionTable>,
+    history_table: Arc<Mutex<HistoryTable>>,
+    eval_cache: Arc<Mutex<EvalCache>>,
     stopper: Arc<AtomicBool>,
     debug: bool,
+    eval_params: EvalParams,
+    quiescence_params: QuiescenceParams,
+    pruning_params: PruningParams,
+    easy_move: bool,
+    move_overhead: Duration,
+    deterministic: bool,
+    clear_on_new_game: bool,
 
     // Meta fields
     search_handle: Option<JoinHandle<()>>,
@@ -102,11 +267,22 @@ pub struct Engine {
 
 impl Engine {
     pub fn new() -> Self {
+        let tt = TranspositionTable::new();
+        let eval_cache = EvalCache::with_mb_and_zobrist(EVAL_CACHE_MB, tt.zobrist_table().clone());
         Self {
             game: Game::from(Position::start_position()),
-            tt: Arc::new(TranspositionTable::new()),
+            tt: Arc::new(tt),
+            history_table: Arc::new(Mutex::new(HistoryTable::new())),
+            eval_cache: Arc::new(Mutex::new(eval_cache)),
             stopper: Arc::new(AtomicBool::new(false)),
             debug: true,
+            eval_params: EvalParams::default(),
+            quiescence_params: QuiescenceParams::default(),
+            pruning_params: PruningParams::default(),
+            easy_move: true,
+            move_overhead: MOVE_OVERHEAD,
+            deterministic: false,
+            clear_on_new_game: true,
             search_handle: None,
         }
     }
@@ -121,6 +297,41 @@ impl Engine {
         &self.debug
     }
 
+    /// Returns reference to engine's evaluation parameters.
+    pub fn eval_params(&self) -> &EvalParams {
+        &self.eval_params
+    }
+
+    /// Returns reference to engine's quiescence search parameters.
+    pub fn quiescence_params(&self) -> &QuiescenceParams {
+        &self.quiescence_params
+    }
+
+    /// Returns reference to engine's pruning parameters.
+    pub fn pruning_params(&self) -> &PruningParams {
+        &self.pruning_params
+    }
+
+    /// Returns the expected time lost communicating each move.
+    pub fn move_overhead(&self) -> Duration {
+        self.move_overhead
+    }
+
+    /// Returns true if the engine's searches are reproducible.
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns true if the transposition table is cleared for a new game.
+    pub fn clear_on_new_game(&self) -> bool {
+        self.clear_on_new_game
+    }
+
+    /// Returns the number of evaluations the engine's evaluation cache holds.
+    pub fn eval_cache_capacity(&self) -> usize {
+        self.eval_cache.lock().unwrap().capacity()
+    }
+
     /// Returns reference to engine's transposition table.
     pub fn transposition_table(&self) -> &TranspositionTable {
         &self.tt
@@ -131,15 +342,61 @@ impl Engine {
         self.game = game.into();
     }
 
+    /// Set the engine's game to a position parsed from a FEN string, with no history.
+    /// The game is unchanged if the FEN does not describe a legal position.
+    pub fn set_position_from_fen(&mut self, fen: &str) -> error::Result<()> {
+        let position = Position::parse_fen(fen)?;
+        position.validate()?;
+        self.game = Game::from(position);
+        Ok(())
+    }
+
+    /// Apply a move in UCI long algebraic notation, such as `e2e4` or `e7e8q`,
+    /// to the engine's current game. The game is unchanged if the move is illegal.
+    pub fn make_uci_move(&mut self, move_str: &str) -> error::Result<()> {
+        let move_: Move = move_str.parse()?;
+        self.game.push_move(move_)
+    }
+
     /// Update the engine's debug parameter.
     pub fn set_debug(&mut self, new_debug: bool) {
         self.debug = new_debug;
     }
 
+    /// Update how much the engine avoids draws, taking effect from the next search.
+    pub fn set_contempt(&mut self, contempt: Cp) {
+        self.eval_params.contempt_cp = contempt;
+    }
+
+    /// Update whether the transposition table is cleared for a new game.
+    pub fn set_clear_on_new_game(&mut self, clear_on_new_game: bool) {
+        self.clear_on_new_game = clear_on_new_game;
+    }
+
+    /// Update the expected time lost communicating each move, taking effect from the next search.
+    pub fn set_move_overhead(&mut self, move_overhead: Duration) {
+        self.move_overhead = move_overhead;
+    }
+
     /// Informs engine that next search will be from a new game.
+    /// This begins a new transposition table generation, so that entries from the previous
+    /// game are stale and replaced first, even if the table is not cleared.
+    /// The transposition table is cleared only if `clear_on_new_game` is set, as clearing
+    /// a large table is slow. The tables are cleared only if no search is running.
     /// Returns Ok if engine succeeded in changing state for a new game, Err otherwise.
     pub fn new_game(&mut self) -> error::Result<()> {
-        self.try_clear_transpositions()
+        self.tt.new_generation();
+        if self.clear_on_new_game {
+            self.try_clear_transpositions()?;
+        }
+        self.history_table.lock().unwrap().clear();
+        Ok(())
+    }
+
+    /// Returns a copy of the history heuristic table, which orders quiet moves by how often
+    /// they caused cutoffs in previous searches of this game.
+    pub fn history_table(&self) -> HistoryTable {
+        self.history_table.lock().unwrap().clone()
     }
 
     /// Attempt to set a new size for the transposition table in Megabytes.
@@ -160,7 +417,20 @@ impl Engine {
             .ok_or(ErrorKind::EngineTranspositionTableInUse.into())
     }
 
-    /// Run a blocking search.
+    /// Returns the static evaluation of the current position decomposed by term,
+    /// formatted as a table with one line per term.
+    pub fn eval_trace(&self) -> String {
+        EvalTrace::new(&self.game.position, &self.eval_params).to_string()
+    }
+
+    /// Count the leaf nodes to `ply` for each legal move of the current position,
+    /// and their total, as in `perft::divide`. `ply` must be at least 1.
+    pub fn perft(&self, ply: PlyKind) -> (Vec<(Move, u64)>, u64) {
+        perft::divide(&self.game.position, ply)
+    }
+
+    /// Run a blocking search. Progress updates from the search are discarded.
+    /// Panics if the engine is deterministic and `mode` can only stop by time.
     pub fn search_sync(&mut self, mode: Mode) -> SearchResult {
         // Block until a search is ready to run.
         self.stop();
@@ -170,40 +440,95 @@ impl Engine {
         let (sender, receiver) = mpsc::channel();
         self.search(mode, sender).unwrap();
         self.wait();
-        receiver.recv().unwrap()
+        receiver
+            .into_iter()
+            .find_map(|message| match message {
+                SyncMessage::Result(search_result) => Some(*search_result),
+                SyncMessage::Progress => None,
+            })
+            .unwrap()
+    }
+
+    /// Analyze the engine's current position, blocking until the search completes.
+    /// This is the simplest way to search when using the engine as a library.
+    /// The search uses the engine's transposition table and stops as directed by `mode`,
+    /// returning the best result found so far if stopped early.
+    ///
+    /// Example:
+    /// ```
+    /// # use blunders_engine::{EngineBuilder, Mode, Position};
+    /// let mut engine = EngineBuilder::new().transpositions_mb(1).build();
+    /// let result = engine.analyze(Mode::depth(3, None));
+    ///
+    /// let legal_moves = Position::start_position().get_legal_moves();
+    /// assert!(legal_moves.contains(&result.best_move));
+    /// assert_eq!(result.depth, 3);
+    /// ```
+    pub fn analyze(&mut self, mode: Mode) -> SearchResult {
+        self.search_sync(mode)
     }
 
     /// Run a non-blocking search.
     /// The engine only runs one search at a time, so if it is not ready, it fails to begin.
     /// If the engine is available for searching, it ensures its stopper is unset.
+    ///
+    /// Progress updates are sent over `sender` while the search runs,
+    /// followed by the final SearchResult once the search completes.
+    /// A deterministic engine fails to begin searching a mode which can only stop by time.
     pub fn search<T>(&mut self, mode: Mode, sender: Sender<T>) -> error::Result<()>
     where
-        T: From<SearchResult> + Send + 'static,
+        T: From<SearchResult> + From<SearchProgress> + Send + 'static,
     {
+        let mode = match self.deterministic {
+            true => mode.deterministic()?,
+            false => mode,
+        };
+
         if self.search_handle.is_none() {
             self.unstop();
 
             let handle = search::search_nonblocking(
                 self.game.clone(),
-                mode,
+                mode.with_easy_move(self.easy_move)
+                    .with_move_overhead(self.move_overhead),
                 Arc::clone(&self.tt),
-                Arc::clone(&self.stopper),
-                self.debug,
+                SharedContext {
+                    history_table: Arc::clone(&self.history_table),
+                    eval_cache: Arc::clone(&self.eval_cache),
+                    params: self.eval_params,
+                    q_params: self.quiescence_params,
+                    pruning: self.pruning_params,
+                    stopper: Arc::clone(&self.stopper),
+                },
                 sender,
             );
             self.search_handle = Some(handle);
 
             Ok(())
         } else {
-            Err((ErrorKind::EngineAlreadySearching, "failed to begin search").into())
+            Err((ErrorKind::EngineBusy, "a search is already running").into())
         }
     }
 
+    /// Run a non-blocking search, first stopping and waiting for any active search,
+    /// so that the new search always begins unless `mode` cannot be searched.
+    /// The stopped search still sends its SearchResult over its own sender.
+    pub fn search_now<T>(&mut self, mode: Mode, sender: Sender<T>) -> error::Result<()>
+    where
+        T: From<SearchResult> + From<SearchProgress> + Send + 'static,
+    {
+        self.stop();
+        self.wait();
+        self.search(mode, sender)
+    }
+
     pub fn ponder(&self) {
         todo!()
     }
 
     /// Informs the active search to stop searching as soon as possible.
+    /// This returns immediately without waiting for the search to finish unwinding,
+    /// use `wait` to block until it has finished.
     pub fn stop(&self) {
         self.stopper.store(true, Ordering::Relaxed);
     }
@@ -222,6 +547,15 @@ impl Engine {
         }
     }
 
+    /// Returns true if a search thread of the engine is still running.
+    /// A search which has finished but has not been waited on is not searching,
+    /// though the engine is not ready until it is waited on.
+    pub fn is_searching(&self) -> bool {
+        self.search_handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
     /// Returns true if the engine is ready to start a search.
     /// Only one search may run at a time, so if a search is in progress, engine is not ready.
     pub fn ready(&self) -> bool {
@@ -236,6 +570,24 @@ impl Engine {
     pub fn shutdown(self) {}
 }
 
+/// Message type used by a blocking search, which only keeps the final result.
+enum SyncMessage {
+    Result(Box<SearchResult>),
+    Progress,
+}
+
+impl From<SearchResult> for SyncMessage {
+    fn from(search_result: SearchResult) -> Self {
+        SyncMessage::Result(Box::new(search_result))
+    }
+}
+
+impl From<SearchProgress> for SyncMessage {
+    fn from(_: SearchProgress) -> Self {
+        SyncMessage::Progress
+    }
+}
+
 impl Default for Engine {
     fn default() -> Self {
         Self::new()
@@ -248,3 +600,276 @@ impl Drop for Engine {
         self.wait();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coretypes::Square::*;
+    use crate::transposition::{Entry, NodeKind};
+    use std::thread;
+
+    #[test]
+    fn set_position_and_make_moves() {
+        let mut engine = EngineBuilder::new().build();
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        engine.set_position_from_fen(fen).unwrap();
+        assert_eq!(engine.game().position.to_fen(), fen);
+        assert!(engine.game().moves.is_empty());
+
+        engine.make_uci_move("f1b5").unwrap();
+        engine.make_uci_move("a7a6").unwrap();
+        assert_eq!(
+            engine.game().position.to_fen(),
+            "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4"
+        );
+        assert_eq!(
+            engine.game().moves.as_slice(),
+            &[Move::new(F1, B5, None), Move::new(A7, A6, None)]
+        );
+
+        // Illegal and malformed moves leave the game unchanged.
+        let game = engine.game().clone();
+        assert!(engine.make_uci_move("e1e3").is_err());
+        assert!(engine.make_uci_move("z9").is_err());
+        assert_eq!(engine.game(), &game);
+        assert!(engine.set_position_from_fen("8/8/8").is_err());
+        assert_eq!(engine.game(), &game);
+    }
+
+    #[test]
+    fn new_game_clears_history_table() {
+        let mut engine = EngineBuilder::new().deterministic(true).build();
+        assert!(engine.history_table().is_empty());
+
+        engine.analyze(Mode::depth(4, None));
+        assert!(!engine.history_table().is_empty());
+
+        engine.new_game().unwrap();
+        assert!(engine.history_table().is_empty());
+    }
+
+    #[test]
+    fn new_game_without_clear_ages_entries() {
+        let mut engine = EngineBuilder::new().clear_on_new_game(false).build();
+        let best_move = Move::new(E2, E4, None);
+        let deep_entry = Entry::new(1, best_move, Cp(10), 8, NodeKind::Cut);
+        let shallow_entry = Entry::new(1, best_move, Cp(20), 2, NodeKind::Cut);
+
+        // Within a game, a shallower entry does not replace the deeper entry.
+        let tt = engine.transposition_table();
+        tt.replace_by(deep_entry, tt.generation(), search::replace_scheme);
+        tt.replace_by(shallow_entry, tt.generation(), search::replace_scheme);
+        assert_eq!(tt.get(1), Some(deep_entry));
+
+        // Entries of the previous game are kept, but replaced first.
+        let generation = tt.generation();
+        engine.new_game().unwrap();
+        let tt = engine.transposition_table();
+        assert_ne!(tt.generation(), generation);
+        assert_eq!(tt.get(1), Some(deep_entry));
+        tt.replace_by(shallow_entry, tt.generation(), search::replace_scheme);
+        assert_eq!(tt.get(1), Some(shallow_entry));
+
+        // Clearing removes every entry.
+        engine.set_clear_on_new_game(true);
+        engine.new_game().unwrap();
+        assert!(engine.transposition_table().is_empty());
+    }
+
+    #[test]
+    fn eval_cache_persists_between_searches() {
+        let mut engine = EngineBuilder::new().eval_cache_mb(2).build();
+        let small_engine = EngineBuilder::new().eval_cache_mb(1).build();
+        assert!(engine.eval_cache_capacity() > small_engine.eval_cache_capacity());
+
+        // A repeated search without transpositions finds its evaluations cached by
+        // the first search, unless the evaluation parameters were changed in between.
+        let hits = |engine: &Engine| engine.eval_cache.lock().unwrap().hits();
+        engine.analyze(Mode::depth(3, None));
+        let first_hits = hits(&engine);
+        engine.try_clear_transpositions().unwrap();
+        engine.analyze(Mode::depth(3, None));
+        assert!(hits(&engine) > first_hits);
+
+        engine.set_contempt(Cp(50));
+        engine.analyze(Mode::depth(3, None));
+        let cache = engine.eval_cache.lock().unwrap();
+        assert_eq!(cache.params().contempt_cp, Cp(50));
+    }
+
+    #[test]
+    fn analyze_returns_result_when_stopped() {
+        let mut engine = EngineBuilder::new().build();
+        let legal_moves = Position::start_position().get_legal_moves();
+
+        let result = engine.analyze(Mode::depth(3, None));
+        assert!(legal_moves.contains(&result.best_move));
+        assert_eq!(result.depth, 3);
+
+        // A timed out search still returns its best move found so far.
+        let result = engine.analyze(Mode::movetime(200, None));
+        assert!(legal_moves.contains(&result.best_move));
+    }
+
+    #[test]
+    fn perft_current_position() {
+        let mut engine = EngineBuilder::new().build();
+        let (divide, total) = engine.perft(2);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(total, 400);
+
+        engine.make_uci_move("e2e4").unwrap();
+        let (divide, total) = engine.perft(1);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(total, 20);
+        assert!(divide.iter().all(|(_, nodes)| *nodes == 1));
+    }
+
+    #[test]
+    fn contempt_avoids_repetition() {
+        // Kg1 repeats a position for the third time, while other moves are about even.
+        let pgn =
+            "[FEN \"7k/p7/8/8/8/8/P7/7K w - - 0 1\"] 1. Kg1 Kg8 2. Kh1 Kh8 3. Kg1 Kg8 4. Kh1 Kh8";
+        let game = Game::from_pgn(pgn).unwrap();
+        let repetition = Move::new(H1, G1, None);
+
+        let search = |contempt: Cp| {
+            let mut engine = EngineBuilder::new()
+                .game(game.clone())
+                .contempt(contempt)
+                .build();
+            engine.search_sync(Mode::depth(2, None))
+        };
+
+        let avoids_draw = search(Cp(100));
+        assert_ne!(avoids_draw.best_move, repetition);
+        assert!(avoids_draw.score > Cp(-100));
+
+        let seeks_draw = search(Cp(-100));
+        assert_eq!(seeks_draw.best_move, repetition);
+        assert_eq!(seeks_draw.score, Cp(100));
+    }
+
+    #[test]
+    fn zobrist_seed_is_reproducible() {
+        let ztable = |builder: EngineBuilder| builder.build().tt.zobrist_table().clone();
+        let seeded = ztable(EngineBuilder::new().zobrist_seed(0x2088));
+        assert_eq!(seeded, ZobristTable::with_seed(0x2088));
+        assert_eq!(seeded, ztable(EngineBuilder::new().zobrist_seed(0x2088)));
+        assert_ne!(seeded, ztable(EngineBuilder::new().zobrist_seed(0x2089)));
+        assert_ne!(seeded, ztable(EngineBuilder::new()));
+
+        let deterministic = ztable(EngineBuilder::new().deterministic(true));
+        assert_eq!(deterministic, ZobristTable::with_seed(DETERMINISTIC_SEED));
+    }
+
+    #[test]
+    fn deterministic_search_is_reproducible() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let search = || {
+            let mut engine = EngineBuilder::new().threads(4).deterministic(true).build();
+            engine.set_position_from_fen(fen).unwrap();
+            engine.search_sync(Mode::depth(3, Some(1)))
+        };
+
+        let first = search();
+        let second = search();
+        assert_eq!(first.depth, 3);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.best_move, second.best_move);
+
+        let mut engine = EngineBuilder::new().deterministic(true).build();
+        let (sender, _receiver) = mpsc::channel::<SyncMessage>();
+        assert!(engine.search(Mode::movetime(100, None), sender).is_err());
+    }
+
+    #[test]
+    fn search_now_replaces_active_search() {
+        let mut engine = EngineBuilder::new().threads(1).build();
+        let (sender, receiver) = mpsc::channel::<SyncMessage>();
+        engine.search(Mode::infinite(), sender.clone()).unwrap();
+        assert!(engine.search(Mode::depth(1, None), sender.clone()).is_err());
+
+        // The second search begins, and both searches send a result.
+        engine.search_now(Mode::depth(2, None), sender).unwrap();
+        engine.wait();
+        let results: Vec<SearchResult> = receiver
+            .into_iter()
+            .filter_map(|message| match message {
+                SyncMessage::Result(search_result) => Some(*search_result),
+                SyncMessage::Progress => None,
+            })
+            .collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].stopped);
+        assert!(!results[1].stopped);
+        assert_eq!(results[1].depth, 2);
+    }
+
+    #[test]
+    fn search_while_busy_is_engine_busy() {
+        let mut engine = EngineBuilder::new().threads(1).build();
+        let (sender, _receiver) = mpsc::channel::<SyncMessage>();
+        engine.search(Mode::infinite(), sender.clone()).unwrap();
+
+        let error = engine.search(Mode::depth(1, None), sender).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::EngineBusy);
+
+        engine.stop();
+        engine.wait();
+    }
+
+    #[test]
+    fn is_searching_until_stopped() {
+        let mut engine = EngineBuilder::new().threads(1).build();
+        assert!(!engine.is_searching());
+
+        let (sender, receiver) = mpsc::channel::<SyncMessage>();
+        engine.search(Mode::infinite(), sender.clone()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(engine.is_searching());
+        assert!(!engine.ready());
+
+        engine.stop();
+        engine.wait();
+        assert!(!engine.is_searching());
+        assert!(engine.ready());
+
+        // A finished search is no longer searching, but must be waited on to be ready.
+        engine.search(Mode::depth(1, None), sender).unwrap();
+        let finished = receiver
+            .iter()
+            .any(|message| matches!(message, SyncMessage::Result(_)));
+        assert!(finished);
+        while engine.is_searching() {
+            thread::yield_now();
+        }
+        assert!(!engine.ready());
+        engine.wait();
+        assert!(engine.ready());
+    }
+
+    #[test]
+    fn stop_sends_one_result() {
+        for delay in [0, 20] {
+            let mut engine = EngineBuilder::new().threads(1).build();
+            let (sender, receiver) = mpsc::channel::<SyncMessage>();
+            engine.search(Mode::infinite(), sender).unwrap();
+            thread::sleep(Duration::from_millis(delay));
+            engine.stop();
+            engine.wait();
+
+            let results: Vec<SearchResult> = receiver
+                .into_iter()
+                .filter_map(|message| match message {
+                    SyncMessage::Result(search_result) => Some(*search_result),
+                    SyncMessage::Progress => None,
+                })
+                .collect();
+            assert_eq!(results.len(), 1);
+            assert!(results[0].stopped);
+            let legal_moves = engine.game().position.get_legal_moves();
+            assert!(legal_moves.contains(&results[0].best_move));
+        }
+    }
+}
@@ -35,8 +35,22 @@ pub enum ErrorKind {
     UciPositionIllegalMove,
     /// Uci Option fails to update.
     UciOptionCannotUpdate,
+    /// Uci perft depth is out of range.
+    UciPerftIllegalDepth,
+    /// Uci bench depth is out of range.
+    UciBenchIllegalDepth,
     /// Fen error kinds.
     Fen,
+    /// Fen position does not have exactly one king per player.
+    FenKingCount,
+    /// Fen position has a pawn on the first or last rank.
+    FenPawnOnBackRank,
+    /// Fen position has the player not to move in check.
+    FenOpponentInCheck,
+    /// Fen position has a castling right without its king or rook in place.
+    FenIllegalCastling,
+    /// Fen position has an en passant square without a pawn that just moved two squares.
+    FenIllegalEnPassant,
 
     /// Square parse string malformed.
     ParseSquareMalformed,
@@ -50,9 +64,19 @@ pub enum ErrorKind {
     ParsePieceMalformed,
     /// Piece parse string malformed.
     ParseCastlingMalformed,
-
-    /// Time Management Mode cannot be created, missing fields.
-    ModeNotSatisfied,
+    /// SAN move string malformed.
+    ParseSanMalformed,
+    /// SAN move does not describe any legal move.
+    SanIllegalMove,
+    /// SAN move describes more than one legal move.
+    SanAmbiguousMove,
+    /// PGN string malformed.
+    PgnMalformed,
+
+    /// Time Management Mode cannot be created, as no search limit is fully given.
+    SearchModeInvalid,
+    /// Time Management Mode can only stop by time, so cannot be searched deterministically.
+    ModeNotDeterministic,
 
     /// The engine can only play games with a finite static number of moves.
     /// That limit has been exceeded.
@@ -61,10 +85,12 @@ pub enum ErrorKind {
     /// Engine's transposition table is being referenced from another thread.
     EngineTranspositionTableInUse,
     /// Engine is currently searching, so another search cannot be started.
-    EngineAlreadySearching,
+    EngineBusy,
 
     // An illegal move was provided, and could not be applied to some base position.
     GameIllegalMove,
+    /// A move index does not refer to a move played in a game.
+    GameMoveIndexOutOfRange,
 }
 
 impl ErrorKind {
@@ -81,7 +107,14 @@ impl ErrorKind {
             ErrorKind::UciPositionMalformed => "uci position malformed",
             ErrorKind::UciPositionIllegalMove => "uci position illegal move",
             ErrorKind::UciOptionCannotUpdate => "uci option cannot update",
+            ErrorKind::UciPerftIllegalDepth => "uci perft illegal depth",
+            ErrorKind::UciBenchIllegalDepth => "uci bench illegal depth",
             ErrorKind::Fen => "fen",
+            ErrorKind::FenKingCount => "fen king count",
+            ErrorKind::FenPawnOnBackRank => "fen pawn on back rank",
+            ErrorKind::FenOpponentInCheck => "fen opponent in check",
+            ErrorKind::FenIllegalCastling => "fen illegal castling",
+            ErrorKind::FenIllegalEnPassant => "fen illegal en passant",
 
             ErrorKind::ParseSquareMalformed => "parse square malformed",
             ErrorKind::ParseFileMalformed => "parse file malformed",
@@ -89,15 +122,21 @@ impl ErrorKind {
             ErrorKind::ParseColorMalformed => "parse color malformed",
             ErrorKind::ParsePieceMalformed => "parse piece malformed",
             ErrorKind::ParseCastlingMalformed => "parse castling malformed",
+            ErrorKind::ParseSanMalformed => "parse san malformed",
+            ErrorKind::SanIllegalMove => "san illegal move",
+            ErrorKind::SanAmbiguousMove => "san ambiguous move",
+            ErrorKind::PgnMalformed => "pgn malformed",
 
-            ErrorKind::ModeNotSatisfied => "mode not satisfied",
+            ErrorKind::SearchModeInvalid => "search mode invalid",
+            ErrorKind::ModeNotDeterministic => "mode not deterministic",
 
             ErrorKind::MoveHistoryExceeded => "move history exceeded",
 
             ErrorKind::EngineTranspositionTableInUse => "engine transposition table in use",
-            ErrorKind::EngineAlreadySearching => "engine already searching",
+            ErrorKind::EngineBusy => "engine busy",
 
             ErrorKind::GameIllegalMove => "position history illegal move",
+            ErrorKind::GameMoveIndexOutOfRange => "game move index out of range",
         }
     }
 }
@@ -123,6 +162,15 @@ impl Error {
     {
         Self::Custom(error_kind, inner_error.into())
     }
+
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Simple(error_kind) => *error_kind,
+            Error::Message(error_kind, _) => *error_kind,
+            Error::Custom(error_kind, _) => *error_kind,
+        }
+    }
 }
 
 impl Display for Error {
@@ -7,11 +7,18 @@
 //! A relative score treats the player to move as the maxing player, so if it is
 //! Black to move, +10 is winning for Black.
 
-use crate::bitboard::{self, Bitboard};
-use crate::coretypes::{Color, Cp, CpKind, PieceKind, SquareIndexable, NUM_RANKS, NUM_SQUARES};
+use std::fmt::{self, Display};
+use std::mem;
+
+use crate::bitboard::Bitboard;
+use crate::coretypes::{
+    Color, Cp, CpKind, File, MoveCount, Piece, PieceKind, PlyKind, Rank, Square, SquareIndexable,
+    MAX_DEPTH, NUM_RANKS, NUM_SQUARES,
+};
 use crate::coretypes::{Color::*, PieceKind::*};
 use crate::movegen as mg;
 use crate::position::Position;
+use crate::zobrist::{HashKind, ZobristTable};
 
 impl PieceKind {
     /// Default, independent value per piece.
@@ -27,17 +34,112 @@ impl PieceKind {
     }
 }
 
+/// EvalParams holds the piece values and term weights used by the evaluation function,
+/// so they can be tuned without editing source.
+/// The default parameters are the engine's built in values.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EvalParams {
+    /// Value of each pawn, knight, bishop, rook, and queen. Kings are not valued.
+    pub pawn_cp: Cp,
+    pub knight_cp: Cp,
+    pub bishop_cp: Cp,
+    pub rook_cp: Cp,
+    pub queen_cp: Cp,
+    /// Value of each square a piece can move to, per piece kind.
+    pub knight_mobility_cp: Cp,
+    pub bishop_mobility_cp: Cp,
+    pub rook_mobility_cp: Cp,
+    pub queen_mobility_cp: Cp,
+    /// Value of each shield pawn directly in front of king, and one rank further.
+    pub shield_near_cp: Cp,
+    pub shield_far_cp: Cp,
+    /// Value of each king attacker weight unit, multiplied again by the number of attackers.
+    pub king_attack_cp: Cp,
+    /// Base value of a passed pawn.
+    pub pass_pawn_cp: Cp,
+    /// Bonus value of passed pawn per rank, relative to its player.
+    pub pass_pawn_rank_cp: [CpKind; NUM_RANKS],
+    /// Bonus value of a passed pawn defended by a friendly pawn.
+    pub pass_pawn_protected_cp: Cp,
+    /// Bonus value of a passed pawn with a passed pawn on an adjacent file.
+    pub pass_pawn_connected_cp: Cp,
+    /// Amount a draw is scored below even for the engine. Negative values prefer draws.
+    pub contempt_cp: Cp,
+}
+
+impl EvalParams {
+    /// Built in evaluation parameters.
+    pub const DEFAULT: EvalParams = EvalParams {
+        pawn_cp: Pawn.centipawns(),
+        knight_cp: Knight.centipawns(),
+        bishop_cp: Bishop.centipawns(),
+        rook_cp: Rook.centipawns(),
+        queen_cp: Queen.centipawns(),
+        knight_mobility_cp: Cp(4),
+        bishop_mobility_cp: Cp(3),
+        rook_mobility_cp: Cp(2),
+        queen_mobility_cp: Cp(1),
+        shield_near_cp: Cp(12),
+        shield_far_cp: Cp(6),
+        king_attack_cp: Cp(2),
+        pass_pawn_cp: Cp(20),
+        // Pass pawns are very valuable on rank 7.
+        pass_pawn_rank_cp: [0, 0, 1, 2, 10, 50, 250, 900],
+        pass_pawn_protected_cp: Cp(15),
+        pass_pawn_connected_cp: Cp(10),
+        contempt_cp: Cp(50),
+    };
+
+    /// Returns the value of a piece kind. Kings use their default value.
+    pub const fn piece_cp(&self, piece_kind: PieceKind) -> Cp {
+        match piece_kind {
+            Pawn => self.pawn_cp,
+            Knight => self.knight_cp,
+            Bishop => self.bishop_cp,
+            Rook => self.rook_cp,
+            Queen => self.queen_cp,
+            King => King.centipawns(),
+        }
+    }
+
+    /// Returns true if the piece values are the default piece values.
+    fn has_default_piece_cps(&self) -> bool {
+        PieceKind::iter().all(|pk| self.piece_cp(pk) == pk.centipawns())
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 // Evaluation Constants
-const MOBILITY_CP: Cp = Cp(1);
+// Game phase of a position with all non-pawn material on the board.
+pub const MAX_PHASE: u8 = 24;
+// Halfmove clock from which scores start shrinking towards a fifty-move rule draw.
+const FIFTY_MOVE_SCALE_START: MoveCount = 80;
+// Divisor of the largest reduction a score receives from the fifty-move scale.
+const FIFTY_MOVE_SCALE_DIVISOR: i32 = 8;
+// Halfmove clock at which a game is drawn by the fifty-move rule.
+const FIFTY_MOVE_HALFMOVES: MoveCount = 100;
+// Value per square the lone king is driven towards its mating edge or corner.
+const MATE_DRIVE_CP: Cp = Cp(20);
+// Value per square the winning king is closer to the lone king.
+const MATE_KING_CP: Cp = Cp(5);
+// Scale of a score for a drawn rook pawn and wrong colored bishop endgame.
+const WRONG_BISHOP_SCALE: f64 = 0.05;
 
 // Relative Evaluation Functions
 
-/// Given a terminal node, return a score representing a checkmate or a draw.
-/// The return score is relative to the player to move.
-pub fn terminal(position: &Position) -> Cp {
+/// Given a terminal node `distance` plies from the root, return a score representing
+/// a checkmate or a draw. The return score is relative to the player to move.
+/// Checkmates nearer the root score further from zero, so that faster mates are preferred.
+pub fn terminal(position: &Position, distance: PlyKind) -> Cp {
+    // A terminal node has no legal moves, so it is checkmate if in check, otherwise stalemate.
     // Checkmate position is strictly bad for player to move.
-    if position.is_checkmate() {
-        -Cp::CHECKMATE
+    if position.is_in_check() {
+        -Cp::CHECKMATE + Cp(distance.min(MAX_DEPTH) as CpKind)
     } else {
         Cp::STALEMATE
     }
@@ -56,7 +158,12 @@ pub fn draw(is_engine: bool, contempt: Cp) -> Cp {
 /// Primary hand-crafted evaluate function for engine, with return relative to player to move.
 /// Statically evaluates a non-terminal position.
 pub fn evaluate(position: &Position) -> Cp {
-    evaluate_abs(position) * position.player.sign()
+    evaluate_with(position, &EvalParams::DEFAULT)
+}
+
+/// Evaluate function relative to player to move, using the given evaluation parameters.
+pub fn evaluate_with(position: &Position, params: &EvalParams) -> Cp {
+    evaluate_abs_with(position, params) * position.player.sign()
 }
 
 // Absolute Evaluation Functions
@@ -77,180 +184,750 @@ pub fn terminal_abs(position: &Position) -> Cp {
 /// Primary evaluate function for engine.
 /// Statically evaluate a non-terminal position using a variety of heuristics.
 pub fn evaluate_abs(position: &Position) -> Cp {
-    let cp_material = material(position);
+    evaluate_abs_with(position, &EvalParams::DEFAULT)
+}
+
+/// Absolute evaluate function using the given evaluation parameters.
+pub fn evaluate_abs_with(position: &Position, params: &EvalParams) -> Cp {
+    fifty_move_scale(position, evaluate_board_abs(position, params))
+}
+
+/// Absolute evaluation of a position before it is scaled by the fifty-move rule,
+/// which depends only on the position's board, castling, and player to move.
+fn evaluate_board_abs(position: &Position, params: &EvalParams) -> Cp {
+    if let Some(cp_basic_endgame) = basic_endgame(position) {
+        return cp_basic_endgame;
+    }
+
+    let cp_material = material_with(position, params);
     let cp_piece_sq = piece_square_lookup(position);
-    let cp_pass_pawns = pass_pawns(position);
+    let cp_pass_pawns = pass_pawns(position, params);
     let cp_xray_king = xray_king_attacks(position);
-    let cp_mobility = mobility(position);
-    let cp_king_safety = king_safety(position);
+    let cp_mobility = mobility(position, White, params) - mobility(position, Black, params);
+    let cp_king_safety = taper(
+        king_safety(position, White, params) - king_safety(position, Black, params),
+        Cp(0),
+        game_phase(position),
+    );
+
+    let cp_imbalance = material_imbalance(position);
+
+    let cp_total = cp_material
+        + cp_piece_sq
+        + cp_pass_pawns
+        + cp_xray_king
+        + cp_mobility
+        + cp_king_safety
+        + cp_imbalance;
+    Cp((cp_total.0 as f64 * endgame_scale(position)).round() as CpKind)
+}
+
+/// A cached absolute evaluation, before scaling by the fifty-move rule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct EvalEntry {
+    hash: HashKind,
+    cp: Cp,
+}
+
+/// EvalCache caches static evaluations keyed by position hash, so that positions evaluated
+/// again, such as by quiescence searches of transposed lines, are not evaluated twice.
+/// Evaluation is deterministic for a set of parameters, so entries are only invalidated
+/// when evaluated with different parameters. An entry is always replaced by a newer one
+/// with the same index.
+///
+/// Positions are probed with the hash a search already keeps for each node,
+/// which must be made with the cache's zobrist table.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    ztable: ZobristTable,
+    params: EvalParams,
+    entries: Vec<Option<EvalEntry>>,
+    hits: u64,
+}
+
+impl EvalCache {
+    /// Returns a new EvalCache which uses approximately mb megabytes.
+    pub fn with_mb(mb: usize) -> Self {
+        Self::with_mb_and_zobrist(mb, ZobristTable::new())
+    }
+
+    /// Returns a new EvalCache which uses approximately mb megabytes and hashes with ztable.
+    /// Positions are evaluated with the default evaluation parameters until they are set.
+    pub fn with_mb_and_zobrist(mb: usize, ztable: ZobristTable) -> Self {
+        let entry_capacity = (mb * 1_000_000) / mem::size_of::<Option<EvalEntry>>();
+        assert!(entry_capacity > 0, "EvalCache requires a non-zero capacity");
+        Self {
+            ztable,
+            params: EvalParams::DEFAULT,
+            entries: vec![None; entry_capacity],
+            hits: 0,
+        }
+    }
+
+    /// Returns the number of entries this cache holds.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of evaluations found in this cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the zobrist table that hashes given to this cache are made with.
+    pub fn zobrist_table(&self) -> &ZobristTable {
+        &self.ztable
+    }
+
+    /// Returns the evaluation parameters positions are evaluated with.
+    pub fn params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    /// Set the evaluation parameters positions are evaluated with.
+    /// The cache is cleared if the parameters are changed.
+    pub fn set_params(&mut self, params: &EvalParams) {
+        if *params != self.params {
+            self.clear();
+            self.params = *params;
+        }
+    }
+
+    /// Remove all cached evaluations from this cache.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// Evaluate function relative to player to move, as `evaluate_with`,
+    /// returning the cached evaluation if the position with `hash` has been evaluated before.
+    pub fn evaluate(&mut self, position: &Position, hash: HashKind) -> Cp {
+        self.evaluate_abs(position, hash) * position.player.sign()
+    }
+
+    /// Absolute evaluate function, as `evaluate_abs_with`,
+    /// returning the cached evaluation if the position with `hash` has been evaluated before.
+    pub fn evaluate_abs(&mut self, position: &Position, hash: HashKind) -> Cp {
+        debug_assert_eq!(hash, self.ztable.generate_hash(position.into()));
+        let index = (hash % self.entries.len() as HashKind) as usize;
+        let cp = match self.entries[index] {
+            Some(entry) if entry.hash == hash => {
+                self.hits += 1;
+                entry.cp
+            }
+            _ => {
+                let cp = evaluate_board_abs(position, &self.params);
+                self.entries[index] = Some(EvalEntry { hash, cp });
+                cp
+            }
+        };
+        fifty_move_scale(position, cp)
+    }
+}
+
+/// A single term of an EvalTrace, with the absolute value of each player and the
+/// net value that the term contributes to an evaluation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EvalTerm {
+    pub name: &'static str,
+    pub white: Cp,
+    pub black: Cp,
+    pub total: Cp,
+}
+
+impl EvalTerm {
+    fn new(name: &'static str, white: Cp, black: Cp, total: Cp) -> Self {
+        Self {
+            name,
+            white,
+            black,
+            total,
+        }
+    }
+}
+
+/// EvalTrace is the static evaluation of a position decomposed by term, for debugging.
+/// The sum of all term totals, scaled for drawish endgames and the fifty-move rule,
+/// is the score of `evaluate_abs_with`.
+/// A position with a known basic endgame score has no terms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalTrace {
+    pub terms: Vec<EvalTerm>,
+    pub basic_endgame: Option<Cp>,
+    pub endgame_scale: f64,
+    pub total: Cp,
+}
+
+impl EvalTrace {
+    /// Trace the absolute static evaluation of a non-terminal position.
+    pub fn new(position: &Position, params: &EvalParams) -> Self {
+        let total = evaluate_abs_with(position, params);
+        if let Some(cp_basic_endgame) = basic_endgame(position) {
+            return Self {
+                terms: Vec::new(),
+                basic_endgame: Some(cp_basic_endgame),
+                endgame_scale: 1.0,
+                total,
+            };
+        }
+
+        let phase = game_phase(position);
+        let by_color = |f: &dyn Fn(Color) -> Cp| (f(White), f(Black));
+        let material_color = |color: Color| {
+            PieceKind::iter()
+                .filter(|pk| *pk != King)
+                .map(|pk| params.piece_cp(pk) * position.pieces.count(color, pk))
+                .fold(Cp(0), |acc, cp| acc + cp)
+        };
+        let king_safety_color = |color| taper(king_safety(position, color, params), Cp(0), phase);
+        let imbalance_color = |color| {
+            let (mg, eg) = imbalance_scores(position, color);
+            taper(mg, eg, phase)
+        };
+
+        let (w_material, b_material) = by_color(&material_color);
+        let (w_pst, b_pst) = by_color(&|color| pst_score(position, color));
+        let (w_mobility, b_mobility) = by_color(&|color| mobility(position, color, params));
+        let (w_king, b_king) = by_color(&king_safety_color);
+        let (w_pass, b_pass) = by_color(&|color| pass_pawns_score(position, color, params));
+        let (w_xray, b_xray) = by_color(&|color| xray_king_score(position, color));
+        let (w_imbalance, b_imbalance) = by_color(&imbalance_color);
+
+        let terms = vec![
+            EvalTerm::new(
+                "Material",
+                w_material,
+                b_material,
+                material_with(position, params),
+            ),
+            EvalTerm::new("PST", w_pst, b_pst, piece_square_lookup(position)),
+            EvalTerm::new("Mobility", w_mobility, b_mobility, w_mobility - b_mobility),
+            EvalTerm::new(
+                "King safety",
+                w_king,
+                b_king,
+                taper(
+                    king_safety(position, White, params) - king_safety(position, Black, params),
+                    Cp(0),
+                    phase,
+                ),
+            ),
+            EvalTerm::new("Passed pawns", w_pass, b_pass, pass_pawns(position, params)),
+            EvalTerm::new("Xray king", w_xray, b_xray, xray_king_attacks(position)),
+            EvalTerm::new(
+                "Imbalance",
+                w_imbalance,
+                b_imbalance,
+                material_imbalance(position),
+            ),
+        ];
+
+        Self {
+            terms,
+            basic_endgame: None,
+            endgame_scale: endgame_scale(position),
+            total,
+        }
+    }
+
+    /// Returns the sum of all term totals, before any scaling.
+    pub fn terms_total(&self) -> Cp {
+        self.terms.iter().fold(Cp(0), |acc, term| acc + term.total)
+    }
+}
+
+impl Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(cp_basic_endgame) = self.basic_endgame {
+            writeln!(f, "basic endgame {}", cp_basic_endgame)?;
+        } else {
+            writeln!(
+                f,
+                "{:>12} | {:>7} | {:>7} | {:>7}",
+                "term", "white", "black", "total"
+            )?;
+            for term in &self.terms {
+                writeln!(
+                    f,
+                    "{:>12} | {:>7} | {:>7} | {:>7}",
+                    term.name,
+                    term.white.to_string(),
+                    term.black.to_string(),
+                    term.total.to_string()
+                )?;
+            }
+            writeln!(f, "terms total {}", self.terms_total())?;
+            writeln!(f, "endgame scale {:.2}", self.endgame_scale)?;
+        }
+        write!(f, "total {} (white side)", self.total)
+    }
+}
+
+/// Returns a factor from 0 to 1 to scale a score by, for endgames which are more drawn
+/// than their material suggests.
+/// * Wrong bishop: King, bishop, and rook pawns against a lone king is drawn when the bishop
+///   does not control the promotion square, and the lone king can reach it first.
+pub fn endgame_scale(position: &Position) -> f64 {
+    match Color::iter().any(|strong| is_wrong_bishop_draw(position, strong)) {
+        true => WRONG_BISHOP_SCALE,
+        false => 1.0,
+    }
+}
+
+/// Returns true if strong has king, bishop, and pawns on a single rook file against a lone
+/// king, where the bishop cannot control the promotion square and the lone king is at least
+/// as close to the promotion square as the most advanced pawn.
+fn is_wrong_bishop_draw(position: &Position, strong: Color) -> bool {
+    let pieces = position.pieces();
+    let weak = !strong;
+    let pawns = pieces[(strong, Pawn)];
+    let num_pieces = pieces.color_occupied(strong).count_squares();
+    if pieces.color_occupied(weak).count_squares() != 1
+        || pieces.count(strong, Bishop) != 1
+        || pawns.is_empty()
+        || num_pieces != 2 + pawns.count_squares()
+    {
+        return false;
+    }
+
+    let file = if Bitboard::FILE_A.contains(&pawns) {
+        File::A
+    } else if Bitboard::FILE_H.contains(&pawns) {
+        File::H
+    } else {
+        return false;
+    };
+    let promotion = match strong {
+        White => Square::from((file, Rank::R8)),
+        Black => Square::from((file, Rank::R1)),
+    };
+    let bishop = pieces[(strong, Bishop)].get_lowest_square().unwrap();
+    if is_light_square(bishop) == is_light_square(promotion) {
+        return false;
+    }
+
+    let weak_king = pieces[(weak, King)].get_lowest_square().unwrap();
+    let pawn_distance = pawns
+        .iter_squares()
+        .map(|pawn| (promotion.rank() as CpKind - pawn.rank() as CpKind).abs())
+        .min()
+        .unwrap();
+    let tempo = (position.player == weak) as CpKind;
+    weak_king.chebyshev_distance(promotion) as CpKind <= pawn_distance + tempo
+}
+
+/// Returns a score for a basic checkmate of a lone king, or None if the material is not
+/// king and rook, king and queen, or king, bishop, and knight against a lone king.
+/// The winning side is rewarded for driving the lone king to the edge, or for king, bishop,
+/// and knight to a corner of the bishop's square color, and for bringing its king closer.
+pub fn basic_endgame(position: &Position) -> Option<Cp> {
+    let pieces = position.pieces();
+    let (strong, weak) = match (
+        pieces.color_occupied(White).count_squares(),
+        pieces.color_occupied(Black).count_squares(),
+    ) {
+        (_, 1) => (White, Black),
+        (1, _) => (Black, White),
+        _ => return None,
+    };
+
+    let count = |piece_kind| pieces.count(strong, piece_kind);
+    let is_basic = match pieces.color_occupied(strong).count_squares() {
+        2 => count(Rook) == 1 || count(Queen) == 1,
+        3 => count(Bishop) == 1 && count(Knight) == 1,
+        _ => false,
+    };
+    if !is_basic {
+        return None;
+    }
+
+    let strong_king = pieces[(strong, King)].get_lowest_square()?;
+    let weak_king = pieces[(weak, King)].get_lowest_square()?;
 
-    let cp_total =
-        cp_material + cp_piece_sq + cp_pass_pawns + cp_xray_king + cp_mobility + cp_king_safety;
-    cp_total
+    // Bishop and knight can only mate in a corner of the bishop's square color.
+    let drive = match pieces[(strong, Bishop)].get_lowest_square() {
+        Some(bishop) => {
+            let corners = match is_light_square(bishop) {
+                true => [Square::A8, Square::H1],
+                false => [Square::A1, Square::H8],
+            };
+            let corner_distance = corners
+                .iter()
+                .map(|&corner| weak_king.manhattan_distance(corner) as CpKind)
+                .min()?;
+            7 - corner_distance.min(7)
+        }
+        None => CENTER_DISTANCE[weak_king.idx()],
+    };
+    let proximity = 14 - strong_king.manhattan_distance(weak_king) as CpKind;
+
+    let cp_basic_endgame =
+        pieces.total_material(strong) + MATE_DRIVE_CP * Cp(drive) + MATE_KING_CP * Cp(proximity);
+    Some(cp_basic_endgame * strong.sign())
+}
+
+/// Returns true if a square is a light square. A1 is a dark square.
+fn is_light_square(square: Square) -> bool {
+    (square.file() as u8 + square.rank() as u8) % 2 == 1
+}
+
+/// Scales a score towards a draw as the halfmove clock approaches the fifty-move rule,
+/// so a player who is ahead prefers making progress over shuffling pieces.
+/// Scores are unchanged until FIFTY_MOVE_SCALE_START halfmoves,
+/// then shrink linearly by up to 1/FIFTY_MOVE_SCALE_DIVISOR of their value at 100 halfmoves.
+pub fn fifty_move_scale(position: &Position, cp: Cp) -> Cp {
+    let halfmoves = position
+        .halfmoves
+        .clamp(FIFTY_MOVE_SCALE_START, FIFTY_MOVE_HALFMOVES);
+    let span = (FIFTY_MOVE_HALFMOVES - FIFTY_MOVE_SCALE_START) as i32;
+    let progress = (halfmoves - FIFTY_MOVE_SCALE_START) as i32;
+    let divisor = FIFTY_MOVE_SCALE_DIVISOR * span;
+    let scaled = cp.0 as i32 * (divisor - progress) / divisor;
+    Cp(scaled as CpKind)
 }
 
 /// Returns relative strength difference of pieces in position.
 /// Is equivalent of piece_centipawn(White) - pieces_centipawn(Black).
 /// A positive value is an advantage for white, 0 is even, negative is advantage for black.
+/// This reads the incrementally updated material of the position.
 pub fn material(position: &Position) -> Cp {
-    let w_piece_cp: Cp = PieceKind::iter()
-        .map(|pk| pk.centipawns() * position.pieces[(White, pk)].count_squares())
-        .fold(Cp::default(), |acc, value| acc + value);
+    position.material
+}
 
-    let b_piece_cp: Cp = PieceKind::iter()
-        .map(|pk| pk.centipawns() * position.pieces[(Black, pk)].count_squares())
-        .fold(Cp::default(), |acc, value| acc + value);
+/// Returns relative strength difference of pieces in position using the given piece values.
+/// The position's incremental material is used if the piece values are the defaults,
+/// otherwise the board is scanned.
+pub fn material_with(position: &Position, params: &EvalParams) -> Cp {
+    if params.has_default_piece_cps() {
+        return material(position);
+    }
 
-    w_piece_cp - b_piece_cp
+    PieceKind::iter()
+        .map(|pk| {
+            let w_count = position.pieces[(White, pk)].count_squares() as CpKind;
+            let b_count = position.pieces[(Black, pk)].count_squares() as CpKind;
+            params.piece_cp(pk) * Cp(w_count - b_count)
+        })
+        .fold(Cp::default(), |acc, value| acc + value)
 }
 
-pub fn king_safety(position: &Position) -> Cp {
-    let mut cp = Cp(0);
+/// Returns relative strength difference of pieces in position by scanning the board.
+/// This is the non-incremental equivalent of `material`.
+pub fn material_scan(position: &Position) -> Cp {
+    position.pieces.total_material(White) - position.pieces.total_material(Black)
+}
 
-    let occupied = position.pieces.occupied();
-    // Virtual mobility: treat king as a queen and the less squares it can attack the better.
-    let w_sliding = position.pieces[(White, Queen)]
-        | position.pieces[(White, Rook)]
-        | position.pieces[(White, Bishop)];
-    let b_sliding = position.pieces[(Black, Queen)]
-        | position.pieces[(Black, Rook)]
-        | position.pieces[(Black, Bishop)];
-    let w_num_sliding = w_sliding.count_squares();
-    let b_num_sliding = b_sliding.count_squares();
-    let w_king = position.pieces[(White, King)];
-    let b_king = position.pieces[(Black, King)];
+/// Returns bonuses and penalties for combinations of pieces, which are worth
+/// more or less together than the sum of their individual values.
+/// * Bishop pair: Bishops on both light and dark squares cover the whole board.
+/// * Knight pair: Two knights are slightly redundant.
+/// * Rook pair: Two rooks are slightly redundant.
+pub fn material_imbalance(position: &Position) -> Cp {
+    let (w_mg, w_eg) = imbalance_scores(position, White);
+    let (b_mg, b_eg) = imbalance_scores(position, Black);
 
-    let w_king_open_squares = mg::queen_attacks(w_king, occupied).count_squares();
-    let b_king_open_squares = mg::queen_attacks(b_king, occupied).count_squares();
+    taper(w_mg - b_mg, w_eg - b_eg, game_phase(position))
+}
 
-    // The more sliding pieces the enemy has, the more value each open square has.
-    let w_value = b_king_open_squares * w_num_sliding / 2;
-    let b_value = w_king_open_squares * b_num_sliding / 2;
+/// Returns the midgame and endgame material imbalance scores of a player.
+fn imbalance_scores(position: &Position, color: Color) -> (Cp, Cp) {
+    // Midgame and endgame values of each imbalance.
+    const BISHOP_PAIR_CP: (Cp, Cp) = (Cp(30), Cp(50));
+    const KNIGHT_PAIR_CP: (Cp, Cp) = (Cp(-8), Cp(-8));
+    const ROOK_PAIR_CP: (Cp, Cp) = (Cp(-10), Cp(-10));
 
-    let value_diff = Cp(w_value as CpKind - b_value as CpKind);
-    cp += value_diff;
+    let bishops = position.pieces[(color, Bishop)];
+    let has_bishop_pair = (bishops & Bitboard::WHITE_SQUARES).count_squares() > 0
+        && (bishops & Bitboard::BLACK_SQUARES).count_squares() > 0;
+    let has_knight_pair = position.pieces[(color, Knight)].count_squares() >= 2;
+    let has_rook_pair = position.pieces[(color, Rook)].count_squares() >= 2;
 
-    cp
+    [
+        (has_bishop_pair, BISHOP_PAIR_CP),
+        (has_knight_pair, KNIGHT_PAIR_CP),
+        (has_rook_pair, ROOK_PAIR_CP),
+    ]
+    .iter()
+    .filter(|(has_imbalance, _)| *has_imbalance)
+    .fold((Cp(0), Cp(0)), |(mg, eg), (_, (imb_mg, imb_eg))| {
+        (mg + *imb_mg, eg + *imb_eg)
+    })
 }
 
-/// Return value of number of moves that can be made from a position.
-pub fn mobility(position: &Position) -> Cp {
-    let w_attacks = position.attacks(White, position.pieces().occupied());
-    let b_attacks = position.attacks(Black, position.pieces().occupied());
+/// Returns the safety of a player's king, where a negative value is a king in danger.
+/// A king is safer with a pawn shield in front of it, and less safe when it has
+/// open lines to enemy sliding pieces or when enemy pieces attack its surroundings.
+/// King safety only matters in the midgame, so this should be tapered by game phase.
+pub fn king_safety(position: &Position, color: Color, params: &EvalParams) -> Cp {
+    let occupied = position.pieces.occupied();
+    let king = position.pieces[(color, King)];
+    let king_square = king.get_lowest_square().unwrap();
+    let enemy = !color;
+
+    // Virtual mobility: treat king as a queen and the less squares it can attack the better.
+    // The more sliding pieces the enemy has, the more each open square is worth.
+    let enemy_sliding = position.pieces[(enemy, Queen)]
+        | position.pieces[(enemy, Rook)]
+        | position.pieces[(enemy, Bishop)];
+    let open_squares = mg::queen_attacks(king, occupied).count_squares();
+    let open_penalty = Cp((open_squares * enemy_sliding.count_squares() / 2) as CpKind);
+
+    // Pawn shield: friendly pawns on king file and adjacent files, up to two ranks ahead.
+    let king_files = king | king.to_east() | king.to_west();
+    let (near_shield, far_shield) = match color {
+        White => (king_files.to_north(), king_files.to_north().to_north()),
+        Black => (king_files.to_south(), king_files.to_south().to_south()),
+    };
+    let pawns = position.pieces[(color, Pawn)];
+    let shield_bonus = params.shield_near_cp * (pawns & near_shield).count_squares()
+        + params.shield_far_cp * (pawns & far_shield).count_squares();
 
-    let attack_surface_area_diff =
-        w_attacks.count_squares() as CpKind - b_attacks.count_squares() as CpKind;
+    // King zone: squares around the king and the squares in front of those.
+    let king_zone = mg::king_pattern(king_square) | far_shield;
+    let attackers = king_zone.into_iter().fold(Bitboard::EMPTY, |acc, sq| {
+        acc | position.attackers_to(sq, enemy)
+    });
+    let attack_weight: u32 = [(Knight, 2), (Bishop, 2), (Rook, 3), (Queen, 5)]
+        .iter()
+        .map(|&(pk, weight)| (attackers & position.pieces[(enemy, pk)]).count_squares() * weight)
+        .sum();
+    let num_attackers = (attackers & !position.pieces[(enemy, Pawn)]).count_squares();
+    let attack_penalty = params.king_attack_cp * (attack_weight * num_attackers);
 
-    Cp(attack_surface_area_diff) * MOBILITY_CP
+    shield_bonus - open_penalty - attack_penalty
+}
+
+/// Returns the mobility value of a player's knights, bishops, rooks, and queens.
+/// Each piece scores a weighted value for every square it attacks that is not
+/// occupied by a friendly piece, ignoring pins and checks.
+pub fn mobility(position: &Position, color: Color, params: &EvalParams) -> Cp {
+    let occupied = position.pieces().occupied();
+    let available = !position.pieces().color_occupied(color);
+
+    [
+        (Knight, params.knight_mobility_cp),
+        (Bishop, params.bishop_mobility_cp),
+        (Rook, params.rook_mobility_cp),
+        (Queen, params.queen_mobility_cp),
+    ]
+    .iter()
+    .map(|&(pk, weight)| {
+        let num_squares: u32 = position.pieces[(color, pk)]
+            .into_iter()
+            .map(|sq| {
+                let piece = Bitboard::from(sq);
+                let attacks = match pk {
+                    Knight => mg::knight_attacks(piece),
+                    Bishop => mg::bishop_attacks(piece, occupied),
+                    Rook => mg::rook_attacks(piece, occupied),
+                    _ => mg::queen_attacks(piece, occupied),
+                };
+                (attacks & available).count_squares()
+            })
+            .sum();
+        weight * num_squares
+    })
+    .fold(Cp(0), |acc, cp| acc + cp)
 }
 
 /// Returns Centipawn difference for passed pawns.
-pub fn pass_pawns(position: &Position) -> Cp {
-    // Base value of a passed pawn.
-    const SCALAR: Cp = Cp(20);
-    // Bonus value of passed pawn per rank. Pass pawns are very valuable on rank 7.
-    const RANK_CP: [CpKind; NUM_RANKS] = [0, 0, 1, 2, 10, 50, 250, 900];
-    let w_passed: Bitboard = pass_pawns_bb(position, White);
-    let b_passed: Bitboard = pass_pawns_bb(position, Black);
-    let w_num_passed = w_passed.count_squares() as CpKind;
-    let b_num_passed = b_passed.count_squares() as CpKind;
-
-    // Sum the bonus rank value of each pass pawn.
-    let w_rank_bonus = w_passed
+pub fn pass_pawns(position: &Position, params: &EvalParams) -> Cp {
+    pass_pawns_score(position, White, params) - pass_pawns_score(position, Black, params)
+}
+
+/// Returns the value of all of a player's passed pawns.
+/// Each passed pawn has a base value with a bonus that grows as it advances.
+/// Passed pawns that are protected by a friendly pawn, or connected to another
+/// passed pawn on an adjacent file, get extra credit.
+fn pass_pawns_score(position: &Position, color: Color, params: &EvalParams) -> Cp {
+    let passed = passed_pawns(position, color);
+    let protected = passed & mg::pawn_attacks(position.pieces[(color, Pawn)], color);
+    let passed_files = passed
         .into_iter()
-        .map(|sq| sq.rank())
-        .fold(Cp(0), |acc, rank| acc + Cp(RANK_CP[rank as usize]));
-    let b_rank_bonus = b_passed
+        .fold(Bitboard::EMPTY, |acc, sq| acc | Bitboard::from(sq.file()));
+    let connected = passed & (passed_files.to_east() | passed_files.to_west());
+
+    let rank_bonus = passed
         .into_iter()
-        .map(|sq| sq.rank().flip())
-        .fold(Cp(0), |acc, rank| acc + Cp(RANK_CP[rank as usize]));
+        .map(|sq| match color {
+            White => sq.rank(),
+            Black => sq.rank().flip(),
+        })
+        .fold(Cp(0), |acc, rank| {
+            acc + Cp(params.pass_pawn_rank_cp[rank as usize])
+        });
 
-    Cp(w_num_passed - b_num_passed) * SCALAR + w_rank_bonus - b_rank_bonus
+    params.pass_pawn_cp * passed.count_squares()
+        + rank_bonus
+        + params.pass_pawn_protected_cp * protected.count_squares()
+        + params.pass_pawn_connected_cp * connected.count_squares()
 }
 
 /// Returns value from sliding pieces attacking opposing king on otherwise empty chessboard.
 pub fn xray_king_attacks(position: &Position) -> Cp {
+    xray_king_score(position, White) - xray_king_score(position, Black)
+}
+
+/// Returns value of a player's sliding pieces which attack the opposing king
+/// on an otherwise empty chessboard.
+fn xray_king_score(position: &Position, color: Color) -> Cp {
     // Base value of xray attackers.
     const SCALAR: Cp = Cp(8);
-    let w_king = position.pieces[(White, King)].get_lowest_square().unwrap();
-    let b_king = position.pieces[(Black, King)].get_lowest_square().unwrap();
-    let w_king_ortho = Bitboard::from(w_king.file()) | Bitboard::from(w_king.rank());
-    let b_king_ortho = Bitboard::from(b_king.file()) | Bitboard::from(b_king.rank());
-    let w_king_diags = mg::bishop_pattern(w_king);
-    let b_king_diags = mg::bishop_pattern(b_king);
+    let enemy_king = position.pieces[(!color, King)].get_lowest_square().unwrap();
+    let king_ortho = Bitboard::from(enemy_king.file()) | Bitboard::from(enemy_king.rank());
+    let king_diags = mg::bishop_pattern(enemy_king);
 
-    let w_diags = position.pieces[(White, Queen)] | position.pieces[(White, Bishop)];
-    let b_diags = position.pieces[(Black, Queen)] | position.pieces[(Black, Bishop)];
-    let w_ortho = position.pieces[(White, Queen)] | position.pieces[(White, Rook)];
-    let b_ortho = position.pieces[(Black, Queen)] | position.pieces[(Black, Rook)];
+    let diags = position.pieces[(color, Queen)] | position.pieces[(color, Bishop)];
+    let ortho = position.pieces[(color, Queen)] | position.pieces[(color, Rook)];
+    let xray_attackers_bb = (king_diags & diags) | (king_ortho & ortho);
 
-    let w_xray_attackers_bb = (b_king_diags & w_diags) | (b_king_ortho & w_ortho);
-    let b_xray_attackers_bb = (w_king_diags & b_diags) | (w_king_ortho & b_ortho);
-
-    let w_xray_attackers: CpKind = w_xray_attackers_bb.count_squares() as CpKind;
-    let b_xray_attackers: CpKind = b_xray_attackers_bb.count_squares() as CpKind;
-
-    Cp(w_xray_attackers - b_xray_attackers) * SCALAR
+    SCALAR * xray_attackers_bb.count_squares()
 }
 
 /// Returns value from looking up each piece square in precalculated tables.
+/// Midgame and endgame tables are interpolated by the game phase of the position.
+/// This reads the incrementally updated table scores of the position.
 pub fn piece_square_lookup(position: &Position) -> Cp {
-    let mut w_values = Cp(0);
-    position.pieces[(White, Pawn)]
-        .into_iter()
-        .for_each(|sq| w_values += Cp(MG_PAWN_TABLE[sq.idx()]));
-    position.pieces[(White, Knight)]
-        .into_iter()
-        .for_each(|sq| w_values += Cp(MG_KNIGHT_TABLE[sq.idx()]));
-    position.pieces[(White, Bishop)]
-        .into_iter()
-        .for_each(|sq| w_values += Cp(MG_BISHOP_TABLE[sq.idx()]));
-    position.pieces[(White, King)]
-        .into_iter()
-        .for_each(|sq| w_values += Cp(MG_KING_TABLE[sq.idx()]));
+    taper(position.pst_mg, position.pst_eg, game_phase(position))
+}
 
-    let mut b_values = Cp(0);
-    position.pieces[(Black, Pawn)]
-        .into_iter()
-        .for_each(|sq| b_values += Cp(MG_PAWN_TABLE[sq.flip_rank().idx()]));
-    position.pieces[(Black, Knight)]
-        .into_iter()
-        .for_each(|sq| b_values += Cp(MG_KNIGHT_TABLE[sq.flip_rank().idx()]));
-    position.pieces[(Black, Bishop)]
-        .into_iter()
-        .for_each(|sq| b_values += Cp(MG_BISHOP_TABLE[sq.flip_rank().idx()]));
-    position.pieces[(Black, King)]
-        .into_iter()
-        .for_each(|sq| b_values += Cp(MG_KING_TABLE[sq.flip_rank().idx()]));
+/// Returns the absolute material, midgame table, and endgame table values of a single piece.
+/// These are the amounts a position's accumulators change by when the piece is placed or removed.
+pub(crate) fn piece_square_values(piece: Piece, square: Square) -> (Cp, Cp, Cp) {
+    let (mg_table, eg_table) = piece_square_tables(piece.piece_kind);
+    let (idx, sign) = match piece.color {
+        White => (square.idx(), Cp(1)),
+        Black => (square.flip_rank().idx(), Cp(-1)),
+    };
+
+    (
+        piece.piece_kind.centipawns() * sign,
+        Cp(mg_table[idx]) * sign,
+        Cp(eg_table[idx]) * sign,
+    )
+}
+
+/// Returns the sum of positional bonuses from piece-square tables for all of a player's pieces.
+/// Tables are from White's perspective, so Black squares are flipped.
+pub fn pst_score(position: &Position, color: Color) -> Cp {
+    let mut mg_values = Cp(0);
+    let mut eg_values = Cp(0);
+
+    for pk in PieceKind::iter() {
+        let (mg_table, eg_table) = piece_square_tables(pk);
+        for sq in position.pieces[(color, pk)] {
+            let idx = match color {
+                White => sq.idx(),
+                Black => sq.flip_rank().idx(),
+            };
+            mg_values += Cp(mg_table[idx]);
+            eg_values += Cp(eg_table[idx]);
+        }
+    }
+
+    taper(mg_values, eg_values, game_phase(position))
+}
+
+/// Returns the midgame and endgame piece-square tables of a piece kind.
+fn piece_square_tables(
+    pk: PieceKind,
+) -> (
+    &'static [CpKind; NUM_SQUARES],
+    &'static [CpKind; NUM_SQUARES],
+) {
+    match pk {
+        Pawn => (&MG_PAWN_TABLE, &EG_PAWN_TABLE),
+        Knight => (&MG_KNIGHT_TABLE, &EG_KNIGHT_TABLE),
+        Bishop => (&MG_BISHOP_TABLE, &EG_BISHOP_TABLE),
+        Rook => (&MG_ROOK_TABLE, &EG_ROOK_TABLE),
+        Queen => (&MG_QUEEN_TABLE, &EG_QUEEN_TABLE),
+        King => (&MG_KING_TABLE, &EG_KING_TABLE),
+    }
+}
+
+/// Returns the game phase of a position, derived from remaining non-pawn material.
+/// Each Queen counts 4, Rook counts 2, and Bishop or Knight counts 1, clamped to MAX_PHASE.
+/// MAX_PHASE is a full midgame and 0 is a pawn endgame.
+pub fn game_phase(position: &Position) -> u8 {
+    let phase: u32 = [(Knight, 1), (Bishop, 1), (Rook, 2), (Queen, 4)]
+        .iter()
+        .map(|&(pk, weight)| {
+            let count = position.pieces.count(White, pk) + position.pieces.count(Black, pk);
+            count * weight
+        })
+        .sum();
+
+    phase.min(MAX_PHASE as u32) as u8
+}
 
-    w_values - b_values
+/// Interpolates between a midgame and an endgame score by the game phase.
+pub fn taper(mg: Cp, eg: Cp, phase: u8) -> Cp {
+    let phase = phase.min(MAX_PHASE) as i32;
+    let max_phase = MAX_PHASE as i32;
+    let tapered = (mg.0 as i32 * phase + eg.0 as i32 * (max_phase - phase)) / max_phase;
+    Cp(tapered as CpKind)
 }
 
 /// A pass pawn is one with no opponent pawns in front of it on same or adjacent files.
 /// This returns a bitboard with all pass pawns of given player.
-#[inline]
-fn pass_pawns_bb(position: &Position, player: Color) -> Bitboard {
-    use Bitboard as Bb;
-
-    let opponent_pawns = position.pieces[(!player, Pawn)];
+pub fn passed_pawns(position: &Position, color: Color) -> Bitboard {
+    let opponent_pawns = position.pieces[(!color, Pawn)];
+    let patterns = match color {
+        White => &W_PASS_PAWN_PATTERN,
+        Black => &B_PASS_PAWN_PATTERN,
+    };
 
-    let spans = opponent_pawns
+    position.pieces[(color, Pawn)]
         .into_iter()
-        .map(|sq| {
-            let file = sq.file();
-            let mut span = Bb::from(file);
-            // Working with opponent pieces, so if finding w_pass, need to clear above sq.
-            match player {
-                Color::White => span.clear_square_and_above(sq),
-                Color::Black => span.clear_square_and_below(sq),
-            };
+        .filter(|sq| (patterns[sq.idx()] & opponent_pawns).is_empty())
+        .fold(Bitboard::EMPTY, |acc, sq| acc | Bitboard::from(sq))
+}
+
+// Tuning Functions
 
-            span | span.to_east() | span.to_west()
+/// Returns the Texel tuning loss of evaluation parameters over labeled positions.
+/// Each label is the game result for White, where 1 is a win, 0.5 is a draw, and 0 is a loss.
+/// The loss is the mean squared error between each label and the expected result
+/// predicted by the position's static evaluation, which is scaled by `k`.
+pub fn texel_loss(positions: &[(Position, f64)], params: &EvalParams, k: f64) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+
+    let squared_error: f64 = positions
+        .iter()
+        .map(|(position, result)| {
+            let cp = evaluate_abs_with(position, params);
+            (result - expected_result(cp, k)).powi(2)
         })
-        .fold(Bitboard::EMPTY, |acc, bb| acc | bb);
+        .sum();
+
+    squared_error / positions.len() as f64
+}
+
+/// Returns the scaling constant `k` which minimizes the Texel loss of the default
+/// evaluation parameters over labeled positions. The loss is searched for over `0.0..=10.0`.
+pub fn best_k(positions: &[(Position, f64)]) -> f64 {
+    const ITERATIONS: usize = 100;
+    let params = EvalParams::DEFAULT;
+    let (mut low, mut high) = (0.0, 10.0);
+
+    // Ternary search, as the loss has a single minimum over k.
+    for _ in 0..ITERATIONS {
+        let mid_low = low + (high - low) / 3.0;
+        let mid_high = high - (high - low) / 3.0;
+        if texel_loss(positions, &params, mid_low) < texel_loss(positions, &params, mid_high) {
+            high = mid_high;
+        } else {
+            low = mid_low;
+        }
+    }
+
+    (low + high) / 2.0
+}
 
-    // Any pawn not in spans is a pass pawn.
-    position.pieces[(player, Pawn)] & !spans
+/// Returns the expected result for White of an absolute score, between 0 and 1.
+fn expected_result(cp: Cp, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * cp.0 as f64 / 400.0))
 }
 
 // Piece Square Tables
@@ -265,7 +942,7 @@ fn pass_pawns_bb(position: &Position, player: Color) -> Bitboard {
 /// TODO:
 /// Dynamically change to consider where king is?
 #[rustfmt::skip]
-const MG_PAWN_TABLE: [CpKind; NUM_SQUARES] = [
+pub const MG_PAWN_TABLE: [CpKind; NUM_SQUARES] = [
     0,   0,   0,   0,   0,   0,   0,   0,
     5,   1,   0, -20, -20,   0,   1,   5,
     5,  -2,   0,   0,   0,   0,  -2,   5,
@@ -279,7 +956,7 @@ const MG_PAWN_TABLE: [CpKind; NUM_SQUARES] = [
 /// Midgame Knight square values
 /// Encourage central squares, penalize edge squares.
 #[rustfmt::skip]
-const MG_KNIGHT_TABLE: [CpKind; NUM_SQUARES] = [
+pub const MG_KNIGHT_TABLE: [CpKind; NUM_SQUARES] = [
     -50, -30, -20, -20, -20, -20, -30, -50,
     -20,   0,   0,   5,   5,   0,   0, -20,
     -10,   0,  10,  15,  15,  10,   0, -10,
@@ -293,7 +970,7 @@ const MG_KNIGHT_TABLE: [CpKind; NUM_SQUARES] = [
 /// Midgame Bishop square values
 /// Avoid corners and borders
 #[rustfmt::skip]
-const MG_BISHOP_TABLE: [CpKind; NUM_SQUARES] = [
+pub const MG_BISHOP_TABLE: [CpKind; NUM_SQUARES] = [
     -20,  -8, -10,  -8,  -8, -10,  -8, -20,
      -8,   5,   0,   0,   0,   0,   5,  -8,
      -8,  10,  10,  10,  10,  10,  10,  -8,
@@ -304,10 +981,38 @@ const MG_BISHOP_TABLE: [CpKind; NUM_SQUARES] = [
     -20,  -8,  -8,  -8,  -8,  -8,  -8, -20,
 ];
 
+/// Midgame Rook square values
+/// Encourage central files and the 7th rank.
+#[rustfmt::skip]
+pub const MG_ROOK_TABLE: [CpKind; NUM_SQUARES] = [
+     0,   0,   0,   5,   5,   0,   0,   0,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     5,  10,  10,  10,  10,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+/// Midgame Queen square values
+/// Slightly prefer central squares, avoid corners.
+#[rustfmt::skip]
+pub const MG_QUEEN_TABLE: [CpKind; NUM_SQUARES] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
 /// Midgame King square values
 /// Keep king in corner, in pawn shelter.
 #[rustfmt::skip]
-const MG_KING_TABLE: [CpKind; NUM_SQUARES] = [
+pub const MG_KING_TABLE: [CpKind; NUM_SQUARES] = [
      20,  30,  10,   0,   0,  10,  30,  20,
      20,  20,   0,   0,   0,   0,  20,  20,
     -10, -10, -15, -15, -15, -15, -10, -10,
@@ -318,83 +1023,165 @@ const MG_KING_TABLE: [CpKind; NUM_SQUARES] = [
       0,   0,   0,   0,   0,   0,   0,   0,
 ];
 
+/// Endgame Pawn square values
+/// Pawns are worth more the closer they are to promoting.
+#[rustfmt::skip]
+pub const EG_PAWN_TABLE: [CpKind; NUM_SQUARES] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,   5,   5,   5,   5,   5,   5,   5,
+    10,  10,  10,  10,  10,  10,  10,  10,
+    20,  20,  20,  20,  20,  20,  20,  20,
+    35,  35,  35,  35,  35,  35,  35,  35,
+    50,  50,  50,  50,  50,  50,  50,  50,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+/// Endgame Knight square values
+/// Encourage central squares, penalize edge squares.
+#[rustfmt::skip]
+pub const EG_KNIGHT_TABLE: [CpKind; NUM_SQUARES] = [
+    -50, -30, -20, -20, -20, -20, -30, -50,
+    -30, -10,   0,   0,   0,   0, -10, -30,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -20,   0,  15,  20,  20,  15,   0, -20,
+    -20,   0,  15,  20,  20,  15,   0, -20,
+    -20,   0,  10,  15,  15,  10,   0, -20,
+    -30, -10,   0,   0,   0,   0, -10, -30,
+    -50, -30, -20, -20, -20, -20, -30, -50,
+];
+
+/// Endgame Bishop square values
+/// Avoid corners and borders
+#[rustfmt::skip]
+pub const EG_BISHOP_TABLE: [CpKind; NUM_SQUARES] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+/// Endgame Rook square values
+/// Rooks are active on any square, with a small bonus on the 7th rank.
+#[rustfmt::skip]
+pub const EG_ROOK_TABLE: [CpKind; NUM_SQUARES] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+/// Endgame Queen square values
+/// Centralize queen.
+#[rustfmt::skip]
+pub const EG_QUEEN_TABLE: [CpKind; NUM_SQUARES] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+     -5,   0,  10,  15,  15,  10,   0,  -5,
+     -5,   0,  10,  15,  15,  10,   0,  -5,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+/// Endgame King square values
+/// Centralize king, it is an active piece once most material is gone.
+#[rustfmt::skip]
+pub const EG_KING_TABLE: [CpKind; NUM_SQUARES] = [
+    -50, -30, -30, -30, -30, -30, -30, -50,
+    -30, -20, -10, -10, -10, -10, -20, -30,
+    -30, -10,  20,  25,  25,  20, -10, -30,
+    -30, -10,  25,  35,  35,  25, -10, -30,
+    -30, -10,  25,  35,  35,  25, -10, -30,
+    -30, -10,  20,  25,  25,  20, -10, -30,
+    -30, -20, -10, -10, -10, -10, -20, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// Distance of each square from the 4 center squares, in king steps without diagonals.
+#[rustfmt::skip]
+pub const CENTER_DISTANCE: [CpKind; NUM_SQUARES] = [
+    6, 5, 4, 3, 3, 4, 5, 6,
+    5, 4, 3, 2, 2, 3, 4, 5,
+    4, 3, 2, 1, 1, 2, 3, 4,
+    3, 2, 1, 0, 0, 1, 2, 3,
+    3, 2, 1, 0, 0, 1, 2, 3,
+    4, 3, 2, 1, 1, 2, 3, 4,
+    5, 4, 3, 2, 2, 3, 4, 5,
+    6, 5, 4, 3, 3, 4, 5, 6,
+];
+
 // Const Data Generation
 
-/// Warning: Do not use, unfinished.
-pub const PASS_PAWN_SIZE: usize = (NUM_SQUARES - 24) * 2;
-pub const PASS_PAWN_PATTERN: [Bitboard; PASS_PAWN_SIZE] = generate_pass_pawn_pattern();
+/// Front span of a White pawn for each square: all squares ahead on the same and adjacent files.
+/// A White pawn is passed if no Black pawns are in its front span.
+pub const W_PASS_PAWN_PATTERN: [Bitboard; NUM_SQUARES] = generate_pass_pawn_patterns(White);
+/// Front span of a Black pawn for each square: all squares ahead on the same and adjacent files.
+/// A Black pawn is passed if no White pawns are in its front span.
+pub const B_PASS_PAWN_PATTERN: [Bitboard; NUM_SQUARES] = generate_pass_pawn_patterns(Black);
 
-// Repeats the form: array[num] = func[num];
+// Repeats the form: array[num] = func(num, arg);
 // where $array and $func are identifiers, followed by 1 or more literals to repeat on.
 // Need to use a macro because loops are not allowed in const fn currently.
-macro_rules! w_repeat_for_each {
-    ($array:ident, $func:ident, $($numbers:literal),+) => {
+macro_rules! repeat_for_each {
+    ($array:ident, $func:ident, $arg:ident, $($numbers:literal),+) => {
         {
-            $($array[$numbers - 8] = $func($numbers);)*
+            $($array[$numbers] = $func($numbers, $arg);)*
         }
     };
 }
 
-/// TODO:
-/// FINISH FOR B_PAWNS.
-/// Unfinished until eval is working.
-/// NOTES:
-/// pass_pawn_pattern does not need to be generated for:
-/// * Rank 1 White (Pawns cannot be on squares)
-/// * Rank 7/8 White (Cannot be blocked by pawns)
-/// * Rank 8 Black ( Pawns cannot be on squares)
-/// * Rank 1/2 Black (Pawns cannot be blocked by pawns)
-const fn generate_pass_pawn_pattern() -> [Bitboard; PASS_PAWN_SIZE] {
-    let mut array = [Bitboard::EMPTY; PASS_PAWN_SIZE];
+/// Generates an array containing a passed pawn front span bitboard for each square.
+const fn generate_pass_pawn_patterns(color: Color) -> [Bitboard; NUM_SQUARES] {
+    let mut pattern_arr = [Bitboard::EMPTY; NUM_SQUARES];
 
     #[rustfmt::skip]
-    w_repeat_for_each!(
-        array,
-        w_pass_pawn_pattern_idx,
+    repeat_for_each!(
+        pattern_arr,
+        pass_pawn_pattern_index,
+        color,
+        0, 1, 2, 3, 4, 5, 6, 7,
         8, 9, 10, 11, 12, 13, 14, 15,
         16, 17, 18, 19, 20, 21, 22, 23,
         24, 25, 26, 27, 28, 29, 30, 31,
         32, 33, 34, 35, 36, 37, 38, 39,
-        40, 41, 42, 43, 44, 45, 46, 47
+        40, 41, 42, 43, 44, 45, 46, 47,
+        48, 49, 50, 51, 52, 53, 54, 55,
+        56, 57, 58, 59, 60, 61, 62, 63
     );
-
-    array
+    pattern_arr
 }
 
-const fn w_pass_pawn_pattern_idx(square: usize) -> Bitboard {
-    use Bitboard as Bb;
-    let square_bb: bitboard::BitboardKind = 1u64 << square;
+/// Generate front span bitboard of a pawn on a single square.
+const fn pass_pawn_pattern_index(index: usize, color: Color) -> Bitboard {
+    let index_bb = Bitboard(1u64 << index);
+    let adjacent = index_bb.0 | index_bb.to_east().0 | index_bb.to_west().0;
 
-    if square_bb & Bitboard::FILE_A.0 > 0 {
-        // On File A
-        let mut pass_pawn_pat = Bitboard::FILE_A.0 | Bitboard::FILE_B.0;
-        pass_pawn_pat &= !square_bb; // Remove idx square.
-        pass_pawn_pat &= !(square_bb << 1); // Remove square to right of idx.
-        if square != 0 {
-            pass_pawn_pat &= !(square_bb - 1);
+    // Fill every rank in front of the pawn's rank, on the same and adjacent files.
+    match color {
+        White => {
+            let mut span = adjacent << 8;
+            span |= span << 8;
+            span |= span << 16;
+            span |= span << 32;
+            Bitboard(span)
+        }
+        Black => {
+            let mut span = adjacent >> 8;
+            span |= span >> 8;
+            span |= span >> 16;
+            span |= span >> 32;
+            Bitboard(span)
         }
-        Bitboard(pass_pawn_pat)
-    } else if square_bb & Bitboard::FILE_H.0 > 0 {
-        // On File H
-        let mut pass_pawn_pat = Bitboard::FILE_G.0 | Bitboard::FILE_H.0;
-        pass_pawn_pat &= !(square_bb ^ (square_bb - 1)); // Remove square and below.
-        Bitboard(pass_pawn_pat)
-    } else {
-        // Not Files A or H
-        let mut pass_pawn_pat = match square_bb {
-            bb if bb & Bb::FILE_B.0 > 0 => Bb::FILE_A.0 | Bb::FILE_B.0 | Bb::FILE_C.0,
-            bb if bb & Bb::FILE_C.0 > 0 => Bb::FILE_B.0 | Bb::FILE_C.0 | Bb::FILE_D.0,
-            bb if bb & Bb::FILE_D.0 > 0 => Bb::FILE_C.0 | Bb::FILE_D.0 | Bb::FILE_E.0,
-            bb if bb & Bb::FILE_E.0 > 0 => Bb::FILE_D.0 | Bb::FILE_E.0 | Bb::FILE_F.0,
-            bb if bb & Bb::FILE_F.0 > 0 => Bb::FILE_E.0 | Bb::FILE_F.0 | Bb::FILE_G.0,
-            bb if bb & Bb::FILE_G.0 > 0 => Bb::FILE_F.0 | Bb::FILE_G.0 | Bb::FILE_H.0,
-            _ => 0,
-        };
-        // Remove Rank of square and all below.
-        pass_pawn_pat &= !(square_bb ^ (square_bb - 1)); // Remove square and below.
-        pass_pawn_pat &= !(square_bb << 1);
-
-        Bitboard(pass_pawn_pat)
     }
 }
 
@@ -403,6 +1190,64 @@ mod tests {
     use super::*;
     use crate::Fen;
 
+    const PARAMS: &EvalParams = &EvalParams::DEFAULT;
+
+    #[test]
+    fn terminal_checkmate_and_stalemate() {
+        // Fool's mate, white is checkmated.
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let checkmate = Position::parse_fen(fen).unwrap();
+        assert!(checkmate.get_legal_moves().is_empty());
+        let score = terminal(&checkmate, 0);
+        assert_eq!(score, -Cp::CHECKMATE);
+        assert!(score.is_mate());
+        assert!(score < Cp(0));
+
+        // Mates further from the root are less severe, so faster mates are preferred.
+        let distant = terminal(&checkmate, 3);
+        assert!(distant.is_mate());
+        assert!(distant > score);
+        assert!(distant < Cp(0));
+
+        // Black is stalemated.
+        let stalemate = Position::parse_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.get_legal_moves().is_empty());
+        assert_eq!(terminal(&stalemate, 0), Cp::STALEMATE);
+        assert_eq!(terminal(&stalemate, 5), Cp::STALEMATE);
+    }
+
+    #[test]
+    fn eval_cache_hits_repeated_positions() {
+        let mut cache = EvalCache::with_mb(1);
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut pos = Position::parse_fen(fen).unwrap();
+        let hash = cache.zobrist_table().generate_hash((&pos).into());
+
+        let score = cache.evaluate(&pos, hash);
+        assert_eq!(score, evaluate_with(&pos, PARAMS));
+        assert_eq!(cache.hits(), 0);
+        assert_eq!(cache.evaluate(&pos, hash), score);
+        assert_eq!(cache.hits(), 1);
+
+        // The fifty-move scale is applied to cached evaluations.
+        pos.halfmoves = 80;
+        assert_eq!(cache.evaluate(&pos, hash), evaluate_with(&pos, PARAMS));
+        assert_eq!(cache.hits(), 2);
+
+        // Changing parameters invalidates the cache, while setting the same ones does not.
+        let params = EvalParams {
+            knight_mobility_cp: Cp(20),
+            ..EvalParams::DEFAULT
+        };
+        cache.set_params(PARAMS);
+        assert_eq!(cache.evaluate(&pos, hash), evaluate_with(&pos, PARAMS));
+        assert_eq!(cache.hits(), 3);
+        cache.set_params(&params);
+        assert_eq!(cache.evaluate(&pos, hash), evaluate_with(&pos, &params));
+        assert_eq!(cache.hits(), 3);
+        assert_ne!(evaluate_with(&pos, &params), evaluate_with(&pos, PARAMS));
+    }
+
     #[test]
     fn start_pos_equal_eval() {
         // The start position is symmetric.
@@ -416,6 +1261,37 @@ mod tests {
         assert_eq!(w_eval, evaluate(&start.color_flip()));
     }
 
+    #[test]
+    fn eval_trace_terms_sum_to_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "6k1/5pp1/8/8/8/8/1P3PPP/6K1 b - - 0 40",
+        ];
+        for fen in fens {
+            let position = Position::parse_fen(fen).unwrap();
+            let trace = EvalTrace::new(&position, PARAMS);
+            assert!(trace.basic_endgame.is_none());
+            assert_eq!(trace.endgame_scale, 1.0);
+            assert_eq!(trace.terms_total(), evaluate_abs(&position), "{}", fen);
+            assert_eq!(trace.total, evaluate_abs(&position));
+            // Tapered terms may round differently per player.
+            for term in &trace.terms {
+                let difference = (term.white - term.black - term.total).0;
+                assert!(difference.abs() <= 1, "{} {}", fen, term.name);
+            }
+        }
+
+        let position = Position::parse_fen("8/8/8/3k4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let trace = EvalTrace::new(&position, PARAMS);
+        assert!(trace.terms.is_empty());
+        assert_eq!(trace.basic_endgame.unwrap(), trace.total);
+        assert!(trace.to_string().contains("basic endgame"));
+    }
+
     #[test]
     fn cp_min_and_max() {
         let min = Cp::MIN;
@@ -439,4 +1315,350 @@ mod tests {
         assert!(!score.is_mate());
         println!("MAX POSSIBLE SCORE: {}", score);
     }
+
+    #[test]
+    fn pst_score_symmetry() {
+        let positions = [
+            Position::start_position(),
+            Position::parse_fen(
+                "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 6 4",
+            )
+            .unwrap(),
+            Position::parse_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap(),
+        ];
+
+        for position in positions {
+            let flipped = position.color_flip();
+            assert_eq!(pst_score(&position, White), pst_score(&flipped, Black));
+            assert_eq!(pst_score(&position, Black), pst_score(&flipped, White));
+            assert_eq!(
+                piece_square_lookup(&flipped),
+                -piece_square_lookup(&position)
+            );
+        }
+    }
+
+    #[test]
+    fn passed_pawn_patterns() {
+        use crate::coretypes::Square::*;
+        let w_e4 = W_PASS_PAWN_PATTERN[E4.idx()];
+        let b_e5 = B_PASS_PAWN_PATTERN[E5.idx()];
+        assert_eq!(w_e4.count_squares(), 12);
+        assert_eq!(b_e5.count_squares(), 12);
+        assert!(w_e4.has_square(D5) && w_e4.has_square(E8) && w_e4.has_square(F6));
+        assert!(!w_e4.has_square(E4) && !w_e4.has_square(D4) && !w_e4.has_square(E3));
+        assert!(b_e5.has_square(D4) && b_e5.has_square(E1) && b_e5.has_square(F3));
+        assert_eq!(W_PASS_PAWN_PATTERN[A2.idx()].count_squares(), 12);
+        assert_eq!(W_PASS_PAWN_PATTERN[H8.idx()], Bitboard::EMPTY);
+    }
+
+    #[test]
+    fn passed_pawn_score() {
+        use crate::coretypes::Square::*;
+        // White has a passed pawn on d5, all other pawns are blocked or guarded.
+        let pos = Position::parse_fen("4k3/p5p1/8/3P4/8/8/P5P1/4K3 w - - 0 1").unwrap();
+        assert_eq!(passed_pawns(&pos, White), Bitboard::from(D5));
+        assert_eq!(passed_pawns(&pos, Black), Bitboard::EMPTY);
+        assert!(pass_pawns(&pos, PARAMS) > Cp(0));
+
+        let flipped = pos.color_flip();
+        assert_eq!(passed_pawns(&flipped, Black), Bitboard::from(D4));
+        assert_eq!(pass_pawns(&flipped, PARAMS), -pass_pawns(&pos, PARAMS));
+        assert_eq!(evaluate_abs(&flipped), -evaluate_abs(&pos));
+
+        // Protected and connected passed pawns are worth more than a lone passed pawn.
+        let lone = Position::parse_fen("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+        let protected = Position::parse_fen("4k3/8/8/3P4/2P5/8/8/4K3 w - - 0 1").unwrap();
+        let connected = Position::parse_fen("4k3/8/8/2PP4/8/8/8/4K3 w - - 0 1").unwrap();
+        let lone_cp = pass_pawns(&lone, PARAMS);
+        let c4_cp = pass_pawns(
+            &Position::parse_fen("4k3/8/8/8/2P5/8/8/4K3 w - - 0 1").unwrap(),
+            PARAMS,
+        );
+        let c5_cp = pass_pawns(
+            &Position::parse_fen("4k3/8/8/2P5/8/8/8/4K3 w - - 0 1").unwrap(),
+            PARAMS,
+        );
+        assert!(pass_pawns(&protected, PARAMS) > lone_cp + c4_cp);
+        assert!(pass_pawns(&connected, PARAMS) > lone_cp + c5_cp);
+    }
+
+    #[test]
+    fn king_safety_pawn_shield() {
+        // Castled king with an intact pawn shield compared to a shattered one.
+        let intact = Position::parse_fen("r4rk1/ppp2ppp/8/8/8/8/PPP2PPP/R4RK1 w - - 0 1").unwrap();
+        let shattered =
+            Position::parse_fen("r4rk1/ppp2ppp/8/8/8/6P1/PPP2P2/R4RK1 w - - 0 1").unwrap();
+        let stripped = Position::parse_fen("r4rk1/ppp2ppp/8/8/8/8/PPP5/R4RK1 w - - 0 1").unwrap();
+
+        let intact_cp = king_safety(&intact, White, PARAMS);
+        let shattered_cp = king_safety(&shattered, White, PARAMS);
+        let stripped_cp = king_safety(&stripped, White, PARAMS);
+        assert!(intact_cp > shattered_cp);
+        assert!(shattered_cp > stripped_cp);
+
+        // Black is unaffected by White's pawn structure.
+        assert_eq!(
+            king_safety(&intact, Black, PARAMS),
+            king_safety(&shattered, Black, PARAMS)
+        );
+
+        // Flipping colors flips which king is safe.
+        assert_eq!(
+            king_safety(&shattered.color_flip(), Black, PARAMS),
+            shattered_cp
+        );
+    }
+
+    #[test]
+    fn king_safety_attackers() {
+        // Enemy pieces attacking the king zone reduce safety.
+        let quiet = Position::parse_fen("r4rk1/ppp2ppp/8/8/8/8/PPP2PPP/R4RK1 w - - 0 1").unwrap();
+        let attacked =
+            Position::parse_fen("r4rk1/ppp2ppp/8/8/6q1/5n2/PPP2PPP/R4RK1 w - - 0 1").unwrap();
+        assert!(king_safety(&attacked, White, PARAMS) < king_safety(&quiet, White, PARAMS));
+    }
+
+    #[test]
+    fn mobility_centralized_queen() {
+        // A queen in the center has more squares than one boxed in by its own pieces.
+        let centered = Position::parse_fen("4k3/8/8/8/3Q4/8/1P6/1N2K3 w - - 0 1").unwrap();
+        let cornered = Position::parse_fen("4k3/8/8/8/8/8/1P6/QN2K3 w - - 0 1").unwrap();
+        assert!(mobility(&centered, White, PARAMS) > mobility(&cornered, White, PARAMS));
+        assert_eq!(mobility(&centered, Black, PARAMS), Cp(0));
+
+        // Mobility is symmetrical between players.
+        assert_eq!(
+            mobility(&centered.color_flip(), Black, PARAMS),
+            mobility(&centered, White, PARAMS)
+        );
+    }
+
+    #[test]
+    fn bishop_pair_bonus() {
+        // White has two bishops against bishop and knight, material is otherwise equal.
+        let pos = Position::parse_fen("2b1kn2/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1").unwrap();
+        let imbalance = material_imbalance(&pos);
+        assert!(imbalance > Cp(0));
+        assert!(imbalance < Cp(Bishop.centipawns().0 / 2));
+        assert_eq!(material_imbalance(&pos.color_flip()), -imbalance);
+
+        // Two bishops on same colored squares are not a bishop pair.
+        let same_color = Position::parse_fen("4k3/8/8/8/8/8/8/2B1B1K1 w - - 0 1").unwrap();
+        assert_eq!(material_imbalance(&same_color), Cp(0));
+    }
+
+    #[test]
+    fn incremental_matches_scan() {
+        let pos = Position::parse_fen(
+            "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 6 4",
+        )
+        .unwrap();
+        assert_eq!(material(&pos), material_scan(&pos));
+        // Tapering each player separately may round differently by at most 1 Cp.
+        let scanned = pst_score(&pos, White) - pst_score(&pos, Black);
+        assert!((piece_square_lookup(&pos) - scanned).abs() <= Cp(1));
+    }
+
+    #[test]
+    fn game_phase_from_material() {
+        let start = Position::start_position();
+        assert_eq!(game_phase(&start), MAX_PHASE);
+
+        let pawns = Position::parse_fen("4k3/8/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&pawns), 0);
+
+        let rooks = Position::parse_fen("r3k3/8/8/8/8/8/PPPP4/R3K3 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&rooks), 4);
+
+        // Promoted material does not exceed a full midgame.
+        let queens = Position::parse_fen("4k3/8/8/8/8/8/QQQQQQQQ/4K3 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&queens), MAX_PHASE);
+    }
+
+    #[test]
+    fn taper_interpolates_phase() {
+        assert_eq!(taper(Cp(100), Cp(-100), MAX_PHASE), Cp(100));
+        assert_eq!(taper(Cp(100), Cp(-100), 0), Cp(-100));
+        assert_eq!(taper(Cp(100), Cp(-100), MAX_PHASE / 2), Cp(0));
+    }
+
+    #[test]
+    fn endgame_king_centralization() {
+        // Bare black king is better placed in the center than in the corner.
+        let cornered = Position::parse_fen("7k/8/8/8/8/8/PPP5/K7 w - - 0 1").unwrap();
+        let centered = Position::parse_fen("8/8/8/4k3/8/8/PPP5/K7 w - - 0 1").unwrap();
+        assert!(evaluate_abs(&centered) < evaluate_abs(&cornered));
+
+        // White king with pawns also prefers moving toward the center.
+        let w_centered = Position::parse_fen("7k/8/8/8/3K4/8/PPP5/8 w - - 0 1").unwrap();
+        assert!(evaluate_abs(&w_centered) > evaluate_abs(&cornered));
+    }
+
+    #[test]
+    fn basic_endgame_drives_king_to_corner() {
+        let eval = |fen| basic_endgame(&Position::parse_fen(fen).unwrap()).unwrap();
+
+        // Lone black king scores worse for black the closer it is to an edge, then corner.
+        let center = eval("8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+        let edge = eval("8/8/8/7k/8/8/8/R3K3 w - - 0 1");
+        let corner = eval("7k/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert!(center > Cp(510));
+        assert!(center < edge);
+        assert!(edge < corner);
+
+        // Winning king is rewarded for approaching the lone king.
+        let near = eval("7k/8/5K2/8/8/8/8/R7 w - - 0 1");
+        assert!(corner < near);
+
+        // Scores are symmetric for black.
+        let flipped = Position::parse_fen("7k/8/8/8/8/8/8/R3K3 w - - 0 1")
+            .unwrap()
+            .color_flip();
+        assert_eq!(basic_endgame(&flipped), Some(-corner));
+        assert_eq!(
+            eval("3qk3/8/8/8/8/8/8/K7 w - - 0 1"),
+            -eval("k7/8/8/8/8/8/8/3QK3 b - - 0 1")
+        );
+
+        // Bishop and knight drive towards the corner of the bishop's color.
+        let dark_corner = eval("8/8/8/8/8/8/8/k1K1BN2 w - - 0 1");
+        let light_corner = eval("k1K5/8/8/8/8/8/8/4BN2 w - - 0 1");
+        assert!(light_corner < dark_corner);
+
+        // Other material is not a basic endgame.
+        for fen in [
+            "4k3/8/8/8/8/8/P7/R3K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/RR2K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/4p3/8/8/8/8/8/R3K3 w - - 0 1",
+        ] {
+            assert_eq!(
+                basic_endgame(&Position::parse_fen(fen).unwrap()),
+                None,
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_bishop_is_drawn() {
+        // Dark squared bishop cannot control h8, and the black king holds the corner.
+        let drawn = Position::parse_fen("7k/8/8/7P/8/8/2B5/5K2 w - - 0 1").unwrap();
+        assert_eq!(endgame_scale(&drawn), WRONG_BISHOP_SCALE);
+        assert!(
+            evaluate_abs(&drawn).abs() < Cp(50),
+            "{}",
+            evaluate_abs(&drawn)
+        );
+        assert_eq!(evaluate_abs(&drawn.color_flip()), -evaluate_abs(&drawn));
+
+        // Doubled rook pawns are no better.
+        let doubled = Position::parse_fen("7k/8/8/7P/7P/8/2B5/5K2 w - - 0 1").unwrap();
+        assert_eq!(endgame_scale(&doubled), WRONG_BISHOP_SCALE);
+
+        // Right bishop, non rook pawn, far away king, and extra material all may win.
+        for fen in [
+            "7k/8/8/7P/8/8/3B4/5K2 w - - 0 1",
+            "7k/8/8/6P1/8/8/2B5/5K2 w - - 0 1",
+            "8/8/8/7P/8/8/k1B5/5K2 w - - 0 1",
+            "7k/8/8/7P/8/8/2B5/4NK2 w - - 0 1",
+            "7k/p7/8/7P/8/8/2B5/5K2 w - - 0 1",
+        ] {
+            let position = Position::parse_fen(fen).unwrap();
+            assert_eq!(endgame_scale(&position), 1.0, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn fifty_move_scale_penalizes_shuffling() {
+        // White is a rook ahead. Ordinary play leaves the score untouched.
+        let fresh = Position::parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 60").unwrap();
+        let mut quiet = fresh;
+        quiet.halfmoves = 60;
+        let fresh_eval = evaluate_abs(&fresh);
+        assert_eq!(evaluate_abs(&quiet), fresh_eval);
+
+        // Close to the fifty-move rule, the same position is worth slightly less.
+        let mut shuffled = fresh;
+        shuffled.halfmoves = 90;
+        let mut almost_drawn = fresh;
+        almost_drawn.halfmoves = 99;
+
+        let shuffled_eval = evaluate_abs(&shuffled);
+        let almost_drawn_eval = evaluate_abs(&almost_drawn);
+        assert!(fresh_eval > shuffled_eval);
+        assert!(shuffled_eval > almost_drawn_eval);
+        // Scores lose at most an eighth of their value.
+        assert!(almost_drawn_eval.0 * 8 > fresh_eval.0 * 7);
+
+        // Scaling is symmetrical between players.
+        assert_eq!(evaluate_abs(&shuffled.color_flip()), -shuffled_eval);
+    }
+
+    #[test]
+    fn eval_params_piece_values() {
+        let pos = Position::parse_fen("4k3/pp6/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap();
+        let mut doubled = EvalParams::default();
+        doubled.pawn_cp = EvalParams::DEFAULT.pawn_cp * 2;
+
+        // Default piece values match the incremental material.
+        assert_eq!(material_with(&pos, PARAMS), material(&pos));
+        assert_eq!(material_with(&pos, PARAMS), Cp(200));
+
+        // Doubling the pawn value doubles the material contribution of pawns.
+        assert_eq!(material_with(&pos, &doubled), Cp(400));
+        assert_eq!(
+            evaluate_abs_with(&pos, &doubled) - evaluate_abs_with(&pos, PARAMS),
+            Cp(200)
+        );
+        assert_eq!(
+            evaluate_with(&pos.color_flip(), &doubled),
+            evaluate_with(&pos, &doubled)
+        );
+    }
+
+    #[test]
+    fn texel_loss_decreases_towards_optimum() {
+        let fens = [
+            "4k3/pp6/8/8/8/8/PPPP4/4K3 w - - 0 1",
+            "4k3/ppp5/8/8/8/8/PP6/4K3 b - - 0 1",
+            "4k3/pppp4/8/8/8/8/PPPPPP2/4K3 w - - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp2ppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ];
+        // Label positions with the results expected from an optimal pawn value.
+        let mut optimum = EvalParams::default();
+        optimum.pawn_cp = Cp(160);
+        let positions: Vec<(Position, f64)> = fens
+            .iter()
+            .map(|fen| Position::parse_fen(fen).unwrap())
+            .map(|pos| (pos, expected_result(evaluate_abs_with(&pos, &optimum), 1.0)))
+            .collect();
+
+        let losses: Vec<f64> = [40, 80, 120, 160]
+            .iter()
+            .map(|&pawn_cp| {
+                let mut params = EvalParams::default();
+                params.pawn_cp = Cp(pawn_cp);
+                texel_loss(&positions, &params, 1.0)
+            })
+            .collect();
+        assert!(
+            losses.windows(2).all(|pair| pair[0] > pair[1]),
+            "{:?}",
+            losses
+        );
+        assert!(losses[3] < 1e-12);
+        assert_eq!(texel_loss(&[], PARAMS, 1.0), 0.0);
+
+        // Labels from the default parameters are best fit by the k used to make them.
+        let positions: Vec<(Position, f64)> = positions
+            .into_iter()
+            .map(|(pos, _)| (pos, expected_result(evaluate_abs(&pos), 1.3)))
+            .collect();
+        assert!((best_k(&positions) - 1.3).abs() < 1e-3);
+    }
 }
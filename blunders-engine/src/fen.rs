@@ -6,14 +6,18 @@
 //! Example:\
 //! Starting Chess FEN = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
 
+use std::cmp::Ordering;
 use std::convert::TryFrom;
-use std::error;
 use std::fmt::{self, Display};
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
+use crate::bitboard::Bitboard;
 use crate::boardrepr::{Mailbox, PieceSets};
-use crate::coretypes::{Castling, Color, File, MoveCount, Piece, Rank, Square};
+use crate::coretypes::{
+    Castling, CastlingFiles, Color, Cp, File, MoveCount, Piece, PieceKind, Rank, Square,
+};
+use crate::error::{self, ErrorKind};
 use crate::position::Position;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -48,7 +52,7 @@ impl Display for ParseFenError {
     }
 }
 
-impl error::Error for ParseFenError {}
+impl std::error::Error for ParseFenError {}
 
 /// Implement Fen for any types which can be fully parsed from a FEN string.
 pub trait Fen: Sized {
@@ -58,6 +62,10 @@ pub trait Fen: Sized {
     /// Returns string representation of implementing type in Fen format.
     fn to_fen(&self) -> String;
 
+    /// Check that a parsed value describes a legal chess state,
+    /// beyond the syntax checked by `parse_fen`.
+    fn validate(&self) -> error::Result<()>;
+
     /// HalfMove Clock is any non-negative number.
     fn parse_halfmove_clock(s: &str) -> Result<MoveCount, ParseFenError> {
         s.parse::<MoveCount>()
@@ -87,28 +95,141 @@ impl Fen for Position {
         // Fen Order: Placement/Side-To-Move/Castling/En-Passant/Halfmove/Fullmove
         let pieces: PieceSets = FenComponent::try_from_fen_str(fen_parts[0])?;
         let player: Color = FenComponent::try_from_fen_str(fen_parts[1])?;
-        let castling: Castling = FenComponent::try_from_fen_str(fen_parts[2])?;
+        let (castling, castling_files) = parse_castling_files(fen_parts[2], &pieces)?;
         let en_passant: Option<Square> = FenComponent::try_from_fen_str(fen_parts[3])?;
         let halfmoves: MoveCount = Self::parse_halfmove_clock(fen_parts[4])?;
         let fullmoves: MoveCount = Self::parse_fullmove_number(fen_parts[5])?;
 
-        Ok(Self {
+        // Castling with a king off the E file or rooks off the corners requires Chess960.
+        let chess960 = !castling_files.is_standard()
+            || Color::iter().any(|color| {
+                let king_start = Square::from((File::E, back_rank(color)));
+                castling.has_any(Castling::king_side_of(color) | Castling::queen_side_of(color))
+                    && !pieces[(color, PieceKind::King)].has_square(king_start)
+            });
+
+        let mut position = Self {
             pieces,
             player,
             castling,
             en_passant,
             halfmoves,
             fullmoves,
-        })
+            castling_files,
+            chess960,
+            material: Cp(0),
+            pst_mg: Cp(0),
+            pst_eg: Cp(0),
+            pawn_hash: 0,
+        };
+        position.recompute_accumulators();
+        Ok(position)
+    }
+
+    /// Check that a position is legal:
+    /// * Each player has exactly one king.
+    /// * No pawns are on the first or last rank.
+    /// * The player not to move is not in check.
+    /// * Each castling right has its king and rook on their starting squares.
+    /// * The en passant square is behind a pawn which could have just moved two squares.
+    fn validate(&self) -> error::Result<()> {
+        for color in Color::iter() {
+            let kings = self.pieces().count(color, PieceKind::King);
+            if kings != 1 {
+                let message = format!("{} has {} kings", color, kings);
+                return Err((ErrorKind::FenKingCount, message).into());
+            }
+        }
+
+        let pawns = self.pieces()[(Color::White, PieceKind::Pawn)]
+            | self.pieces()[(Color::Black, PieceKind::Pawn)];
+        if let Some(square) = (pawns & (Bitboard::RANK_1 | Bitboard::RANK_8)).get_lowest_square() {
+            return Err((ErrorKind::FenPawnOnBackRank, square).into());
+        }
+
+        let waiting = !*self.player();
+        let waiting_king = self.pieces()[(waiting, PieceKind::King)]
+            .get_lowest_square()
+            .unwrap();
+        if self.is_attacked_by(waiting_king, *self.player()) {
+            let message = format!("{} king on {} is in check", waiting, waiting_king);
+            return Err((ErrorKind::FenOpponentInCheck, message).into());
+        }
+
+        for color in Color::iter() {
+            let rank = back_rank(color);
+            for right in [
+                Castling::king_side_of(color),
+                Castling::queen_side_of(color),
+            ] {
+                if !self.castling().has(right) {
+                    continue;
+                }
+                let rook_square = Square::from((self.castling_files().rook_file(right), rank));
+                let king_in_place =
+                    !(self.pieces()[(color, PieceKind::King)] & Bitboard::from(rank)).is_empty();
+                let rook_in_place = self.pieces()[(color, PieceKind::Rook)].has_square(rook_square);
+                if !king_in_place || !rook_in_place {
+                    let message = format!(
+                        "{} requires {} king on rank {} and rook on {}",
+                        right.to_fen_str(),
+                        color,
+                        rank,
+                        rook_square
+                    );
+                    return Err((ErrorKind::FenIllegalCastling, message).into());
+                }
+            }
+        }
+
+        if let Some(square) = *self.en_passant() {
+            // The pawn which moved two squares belongs to the player not to move.
+            let (ep_rank, pawn_rank) = match self.player() {
+                Color::White => (Rank::R6, Rank::R5),
+                Color::Black => (Rank::R3, Rank::R4),
+            };
+            let pawn_square = Square::from((square.file(), pawn_rank));
+            if square.rank() != ep_rank
+                || !self.pieces()[(waiting, PieceKind::Pawn)].has_square(pawn_square)
+            {
+                return Err((ErrorKind::FenIllegalEnPassant, square).into());
+            }
+        }
+
+        Ok(())
     }
 
     /// Returns string representation of implementing type in Fen format.
+    /// Castling rights are written in Shredder-FEN for Chess960 positions, otherwise in X-FEN,
+    /// so that castling rooks survive a round trip.
+    /// The en passant square is written whenever the last move was a double pawn push,
+    /// even if no pawn can capture en passant.
     fn to_fen(&self) -> String {
+        match self.chess960() {
+            true => self.to_fen_with(CastlingNotation::Shredder),
+            false => self.to_fen_with(CastlingNotation::XFen),
+        }
+    }
+}
+
+/// Notation used to write the castling rights of a position in FEN.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CastlingNotation {
+    /// Standard `KQkq`, except a right whose castling rook is not the outermost rook
+    /// on its side of the king is written as the rook's file letter.
+    XFen,
+    /// The file letter of each castling rook, such as `HAha`.
+    Shredder,
+}
+
+impl Position {
+    /// Returns the Fen string of this position, writing castling rights in a given notation.
+    pub fn to_fen_with(&self, notation: CastlingNotation) -> String {
         format!(
             "{} {} {} {} {} {}",
             self.pieces().to_fen_str(),
             self.player().to_fen_str(),
-            self.castling().to_fen_str(),
+            castling_to_fen_str(self, notation),
             self.en_passant().to_fen_str(),
             self.halfmoves(),
             self.fullmoves()
@@ -116,6 +237,118 @@ impl Fen for Position {
     }
 }
 
+/// Returns the rank that a color's king and rooks start on.
+fn back_rank(color: Color) -> Rank {
+    match color {
+        Color::White => Rank::R1,
+        Color::Black => Rank::R8,
+    }
+}
+
+/// Parse castling rights along with the starting files of each castling rook.
+/// Accepts standard `KQkq`, Shredder-FEN `HAha`, and X-FEN, which mixes both.
+/// For `K` and `Q` rights the castling rook is the outermost rook on that side of the king.
+/// For file letters, the right's side is determined by the rook file relative to the king.
+fn parse_castling_files(
+    s: &str,
+    pieces: &PieceSets,
+) -> Result<(Castling, CastlingFiles), ParseFenError> {
+    const ERR: ParseFenError = ParseFenError::Castling;
+    let mut castling = Castling::NONE;
+    let mut castling_files = CastlingFiles::STANDARD;
+
+    if s == "-" {
+        return Ok((castling, castling_files));
+    } else if s.is_empty() {
+        return Err(ERR);
+    }
+
+    for ch in s.chars() {
+        let color = match ch.is_ascii_uppercase() {
+            true => Color::White,
+            false => Color::Black,
+        };
+        let rank = back_rank(color);
+        let king_file = pieces[(color, PieceKind::King)]
+            .into_iter()
+            .find(|square| square.rank() == rank)
+            .map(|square| square.file());
+        let rook_files = pieces[(color, PieceKind::Rook)]
+            .into_iter()
+            .filter(|square| square.rank() == rank)
+            .map(|square| square.file());
+
+        let (right, file) = match ch.to_ascii_lowercase() {
+            'k' => {
+                // Outermost rook on kingside, or standard if one cannot be found.
+                let file = king_file
+                    .and_then(|king_file| rook_files.filter(|file| *file > king_file).max())
+                    .unwrap_or(File::H);
+                (Castling::king_side_of(color), file)
+            }
+            'q' => {
+                // Outermost rook on queenside, or standard if one cannot be found.
+                let file = king_file
+                    .and_then(|king_file| rook_files.filter(|file| *file < king_file).min())
+                    .unwrap_or(File::A);
+                (Castling::queen_side_of(color), file)
+            }
+            'a'..='h' => {
+                let file = File::try_from(ch.to_ascii_lowercase() as u8 - b'a').or(Err(ERR))?;
+                match king_file.ok_or(ERR)?.cmp(&file) {
+                    Ordering::Less => (Castling::king_side_of(color), file),
+                    Ordering::Greater => (Castling::queen_side_of(color), file),
+                    Ordering::Equal => return Err(ERR),
+                }
+            }
+            _ => return Err(ERR),
+        };
+
+        castling.set(right);
+        castling_files.set_rook_file(right, file);
+    }
+
+    Ok((castling, castling_files))
+}
+
+/// Format the castling rights of a position in X-FEN or Shredder-FEN.
+/// X-FEN rights are only written as file letters where `K` or `Q` would find another rook.
+fn castling_to_fen_str(position: &Position, notation: CastlingNotation) -> String {
+    let castling = *position.castling();
+    if castling.is_none() {
+        return castling.to_fen_str();
+    }
+
+    let mut castling_str = String::with_capacity(4);
+    for color in Color::iter() {
+        for (right, ch) in [
+            (Castling::king_side_of(color), 'K'),
+            (Castling::queen_side_of(color), 'Q'),
+        ] {
+            if !castling.has(right) {
+                continue;
+            }
+            let ch = match color {
+                Color::White => ch,
+                Color::Black => ch.to_ascii_lowercase(),
+            };
+            let file = position.castling_files().rook_file(right);
+            let parsed_file = parse_castling_files(&ch.to_string(), position.pieces())
+                .map(|(_, castling_files)| castling_files.rook_file(right));
+
+            if notation == CastlingNotation::XFen && parsed_file == Ok(file) {
+                castling_str.push(ch);
+            } else {
+                match color {
+                    Color::White => castling_str.push(char::from(file).to_ascii_uppercase()),
+                    Color::Black => castling_str.push(char::from(file)),
+                }
+            }
+        }
+    }
+    castling_str
+}
+
 /// Allows converting data that can be represented as a FEN sub-string
 /// to and from &str.
 pub trait FenComponent: Sized {
@@ -365,4 +598,173 @@ mod tests {
         assert!(Castling::try_from_fen_str(INVALID2).is_err());
         assert!(Castling::try_from_fen_str(INVALID3).is_err());
     }
+
+    #[test]
+    fn validate_illegal_positions() {
+        let validate = |fen: &str| match Position::parse_fen(fen).unwrap().validate() {
+            Ok(()) => None,
+            Err(error::Error::Message(kind, _)) => Some(kind),
+            Err(error) => panic!("unexpected error {}", error),
+        };
+
+        let legal = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rkrnnbbq/pppppppp/8/8/8/8/PPPPPPPP/RKRNNBBQ w KQkq - 0 1",
+        ];
+        for fen in legal {
+            assert_eq!(validate(fen), None, "{}", fen);
+        }
+
+        let illegal = [
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", ErrorKind::FenKingCount),
+            ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", ErrorKind::FenKingCount),
+            (
+                "4k3/8/8/8/8/8/8/P3K3 w - - 0 1",
+                ErrorKind::FenPawnOnBackRank,
+            ),
+            (
+                "3pk3/8/8/8/8/8/8/4K3 b - - 0 1",
+                ErrorKind::FenPawnOnBackRank,
+            ),
+            (
+                "4k2R/8/8/8/8/8/8/4K3 w - - 0 1",
+                ErrorKind::FenOpponentInCheck,
+            ),
+            (
+                "4k3/4Q3/8/8/8/8/8/4K3 w - - 0 1",
+                ErrorKind::FenOpponentInCheck,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+                ErrorKind::FenIllegalCastling,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/R3K3 w KQ - 0 1",
+                ErrorKind::FenIllegalCastling,
+            ),
+            (
+                "r3k3/8/8/8/8/8/8/4K3 w k - 0 1",
+                ErrorKind::FenIllegalCastling,
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e3 0 1",
+                ErrorKind::FenIllegalEnPassant,
+            ),
+            (
+                "4k3/8/8/8/4p3/8/8/4K3 w - e6 0 1",
+                ErrorKind::FenIllegalEnPassant,
+            ),
+        ];
+        for (fen, kind) in illegal {
+            assert_eq!(validate(fen), Some(kind), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn to_fen_round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 57 90",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 99 150",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1",
+        ];
+        for fen in fens {
+            let position = Position::parse_fen(fen).unwrap();
+            assert_eq!(position.to_fen(), fen);
+
+            // Every position reachable in two moves also survives a round trip.
+            for move_ in position.get_legal_moves() {
+                let child = position.make_move(move_);
+                assert_eq!(
+                    Position::parse_fen(&child.to_fen()).unwrap(),
+                    child,
+                    "{}",
+                    fen
+                );
+                for move_ in child.get_legal_moves() {
+                    let grandchild = child.make_move(move_);
+                    let round_trip = Position::parse_fen(&grandchild.to_fen()).unwrap();
+                    assert_eq!(round_trip, grandchild, "{} {}", fen, grandchild.to_fen());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn to_fen_round_trip_chess960() {
+        // FEN cannot record Chess960 once castling rights are lost, or the files of lost rights,
+        // so only the FEN and the legal moves of each position are compared.
+        let fens = [
+            (
+                "rkrnnbbq/pppppppp/8/8/8/8/PPPPPPPP/RKRNNBBQ w KQkq - 0 1",
+                "CAca",
+            ),
+            ("1r2k2r/8/8/8/8/8/8/R2K2R1 w KQkq - 0 1", "GAhb"),
+            ("rrk5/8/8/8/8/8/8/RRK5 w Bb - 0 1", "Bb"),
+            ("1rk1r3/8/8/8/8/8/8/1RK1R3 b Kk - 12 30", "Ee"),
+            ("1rk1r3/8/8/8/8/8/8/1RK1R3 b EBeb - 12 30", "EBeb"),
+        ];
+        let sorted_moves = |position: &Position| {
+            let mut moves: Vec<String> = position
+                .get_legal_moves()
+                .into_iter()
+                .map(|move_| move_.to_string())
+                .collect();
+            moves.sort();
+            moves
+        };
+        for (fen, shredder) in fens {
+            let position = Position::parse_fen(fen).unwrap();
+            assert!(*position.chess960());
+            let fen_castling = |fen: String| fen.split_whitespace().nth(2).unwrap().to_string();
+            assert_eq!(fen_castling(position.to_fen()), shredder);
+            assert_eq!(Position::parse_fen(&position.to_fen()).unwrap(), position);
+
+            for move_ in position.get_legal_moves() {
+                let child = position.make_move(move_);
+                let mut round_trip = Position::parse_fen(&child.to_fen()).unwrap();
+                round_trip.set_chess960(true);
+                assert_eq!(round_trip.to_fen(), child.to_fen());
+                assert_eq!(sorted_moves(&round_trip), sorted_moves(&child), "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn to_fen_with_castling_notation() {
+        let mut position = Position::start_position();
+        assert_eq!(
+            position.to_fen_with(CastlingNotation::XFen),
+            position.to_fen()
+        );
+        assert_eq!(
+            position.to_fen_with(CastlingNotation::Shredder),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+
+        // Standard positions played as Chess960 are written in Shredder-FEN.
+        position.set_chess960(true);
+        assert_eq!(
+            position.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+        let mut round_trip = Position::parse_fen(&position.to_fen()).unwrap();
+        round_trip.set_chess960(true);
+        assert_eq!(round_trip, position);
+
+        let position = Position::parse_fen("rrk5/8/8/8/8/8/8/RRK5 w Qq - 0 1").unwrap();
+        assert!(position
+            .to_fen_with(CastlingNotation::XFen)
+            .contains(" Qq "));
+        assert!(position
+            .to_fen_with(CastlingNotation::Shredder)
+            .contains(" Aa "));
+    }
 }
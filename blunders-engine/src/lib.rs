@@ -1,6 +1,7 @@
 //! Blunders Chess Engine Core Library.
 
 pub mod arrayvec;
+pub mod bench;
 pub mod bitboard;
 pub mod boardrepr;
 pub mod coretypes;
@@ -11,8 +12,11 @@ pub mod fen;
 pub(crate) mod movegen;
 pub mod movelist;
 pub mod moveorder;
+pub mod movepick;
 pub mod perft;
+pub mod pgn;
 pub mod position;
+pub mod san;
 pub mod search;
 pub mod threads;
 pub mod timeman;
@@ -24,7 +28,7 @@ pub use coretypes::{File, Move, Rank, Square};
 pub use engine::{Engine, EngineBuilder};
 pub use fen::Fen;
 pub use position::{Game, Position};
-pub use search::SearchResult;
+pub use search::{SearchProgress, SearchResult};
 pub use timeman::Mode;
 pub use transposition::TranspositionTable;
 pub use zobrist::ZobristTable;
@@ -3,9 +3,15 @@
 // Temporary. TODO: Delete this
 #![allow(dead_code)]
 
+mod tables;
+
+pub use tables::{magic_bishop_attacks, magic_rook_attacks, squares_between};
+
 use crate::bitboard::Bitboard;
-use crate::coretypes::{Castling, Color, Move, Square, SquareIndexable, NUM_SQUARES};
-use crate::coretypes::{Color::*, PieceKind::*, Square::*};
+use crate::coretypes::{
+    Castling, CastlingFiles, Color, Move, Square, SquareIndexable, NUM_SQUARES,
+};
+use crate::coretypes::{Color::*, PieceKind::*};
 use crate::movelist::MoveList;
 
 //////////////////////////////////////
@@ -124,57 +130,63 @@ pub fn absolute_pins(
 }
 
 /// Generate castling moves and append to move list.
-/// Castling is legal is there are no pieces between rook and king,
+/// Castling is legal is there are no pieces between rook and king or their destinations,
 /// the king does not pass through check, and has appropriate castling rights.
+/// The king and rook may begin on any file as in Chess960, with the king between the rooks.
+/// Castling moves are described as the king moving onto its own rook.
 /// params:
 /// moves - Move list to append to.
 /// player - Player that is castling.
 /// castling - Castling rights for player.
+/// castling_files - Starting files of castling rooks.
+/// king - Square of player's king.
 /// occupied - All occupied squares on chess board.
 /// attacked - All Squares directly attacked by opposite player.
 pub fn legal_castling_moves(
     moves: &mut MoveList,
     player: Color,
     castling: Castling,
+    castling_files: CastlingFiles,
+    king: Square,
     occupied: Bitboard,
     attacked: Bitboard,
 ) {
-    let (has_kingside, has_queenside, king_rank) = match player {
-        White => {
-            let kingside = castling.has(Castling::W_KING);
-            let queenside = castling.has(Castling::W_QUEEN);
-            let king_rank = Bitboard::RANK_1;
-            (kingside, queenside, king_rank)
-        }
-        Black => {
-            let kingside = castling.has(Castling::B_KING);
-            let queenside = castling.has(Castling::B_QUEEN);
-            let king_rank = Bitboard::RANK_8;
-            (kingside, queenside, king_rank)
-        }
-    };
-    if has_kingside {
-        let between = occupied & Bitboard::KINGSIDE_BETWEEN & king_rank;
-        let pass_attacked = attacked & Bitboard::KINGSIDE_PASS & king_rank;
-        if between.is_empty() && pass_attacked.is_empty() {
-            match player {
-                White => moves.push(Move::new(E1, G1, None)),
-                Black => moves.push(Move::new(E8, G8, None)),
-            }
-        }
-    }
-    if has_queenside {
-        let between = occupied & Bitboard::QUEENSIDE_BETWEEN & king_rank;
-        let pass_attacked = attacked & Bitboard::QUEENSIDE_PASS & king_rank;
-        if between.is_empty() && pass_attacked.is_empty() {
-            match player {
-                White => moves.push(Move::new(E1, C1, None)),
-                Black => moves.push(Move::new(E8, C8, None)),
+    for right in [
+        Castling::king_side_of(player),
+        Castling::queen_side_of(player),
+    ] {
+        if castling.has(right) {
+            let rook = castling_files.rook_square(right);
+            let (king_to, rook_from, rook_to) = castling_files.castling_squares(king, rook);
+            debug_assert_eq!(rook, rook_from);
+
+            // All squares both pieces travel over must be empty, except for the pieces themselves.
+            let king_path = rank_span(king, king_to);
+            let rook_path = rank_span(rook, rook_to);
+            let castlers = Bitboard::from(king) | Bitboard::from(rook);
+            let between = occupied & (king_path | rook_path) & !castlers;
+            let pass_attacked = attacked & king_path;
+
+            if between.is_empty() && pass_attacked.is_empty() {
+                moves.push(Move::new(king, rook, None));
             }
         }
     }
 }
 
+/// Returns a Bitboard with all squares from `a` to `b` inclusive, where both squares share a rank.
+fn rank_span(a: Square, b: Square) -> Bitboard {
+    debug_assert_eq!(a.rank(), b.rank());
+    let (low, high) = match a <= b {
+        true => (a, b),
+        false => (b, a),
+    };
+    // All bits up to and including high, without the bits below low.
+    let up_to_high = (high.shift() << 1).wrapping_sub(1);
+    let below_low = low.shift() - 1;
+    Bitboard(up_to_high & !below_low)
+}
+
 // *_pseudo_moves:
 // generate a move list of pseudo legal moves for each piece, including
 // pushes and attacks. These moves do not consider check, but they do consider
@@ -351,7 +363,7 @@ pub fn king_attacks(king: Bitboard) -> Bitboard {
 }
 
 /// Generate and return Bitboard with squares attacked by all queens.
-/// Queen attacks are found in linear time, with 8 rays calculated per queen.
+/// Queen attacks are found in linear time, with 2 magic lookups per queen.
 pub fn queen_attacks(queens: Bitboard, occupied: Bitboard) -> Bitboard {
     queens
         .into_iter()
@@ -360,7 +372,7 @@ pub fn queen_attacks(queens: Bitboard, occupied: Bitboard) -> Bitboard {
 }
 
 /// Generate and return Bitboard with squares attacked by all rooks.
-/// Rook attacks are found in linear time, with 4 rays calculated per rook.
+/// Rook attacks are found in linear time, with 1 magic lookup per rook.
 pub fn rook_attacks(rooks: Bitboard, occupied: Bitboard) -> Bitboard {
     rooks
         .into_iter()
@@ -369,7 +381,7 @@ pub fn rook_attacks(rooks: Bitboard, occupied: Bitboard) -> Bitboard {
 }
 
 /// Generate and return Bitboard with squares attacked by all bishops.
-/// Bishop attacks are found in linear time, with 4 rays calculated per bishops.
+/// Bishop attacks are found in linear time, with 1 magic lookup per bishop.
 pub fn bishop_attacks(bishops: Bitboard, occupied: Bitboard) -> Bitboard {
     bishops
         .into_iter()
@@ -409,15 +421,29 @@ pub fn solo_queen_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
 }
 
 /// Returns Bitboard with Squares directly attacked from origin in 4 orthogonal directions.
+#[inline(always)]
 pub fn solo_rook_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
+    magic_rook_attacks(origin, occupancy)
+}
+
+/// Returns Bitboard with Squares directly attacked from origin in 4 diagonal directions.
+#[inline(always)]
+pub fn solo_bishop_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
+    magic_bishop_attacks(origin, occupancy)
+}
+
+/// Returns Bitboard with Squares directly attacked from origin in 4 orthogonal directions,
+/// found by walking each ray. Used to generate magic bitboard tables.
+pub fn ray_rook_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
     ray_attack_no(origin, occupancy)
         | ray_attack_ea(origin, occupancy)
         | ray_attack_so(origin, occupancy)
         | ray_attack_we(origin, occupancy)
 }
 
-/// Returns Bitboard with Squares directly attacked from origin in 4 diagonal directions.
-pub fn solo_bishop_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
+/// Returns Bitboard with Squares directly attacked from origin in 4 diagonal directions,
+/// found by walking each ray. Used to generate magic bitboard tables.
+pub fn ray_bishop_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
     ray_attack_noea(origin, occupancy)
         | ray_attack_soea(origin, occupancy)
         | ray_attack_sowe(origin, occupancy)
@@ -432,7 +458,7 @@ pub fn solo_bishop_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
 /// color: Color of pawns in pawns Bitboard.
 pub fn pawn_attackers_to(target: Square, pawns: Bitboard, color: Color) -> Bitboard {
     let mut attackers = Bitboard::EMPTY;
-    for pawn_square in pawns.into_iter() {
+    for pawn_square in pawns.iter_squares() {
         let pawn = Bitboard::from(pawn_square);
         if pawn_attacks(pawn, color).has_square(target) {
             attackers.set_square(pawn_square);
@@ -450,7 +476,7 @@ pub fn knight_attackers_to(target: Square, knights: Bitboard) -> Bitboard {
 /// Return Bitboard with Squares of all kings from occupancy that attack target square.
 pub fn king_attackers_to(target: Square, kings: Bitboard) -> Bitboard {
     let mut attackers = Bitboard::EMPTY;
-    for king_square in kings.into_iter() {
+    for king_square in kings.iter_squares() {
         if king_pattern(king_square).has_square(target) {
             attackers.set_square(king_square);
         }
@@ -460,7 +486,7 @@ pub fn king_attackers_to(target: Square, kings: Bitboard) -> Bitboard {
 /// Returns Bitboard with all queens that attack target square, considering occupied squares.
 pub fn queen_attackers_to(target: Square, queens: Bitboard, occupied: Bitboard) -> Bitboard {
     let mut attackers = Bitboard::EMPTY;
-    for queen_square in queens.into_iter() {
+    for queen_square in queens.iter_squares() {
         if solo_queen_attacks(queen_square, occupied).has_square(target) {
             attackers.set_square(queen_square);
         }
@@ -470,7 +496,7 @@ pub fn queen_attackers_to(target: Square, queens: Bitboard, occupied: Bitboard)
 /// Returns Bitboard with all rooks that attack target square, considering occupied squares.
 pub fn rook_attackers_to(target: Square, rooks: Bitboard, occupied: Bitboard) -> Bitboard {
     let mut attackers = Bitboard::EMPTY;
-    for rook_square in rooks.into_iter() {
+    for rook_square in rooks.iter_squares() {
         if solo_rook_attacks(rook_square, occupied).has_square(target) {
             attackers.set_square(rook_square);
         }
@@ -480,7 +506,7 @@ pub fn rook_attackers_to(target: Square, rooks: Bitboard, occupied: Bitboard) ->
 /// Returns Bitboard with all bishops that attack target square, considering occupied squares.
 pub fn bishop_attackers_to(target: Square, bishops: Bitboard, occupied: Bitboard) -> Bitboard {
     let mut attackers = Bitboard::EMPTY;
-    for bishop_square in bishops.into_iter() {
+    for bishop_square in bishops.iter_squares() {
         if solo_bishop_attacks(bishop_square, occupied).has_square(target) {
             attackers.set_square(bishop_square);
         }
@@ -782,7 +808,7 @@ const fn queen_pattern_index(index: usize) -> Bitboard {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coretypes::*;
+    use crate::coretypes::{Square::*, *};
 
     #[test]
     fn check_knight_patterns() {
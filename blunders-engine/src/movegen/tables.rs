@@ -0,0 +1,370 @@
+//! Magic bitboard tables for sliding piece attacks.
+//!
+//! Rook and bishop attacks depend only on the occupancy of the squares along their rays,
+//! excluding the board edge. A magic number per square maps each such relevant occupancy
+//! to a unique index into a table of precomputed attacks, so that sliding attacks
+//! are found with a mask, multiply, and shift instead of walking each ray.
+//!
+//! Magic numbers were found by a random search, and attack tables are generated
+//! from the ray-based attack functions on first use.
+//!
+//! The squares between each pair of aligned squares are also precomputed on first use.
+
+use std::sync::OnceLock;
+
+use super::{ray_bishop_attacks, ray_rook_attacks};
+use crate::bitboard::Bitboard;
+use crate::coretypes::{Square, SquareIndexable, NUM_SQUARES};
+
+pub const ROOK_MAGICS: [u64; NUM_SQUARES] = [
+    0x2080002080400010,
+    0x00C0002001401000,
+    0x2100110008402002,
+    0x0880080081041000,
+    0x0200020020041008,
+    0x2300040008010012,
+    0x0C00283004008201,
+    0x0180010000407A80,
+    0x0168800080400020,
+    0x0010400040201000,
+    0x1001002001001048,
+    0x1001002408100100,
+    0x0801000408010012,
+    0x4001000209000400,
+    0x08A20004C8020001,
+    0x2002801145002280,
+    0x0080860021004200,
+    0x001000C009402002,
+    0x00B0002004002800,
+    0x100A808010020800,
+    0x8101010008000410,
+    0x0244008002000480,
+    0x0000040010810208,
+    0x2000020000448534,
+    0x4104400480008033,
+    0x0000810100204000,
+    0x0440430900200010,
+    0x4600240900100100,
+    0x0060080080040080,
+    0x0001000300080400,
+    0x0004084400011002,
+    0x0023040200008041,
+    0x0580050043002080,
+    0x0400804002802008,
+    0x0001002001004010,
+    0x1000200901001000,
+    0x4410800801800C00,
+    0xA012003806001004,
+    0x0020100104008802,
+    0x0004808402000041,
+    0x0010400170898000,
+    0x0080500020004004,
+    0x1040408012020020,
+    0x8010040008004040,
+    0x2001080100110004,
+    0x0000020004008080,
+    0x0021010810040002,
+    0x0800008C43020024,
+    0x0000800021005100,
+    0x0070201040008080,
+    0x0000D04282006A00,
+    0x0010014400080240,
+    0x0001080110050100,
+    0x0012000810240600,
+    0x0402000801040200,
+    0x028100108A004100,
+    0x0050800300102045,
+    0x8208210040120882,
+    0x8010600101183441,
+    0x020B000910006045,
+    0x0241001002480005,
+    0x0081000400880241,
+    0x0000009008024124,
+    0x0048122980410402,
+];
+pub const BISHOP_MAGICS: [u64; NUM_SQUARES] = [
+    0x0848020822040013,
+    0x8010A40085821200,
+    0x0008008430840822,
+    0x0808048108040000,
+    0x1304042100008104,
+    0x5001012010204023,
+    0x81048801B8200420,
+    0x200A008084012000,
+    0x0040102001042084,
+    0x840A505042428020,
+    0x0000700102202920,
+    0x44101C0C10800002,
+    0x0040040422000000,
+    0x0180020802090202,
+    0x4020020811041202,
+    0x000104308C042000,
+    0x4140661002424400,
+    0x0028012008010460,
+    0x0188062102002A00,
+    0x0014004840102008,
+    0x0105000290400002,
+    0x8001022200410400,
+    0x104A041918013446,
+    0x008A000082008238,
+    0x04A0060008100430,
+    0x0008220008820801,
+    0x2508041208005010,
+    0x4008080200202020,
+    0x2441001013004000,
+    0x0030008060407000,
+    0x4008108000420800,
+    0x0012021050290100,
+    0x0210080482200500,
+    0xCC01112048100480,
+    0x0020402806500440,
+    0x00048E0080580080,
+    0x0040102020020080,
+    0x0028010440080807,
+    0x4601041108008800,
+    0x8040810E04104200,
+    0x901210110400088A,
+    0xA003080212081050,
+    0x00C1004048401004,
+    0x900000A014400800,
+    0x0008021040405401,
+    0x4020008206002090,
+    0x0004190424030100,
+    0x0424008A02026250,
+    0x8004088250900040,
+    0x1C00430088A04200,
+    0x0001020094040001,
+    0x8040210020880061,
+    0x2010040450442032,
+    0x0800840850044001,
+    0x0004040802140004,
+    0x0004080A04222020,
+    0x8088802110022000,
+    0x1081A10416114400,
+    0x0205010A24060820,
+    0x0000000720411080,
+    0x1008000208430400,
+    0x580C026028810840,
+    0x802020441020A110,
+    0x12C0022401020018,
+];
+
+/// Magic lookup parameters for a single square.
+#[derive(Debug, Copy, Clone)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    /// Returns index of attacks for an occupancy in a table of attacks.
+    #[inline(always)]
+    fn index(&self, occupancy: Bitboard) -> usize {
+        let relevant = occupancy.0 & self.mask.0;
+        self.offset + (relevant.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Magic lookup parameters and attacks for one sliding piece kind, for all squares.
+struct MagicTable {
+    magics: [Magic; NUM_SQUARES],
+    attacks: Vec<Bitboard>,
+}
+
+impl MagicTable {
+    /// Generate the attack table of all squares for a sliding piece kind.
+    /// magics: Magic numbers for each square.
+    /// mask: Returns the relevant occupancy squares of a square.
+    /// ray_attacks: Returns attacks from a square for an occupancy.
+    fn new(
+        magics: &[u64; NUM_SQUARES],
+        mask: fn(Square) -> Bitboard,
+        ray_attacks: fn(Square, Bitboard) -> Bitboard,
+    ) -> Self {
+        let empty_magic = Magic {
+            mask: Bitboard::EMPTY,
+            magic: 0,
+            shift: 0,
+            offset: 0,
+        };
+        let mut table = Self {
+            magics: [empty_magic; NUM_SQUARES],
+            attacks: Vec::new(),
+        };
+
+        for square in Square::iter() {
+            let mask = mask(square);
+            let num_bits = mask.count_squares();
+            let magic = Magic {
+                mask,
+                magic: magics[square.idx()],
+                shift: 64 - num_bits,
+                offset: table.attacks.len(),
+            };
+            table.magics[square.idx()] = magic;
+            table
+                .attacks
+                .resize(magic.offset + (1 << num_bits), Bitboard::EMPTY);
+
+            // Enumerate every subset of the mask, using the Carry-Rippler trick.
+            let mut occupancy = Bitboard::EMPTY;
+            loop {
+                let index = magic.index(occupancy);
+                let attacks = ray_attacks(square, occupancy);
+                debug_assert!(
+                    table.attacks[index] == Bitboard::EMPTY || table.attacks[index] == attacks,
+                    "magic number collision"
+                );
+                table.attacks[index] = attacks;
+
+                occupancy = Bitboard(occupancy.0.wrapping_sub(mask.0) & mask.0);
+                if occupancy.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        table
+    }
+
+    /// Returns attacks from a square for an occupancy.
+    #[inline(always)]
+    fn get(&self, square: Square, occupancy: Bitboard) -> Bitboard {
+        self.attacks[self.magics[square.idx()].index(occupancy)]
+    }
+}
+
+/// Returns squares whose occupancy affects rook attacks from a square.
+/// Edge squares are excluded, because a ray always ends at the edge.
+fn rook_mask(square: Square) -> Bitboard {
+    let rank_edges = (Bitboard::RANK_1 | Bitboard::RANK_8) & !Bitboard::from(square.rank());
+    let file_edges = (Bitboard::FILE_A | Bitboard::FILE_H) & !Bitboard::from(square.file());
+    ray_rook_attacks(square, Bitboard::EMPTY) & !(rank_edges | file_edges)
+}
+
+/// Returns squares whose occupancy affects bishop attacks from a square.
+/// Edge squares are excluded, because a ray always ends at the edge.
+fn bishop_mask(square: Square) -> Bitboard {
+    let edges = Bitboard::RANK_1 | Bitboard::RANK_8 | Bitboard::FILE_A | Bitboard::FILE_H;
+    ray_bishop_attacks(square, Bitboard::EMPTY) & !edges
+}
+
+fn rook_table() -> &'static MagicTable {
+    static TABLE: OnceLock<MagicTable> = OnceLock::new();
+    TABLE.get_or_init(|| MagicTable::new(&ROOK_MAGICS, rook_mask, ray_rook_attacks))
+}
+
+fn bishop_table() -> &'static MagicTable {
+    static TABLE: OnceLock<MagicTable> = OnceLock::new();
+    TABLE.get_or_init(|| MagicTable::new(&BISHOP_MAGICS, bishop_mask, ray_bishop_attacks))
+}
+
+/// Squares strictly between each pair of squares, indexed by both squares.
+type BetweenTable = [[Bitboard; NUM_SQUARES]; NUM_SQUARES];
+
+fn between_table() -> &'static BetweenTable {
+    static TABLE: OnceLock<Box<BetweenTable>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Box::new([[Bitboard::EMPTY; NUM_SQUARES]; NUM_SQUARES]);
+        for a in Square::iter() {
+            for b in Square::iter() {
+                let occupied = Bitboard::from(a) | Bitboard::from(b);
+                table[a.idx()][b.idx()] = if ray_rook_attacks(a, Bitboard::EMPTY).has_square(b) {
+                    ray_rook_attacks(a, occupied) & ray_rook_attacks(b, occupied)
+                } else if ray_bishop_attacks(a, Bitboard::EMPTY).has_square(b) {
+                    ray_bishop_attacks(a, occupied) & ray_bishop_attacks(b, occupied)
+                } else {
+                    Bitboard::EMPTY
+                };
+            }
+        }
+        table
+    })
+}
+
+/// Returns the squares strictly between two squares which share a rank, file, or diagonal.
+/// Returns an empty Bitboard if the squares are not aligned.
+#[inline(always)]
+pub fn squares_between(a: Square, b: Square) -> Bitboard {
+    between_table()[a.idx()][b.idx()]
+}
+
+/// Returns Bitboard with Squares directly attacked from origin in 4 orthogonal directions,
+/// found by magic bitboard lookup.
+#[inline(always)]
+pub fn magic_rook_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
+    rook_table().get(origin, occupancy)
+}
+
+/// Returns Bitboard with Squares directly attacked from origin in 4 diagonal directions,
+/// found by magic bitboard lookup.
+#[inline(always)]
+pub fn magic_bishop_attacks(origin: Square, occupancy: Bitboard) -> Bitboard {
+    bishop_table().get(origin, occupancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn magic_attacks_equal_ray_attacks() {
+        let mut rng = StdRng::seed_from_u64(0x3A61C);
+
+        for square in Square::iter() {
+            // Dense, medium, and sparse random occupancies.
+            for _ in 0..1000 {
+                let dense = Bitboard(rng.gen::<u64>() | rng.gen::<u64>());
+                let medium = Bitboard(rng.gen::<u64>());
+                let sparse = Bitboard(rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>());
+
+                for occupancy in [dense, medium, sparse, Bitboard::EMPTY] {
+                    assert_eq!(
+                        magic_rook_attacks(square, occupancy),
+                        ray_rook_attacks(square, occupancy)
+                    );
+                    assert_eq!(
+                        magic_bishop_attacks(square, occupancy),
+                        ray_bishop_attacks(square, occupancy)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn squares_between_aligned_squares() {
+        use crate::coretypes::Square::*;
+        let squares = |a, b| squares_between(a, b).squares();
+        assert_eq!(squares(A1, A4), vec![A2, A3]);
+        assert_eq!(squares(H1, E1), vec![F1, G1]);
+        assert_eq!(squares(A1, H8), vec![B2, C3, D4, E5, F6, G7]);
+        assert_eq!(squares(F2, B6), vec![E3, D4, C5]);
+        assert_eq!(squares_between(H8, A1), squares_between(A1, H8));
+
+        // Adjacent, identical, and non-aligned squares have no squares between.
+        for (a, b) in [
+            (E4, E5),
+            (E4, F5),
+            (E4, E4),
+            (A1, B3),
+            (G1, F3),
+            (A1, H7),
+            (A2, H1),
+        ] {
+            assert_eq!(squares_between(a, b), Bitboard::EMPTY, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn masks_exclude_edges() {
+        use crate::coretypes::Square::*;
+        assert_eq!(rook_mask(A1).count_squares(), 12);
+        assert_eq!(rook_mask(E4).count_squares(), 10);
+        assert_eq!(bishop_mask(A1).count_squares(), 6);
+        assert_eq!(bishop_mask(E4).count_squares(), 9);
+    }
+}
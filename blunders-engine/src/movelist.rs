@@ -4,7 +4,7 @@
 //! pre-1.0 development, so a MoveList type alias makes changes easy.
 
 use crate::arrayvec::ArrayVec;
-use crate::coretypes::{Move, MoveInfo};
+use crate::coretypes::{Move, MoveInfo, Square};
 use crate::coretypes::{MAX_DEPTH, MAX_HISTORY, MAX_MOVES};
 
 /// MoveList is a container that can hold at most `MAX_MOVES`, the most number of moves per any chess position.
@@ -22,3 +22,66 @@ pub type Line = ArrayVec<Move, { MAX_DEPTH as usize }>;
 /// MoveHistory stores the sequence of moves that have been applied to some base position.
 /// The size limit of this is the longest contiguous game that Blunders can support.
 pub type MoveHistory = ArrayVec<Move, MAX_HISTORY>;
+
+/// Helper methods for MoveList, which is a foreign type.
+/// Slice methods such as `contains` and `sort_by_key` are available through deref.
+pub trait MoveListExt {
+    /// Returns true if the list has a move between two squares, with any or no promotion.
+    fn contains_from_to(&self, from: Square, to: Square) -> bool;
+
+    /// Removes repeated moves, keeping the first of each in its original order.
+    fn dedup(&mut self);
+}
+
+impl MoveListExt for MoveList {
+    fn contains_from_to(&self, from: Square, to: Square) -> bool {
+        self.iter()
+            .any(|move_| move_.from == from && move_.to == to)
+    }
+
+    fn dedup(&mut self) {
+        let mut seen = MoveList::new();
+        self.retain(|move_| match seen.contains(move_) {
+            true => false,
+            false => {
+                seen.push(*move_);
+                true
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coretypes::PieceKind;
+    use crate::coretypes::Square::*;
+
+    #[test]
+    fn move_list_dedup_and_from_to() {
+        let e2e4 = Move::new(E2, E4, None);
+        let g1f3 = Move::new(G1, F3, None);
+        let a7a8q = Move::new(A7, A8, Some(PieceKind::Queen));
+        let a7a8n = Move::new(A7, A8, Some(PieceKind::Knight));
+
+        let mut moves = MoveList::new();
+        for move_ in [e2e4, g1f3, e2e4, a7a8q, a7a8n, g1f3, a7a8q] {
+            moves.push(move_);
+        }
+        moves.dedup();
+        assert_eq!(moves.as_slice(), [e2e4, g1f3, a7a8q, a7a8n]);
+
+        assert!(moves.contains_from_to(A7, A8));
+        assert!(moves.contains_from_to(E2, E4));
+        assert!(!moves.contains_from_to(E4, E2));
+        assert!(!moves.contains_from_to(B7, B8));
+
+        moves.sort_by_key(|move_| move_.to);
+        assert_eq!(moves.as_slice(), [g1f3, e2e4, a7a8q, a7a8n]);
+
+        let mut empty = MoveList::new();
+        empty.dedup();
+        assert!(empty.is_empty());
+        assert!(!empty.contains_from_to(E2, E4));
+    }
+}
@@ -15,8 +15,9 @@
 //! 1. Sort first by principal variation moves, then by hash moves, then by Captures (SEE)
 
 use crate::arrayvec::ArrayVec;
-use crate::coretypes::{Cp, Move, MoveInfo, MAX_MOVES};
+use crate::coretypes::{Cp, Move, MoveInfo, MoveKind, PieceKind, PlyKind, MAX_MOVES, NUM_SQUARES};
 use crate::movelist::MoveInfoList;
+use crate::position::Position;
 
 // General considerations for move ordering and searching:
 // For tt look ups during a search, a node only needs to search itself, not it's children.
@@ -27,51 +28,187 @@ use crate::movelist::MoveInfoList;
 // 1. The tt hit depth >= current search depth. Otherwise value is not valid.
 // 2. If depth if great enough, then we only return immediately if
 
-/// Simple move ordering strategy. The following information is extracted from a move,
+/// Table of history heuristic scores, indexed by the from and to squares of a move.
+/// Quiet moves which cause beta cutoffs are rewarded, so that they are tried early
+/// in other nodes of the same search.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HistoryTable {
+    scores: [[u32; NUM_SQUARES]; NUM_SQUARES],
+}
+
+impl HistoryTable {
+    /// Create a new HistoryTable with all scores zeroed.
+    pub fn new() -> Self {
+        Self {
+            scores: [[0; NUM_SQUARES]; NUM_SQUARES],
+        }
+    }
+
+    /// Returns the history score of a move.
+    pub fn get(&self, move_: Move) -> u32 {
+        self.scores[move_.from as usize][move_.to as usize]
+    }
+
+    /// Reward a move which caused a cutoff with `ply` plies remaining.
+    /// Cutoffs closer to the root are rewarded more.
+    pub fn update(&mut self, move_: Move, ply: PlyKind) {
+        let score = &mut self.scores[move_.from as usize][move_.to as usize];
+        *score = score.saturating_add(u32::from(ply) * u32::from(ply));
+    }
+
+    /// Reset all scores to zero.
+    pub fn clear(&mut self) {
+        self.scores = [[0; NUM_SQUARES]; NUM_SQUARES];
+    }
+
+    /// Returns true if no move has a history score.
+    pub fn is_empty(&self) -> bool {
+        self.scores.iter().flatten().all(|score| *score == 0)
+    }
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Information used to order the moves of a position, from most to least important:
+/// 1. Hash move.
+/// 2. Good noisy moves (captures and promotions), by promotion then MVV-LVA.
+/// 3. Killer moves, in order.
+/// 4. Quiet moves, by history score.
+/// 5. Bad noisy moves, which lose material by SEE.
+///
+/// Noisy moves are only found to be bad when a SEE position is given.
+/// Each move ordered with a context must be a legal move of that position.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct OrderingContext<'a> {
+    /// Transposition Table move for the position.
+    pub hash_move: Option<Move>,
+    /// Quiet moves which caused cutoffs in sibling nodes.
+    pub killers: [Option<Move>; 2],
+    /// History heuristic scores used to order quiet moves.
+    pub history: Option<&'a HistoryTable>,
+    /// Position of the ordered moves, used to split noisy moves by static exchange evaluation.
+    pub see: Option<&'a Position>,
+}
+
+impl<'a> OrderingContext<'a> {
+    /// Create a new OrderingContext with only a hash move.
+    pub fn new(hash_move: Option<Move>) -> Self {
+        Self {
+            hash_move,
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> From<Option<Move>> for OrderingContext<'a> {
+    fn from(hash_move: Option<Move>) -> Self {
+        Self::new(hash_move)
+    }
+}
+
+impl<'a> From<&OrderingContext<'a>> for OrderingContext<'a> {
+    fn from(context: &OrderingContext<'a>) -> Self {
+        *context
+    }
+}
+
+/// Returns the Most-Valuable-Victim/Least-Valuable-Attacker score of a capture.
+/// A decent heuristic that prioritizes capturing enemy most valuable pieces first,
+/// and breaks ties by capturing with the least valuable piece.
+/// Scores of captures are always positive.
+pub fn mvv_lva(attacker: PieceKind, victim: PieceKind) -> i32 {
+    // Scale victims so that any difference in victim outweighs the greatest difference in attacker.
+    const VICTIM_SCALE: i32 = 16_384;
+    let attacker = i32::from(attacker.centipawns().0);
+    let victim = i32::from(victim.centipawns().0);
+    victim * VICTIM_SCALE - attacker
+}
+
+/// Class of a move in an ordering, from worst to best.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum OrderClass {
+    BadNoisy,
+    Quiet,
+    Killer,
+    GoodNoisy,
+    HashMove,
+}
+
+/// Move ordering strategy. The following information is extracted from a move,
 /// and used for sorting. The values go from most-to-least important based on
 /// top-to-bottom declaration of fields.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub(crate) struct OrderStrategy {
-    is_tt_move: bool,      // Move listed as best move for root position in tt.
+    class: OrderClass,
     promotion: Option<Cp>, // Cp value of promoting piece, or none.
-    mvv_lva: (bool, Cp),   // is capture, followed by mvv-lva.
-                           // All other nodes remain with lowest but equal priority.
+    score: i64,            // Ordering of moves within their class.
 }
 
-/// OrderStrategy defaults to all false.
+/// OrderStrategy defaults to the lowest priority quiet move.
 impl Default for OrderStrategy {
     fn default() -> Self {
         OrderStrategy {
-            is_tt_move: false,
+            class: OrderClass::Quiet,
             promotion: None,
-            mvv_lva: (false, Cp(0)),
+            score: 0,
         }
     }
 }
 
-impl From<(MoveInfo, Option<Move>)> for OrderStrategy {
-    fn from((move_info, key_move): (MoveInfo, Option<Move>)) -> Self {
+impl From<(MoveInfo, &OrderingContext<'_>)> for OrderStrategy {
+    fn from((move_info, context): (MoveInfo, &OrderingContext<'_>)) -> Self {
+        let move_ = move_info.move_();
+
         // Give high priority to move if root position listed it in tt.
-        let is_tt_move = key_move == Some(move_info.move_());
+        if context.hash_move == Some(move_) {
+            return Self {
+                class: OrderClass::HashMove,
+                ..Default::default()
+            };
+        }
 
         // Set promotion CP.
         let promotion = move_info.promotion.map(|pk| pk.centipawns());
 
-        // Sort by most-valuable-victim -> least-valuable-aggressor.
-        // A decent heuristic that prioritizes capturing enemy most valuable pieces first.
-        // Also prioritizes positive capture above all.
-        let mvv_lva = if let Some(victim) = move_info.captured() {
-            let attacker = move_info.piece_kind.centipawns();
-            let victim = victim.centipawns();
-            (true, victim - attacker)
-        } else {
-            (false, Cp(0))
+        let victim = match move_info.move_kind {
+            MoveKind::Capture(victim) => Some(victim),
+            MoveKind::EnPassant => Some(PieceKind::Pawn),
+            _ => None,
         };
 
-        Self {
-            is_tt_move,
-            promotion,
-            mvv_lva,
+        if victim.is_some() || promotion.is_some() {
+            let mvv_lva = victim.map_or(0, |victim| mvv_lva(move_info.piece_kind, victim));
+
+            let see = context.see.map(|position| position.see(move_));
+            match see {
+                Some(see) if see < Cp(0) => Self {
+                    class: OrderClass::BadNoisy,
+                    promotion: None,
+                    score: i64::from(see.0),
+                },
+                _ => Self {
+                    class: OrderClass::GoodNoisy,
+                    promotion,
+                    score: i64::from(mvv_lva),
+                },
+            }
+        } else if let Some(index) = context.killers.iter().position(|k| *k == Some(move_)) {
+            Self {
+                class: OrderClass::Killer,
+                promotion: None,
+                score: -(index as i64),
+            }
+        } else {
+            let history = context.history.map_or(0, |history| history.get(move_));
+            Self {
+                class: OrderClass::Quiet,
+                promotion: None,
+                score: i64::from(history),
+            }
         }
     }
 }
@@ -83,11 +220,15 @@ impl From<(MoveInfo, Option<Move>)> for OrderStrategy {
 /// # Arguments
 ///
 /// * `legal_moves`: List of MoveInfos for all legal moves of current position.
-/// * `maybe_key_move`: Transposition Table move for current position.
-pub fn order_all_moves(legal_moves: MoveInfoList, maybe_key_move: Option<Move>) -> MoveInfoList {
+/// * `context`: OrderingContext of current position, or only its Transposition Table move.
+pub fn order_all_moves<'a, C>(legal_moves: MoveInfoList, context: C) -> MoveInfoList
+where
+    C: Into<OrderingContext<'a>>,
+{
+    let context = context.into();
     let mut ordering_vec: ArrayVec<(MoveInfo, OrderStrategy), MAX_MOVES> = legal_moves
         .into_iter()
-        .map(|move_info| (move_info, OrderStrategy::from((move_info, maybe_key_move))))
+        .map(|move_info| (move_info, OrderStrategy::from((move_info, &context))))
         .collect();
 
     // Sort all moves using their OrderStrategy as a key.
@@ -104,8 +245,9 @@ pub fn pick_best_move(legal_moves: &mut MoveInfoList, key_move: Option<Move>) ->
         .iter()
         .enumerate()
         .max_by(|left, right| {
-            let left = OrderStrategy::from((*left.1, key_move));
-            let right = OrderStrategy::from((*right.1, key_move));
+            let context = OrderingContext::new(key_move);
+            let left = OrderStrategy::from((*left.1, &context));
+            let right = OrderStrategy::from((*right.1, &context));
 
             left.cmp(&right)
         })
@@ -148,7 +290,7 @@ mod tests {
     fn order_strategy_cmp() {
         let os = OrderStrategy::default();
         let mut gt_os = OrderStrategy::default();
-        gt_os.is_tt_move = true;
+        gt_os.class = OrderClass::HashMove;
 
         let mut lt_os = OrderStrategy::default();
         lt_os.promotion = Some(PieceKind::Queen.centipawns());
@@ -156,4 +298,87 @@ mod tests {
         assert!(gt_os > os);
         assert!(gt_os > lt_os);
     }
+
+    #[test]
+    fn order_all_moves_priority() {
+        let pos = Position::parse_fen("2n1k3/p7/3p4/2r5/3P4/8/8/R3K1N1 w - - 0 1").unwrap();
+        let hash_move = Move::new(G1, F3, None);
+        let good_capture = Move::new(D4, C5, None);
+        let killer = Move::new(G1, H3, None);
+        let history_quiet = Move::new(E1, D2, None);
+        let bad_capture = Move::new(A1, A7, None);
+
+        let mut history = HistoryTable::new();
+        history.update(history_quiet, 3);
+        let context = OrderingContext {
+            hash_move: Some(hash_move),
+            killers: [Some(killer), None],
+            history: Some(&history),
+            see: Some(&pos),
+        };
+        let ordered: Vec<Move> = order_all_moves(pos.get_legal_move_infos(), &context)
+            .into_iter()
+            .rev()
+            .map(|move_info| move_info.move_())
+            .collect();
+
+        assert_eq!(ordered.len(), pos.get_legal_moves().len());
+        assert_eq!(
+            ordered[..4],
+            [hash_move, good_capture, killer, history_quiet]
+        );
+        assert_eq!(ordered.last(), Some(&bad_capture));
+
+        // Without SEE, the losing capture is ordered with the good captures by MVV-LVA.
+        let context = OrderingContext {
+            see: None,
+            ..context
+        };
+        let ordered: Vec<Move> = order_all_moves(pos.get_legal_move_infos(), &context)
+            .into_iter()
+            .rev()
+            .map(|move_info| move_info.move_())
+            .collect();
+        assert_eq!(ordered[..3], [hash_move, good_capture, bad_capture]);
+    }
+
+    #[test]
+    fn history_table_scores() {
+        let mut history = HistoryTable::new();
+        assert!(history.is_empty());
+
+        let move_ = Move::new(E2, E4, None);
+        history.update(move_, 2);
+        history.update(move_, 3);
+        assert_eq!(history.get(move_), 13);
+        assert_eq!(history.get(Move::new(E4, E2, None)), 0);
+        assert!(!history.is_empty());
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn mvv_lva_prefers_valuable_victims_then_cheap_attackers() {
+        use PieceKind::*;
+        assert!(mvv_lva(Pawn, Queen) > mvv_lva(Queen, Pawn));
+        assert!(mvv_lva(King, Bishop) > mvv_lva(Pawn, Knight));
+
+        // Ordered from least to most valuable, without the uncapturable king as a victim.
+        let victims = [Pawn, Knight, Bishop, Rook, Queen];
+        let attackers = [Pawn, Knight, Bishop, Rook, Queen, King];
+        for attacker in attackers {
+            for pair in victims.windows(2) {
+                assert!(mvv_lva(attacker, pair[0]) < mvv_lva(attacker, pair[1]));
+            }
+        }
+        for victim in victims {
+            for pair in attackers.windows(2) {
+                assert!(mvv_lva(pair[0], victim) > mvv_lva(pair[1], victim));
+            }
+            for attacker in attackers {
+                assert!(mvv_lva(attacker, victim) > 0);
+            }
+        }
+    }
 }
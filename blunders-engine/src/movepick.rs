@@ -0,0 +1,275 @@
+//! Move Picker
+//!
+//! Staged move generation used by search.
+//!
+//! Most nodes of an alpha-beta search that cut off do so on one of the first few moves searched,
+//! so generating every legal move up front is often wasted work.
+//! A MovePicker instead generates and yields moves in stages, best guesses first:
+//! 1. Hash move, if it is legal.
+//! 2. Noisy moves (captures and promotions), ordered by MVV-LVA.
+//! 3. Killer moves, if they are legal quiet moves.
+//! 4. All remaining quiet moves, by history score if a history table is given.
+//!
+//! Quiet moves are never generated if search stops taking moves before they are reached.
+
+use crate::coretypes::{Move, MoveInfo};
+use crate::movelist::{MoveInfoList, MoveList};
+use crate::moveorder::{pick_best_move, HistoryTable};
+use crate::position::Position;
+
+/// Stage of a MovePicker, in order of progression.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Stage {
+    HashMove,
+    Noisy,
+    Killers,
+    Quiets,
+    Done,
+}
+
+/// MovePicker is an iterator which yields each legal move of a position exactly once,
+/// generating moves lazily in stages.
+#[derive(Debug, Clone)]
+pub struct MovePicker {
+    position: Position,
+    stage: Stage,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    noisy: Option<MoveInfoList>,
+    quiets: Option<MoveList>,
+}
+
+impl MovePicker {
+    /// Create a new MovePicker for the legal moves of a position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position`: Position to pick legal moves of.
+    /// * `hash_move`: Transposition Table move for position, yielded first if legal.
+    /// * `killers`: Quiet moves which caused cutoffs in sibling nodes, yielded after noisy moves if legal.
+    pub fn new(position: &Position, hash_move: Option<Move>, killers: [Option<Move>; 2]) -> Self {
+        Self {
+            position: *position,
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            noisy: None,
+            quiets: None,
+        }
+    }
+
+    /// Returns the legal noisy moves of the position, generating them if needed.
+    fn noisy(&mut self) -> &mut MoveInfoList {
+        let position = &self.position;
+        self.noisy.get_or_insert_with(|| {
+            position
+                .get_legal_noisy_moves()
+                .into_iter()
+                .map(|move_| position.move_info(move_))
+                .collect()
+        })
+    }
+
+    /// Returns the legal quiet moves of the position, generating them if needed.
+    fn quiets(&mut self) -> &mut MoveList {
+        let position = &self.position;
+        self.quiets
+            .get_or_insert_with(|| position.get_legal_quiet_moves())
+    }
+
+    /// Returns the next legal move, as `next`, picking quiet moves by their history scores.
+    /// The greatest score is picked from the history table at the time of each pick.
+    pub fn next_with_history(&mut self, history: &HistoryTable) -> Option<MoveInfo> {
+        self.pick(Some(history))
+    }
+
+    /// Remove and return the next quiet move, which is the move with the greatest
+    /// history score if a history table is given.
+    fn pick_quiet(&mut self, history: Option<&HistoryTable>) -> Option<Move> {
+        let quiets = self.quiets();
+        match history {
+            Some(history) => quiets
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, quiet)| history.get(**quiet))
+                .map(|(index, _)| index)
+                .map(|index| quiets.swap_remove(index)),
+            None => quiets.pop(),
+        }
+    }
+
+    /// Returns the next legal move of the current stage, advancing stages as they are emptied.
+    fn pick(&mut self, history: Option<&HistoryTable>) -> Option<MoveInfo> {
+        loop {
+            match self.stage {
+                // Only the moves of the same kind as the hash move are generated to check it.
+                // It is removed from later stages so that it is not yielded twice.
+                Stage::HashMove => {
+                    self.stage = Stage::Noisy;
+                    if let Some(hash_move) = self.hash_move {
+                        let is_legal = match self.position.is_noisy(hash_move) {
+                            true => self.take_noisy(hash_move),
+                            false => self.take_quiet(hash_move),
+                        };
+                        if is_legal {
+                            return Some(self.position.move_info(hash_move));
+                        }
+                    }
+                }
+                Stage::Noisy => match pick_best_move(self.noisy(), None) {
+                    Some(move_info) => return Some(move_info),
+                    None => self.stage = Stage::Killers,
+                },
+                // Killers are only yielded if they are legal quiet moves that have not been yielded.
+                Stage::Killers => {
+                    self.stage = Stage::Quiets;
+                    for idx in 0..self.killers.len() {
+                        if let Some(killer) = self.killers[idx].take() {
+                            if self.take_quiet(killer) {
+                                self.stage = Stage::Killers;
+                                return Some(self.position.move_info(killer));
+                            }
+                        }
+                    }
+                }
+                Stage::Quiets => match self.pick_quiet(history) {
+                    Some(move_) => return Some(self.position.move_info(move_)),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Remove a move from the quiet moves, returning true if it was present.
+    fn take_quiet(&mut self, move_: Move) -> bool {
+        let quiets = self.quiets();
+        match quiets.iter().position(|quiet| *quiet == move_) {
+            Some(index) => {
+                quiets.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a move from the noisy moves, returning true if it was present.
+    fn take_noisy(&mut self, move_: Move) -> bool {
+        let noisy = self.noisy();
+        match noisy.iter().position(|info| info.move_() == move_) {
+            Some(index) => {
+                noisy.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = MoveInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pick(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coretypes::Square::*;
+    use crate::fen::Fen;
+
+    /// Returns the sorted moves of a MovePicker and of get_legal_moves for comparison.
+    fn picked_and_legal(
+        position: &Position,
+        hash_move: Option<Move>,
+        killers: [Option<Move>; 2],
+    ) -> (Vec<Move>, Vec<Move>) {
+        let mut picked: Vec<Move> = MovePicker::new(position, hash_move, killers)
+            .map(|move_info| move_info.move_())
+            .collect();
+        let mut legal: Vec<Move> = position.get_legal_moves().into_iter().collect();
+        picked.sort_by_key(|move_| format!("{}", move_));
+        legal.sort_by_key(|move_| format!("{}", move_));
+        (picked, legal)
+    }
+
+    #[test]
+    fn picks_same_moves_as_legal_moves() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnb1kbnr/pppp1ppp/8/4p3/5PPq/8/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        ];
+        for fen in fens {
+            let position = Position::parse_fen(fen).unwrap();
+            let (picked, legal) = picked_and_legal(&position, None, [None, None]);
+            assert_eq!(picked, legal, "{}", fen);
+
+            // Every legal move as a hash move or killer still yields each legal move once.
+            for move_ in position.get_legal_moves() {
+                let (picked, legal) = picked_and_legal(&position, Some(move_), [Some(move_), None]);
+                assert_eq!(picked, legal, "{} {}", fen, move_);
+            }
+        }
+    }
+
+    #[test]
+    fn picks_hash_move_first() {
+        let position = Position::start_position();
+        let hash_move = Move::new(G1, F3, None);
+        let mut picker = MovePicker::new(&position, Some(hash_move), [None, None]);
+        assert_eq!(picker.next().unwrap().move_(), hash_move);
+        assert_eq!(picker.count(), 19);
+
+        // Illegal hash move is never yielded.
+        let illegal_move = Move::new(E2, E5, None);
+        let picker = MovePicker::new(&position, Some(illegal_move), [Some(illegal_move), None]);
+        assert!(picker
+            .map(|info| info.move_())
+            .all(|move_| move_ != illegal_move));
+    }
+
+    #[test]
+    fn picks_captures_then_killers_then_quiets() {
+        let position =
+            Position::parse_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")
+                .unwrap();
+        let capture = Move::new(E4, D5, None);
+        let killer = Move::new(B1, C3, None);
+        let mut picker = MovePicker::new(&position, None, [None, Some(killer)]);
+
+        assert_eq!(picker.next().unwrap().move_(), capture);
+        assert_eq!(picker.next().unwrap().move_(), killer);
+        assert!(picker.all(|info| !info.is_capture()));
+    }
+
+    #[test]
+    fn picks_quiets_by_history() {
+        let position = Position::start_position();
+        let mut history = HistoryTable::new();
+        let best = Move::new(G1, F3, None);
+        let second = Move::new(D2, D4, None);
+        history.update(best, 3);
+        history.update(second, 2);
+
+        let mut picker = MovePicker::new(&position, None, [None, None]);
+        assert_eq!(picker.next_with_history(&history).unwrap().move_(), best);
+        assert_eq!(picker.next_with_history(&history).unwrap().move_(), second);
+
+        // Killers are still picked before quiet moves with greater history scores.
+        let killer = Move::new(A2, A3, None);
+        let mut picker = MovePicker::new(&position, None, [Some(killer), None]);
+        assert_eq!(picker.next_with_history(&history).unwrap().move_(), killer);
+        assert_eq!(picker.next_with_history(&history).unwrap().move_(), best);
+        let mut count = 2;
+        while picker.next_with_history(&history).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 20);
+    }
+}
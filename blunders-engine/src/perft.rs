@@ -5,13 +5,16 @@
 //! A simple debugging and testing function used to count
 //! the number of nodes at a specific depth.
 
+use std::mem;
 use std::ops::{Add, AddAssign};
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
-use crate::coretypes::PlyKind;
+use crate::coretypes::{Move, PlyKind};
 use crate::movelist::MoveList;
 use crate::position::Position;
+use crate::threads::ThreadPool;
+use crate::zobrist::{HashKind, ZobristTable};
 
 /// Debugging information about results of perft test.
 /// nodes: Number of nodes at lowest depth of perft.
@@ -92,6 +95,168 @@ pub fn perft(mut position: Position, ply: PlyKind, threads: usize) -> PerftInfo
         .unwrap()
 }
 
+/// Perft divide counts the number of nodes at a certain depth for each legal move of the root position.
+/// Each move is paired with the perft of the position after that move, at `ply - 1`.
+/// The counts are returned with their total, which is the perft of the root position at `ply`.
+/// This is used to find movegen bugs by comparing per-move counts against a reference engine.
+pub fn divide(position: &Position, ply: PlyKind) -> (Vec<(Move, u64)>, u64) {
+    assert_ne!(ply, 0, "perft divide requires at least 1 ply");
+    let mut position = *position;
+    let cache = position.cache();
+
+    let divided: Vec<(Move, u64)> = position
+        .get_legal_moves()
+        .into_iter()
+        .map(|legal_move| {
+            let move_info = position.do_move(legal_move);
+            let nodes = match ply {
+                1 => 1,
+                _ => perft_recurse(&mut position, ply - 1).nodes,
+            };
+            position.undo_move(move_info, cache);
+            (legal_move, nodes)
+        })
+        .collect();
+    let total = divided.iter().map(|(_, nodes)| nodes).sum();
+
+    (divided, total)
+}
+
+/// Count the number of nodes at a certain depth, splitting root moves across a pool of threads.
+/// Each root move is a job for the ThreadPool, and sub-perft counts are summed as they finish.
+/// The result is identical to that of single threaded perft.
+pub fn perft_parallel(position: &Position, ply: PlyKind, threads: usize) -> u64 {
+    if ply <= 1 || threads <= 1 {
+        return perft(*position, ply, 1).nodes;
+    }
+
+    let legal_moves = position.get_legal_moves();
+    let num_moves = legal_moves.len();
+    let pool = ThreadPool::new(threads);
+    let (sender, receiver) = mpsc::channel::<u64>();
+
+    for legal_move in legal_moves {
+        let mut position = *position;
+        let sender = sender.clone();
+        let job: Box<dyn FnOnce() + Send> = Box::new(move || {
+            position.do_move(legal_move);
+            let nodes = perft_recurse(&mut position, ply - 1).nodes;
+            sender.send(nodes).unwrap();
+        });
+        pool.run(job);
+    }
+
+    receiver.iter().take(num_moves).sum()
+}
+
+/// A cached perft count of the subtree of a position to a ply.
+/// Ply 0 is never cached, so entries with ply 0 are empty.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+struct PerftEntry {
+    hash: HashKind,
+    ply: PlyKind,
+    nodes: u64,
+}
+
+/// PerftTable caches node counts of perft subtrees keyed by position hash and ply,
+/// so that transpositions are only counted once by `perft_tt`.
+/// An entry is always replaced by a newer one with the same index.
+#[derive(Debug, Clone)]
+pub struct PerftTable {
+    ztable: ZobristTable,
+    entries: Vec<PerftEntry>,
+}
+
+impl PerftTable {
+    /// Returns a new PerftTable which holds entry_capacity entries.
+    pub fn with_capacity(entry_capacity: usize) -> Self {
+        Self::with_capacity_and_zobrist(entry_capacity, ZobristTable::new())
+    }
+
+    /// Returns a new PerftTable which uses approximately mb megabytes.
+    pub fn with_mb(mb: usize) -> Self {
+        Self::with_capacity((mb * 1_000_000) / mem::size_of::<PerftEntry>())
+    }
+
+    /// Returns a new PerftTable with entry_capacity entries that hashes with ztable.
+    pub fn with_capacity_and_zobrist(entry_capacity: usize, ztable: ZobristTable) -> Self {
+        assert!(
+            entry_capacity > 0,
+            "PerftTable requires a non-zero capacity"
+        );
+        Self {
+            ztable,
+            entries: vec![PerftEntry::default(); entry_capacity],
+        }
+    }
+
+    /// Returns the number of entries this table holds.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Remove all cached counts from this table.
+    pub fn clear(&mut self) {
+        self.entries.fill(PerftEntry::default());
+    }
+
+    fn hash_to_index(&self, hash: HashKind) -> usize {
+        (hash % self.entries.len() as HashKind) as usize
+    }
+
+    fn get(&self, hash: HashKind, ply: PlyKind) -> Option<u64> {
+        let entry = &self.entries[self.hash_to_index(hash)];
+        (entry.ply == ply && entry.hash == hash).then_some(entry.nodes)
+    }
+
+    fn insert(&mut self, hash: HashKind, ply: PlyKind, nodes: u64) {
+        let index = self.hash_to_index(hash);
+        self.entries[index] = PerftEntry { hash, ply, nodes };
+    }
+}
+
+/// Count the number of nodes at a certain depth, caching subtree counts in a PerftTable.
+/// Positions reached through different move orders are only counted once per ply,
+/// which makes deep perft much faster. The result is identical to that of perft
+/// unless two positions have colliding hashes, so this also tests zobrist hashing.
+pub fn perft_tt(mut position: Position, ply: PlyKind, tt: &mut PerftTable) -> PerftInfo {
+    if ply == 0 {
+        return PerftInfo::new(1);
+    }
+    let hash = tt.ztable.generate_hash((&position).into());
+    PerftInfo::new(perft_tt_recurse(&mut position, hash, ply, tt))
+}
+
+/// Ply must be non-zero, and hash must be the hash of position.
+fn perft_tt_recurse(
+    position: &mut Position,
+    hash: HashKind,
+    ply: PlyKind,
+    tt: &mut PerftTable,
+) -> u64 {
+    debug_assert_ne!(ply, 0);
+    if ply == 1 {
+        return position.get_legal_moves().len() as u64;
+    }
+    if let Some(nodes) = tt.get(hash, ply) {
+        return nodes;
+    }
+
+    let cache = position.cache();
+    let mut nodes = 0;
+    for legal_move in position.get_legal_moves() {
+        let move_info = position.do_move(legal_move);
+        let mut child_hash = hash;
+        tt.ztable
+            .update_hash(&mut child_hash, (&*position).into(), move_info, cache);
+        nodes += perft_tt_recurse(position, child_hash, ply - 1, tt);
+        position.undo_move(move_info, cache);
+    }
+
+    tt.insert(hash, ply, nodes);
+    nodes
+}
+
 /// perft_executor works by stealing one move at a time from given moves list and running perft on that move.
 /// When there are no moves left to steal, this function stores the data it has collected so far and returns.
 /// params:
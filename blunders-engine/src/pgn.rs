@@ -0,0 +1,293 @@
+//! Portable Game Notation, a standard notation for recording a chess game.
+//!
+//! [Wikipedia PGN](https://en.wikipedia.org/wiki/Portable_Game_Notation)\
+//! [Chess Programming PGN](https://www.chessprogramming.org/Portable_Game_Notation)
+//!
+//! Example:\
+//! `[Event "?"] 1. e4 e5 {Open game} 2. Nf3 $1 Nc6 (2... d6) 1/2-1/2`
+
+use std::time::Duration;
+
+use crate::coretypes::Color;
+use crate::error::{self, ErrorKind};
+use crate::fen::Fen;
+use crate::movelist::MoveHistory;
+use crate::position::{Game, Position};
+use crate::san::{parse_san, to_san};
+
+/// Game termination markers which end PGN movetext.
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Recommended maximum length of a line of PGN movetext.
+const LINE_LENGTH: usize = 80;
+
+impl Game {
+    /// Parse a single PGN game into a Game.
+    /// The game begins from the position of the `FEN` tag if present, otherwise from the start position.
+    /// Other tags, comments, NAGs, and variations are skipped.
+    pub fn from_pgn(pgn: &str) -> error::Result<Game> {
+        let mut base_position = Position::start_position();
+        let mut san_moves = Vec::new();
+        let mut chars = pgn.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                // Tag pair, of form `[Name "Value"]`.
+                '[' => {
+                    let tag: String = chars.by_ref().take_while(|ch| *ch != ']').collect();
+                    let (name, value) = tag.trim().split_once(char::is_whitespace).ok_or((
+                        ErrorKind::PgnMalformed,
+                        format!("tag [{}] missing value", tag),
+                    ))?;
+                    if name == "FEN" {
+                        base_position = Position::parse_fen(value.trim().trim_matches('"'))?;
+                        base_position.validate()?;
+                    }
+                }
+                // Brace comments end at the first closing brace, line comments at end of line.
+                '{' => chars.by_ref().take_while(|ch| *ch != '}').for_each(drop),
+                ';' => chars.by_ref().take_while(|ch| *ch != '\n').for_each(drop),
+                // Variations may be nested.
+                '(' => {
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match chars.next() {
+                            Some('(') => depth += 1,
+                            Some(')') => depth -= 1,
+                            Some(_) => (),
+                            None => {
+                                return Err((ErrorKind::PgnMalformed, "unclosed variation").into())
+                            }
+                        }
+                    }
+                }
+                ch if ch.is_whitespace() => (),
+                // Symbol tokens: move numbers, NAGs, results, and SAN moves.
+                _ => {
+                    let mut token = ch.to_string();
+                    while let Some(next) =
+                        chars.next_if(|ch| !"[]{}();".contains(*ch) && !ch.is_whitespace())
+                    {
+                        token.push(next);
+                    }
+
+                    if RESULTS.contains(&token.as_str()) {
+                        break;
+                    }
+
+                    // Move numbers may be joined to the following move, as in `1.e4`.
+                    let token =
+                        token.trim_start_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+                    if !token.is_empty() && !token.starts_with('$') {
+                        san_moves.push(token.to_string());
+                    }
+                }
+            }
+        }
+
+        let mut position = base_position;
+        let mut moves = MoveHistory::new();
+        for san in san_moves {
+            let move_ = parse_san(&position, &san)?;
+            position.do_move(move_);
+            moves
+                .try_push(move_)
+                .map_err(|_| ErrorKind::MoveHistoryExceeded)?;
+        }
+
+        Game::new(base_position, moves)
+    }
+
+    /// Format this Game as a PGN string.
+    /// The seven tag roster is written with unknown values, followed by `SetUp` and `FEN` tags
+    /// if the game did not begin from the start position. The result is only known for games
+    /// ending in checkmate or a draw, otherwise the game is ongoing.
+    pub fn to_pgn(&self) -> String {
+        let result = self.pgn_result();
+        let mut pgn = String::new();
+
+        for (name, value) in [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+        ] {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        if self.base_position != Position::start_position() {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{}\"]\n", self.base_position.to_fen()));
+        }
+        pgn.push('\n');
+
+        // Each token is a move number, a SAN move, a move time comment, or the result.
+        // Black moves are numbered if they begin the movetext or follow a comment.
+        let mut tokens = Vec::new();
+        let mut position = self.base_position;
+        let mut after_comment = false;
+        for (idx, move_) in self.moves.iter().enumerate() {
+            match (*position.player(), idx == 0 || after_comment) {
+                (Color::White, _) => tokens.push(format!("{}.", position.fullmoves())),
+                (Color::Black, true) => tokens.push(format!("{}...", position.fullmoves())),
+                _ => (),
+            }
+            tokens.push(to_san(&position, *move_));
+            position.do_move(*move_);
+
+            after_comment = match self.move_time(idx) {
+                Some(time) => {
+                    tokens.push(format!("{{[%emt {}]}}", format_move_time(time)));
+                    true
+                }
+                None => false,
+            };
+        }
+        tokens.push(result.to_string());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > LINE_LENGTH {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+            pgn.push_str(&token);
+            line_len += token.len();
+        }
+        pgn.push('\n');
+
+        pgn
+    }
+
+    /// Returns the PGN result token for the current position of this game.
+    fn pgn_result(&self) -> &'static str {
+        let position = &self.position;
+        if position.is_checkmate() {
+            match position.player() {
+                Color::White => "0-1",
+                Color::Black => "1-0",
+            }
+        } else if position.is_stalemate()
+            || position.is_insufficient_material()
+            || self.is_fifty_move_draw()
+            || self.is_threefold_repetition()
+        {
+            "1/2-1/2"
+        } else {
+            "*"
+        }
+    }
+}
+
+/// Format a move time as `h:mm:ss`, with milliseconds if the time is not whole seconds.
+fn format_move_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    let millis = time.subsec_millis();
+    let hms = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    match millis {
+        0 => hms,
+        _ => format!("{}.{:03}", hms, millis),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_pgn_annotated_game() {
+        let pgn = r#"[Event "Casual Game"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "White"]
+[Black "Black"]
+[Result "*"]
+
+1. e4 {King's pawn} e5 2. Nf3 $1 Nc6 (2... d6 3. d4 (3. Bc4) exd4) 3. Bb5 a6 ; Ruy Lopez
+4.Ba4 Nf6 5. O-O Be7! *"#;
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.moves.len(), 10);
+        assert_eq!(
+            game.position.to_fen(),
+            "r1bqk2r/1pppbppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 4 6"
+        );
+    }
+
+    #[test]
+    fn from_pgn_fen_tag() {
+        let pgn = r#"[FEN "4k3/1P6/8/8/8/8/8/4K3 w - - 0 40"]
+[SetUp "1"]
+
+40. b8=Q+ Kd7 41. Qb5+ 1-0"#;
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(
+            game.base_position.to_fen(),
+            "4k3/1P6/8/8/8/8/8/4K3 w - - 0 40"
+        );
+        assert_eq!(game.position.to_fen(), "8/3k4/8/1Q6/8/8/8/4K3 b - - 2 41");
+    }
+
+    #[test]
+    fn from_pgn_errors() {
+        assert!(Game::from_pgn("1. e4 e4").is_err());
+        assert!(Game::from_pgn("1. e4 (1. d4").is_err());
+        assert!(Game::from_pgn("[FEN \"8/8/8\"] 1. e4").is_err());
+        assert_eq!(Game::from_pgn("").unwrap(), Game::start_position());
+    }
+
+    #[test]
+    fn to_pgn_round_trip() {
+        let pgns = [
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O",
+            "1. f3 e5 2. g4 Qh4#",
+            "1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8",
+            "[FEN \"4k3/1P6/8/8/8/8/8/4K3 b - - 0 40\"] 40... Kd7 41. b8=N+ Kc7",
+        ];
+        for pgn in pgns {
+            let game = Game::from_pgn(pgn).unwrap();
+            let exported = game.to_pgn();
+            let round_trip = Game::from_pgn(&exported).unwrap();
+            assert_eq!(round_trip, game, "{}", exported);
+        }
+    }
+
+    #[test]
+    fn to_pgn_format() {
+        let game = Game::from_pgn("1. f3 e5 2. g4 Qh4#").unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.starts_with("[Event \"?\"]\n"));
+        assert!(pgn.contains("[Result \"0-1\"]\n\n"));
+        assert!(pgn.ends_with("\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+        assert!(!pgn.contains("FEN"));
+
+        let game = Game::from_pgn("[FEN \"4k3/1P6/8/8/8/8/8/4K3 b - - 0 40\"] 40... Kd7").unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/1P6/8/8/8/8/8/4K3 b - - 0 40\"]\n"));
+        assert!(pgn.ends_with("\n40... Kd7 *\n"));
+
+        let game = Game::from_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8").unwrap();
+        assert!(game.to_pgn().ends_with("Ng8 1/2-1/2\n"));
+    }
+
+    #[test]
+    fn to_pgn_move_times() {
+        let mut game = Game::from_pgn("1. e4 e5 2. Nf3 Nc6").unwrap();
+        game.record_move_time(0, Duration::from_millis(1500))
+            .unwrap();
+        game.record_move_time(3, Duration::from_secs(3723)).unwrap();
+
+        let pgn = game.to_pgn();
+        assert!(
+            pgn.ends_with("\n1. e4 {[%emt 0:00:01.500]} 1... e5 2. Nf3 Nc6 {[%emt 1:02:03]} *\n")
+        );
+
+        // Move time comments are skipped when parsed.
+        assert_eq!(Game::from_pgn(&pgn).unwrap().moves, game.moves);
+    }
+}
@@ -3,27 +3,38 @@
 //! Positions and moves are assumed to be strictly legal,
 //! and have undefined behavior for illegal activity.
 
+use std::convert::TryFrom;
 use std::fmt::{self, Display};
+use std::str::{FromStr, SplitWhitespace};
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::bitboard::Bitboard;
 use crate::boardrepr::PieceSets;
 use crate::coretypes::{
-    Castling, Color, Move, MoveCount, MoveInfo, MoveKind, Piece, PieceKind, Square,
+    Castling, CastlingFiles, Color, Cp, Move, MoveCount, MoveInfo, MoveKind, Piece, PieceKind,
+    Square,
 };
 use crate::coretypes::{Color::*, PieceKind::*, Square::*};
 use crate::error::{self, ErrorKind};
-use crate::fen::Fen;
+use crate::eval;
+use crate::fen::{Fen, ParseFenError};
 use crate::movegen as mg;
-use crate::movelist::{MoveHistory, MoveList};
+use crate::movelist::{MoveHistory, MoveInfoList, MoveList};
+use crate::zobrist::{self, HashKind};
 
 /// Game contains information for an in progress game:
 /// The base position the game started from, the sequence of moves that were
 /// played, and the current position.
+/// The time spent choosing each move may optionally be recorded.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Game {
     pub base_position: Position,
     pub moves: MoveHistory,
     pub position: Position,
+    move_times: Vec<Option<Duration>>,
 }
 
 impl Game {
@@ -44,13 +55,184 @@ impl Game {
             base_position,
             moves,
             position,
+            move_times: Vec::new(),
         })
     }
 
+    /// Apply a move to the current position of this game, adding it to the move history.
+    /// If the move is illegal, Err is returned and the game is unchanged.
+    pub fn push_move(&mut self, move_: Move) -> error::Result<()> {
+        if self.moves.is_full() {
+            return Err(ErrorKind::MoveHistoryExceeded.into());
+        }
+        self.position
+            .do_legal_move(move_)
+            .ok_or(ErrorKind::GameIllegalMove)?;
+        self.moves.push(move_);
+        Ok(())
+    }
+
+    /// Remove the last move from the move history, returning it.
+    /// The current position is replayed from the base position, since a position
+    /// cannot be restored from a Move alone. Returns None if no moves have been played.
+    pub fn pop_move(&mut self) -> Option<Move> {
+        let move_ = self.moves.pop()?;
+        let mut position = self.base_position;
+        for played in &self.moves {
+            position.do_move(*played);
+        }
+        self.position = position;
+        self.move_times.truncate(self.moves.len());
+        Some(move_)
+    }
+
+    /// Record the time spent choosing the move at an index of the move history.
+    /// If the index is not of a played move, Err is returned.
+    pub fn record_move_time(&mut self, index: usize, time: Duration) -> error::Result<()> {
+        if index >= self.moves.len() {
+            return Err(ErrorKind::GameMoveIndexOutOfRange.into());
+        }
+        if self.move_times.len() < self.moves.len() {
+            self.move_times.resize(self.moves.len(), None);
+        }
+        self.move_times[index] = Some(time);
+        Ok(())
+    }
+
+    /// Returns the time recorded for the move at an index of the move history, if any.
+    pub fn move_time(&self, index: usize) -> Option<Duration> {
+        match index < self.moves.len() {
+            true => self.move_times.get(index).copied().flatten(),
+            false => None,
+        }
+    }
+
     /// Create a new game in the standard chess start position.
     pub fn start_position() -> Self {
         Self::from(Position::start_position())
     }
+
+    /// Returns true if the current position has occurred at least three times in this game.
+    /// Positions before the most recent capture or pawn move cannot repeat, so they are not counted.
+    /// Returns false if the move history has a move which is not legal from the base position.
+    pub fn is_threefold_repetition(&self) -> bool {
+        let mut position = self.base_position;
+        let mut positions = vec![position];
+
+        for move_ in &self.moves {
+            let move_info = match position.do_legal_move(*move_) {
+                Some(move_info) => move_info,
+                None => return false,
+            };
+            if move_info.is_unrepeatable() {
+                positions.clear();
+            }
+            positions.push(position);
+        }
+
+        let current = positions.last().unwrap();
+        positions
+            .iter()
+            .filter(|other| other.is_same_as(current))
+            .count()
+            >= 3
+    }
+
+    /// Returns true if the current position is drawn by the fifty-move rule.
+    /// A position which is checkmate is not drawn.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        let num_legal_moves = self.position.get_legal_moves().len();
+        self.position.fifty_move_rule(num_legal_moves)
+    }
+
+    /// Parse a game from the arguments of a UCI `position` command.
+    /// format: `[fen fen_str | startpos] (moves move_list ...)`
+    ///
+    /// Example:
+    /// ```
+    /// # use blunders_engine::Game;
+    /// let game = Game::from_uci_position_str("startpos moves e2e4 e7e5").unwrap();
+    /// assert_eq!(game.moves.len(), 2);
+    /// assert_eq!(game.to_uci_position_str(), "startpos moves e2e4 e7e5");
+    /// ```
+    pub fn from_uci_position_str(s: &str) -> error::Result<Self> {
+        Self::parse_uci_position(s.split_whitespace(), false)
+    }
+
+    /// Parse a game from the words of a UCI `position` command following `position`.
+    /// If `chess960` is set, castling moves are described as the king moving onto its rook.
+    pub(crate) fn parse_uci_position(
+        mut input: SplitWhitespace,
+        chess960: bool,
+    ) -> error::Result<Self> {
+        let position_input = input.next().ok_or((
+            ErrorKind::UciNoArgument,
+            "position missing description [fen | startpos]",
+        ))?;
+
+        // Parse a valid position from startpos or FEN, or return an Err(_).
+        let mut base_position = match position_input {
+            "startpos" => Ok(Position::start_position()),
+            "fen" => {
+                let mut fen_str = String::new();
+                for _ in 0..6 {
+                    fen_str.push_str(input.next().ok_or(ErrorKind::UciPositionMalformed)?);
+                    fen_str.push(' ');
+                }
+                Position::parse_fen(&fen_str)
+            }
+            _ => return Err(ErrorKind::UciPositionMalformed.into()),
+        }?;
+        base_position.validate()?;
+        if chess960 {
+            base_position.set_chess960(true);
+        }
+
+        let mut moves = MoveHistory::new();
+
+        // Check if there is a sequence of moves to apply to the position.
+        if let Some("moves") = input.next() {
+            for move_str in input {
+                moves
+                    .try_push(Move::from_str(move_str)?)
+                    .map_err(|_| ErrorKind::MoveHistoryExceeded)?;
+            }
+        }
+
+        Game::new(base_position, moves)
+    }
+
+    /// Returns the arguments of a UCI `position` command which describe this game,
+    /// the inverse of `from_uci_position_str`.
+    pub fn to_uci_position_str(&self) -> String {
+        let mut position_str = match self.base_position == Position::start_position() {
+            true => String::from("startpos"),
+            false => format!("fen {}", self.base_position.to_fen()),
+        };
+        if !self.moves.is_empty() {
+            position_str.push_str(" moves");
+            for move_ in &self.moves {
+                position_str.push(' ');
+                position_str.push_str(&move_.to_string());
+            }
+        }
+        position_str
+    }
+}
+
+/// Game is parsed from the arguments of a UCI `position` command.
+impl TryFrom<&str> for Game {
+    type Error = error::Error;
+    fn try_from(s: &str) -> error::Result<Self> {
+        Self::from_uci_position_str(s)
+    }
+}
+
+/// Game is displayed as the arguments of a UCI `position` command.
+impl Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_uci_position_str())
+    }
 }
 
 /// Convert a position to a Game with no past moves.
@@ -95,6 +277,69 @@ impl From<&Position> for Cache {
     }
 }
 
+/// Kind of legal moves to generate for a position.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum GenKind {
+    All,   // Every legal move.
+    Noisy, // Captures, en-passant and promotions.
+    Quiet, // Every move which is not noisy.
+}
+
+impl GenKind {
+    /// Returns true if a move which is or is not noisy is of this kind.
+    fn includes(self, noisy: bool) -> bool {
+        match self {
+            GenKind::All => true,
+            GenKind::Noisy => noisy,
+            GenKind::Quiet => !noisy,
+        }
+    }
+}
+
+/// A list which legal moves are appended to during generation.
+/// Each move is given with the kind of its moving piece, and its move kind if it is
+/// castling or en-passant, so that lists of MoveInfo do not classify moves again.
+trait MoveSink: Default {
+    fn push_move(
+        &mut self,
+        position: &Position,
+        move_: Move,
+        piece_kind: PieceKind,
+        special: Option<MoveKind>,
+    );
+
+    /// Drain a list of moves of a single piece kind, none of which are special, into self.
+    fn push_moves(&mut self, position: &Position, moves: &mut MoveList, piece_kind: PieceKind) {
+        for move_ in moves.drain(..) {
+            self.push_move(position, move_, piece_kind, None);
+        }
+    }
+}
+
+impl MoveSink for MoveList {
+    fn push_move(&mut self, _: &Position, move_: Move, _: PieceKind, _: Option<MoveKind>) {
+        self.push(move_);
+    }
+}
+
+impl MoveSink for MoveInfoList {
+    fn push_move(
+        &mut self,
+        position: &Position,
+        move_: Move,
+        piece_kind: PieceKind,
+        special: Option<MoveKind>,
+    ) {
+        let move_kind = special.unwrap_or_else(|| {
+            match position.pieces.on_player_square(!position.player, move_.to) {
+                Some(captured) => MoveKind::Capture(captured),
+                None => MoveKind::Quiet,
+            }
+        });
+        self.push(MoveInfo::new(move_, piece_kind, move_kind));
+    }
+}
+
 /// struct Position
 /// A complete data set that can represent any chess position.
 /// # Members:
@@ -104,6 +349,12 @@ impl From<&Position> for Cache {
 /// * en_passant - Indicates if en passant is possible, and for which square.
 /// * halfmoves - Tracker for 50 move draw rule. Resets after capture/pawn move.
 /// * fullmoves - Starts at 1, increments after each black player's move.
+/// * castling_files - Starting files of castling rooks, which only differ from standard in Chess960.
+/// * chess960 - Castling moves are described as the king moving onto its own castling rook.
+/// * material - Incrementally updated absolute material score of pieces.
+/// * pst_mg - Incrementally updated absolute midgame piece-square table score.
+/// * pst_eg - Incrementally updated absolute endgame piece-square table score.
+/// * pawn_hash - Incrementally updated Zobrist hash of only the placement of pawns.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Position {
     pub(crate) pieces: PieceSets,
@@ -112,19 +363,33 @@ pub struct Position {
     pub(crate) en_passant: Option<Square>,
     pub(crate) halfmoves: MoveCount,
     pub(crate) fullmoves: MoveCount,
+    pub(crate) castling_files: CastlingFiles,
+    pub(crate) chess960: bool,
+    pub(crate) material: Cp,
+    pub(crate) pst_mg: Cp,
+    pub(crate) pst_eg: Cp,
+    pub(crate) pawn_hash: HashKind,
 }
 
 impl Position {
     /// Standard chess start position.
     pub fn start_position() -> Self {
-        Self {
+        let mut position = Self {
             pieces: PieceSets::start_position(),
             player: Color::White,
             castling: Castling::start_position(),
             en_passant: None,
             halfmoves: 0,
             fullmoves: 1,
-        }
+            castling_files: CastlingFiles::STANDARD,
+            chess960: false,
+            material: Cp(0),
+            pst_mg: Cp(0),
+            pst_eg: Cp(0),
+            pawn_hash: 0,
+        };
+        position.recompute_accumulators();
+        position
     }
 
     /// Const getters.
@@ -146,6 +411,96 @@ impl Position {
     pub fn fullmoves(&self) -> &MoveCount {
         &self.fullmoves
     }
+    pub fn castling_files(&self) -> &CastlingFiles {
+        &self.castling_files
+    }
+    pub fn chess960(&self) -> &bool {
+        &self.chess960
+    }
+    pub fn material(&self) -> &Cp {
+        &self.material
+    }
+    pub fn pst_mg(&self) -> &Cp {
+        &self.pst_mg
+    }
+    pub fn pst_eg(&self) -> &Cp {
+        &self.pst_eg
+    }
+    pub fn pawn_hash(&self) -> &HashKind {
+        &self.pawn_hash
+    }
+
+    /// Set whether castling moves use Chess960 notation, where the king moves onto its own rook.
+    /// Positions with non-standard castling rook files are always Chess960.
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960 || !self.castling_files.is_standard();
+    }
+
+    /// Recompute material, piece-square table, and pawn hash accumulators by scanning all pieces.
+    /// Accumulators are updated incrementally as moves are made, so this is only needed
+    /// when pieces are placed directly, or to verify incremental values.
+    pub fn recompute_accumulators(&mut self) {
+        self.material = Cp(0);
+        self.pst_mg = Cp(0);
+        self.pst_eg = Cp(0);
+        self.pawn_hash = 0;
+        for color in Color::iter() {
+            for piece_kind in PieceKind::iter() {
+                let piece = Piece::new(color, piece_kind);
+                for square in self.pieces[&piece] {
+                    self.add_accumulators(piece, square);
+                }
+            }
+        }
+    }
+
+    /// Returns true if incremental accumulators are equal to a full recompute.
+    fn accumulators_match(&self) -> bool {
+        let mut recomputed = *self;
+        recomputed.recompute_accumulators();
+        self.material == recomputed.material
+            && self.pst_mg == recomputed.pst_mg
+            && self.pst_eg == recomputed.pst_eg
+            && self.pawn_hash == recomputed.pawn_hash
+    }
+
+    fn add_accumulators(&mut self, piece: Piece, square: Square) {
+        let (material, mg, eg) = eval::piece_square_values(piece, square);
+        self.material += material;
+        self.pst_mg += mg;
+        self.pst_eg += eg;
+        if piece.piece_kind == Pawn {
+            self.pawn_hash ^= zobrist::pawn_table().pawn_key(piece.color, square);
+        }
+    }
+
+    fn remove_accumulators(&mut self, piece: Piece, square: Square) {
+        let (material, mg, eg) = eval::piece_square_values(piece, square);
+        self.material = self.material - material;
+        self.pst_mg = self.pst_mg - mg;
+        self.pst_eg = self.pst_eg - eg;
+        if piece.piece_kind == Pawn {
+            self.pawn_hash ^= zobrist::pawn_table().pawn_key(piece.color, square);
+        }
+    }
+
+    /// Place a piece on a square and update accumulators.
+    /// Does nothing if the piece is already on the square.
+    fn set_piece(&mut self, piece: Piece, square: Square) {
+        if !self.pieces[&piece].has_square(square) {
+            self.pieces[&piece].set_square(square);
+            self.add_accumulators(piece, square);
+        }
+    }
+
+    /// Remove a piece from a square and update accumulators.
+    /// Does nothing if the piece is not on the square.
+    fn clear_piece(&mut self, piece: Piece, square: Square) {
+        if self.pieces[&piece].has_square(square) {
+            self.pieces[&piece].clear_square(square);
+            self.remove_accumulators(piece, square);
+        }
+    }
 
     /// Return the number of moves played in this game so far, from the fullmove counter.
     pub fn moves_played(&self) -> MoveCount {
@@ -157,12 +512,6 @@ impl Position {
             }
     }
 
-    /// Returns the age of this position.
-    /// Age is the same as moves_played(), except wrapped into a u8.
-    pub(crate) fn age(&self) -> u8 {
-        (self.moves_played() % u8::MAX as MoveCount) as u8
-    }
-
     /// Create a new position where the relative position is the same for the active player,
     /// but the player gets switched.
     /// This is equivalent to a vertical flip and color swap for all pieces,
@@ -177,10 +526,7 @@ impl Position {
         let mut pieces = PieceSets::new();
         for color in Color::iter() {
             for piece_kind in PieceKind::iter() {
-                for sq in self.pieces[(color, piece_kind)] {
-                    let flipped_sq = Square::from((sq.file(), sq.rank().flip()));
-                    pieces[(!color, piece_kind)].set_square(flipped_sq);
-                }
+                pieces[(!color, piece_kind)] = self.pieces[(color, piece_kind)].flip_vertical();
             }
         }
         flipped.pieces = pieces;
@@ -204,6 +550,21 @@ impl Position {
             .then(|| cr.set(Castling::W_QUEEN));
         flipped.castling = cr;
 
+        // Flip castling rook files between players.
+        let mut files = self.castling_files;
+        for color in Color::iter() {
+            let king_side = self.castling_files.rook_file(Castling::king_side_of(color));
+            let queen_side = self
+                .castling_files
+                .rook_file(Castling::queen_side_of(color));
+            files.set_rook_file(Castling::king_side_of(!color), king_side);
+            files.set_rook_file(Castling::queen_side_of(!color), queen_side);
+        }
+        flipped.castling_files = files;
+
+        // Accumulators are not symmetric by color, so recompute from flipped pieces.
+        flipped.recompute_accumulators();
+
         // Flip ep passant square
         flipped.en_passant = self
             .en_passant
@@ -230,6 +591,43 @@ impl Position {
         self.halfmoves >= 100 && num_legal_moves != 0
     }
 
+    /// Returns true if neither player has enough material to checkmate, indicating that it is drawn.
+    /// Drawn material configurations are:
+    /// * King vs King
+    /// * King and Bishop or Knight vs King
+    /// * King and Bishop vs King and Bishop, with both bishops on same colored squares
+    pub fn is_insufficient_material(&self) -> bool {
+        let has_major_or_pawn = [Pawn, Rook, Queen].iter().any(|&pk| {
+            self.pieces[(White, pk)].count_squares() > 0
+                || self.pieces[(Black, pk)].count_squares() > 0
+        });
+        if has_major_or_pawn {
+            return false;
+        }
+
+        let knights = self.pieces[(White, Knight)] | self.pieces[(Black, Knight)];
+        let bishops = self.pieces[(White, Bishop)] | self.pieces[(Black, Bishop)];
+        let num_minors = knights.count_squares() + bishops.count_squares();
+
+        match num_minors {
+            0 | 1 => true,
+            2 => {
+                // Only one bishop per player, on same colored squares.
+                let w_bishop = self.pieces[(White, Bishop)].count_squares() == 1;
+                let b_bishop = self.pieces[(Black, Bishop)].count_squares() == 1;
+                let same_colored = (bishops & Bitboard::WHITE_SQUARES).is_empty()
+                    || (bishops & Bitboard::BLACK_SQUARES).is_empty();
+                w_bishop && b_bishop && same_colored
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the piece occupying a square, or None if the square is empty.
+    pub fn piece_on(&self, square: Square) -> Option<Piece> {
+        self.pieces.on_square(square)
+    }
+
     /// Generate a MoveInfo for this position from a given Move.
     pub fn move_info(&self, move_: Move) -> MoveInfo {
         let moved_piece_kind = self
@@ -251,12 +649,17 @@ impl Position {
                 }
             }
         }
-        // Check for Castling
+        // Check for Castling. The king moving onto its own rook is always a castle.
+        // Outside of Chess960, the king moving two squares from its start is also a castle.
         else if moved_piece_kind == King {
-            match (move_.from, move_.to) {
-                (E1, C1) | (E1, G1) | (E8, C8) | (E8, G8) => move_kind = MoveKind::Castle,
-                _ => (),
+            let onto_rook = self.pieces[(self.player, Rook)].has_square(move_.to);
+            let two_squares = match (move_.from, move_.to) {
+                (E1, C1) | (E1, G1) | (E8, C8) | (E8, G8) => !self.chess960,
+                _ => false,
             };
+            if onto_rook || two_squares {
+                move_kind = MoveKind::Castle;
+            }
         }
 
         MoveInfo::new(move_, moved_piece_kind, move_kind)
@@ -322,15 +725,15 @@ impl Position {
         self.step_halfmoves(&move_info);
         self.step_fullmoves();
         self.en_passant = None;
-        self.pieces[active_piece].clear_square(move_info.from);
+        self.clear_piece(active_piece, move_info.from);
         self.player = !self.player;
 
         // If promoting, place promoting piece. Otherwise place active piece.
         if let Some(promoting_piece_kind) = move_info.promotion {
             let promoting_piece = Piece::new(player, promoting_piece_kind);
-            self.pieces[promoting_piece].set_square(move_info.to);
+            self.set_piece(promoting_piece, move_info.to);
         } else {
-            self.pieces[active_piece].set_square(move_info.to);
+            self.set_piece(active_piece, move_info.to);
         }
 
         // Handle all special moves.
@@ -338,27 +741,28 @@ impl Position {
             // Clear opposing player's captured piece.
             MoveKind::Capture(piece_kind) => {
                 let captured_piece = Piece::new(!player, piece_kind);
-                self.pieces[captured_piece].clear_square(move_info.to);
+                self.clear_piece(captured_piece, move_info.to);
             }
             // Remove captured pawn near the en-passant square.
             MoveKind::EnPassant => {
                 let to = Bitboard::from(move_info.to);
                 let captured_pawn = mg::pawn_single_pushes(to, !player);
-                self.pieces[(!player, Pawn)].remove(&captured_pawn);
+                let captured_square = captured_pawn.get_lowest_square().unwrap();
+                self.clear_piece(Piece::new(!player, Pawn), captured_square);
             }
-            // Move Rook to castling square and clear castling rights.
+            // Move King and Rook to castling squares and clear castling rights.
+            // In Chess960 the king was placed onto its own rook, so it is moved again.
+            // King and rook squares may overlap, so each piece is cleared before being set.
             MoveKind::Castle => {
-                let castling_rook_squares = match (move_info.from, move_info.to) {
-                    (E1, G1) => (H1, F1), // White Kingside
-                    (E1, C1) => (A1, D1), // White Queenside
-                    (E8, G8) => (H8, F8), // Black Kingside
-                    (E8, C8) => (A8, D8), // Black Queenside
-                    _ => panic!("move_kind is Castle however squares are illegal"),
-                };
-                let (clear, set) = castling_rook_squares;
-                let active_rook = (active_piece.color, Rook);
-                self.pieces[active_rook].clear_square(clear);
-                self.pieces[active_rook].set_square(set);
+                let (king_to, rook_from, rook_to) = self
+                    .castling_files
+                    .castling_squares(move_info.from, move_info.to);
+                self.clear_piece(active_piece, move_info.to);
+                self.set_piece(active_piece, king_to);
+
+                let active_rook = Piece::new(active_piece.color, Rook);
+                self.clear_piece(active_rook, rook_from);
+                self.set_piece(active_rook, rook_to);
 
                 self.castling.clear_color(player);
             }
@@ -379,23 +783,21 @@ impl Position {
             }
         };
 
-        // If any corner square is moved from or in to, remove those castling rights.
+        // If any castling rook square is moved from or in to, remove those castling rights.
         // This covers active player moving rook, and passive player losing a rook.
-        let moved_rights = match move_info.from {
-            A1 => Castling::W_QUEEN,
-            A8 => Castling::B_QUEEN,
-            H1 => Castling::W_KING,
-            H8 => Castling::B_KING,
-            _ => Castling::NONE,
-        };
-        let captured_rights = match move_info.to {
-            A1 => Castling::W_QUEEN,
-            A8 => Castling::B_QUEEN,
-            H1 => Castling::W_KING,
-            H8 => Castling::B_KING,
-            _ => Castling::NONE,
-        };
-        self.castling.clear(moved_rights | captured_rights);
+        if !self.castling.is_none() {
+            for right in [
+                Castling::W_KING,
+                Castling::W_QUEEN,
+                Castling::B_KING,
+                Castling::B_QUEEN,
+            ] {
+                let rook_square = self.castling_files.rook_square(right);
+                if move_info.from == rook_square || move_info.to == rook_square {
+                    self.castling.clear(right);
+                }
+            }
+        }
 
         // If King has moved, remove all castling rights.
         if move_info.piece_kind == King {
@@ -403,6 +805,7 @@ impl Position {
         }
 
         debug_assert!(self.pieces().is_valid());
+        debug_assert!(self.accumulators_match());
     }
 
     /// Undo the application of a move, in place.
@@ -422,30 +825,29 @@ impl Position {
 
         // Restore explicitly moved piece of move's active player.
         let moved_piece = Piece::new(player, move_info.piece_kind);
-        self.pieces[moved_piece].set_square(move_info.from);
-        self.pieces[moved_piece].clear_square(move_info.to);
+        self.set_piece(moved_piece, move_info.from);
+        self.clear_piece(moved_piece, move_info.to);
         if let Some(promoted) = move_info.promotion {
-            self.pieces[(player, promoted)].clear_square(move_info.to);
+            self.clear_piece(Piece::new(player, promoted), move_info.to);
         }
 
         // Handle special MoveKind cases.
         match move_info.move_kind {
             MoveKind::Capture(piece_kind) => {
-                self.pieces[(!player, piece_kind)].set_square(move_info.to);
+                self.set_piece(Piece::new(!player, piece_kind), move_info.to);
             }
 
             MoveKind::Castle => {
-                // Identify what kind of castle.
-                let (rook_from, rook_to) = match move_info.to {
-                    C1 => (A1, D1), // White Queenside
-                    G1 => (H1, F1), // White Kingside
-                    C8 => (A8, D8), // Black Queenside
-                    G8 => (H8, F8), // Black Kingside
-                    _ => panic!("MoveKind is Castle but Move is not a castling move."),
-                };
-                // Restore Rook position before castling.
-                self.pieces[(player, Rook)].set_square(rook_from);
-                self.pieces[(player, Rook)].clear_square(rook_to);
+                // Restore King and Rook positions before castling.
+                // King and rook squares may overlap, so each piece is cleared before being set.
+                let (king_to, rook_from, rook_to) = self
+                    .castling_files
+                    .castling_squares(move_info.from, move_info.to);
+                let rook = Piece::new(player, Rook);
+                self.clear_piece(moved_piece, king_to);
+                self.set_piece(moved_piece, move_info.from);
+                self.clear_piece(rook, rook_to);
+                self.set_piece(rook, rook_from);
             }
 
             MoveKind::EnPassant => {
@@ -454,12 +856,48 @@ impl Position {
                     .expect("MoveKind is EnPassant, but en_passant square is not set.");
                 let ep_bb = Bitboard::from(ep_square);
                 let original_bb = mg::pawn_single_pushes(ep_bb, !player);
-                self.pieces[(!player, Pawn)] |= original_bb;
+                let original_square = original_bb.get_lowest_square().unwrap();
+                self.set_piece(Piece::new(!player, Pawn), original_square);
             }
 
             _ => (),
         }
         debug_assert!(self.pieces().is_valid());
+        debug_assert!(self.accumulators_match());
+    }
+
+    /// Pass the turn to the opponent without moving a piece, in place.
+    /// En-passant is cleared, and the move counters step as if a quiet move was made.
+    /// Returns the Cache of the position before the null move, which undo_null_move requires.
+    ///
+    /// A null move is illegal when the player to move is in check,
+    /// as the opponent could then capture the king. This is not checked.
+    pub fn do_null_move(&mut self) -> Cache {
+        let cache = self.cache();
+        self.halfmoves += 1;
+        self.step_fullmoves();
+        self.en_passant = None;
+        self.player = !self.player;
+        cache
+    }
+
+    /// Undo a null move, in place, given the Cache returned by do_null_move.
+    pub fn undo_null_move(&mut self, cache: Cache) {
+        self.unstep_fullmoves();
+        self.player = !self.player;
+        self.en_passant = cache.en_passant;
+        self.halfmoves = cache.halfmoves;
+    }
+
+    /// Generates a new Position from applying a null move on current Position.
+    /// Returns None when the player to move is in check, where a null move is illegal.
+    pub fn make_null_safe(&self) -> Option<Self> {
+        if self.is_in_check() {
+            return None;
+        }
+        let mut position_clone = *self;
+        position_clone.do_null_move();
+        Some(position_clone)
     }
 
     /// Checks if move is legal before applying it.
@@ -504,9 +942,13 @@ impl Position {
     }
 
     /// Checks if given move is legal for current position.
+    /// Only the moves of the same kind (noisy or quiet) are generated to check.
     pub fn is_legal_move(&self, move_: Move) -> bool {
-        let legal_moves = self.get_legal_moves();
-        legal_moves.contains(&move_)
+        let kind = match self.is_noisy(move_) {
+            true => GenKind::Noisy,
+            false => GenKind::Quiet,
+        };
+        self.generate_legal_moves::<MoveList>(kind).contains(&move_)
     }
 
     /// Returns true if active player's king is in any check.
@@ -533,6 +975,12 @@ impl Position {
     /// Returns bitboard with positions of all pieces of a player attacking a square.
     /// Assumes there is no overlap for pieces of a color.
     pub fn attackers_to(&self, target: Square, attacking: Color) -> Bitboard {
+        self.attackers_to_with(target, attacking, self.pieces.occupied())
+    }
+
+    /// Returns bitboard with positions of all pieces of a player attacking a square,
+    /// where sliding pieces are blocked by the given occupancy.
+    fn attackers_to_with(&self, target: Square, attacking: Color, occupied: Bitboard) -> Bitboard {
         let pawns = self.pieces[(attacking, Pawn)];
         let knights = self.pieces[(attacking, Knight)];
         let king = self.pieces[(attacking, King)];
@@ -540,8 +988,6 @@ impl Position {
         let rooks = self.pieces[(attacking, Rook)];
         let queens = self.pieces[(attacking, Queen)];
 
-        let occupied = self.pieces().occupied();
-
         mg::pawn_attackers_to(target, pawns, attacking)
             | mg::knight_attackers_to(target, knights)
             | mg::king_attackers_to(target, king)
@@ -555,6 +1001,130 @@ impl Position {
         self.attackers_to(target, attacking).count_squares() > 0
     }
 
+    /// Returns true if a legal move for the active player would check the passive player's king.
+    /// This is cheaper than making the move and calling `is_in_check`.
+    /// Direct checks are from the moved or promoted piece on its destination, or the rook of a
+    /// castling move. Discovered checks are from sliders uncovered by the moved piece,
+    /// including those uncovered along a rank by an en-passant capture.
+    pub fn gives_check(&self, move_: Move) -> bool {
+        let move_info = self.move_info(move_);
+        let player = self.player;
+        let king_square = match self.pieces[(!player, King)].get_lowest_square() {
+            Some(king_square) => king_square,
+            None => return false,
+        };
+
+        // Find occupancy after the move, and the piece which may give a direct check.
+        let mut occupied = self.pieces.occupied();
+        let mut diagonal_sliders = self.pieces[(player, Bishop)] | self.pieces[(player, Queen)];
+        let mut line_sliders = self.pieces[(player, Rook)] | self.pieces[(player, Queen)];
+        occupied.clear_square(move_.from);
+        diagonal_sliders.clear_square(move_.from);
+        line_sliders.clear_square(move_.from);
+
+        let (checker_kind, checker_square) = match move_info.move_kind {
+            MoveKind::Castle => {
+                let (king_to, rook_from, rook_to) =
+                    self.castling_files.castling_squares(move_.from, move_.to);
+                occupied.clear_square(rook_from);
+                line_sliders.clear_square(rook_from);
+                occupied.set_square(king_to);
+                (Rook, rook_to)
+            }
+            MoveKind::EnPassant => {
+                let to = Bitboard::from(move_.to);
+                occupied.remove(&mg::pawn_single_pushes(to, !player));
+                (Pawn, move_.to)
+            }
+            _ => (move_.promotion.unwrap_or(move_info.piece_kind), move_.to),
+        };
+        occupied.set_square(checker_square);
+
+        let checker = Bitboard::from(checker_square);
+        let direct_checkers = match checker_kind {
+            Pawn => mg::pawn_attackers_to(king_square, checker, player),
+            Knight => mg::knight_attackers_to(king_square, checker),
+            Bishop => mg::bishop_attackers_to(king_square, checker, occupied),
+            Rook => mg::rook_attackers_to(king_square, checker, occupied),
+            Queen => mg::queen_attackers_to(king_square, checker, occupied),
+            King => Bitboard::EMPTY,
+        };
+        let discovered_checkers = mg::bishop_attackers_to(king_square, diagonal_sliders, occupied)
+            | mg::rook_attackers_to(king_square, line_sliders, occupied);
+
+        !(direct_checkers | discovered_checkers).is_empty()
+    }
+
+    /// Returns the static exchange evaluation of a move, the material the active player is
+    /// expected to gain from the sequence of captures on the move's destination square,
+    /// where each side recaptures with its least valuable attacker and may stop at any time.
+    /// The move is assumed to be pseudo-legal for the active player.
+    pub fn see(&self, move_: Move) -> Cp {
+        let move_info = self.move_info(move_);
+        let target = move_.to;
+        let mut occupied = self.pieces.occupied();
+        occupied.clear_square(move_.from);
+
+        // Material gained at each capture of the exchange, assuming it ends after that capture.
+        let mut gains = [Cp(0); 32];
+        gains[0] = match move_info.move_kind {
+            MoveKind::Capture(victim) => victim.centipawns(),
+            MoveKind::EnPassant => {
+                let to = Bitboard::from(target);
+                occupied.remove(&mg::pawn_single_pushes(to, !self.player));
+                Pawn.centipawns()
+            }
+            _ => Cp(0),
+        };
+        if let Some(promotion) = move_.promotion {
+            gains[0] = gains[0] + promotion.centipawns() - Pawn.centipawns();
+        }
+
+        let mut on_target = move_.promotion.unwrap_or(move_info.piece_kind);
+        let mut side = !self.player;
+        let mut depth = 0;
+
+        loop {
+            // Sliders behind captured pieces are uncovered as the pieces leave the board.
+            let attackers = (self.attackers_to_with(target, White, occupied)
+                | self.attackers_to_with(target, Black, occupied))
+                & occupied;
+            let side_attackers = attackers & self.pieces.color_occupied(side);
+
+            let least_valuable = [Pawn, Knight, Bishop, Rook, Queen, King]
+                .iter()
+                .copied()
+                .find(|&pk| self.pieces[(side, pk)].has_any(&side_attackers));
+            let attacker_kind = match least_valuable {
+                Some(attacker_kind) => attacker_kind,
+                None => break,
+            };
+
+            // A king cannot capture onto a square which is still defended.
+            let defenders = attackers & self.pieces.color_occupied(!side);
+            if attacker_kind == King && !defenders.is_empty() {
+                break;
+            }
+
+            depth += 1;
+            gains[depth] = on_target.centipawns() - gains[depth - 1];
+            if (-gains[depth - 1]).max(gains[depth]) < Cp(0) {
+                break;
+            }
+
+            let attacker = self.pieces[(side, attacker_kind)] & side_attackers;
+            occupied.clear_square(attacker.get_lowest_square().unwrap());
+            on_target = attacker_kind;
+            side = !side;
+        }
+
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
+    }
+
     /// Returns bitboard with all squares attacked by a player's pieces.
     pub fn attacks(&self, attacking: Color, occupied: Bitboard) -> Bitboard {
         let pawns = self.pieces[(attacking, Pawn)];
@@ -577,14 +1147,90 @@ impl Position {
     /// If king is in check, number of moves are restricted.
     /// If king is pinned, number of moves are restricted.
     pub fn get_legal_moves(&self) -> MoveList {
+        self.generate_legal_moves(GenKind::All)
+    }
+
+    /// Returns a list of all legal noisy moves for active player in current position.
+    /// Noisy moves are captures, including en-passant, and promotions.
+    pub fn get_legal_noisy_moves(&self) -> MoveList {
+        self.generate_legal_moves(GenKind::Noisy)
+    }
+
+    /// Returns a list of all legal quiet moves for active player in current position.
+    /// Quiet moves are all moves which are not noisy, including castling.
+    pub fn get_legal_quiet_moves(&self) -> MoveList {
+        self.generate_legal_moves(GenKind::Quiet)
+    }
+
+    /// Returns a list of all legal moves for active player in current position, with the
+    /// MoveInfo of each. This is equivalent to `move_info` of each move of `get_legal_moves`,
+    /// but moves are classified as they are generated.
+    pub fn get_legal_move_infos(&self) -> MoveInfoList {
+        self.generate_legal_moves(GenKind::All)
+    }
+
+    /// Returns true if a move would capture a piece in current position, including en-passant.
+    /// The move is assumed to be pseudo-legal for the active player.
+    pub fn is_capture(&self, move_: Move) -> bool {
+        let them = self.pieces.color_occupied(!self.player);
+        let is_en_passant = self.en_passant == Some(move_.to)
+            && self.pieces[(self.player, Pawn)].has_square(move_.from);
+
+        them.has_square(move_.to) || is_en_passant
+    }
+
+    /// Returns true if a move promotes a pawn.
+    pub fn is_promotion(&self, move_: Move) -> bool {
+        move_.promotion.is_some()
+    }
+
+    /// Returns true if a move would castle in current position.
+    /// The move is assumed to be pseudo-legal for the active player.
+    pub fn is_castle(&self, move_: Move) -> bool {
+        if !self.pieces[(self.player, King)].has_square(move_.from) {
+            return false;
+        }
+        let onto_rook = self.pieces[(self.player, Rook)].has_square(move_.to);
+        let two_squares = match (move_.from, move_.to) {
+            (E1, C1) | (E1, G1) | (E8, C8) | (E8, G8) => !self.chess960,
+            _ => false,
+        };
+        onto_rook || two_squares
+    }
+
+    /// Returns true if a move would neither capture nor promote in current position.
+    /// Castling is a quiet move.
+    pub fn is_quiet(&self, move_: Move) -> bool {
+        !self.is_noisy(move_)
+    }
+
+    /// Returns true if a move would capture a piece or promote in current position.
+    /// The move is assumed to be pseudo-legal for the active player.
+    pub(crate) fn is_noisy(&self, move_: Move) -> bool {
+        self.is_capture(move_) || self.is_promotion(move_)
+    }
+
+    /// Generate legal moves of a given kind for active player in current position.
+    fn generate_legal_moves<S: MoveSink>(&self, kind: GenKind) -> S {
         let (single_check, double_check) = self.active_king_checks();
 
         if double_check {
-            self.generate_legal_double_check_moves()
+            self.generate_legal_double_check_moves(kind)
         } else if single_check {
-            self.generate_legal_single_check_moves()
+            self.generate_legal_single_check_moves(kind)
         } else {
-            self.generate_legal_no_check_moves()
+            self.generate_legal_no_check_moves(kind)
+        }
+    }
+
+    /// Returns the squares which moves of a given kind, other than pawn moves, may move to.
+    fn gen_targets(&self, kind: GenKind) -> Bitboard {
+        let us = self.pieces.color_occupied(self.player);
+        let them = self.pieces.color_occupied(!self.player);
+        match kind {
+            GenKind::All => !us,
+            GenKind::Noisy => them,
+            GenKind::Quiet => !(us | them),
         }
     }
 
@@ -604,7 +1250,7 @@ impl Position {
 
     /// Generate king moves assuming double check.
     /// Only the king can move when in double check.
-    fn generate_legal_double_check_moves(&self) -> MoveList {
+    fn generate_legal_double_check_moves<S: MoveSink>(&self, kind: GenKind) -> S {
         let king = self.pieces[(self.player, King)];
 
         // Generate bitboard with all squares attacked by passive player.
@@ -615,67 +1261,115 @@ impl Position {
 
         // Filter illegal moves from pseudo-legal king moves.
         // King cannot move into attacked square, or into piece of same color.
-        let mut possible_moves = mg::king_attacks(king);
+        let mut possible_moves = mg::king_attacks(king) & self.gen_targets(kind);
         possible_moves.remove(&attacked);
-        possible_moves.remove(&self.pieces.color_occupied(self.player));
 
         // Convert remaining move squares into Move structs.
-        let mut legal_moves = MoveList::new(); // Eight max possible moves.
+        let mut legal_moves = S::default(); // Eight max possible moves.
         let from = king.get_lowest_square().unwrap();
         for to in possible_moves {
-            legal_moves.push(Move::new(from, to, None));
+            legal_moves.push_move(self, Move::new(from, to, None), King, None);
         }
 
         legal_moves
     }
 
     /// Generate moves assuming active player is in single check.
-    fn generate_legal_single_check_moves(&self) -> MoveList {
+    /// Moves are generated legal from the checker's square, without making any move
+    /// except for en-passant, which may capture the checker or uncover an attack on the king.
+    fn generate_legal_single_check_moves<S: MoveSink>(&self, kind: GenKind) -> S {
         // Can capture checking piece with non-absolute-pinned piece,
         // move king to non-attacked squares,
         // block checking piece with non-absolute-pinned piece
-        let mut legal_moves: MoveList = MoveList::new();
+        let mut legal_moves = S::default();
 
         let king = self.pieces[(self.player, King)];
         let king_square = king.get_lowest_square().unwrap();
         let passive_player = !self.player;
-        let us = self.pieces.color_occupied(self.player);
-        let them = self.pieces.color_occupied(passive_player);
         let occupied = self.pieces.occupied();
+        // Pieces other than pawns cannot move to squares outside of targets.
+        let not_targets = !self.gen_targets(kind);
 
         // Generate all legal king moves.
         let occupied_without_king = occupied & !king;
         let attacked_xray_king = self.attacks(passive_player, occupied_without_king);
-        let mut possible_moves = mg::king_attacks(king);
+        let mut possible_moves = mg::king_attacks(king) & !not_targets;
         possible_moves.remove(&attacked_xray_king);
-        possible_moves.remove(&us);
         for to in possible_moves {
-            legal_moves.push(Move::new(king_square, to, None));
-        }
-
-        // Notes
-        // Only sliding pieces can cause absolute pins and pins in general.
-        // If a piece is absolutely pinned, it can only move along pinned direction.
-        // a pinning piece must already pseudo attack the king to absolutely pin.
-        // If there are multiple in between pieces, there is no pin.
-        // Once a piece is known to be pinned, how to determine where it can move?
-        // Algorithm:
-        // For each sliding piece, check if it pseudo checks the king.
-        // If it does, need to find if there is a single piece between them of active color.
-        // Sliding checker can be blocked or captured with non-pinned piece.
-        // If not sliding, then checker can be captured with non-pinned piece.
-        // TODO: Make more efficient (change from verifying by making move).
-        let queens = self.pieces[(self.player, Queen)];
-        let rooks = self.pieces[(self.player, Rook)];
-        let bishops = self.pieces[(self.player, Bishop)];
-        let knights = self.pieces[(self.player, Knight)];
-        let pawns = self.pieces[(self.player, Pawn)];
+            legal_moves.push_move(self, Move::new(king_square, to, None), King, None);
+        }
+
+        // Other pieces must capture the checker or block it if it is a sliding piece.
+        // An absolutely pinned piece can never do either, as its pin ray only crosses
+        // the check ray on the king's square.
+        let checker = self
+            .attackers_to(king_square, passive_player)
+            .get_lowest_square()
+            .unwrap();
+        let evasions = Bitboard::from(checker) | mg::squares_between(king_square, checker);
+        let not_evasions = not_targets | !evasions;
+        let (absolute_pins, pinned_between) = self.absolute_pins(king_square);
+        let free = !absolute_pins;
+
+        let queens = self.pieces[(self.player, Queen)] & free;
+        let rooks = self.pieces[(self.player, Rook)] & free;
+        let bishops = self.pieces[(self.player, Bishop)] & free;
+        let knights = self.pieces[(self.player, Knight)] & free;
+        let pawns = self.pieces[(self.player, Pawn)] & free;
+
+        let mut moves = MoveList::new();
+        mg::queen_pseudo_moves(&mut moves, queens, occupied, not_evasions);
+        legal_moves.push_moves(self, &mut moves, Queen);
+        mg::rook_pseudo_moves(&mut moves, rooks, occupied, not_evasions);
+        legal_moves.push_moves(self, &mut moves, Rook);
+        mg::bishop_pseudo_moves(&mut moves, bishops, occupied, not_evasions);
+        legal_moves.push_moves(self, &mut moves, Bishop);
+        mg::knight_pseudo_moves(&mut moves, knights, not_evasions);
+        legal_moves.push_moves(self, &mut moves, Knight);
+        self.legal_pawn_moves(&mut legal_moves, kind, pawns, evasions, &pinned_between);
+
+        legal_moves
+    }
+
+    /// Returns the absolute pins against the active player's king, as from `mg::absolute_pins`.
+    fn absolute_pins(&self, king_square: Square) -> (Bitboard, [Option<(Square, Bitboard)>; 8]) {
+        let passive_player = !self.player;
+        let us = self.pieces.color_occupied(self.player);
+        let them = self.pieces.color_occupied(passive_player);
+        let queens = self.pieces[(passive_player, Queen)];
+        let rooks = self.pieces[(passive_player, Rook)];
+        let bishops = self.pieces[(passive_player, Bishop)];
+
+        mg::absolute_pins(king_square, us, them, queens | rooks, queens | bishops)
+    }
+
+    /// Append the legal moves of a given kind for some of the active player's pawns.
+    /// Moves other than en-passant must move to one of the target squares, and pinned pawns
+    /// may only move along their pin ray. En-passant is verified by making the move,
+    /// because removing both pawns from a rank may uncover an attack on the king.
+    fn legal_pawn_moves<S: MoveSink>(
+        &self,
+        legal_moves: &mut S,
+        kind: GenKind,
+        pawns: Bitboard,
+        targets: Bitboard,
+        pinned_between: &[Option<(Square, Bitboard)>; 8],
+    ) {
+        let passive_player = !self.player;
+        let them = self.pieces.color_occupied(passive_player);
+        let occupied = self.pieces.occupied();
+        let king_square = self.pieces[(self.player, King)]
+            .get_lowest_square()
+            .unwrap();
+        let pin_ray = |from: Square| {
+            pinned_between
+                .iter()
+                .flatten()
+                .find(|(pinned, _)| *pinned == from)
+                .map(|(_, ray)| *ray)
+        };
 
         let mut pseudo_moves = MoveList::new();
-        mg::queen_pseudo_moves(&mut pseudo_moves, queens, occupied, us);
-        mg::rook_pseudo_moves(&mut pseudo_moves, rooks, occupied, us);
-        mg::bishop_pseudo_moves(&mut pseudo_moves, bishops, occupied, us);
-        mg::knight_pseudo_moves(&mut pseudo_moves, knights, us);
         mg::pawn_pseudo_moves(
             &mut pseudo_moves,
             pawns,
@@ -685,23 +1379,28 @@ impl Position {
             self.en_passant,
         );
 
-        let mut position = self.clone();
-        let cache = position.cache();
-        pseudo_moves
-            .into_iter()
-            .filter(|pseudo_move| {
-                let move_info = position.do_move(*pseudo_move);
-                let is_legal = !position.is_attacked_by(king_square, passive_player);
-                position.undo_move(move_info, cache);
-                is_legal
-            })
-            .for_each(|legal_move| legal_moves.push(legal_move));
-
-        legal_moves
+        for pseudo_move in pseudo_moves {
+            if !kind.includes(self.is_noisy(pseudo_move)) {
+                continue;
+            }
+            let is_en_passant = self.en_passant == Some(pseudo_move.to);
+            let is_legal = if is_en_passant {
+                let mut position = *self;
+                position.do_move(pseudo_move);
+                !position.is_attacked_by(king_square, passive_player)
+            } else {
+                targets.has_square(pseudo_move.to)
+                    && pin_ray(pseudo_move.from).is_none_or(|ray| ray.has_square(pseudo_move.to))
+            };
+            if is_legal {
+                let special = is_en_passant.then_some(MoveKind::EnPassant);
+                legal_moves.push_move(self, pseudo_move, Pawn, special);
+            }
+        }
     }
 
     /// Generate moves assuming active player is not in check.
-    fn generate_legal_no_check_moves(&self) -> MoveList {
+    fn generate_legal_no_check_moves<S: MoveSink>(&self, kind: GenKind) -> S {
         // moves:
         // move absolutely-pinned piece along pin direction
         // Castling with no pieces or attacked squares between
@@ -710,23 +1409,17 @@ impl Position {
         // For non king moves, only need to consider leaving absolute pin.
         // For king moves, need to consider all attacked squares.
         // Most positions will have fewer moves than this capacity.
-        let mut legal_moves = MoveList::new();
+        let mut legal_moves = S::default();
 
         let king = self.pieces[(self.player, King)];
         let king_square = king.get_lowest_square().unwrap();
         let passive_player = !self.player;
-        let us = self.pieces.color_occupied(self.player);
-        let them = self.pieces.color_occupied(passive_player);
-        let occupied = us | them;
+        let occupied = self.pieces.occupied();
         let attacked = self.attacks(passive_player, occupied);
+        // Pieces other than pawns cannot move to squares outside of targets.
+        let not_targets = !self.gen_targets(kind);
 
-        let (absolute_pins, _pinned_moves) = {
-            let queens = self.pieces[(passive_player, Queen)];
-            let rooks = self.pieces[(passive_player, Rook)];
-            let bishops = self.pieces[(passive_player, Bishop)];
-
-            mg::absolute_pins(king_square, us, them, queens | rooks, queens | bishops)
-        };
+        let (absolute_pins, pinned_between) = self.absolute_pins(king_square);
 
         // Generate all normal Queen, Rook, Bishop, Knight moves.
         // Generate all normal and special Pawn moves (single/double push, attacks, ep).
@@ -741,60 +1434,83 @@ impl Position {
         let bishops_free = bishops & !absolute_pins;
         let queens_free = queens & !absolute_pins;
         let rooks_free = rooks & !absolute_pins;
-        mg::knight_pseudo_moves(&mut legal_moves, knights_free, us);
-        mg::bishop_pseudo_moves(&mut legal_moves, bishops_free, occupied, us);
-        mg::queen_pseudo_moves(&mut legal_moves, queens_free, occupied, us);
-        mg::rook_pseudo_moves(&mut legal_moves, rooks_free, occupied, us);
+        let mut moves = MoveList::new();
+        mg::knight_pseudo_moves(&mut moves, knights_free, not_targets);
+        legal_moves.push_moves(self, &mut moves, Knight);
+        mg::bishop_pseudo_moves(&mut moves, bishops_free, occupied, not_targets);
+        legal_moves.push_moves(self, &mut moves, Bishop);
+        mg::queen_pseudo_moves(&mut moves, queens_free, occupied, not_targets);
+        legal_moves.push_moves(self, &mut moves, Queen);
+        mg::rook_pseudo_moves(&mut moves, rooks_free, occupied, not_targets);
+        legal_moves.push_moves(self, &mut moves, Rook);
 
         // Generate all normal legal king moves.
-        let mut king_tos = mg::king_attacks(king);
-        king_tos.remove(&us);
+        let mut king_tos = mg::king_attacks(king) & !not_targets;
         king_tos.remove(&attacked);
         for to in king_tos {
-            legal_moves.push(Move::new(king_square, to, None));
+            legal_moves.push_move(self, Move::new(king_square, to, None), King, None);
         }
 
-        // Generate pseudo moves and check for legality with "do/undo".
-        let mut pseudo_moves = MoveList::new();
-        let bishops_pinned = bishops & absolute_pins;
-        let rooks_pinned = rooks & absolute_pins;
-        let queens_pinned = queens & absolute_pins;
+        // Pinned sliding pieces may only move along their pin ray, and pinned knights never move.
+        for (pinned, ray) in pinned_between.iter().flatten() {
+            let pinned_bb = Bitboard::from(*pinned);
+            let not_ray = not_targets | !*ray;
+            mg::queen_pseudo_moves(&mut moves, queens & pinned_bb, occupied, not_ray);
+            legal_moves.push_moves(self, &mut moves, Queen);
+            mg::rook_pseudo_moves(&mut moves, rooks & pinned_bb, occupied, not_ray);
+            legal_moves.push_moves(self, &mut moves, Rook);
+            mg::bishop_pseudo_moves(&mut moves, bishops & pinned_bb, occupied, not_ray);
+            legal_moves.push_moves(self, &mut moves, Bishop);
+        }
 
-        mg::queen_pseudo_moves(&mut pseudo_moves, queens_pinned, occupied, us);
-        mg::rook_pseudo_moves(&mut pseudo_moves, rooks_pinned, occupied, us);
-        mg::bishop_pseudo_moves(&mut pseudo_moves, bishops_pinned, occupied, us);
-        mg::pawn_pseudo_moves(
-            &mut pseudo_moves,
+        self.legal_pawn_moves(
+            &mut legal_moves,
+            kind,
             pawns,
-            self.player,
-            occupied,
-            them,
-            self.en_passant,
+            !Bitboard::EMPTY,
+            &pinned_between,
         );
 
-        let mut position = self.clone();
-        let cache = position.cache();
-        pseudo_moves
-            .into_iter()
-            .filter(|pseudo_move| {
-                let move_info = position.do_move(*pseudo_move);
-                let is_legal = !position.is_attacked_by(king_square, passive_player);
-                position.undo_move(move_info, cache);
-                is_legal
-            })
-            .for_each(|legal_move| legal_moves.push(legal_move));
+        // Castling moves are always quiet.
+        if kind == GenKind::Noisy {
+            return legal_moves;
+        }
 
         // Generate Castling moves
         // Check if current player can castle. If can, for each side that can castle,
         // check if there are any pieces between king and castling rook.
         // check if king will pass through an attacked square.
+        // Castling moves are verified once more, because in Chess960 the castling rook
+        // may block an attack on the king's destination before it moves.
+        let mut castling_moves = MoveList::new();
         mg::legal_castling_moves(
-            &mut legal_moves,
+            &mut castling_moves,
             self.player,
             self.castling,
+            self.castling_files,
+            king_square,
             occupied,
             attacked,
         );
+        let mut position = *self;
+        let cache = position.cache();
+        for castling_move in castling_moves {
+            let move_info = position.do_move(castling_move);
+            let king_to = position.pieces[(self.player, King)]
+                .get_lowest_square()
+                .unwrap();
+            let is_legal = !position.is_attacked_by(king_to, passive_player);
+            position.undo_move(move_info, cache);
+
+            // Outside of Chess960, castling is described as the king moving two squares.
+            let castling_move = match self.chess960 {
+                true => castling_move,
+                false => Move::new(king_square, king_to, None),
+            };
+            if is_legal {
+                legal_moves.push_move(self, castling_move, King, Some(MoveKind::Castle));
+            }
+        }
 
         legal_moves
     }
@@ -851,9 +1567,35 @@ impl Display for Position {
     }
 }
 
+/// Position is parsed from its FEN string, the inverse of `Fen::to_fen`.
+/// The Display output of a Position is meant for people, not for parsing.
+impl FromStr for Position {
+    type Err = ParseFenError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Position::parse_fen(s)
+    }
+}
+
+/// Position is serialized as its FEN string.
+#[cfg(feature = "serde")]
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Position::parse_fen(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::zobrist::ZobristTable;
 
     #[test]
     fn pretty_print_position() {
@@ -861,6 +1603,21 @@ mod tests {
         println!("{}", start_pos);
     }
 
+    #[test]
+    fn parse_position_from_str() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let position = fen.parse::<Position>().unwrap();
+        assert_eq!(position, Position::start_position());
+        assert_eq!(position.to_fen(), fen);
+        assert!(position.to_string().contains(fen));
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let position: Position = fen.parse().unwrap();
+        assert_eq!(position.to_fen().parse::<Position>().unwrap(), position);
+
+        assert_eq!("8/8/8".parse::<Position>(), Err(ParseFenError::IllFormed));
+    }
+
     #[test]
     fn do_move_with_legal_move() {
         let move1 = Move::new(E2, E4, None);
@@ -926,6 +1683,195 @@ mod tests {
         }
     }
 
+    #[test]
+    fn do_and_undo_null_move() {
+        let ztable = ZobristTable::with_seed(0);
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 2",
+        ];
+        for fen in fens {
+            let pos = Position::parse_fen(fen).unwrap();
+            let hash = ztable.generate_hash((&pos).into());
+
+            let mut pos_nulled = pos.clone();
+            let cache = pos_nulled.do_null_move();
+            assert_eq!(pos_nulled.player, !pos.player);
+            assert_eq!(pos_nulled.en_passant, None);
+            assert_eq!(pos_nulled.halfmoves, pos.halfmoves + 1);
+            assert_eq!(pos_nulled.pieces, pos.pieces);
+
+            let mut null_hash = hash;
+            ztable.update_null_hash(&mut null_hash, (&pos_nulled).into(), cache);
+            assert_eq!(null_hash, ztable.generate_hash((&pos_nulled).into()));
+            ztable.update_null_hash(&mut null_hash, (&pos_nulled).into(), cache);
+            assert_eq!(null_hash, hash);
+
+            pos_nulled.undo_null_move(cache);
+            assert_eq!(pos_nulled, pos);
+            assert_eq!(ztable.generate_hash((&pos_nulled).into()), hash);
+        }
+    }
+
+    #[test]
+    fn make_null_safe_rejects_check() {
+        let pos = Position::start_position();
+        let nulled = pos.make_null_safe().unwrap();
+        assert_eq!(nulled.player, !pos.player);
+        assert_eq!(nulled.pieces, pos.pieces);
+
+        let in_check = Position::parse_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        assert!(in_check.is_in_check());
+        assert_eq!(in_check.make_null_safe(), None);
+    }
+
+    #[test]
+    fn legal_move_infos_match_move_info() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+            "4k3/8/8/8/8/8/8/R5KR w HA - 0 1",
+        ];
+        for fen in fens {
+            let pos = Position::parse_fen(fen).unwrap();
+            let move_infos = pos.get_legal_move_infos();
+            let legal_moves = pos.get_legal_moves();
+            assert_eq!(move_infos.len(), legal_moves.len(), "{}", fen);
+            for move_info in move_infos {
+                assert!(legal_moves.contains(&move_info.move_()), "{}", fen);
+                assert_eq!(move_info, pos.move_info(move_info.move_()), "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn move_classifiers() {
+        let position = Position::parse_fen("r3k3/1P6/8/3pP3/8/8/8/R3K2R w KQq d6 0 1").unwrap();
+
+        // En-passant is a capture even though its target square is empty.
+        let en_passant = Move::new(E5, D6, None);
+        assert!(position.is_capture(en_passant));
+        assert!(!position.is_quiet(en_passant));
+
+        let capture_promotion = Move::new(B7, A8, Some(Queen));
+        assert!(position.is_capture(capture_promotion));
+        assert!(position.is_promotion(capture_promotion));
+
+        let promotion = Move::new(B7, B8, Some(Knight));
+        assert!(!position.is_capture(promotion));
+        assert!(position.is_promotion(promotion));
+        assert!(!position.is_quiet(promotion));
+
+        let capture = Move::new(A1, A8, None);
+        assert!(position.is_capture(capture));
+        assert!(!position.is_promotion(capture));
+
+        for castle in [Move::new(E1, G1, None), Move::new(E1, C1, None)] {
+            assert!(position.is_castle(castle));
+            assert!(position.is_quiet(castle));
+        }
+        let king_step = Move::new(E1, F1, None);
+        assert!(!position.is_castle(king_step));
+        assert!(position.is_quiet(king_step));
+        assert!(!position.is_castle(Move::new(A1, B1, None)));
+
+        // Chess960 castling is the king moving onto its own rook.
+        let chess960 = Position::parse_fen("4k3/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+        assert!(chess960.is_castle(Move::new(G1, H1, None)));
+        assert!(chess960.is_castle(Move::new(G1, A1, None)));
+        assert!(!chess960.is_castle(Move::new(G1, F1, None)));
+
+        // Agrees with the classification of each legal move.
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        for fen in fens {
+            let position = Position::parse_fen(fen).unwrap();
+            for move_info in position.get_legal_move_infos() {
+                let move_ = move_info.move_();
+                let is_capture = matches!(
+                    move_info.move_kind,
+                    MoveKind::Capture(_) | MoveKind::EnPassant
+                );
+                let is_castle = move_info.move_kind == MoveKind::Castle;
+                assert_eq!(position.is_capture(move_), is_capture, "{} {}", fen, move_);
+                assert_eq!(position.is_castle(move_), is_castle, "{} {}", fen, move_);
+                assert_eq!(
+                    position.is_quiet(move_),
+                    !is_capture && move_info.promotion.is_none(),
+                    "{} {}",
+                    fen,
+                    move_
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn static_exchange_evaluation() {
+        let pos = Position::parse_fen("2n1k3/p7/3p4/2r5/3P4/8/8/R3K1N1 w - - 0 1").unwrap();
+        assert_eq!(
+            pos.see(Move::new(D4, C5, None)),
+            Rook.centipawns() - Pawn.centipawns()
+        );
+        assert_eq!(
+            pos.see(Move::new(A1, A7, None)),
+            Pawn.centipawns() - Rook.centipawns()
+        );
+        assert_eq!(pos.see(Move::new(G1, F3, None)), Cp(0));
+
+        // The rook behind the capturing rook joins the exchange.
+        let x_ray = Position::parse_fen("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1").unwrap();
+        assert_eq!(x_ray.see(Move::new(E2, E5, None)), Pawn.centipawns());
+        let single = Position::parse_fen("4r1k1/8/8/4p3/8/8/4R3/6K1 w - - 0 1").unwrap();
+        assert_eq!(
+            single.see(Move::new(E2, E5, None)),
+            Pawn.centipawns() - Rook.centipawns()
+        );
+
+        // The king may only recapture onto an undefended square.
+        let king = Position::parse_fen("8/8/8/3k4/4p3/5P2/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(king.see(Move::new(F3, E4, None)), Cp(0));
+        let king = Position::parse_fen("8/8/8/3k4/4p3/3B1P2/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(king.see(Move::new(F3, E4, None)), Pawn.centipawns());
+
+        let en_passant = Position::parse_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(en_passant.see(Move::new(E5, D6, None)), Pawn.centipawns());
+    }
+
+    #[test]
+    fn chess960_castling() {
+        // King already stands on its kingside castling square, only the rook moves.
+        let pos = Position::parse_fen("4k3/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+        assert!(pos.chess960());
+        let legal_moves = pos.get_legal_moves();
+        assert!(legal_moves.contains(&Move::new(G1, H1, None)));
+        assert!(legal_moves.contains(&Move::new(G1, A1, None)));
+
+        for (move_, king_to, rook_to) in [
+            (Move::new(G1, H1, None), G1, F1),
+            (Move::new(G1, A1, None), C1, D1),
+        ] {
+            let mut pos_moved = pos.clone();
+            let cache = pos_moved.cache();
+            let move_info = pos_moved.do_move(move_);
+            assert_eq!(move_info.move_kind, MoveKind::Castle);
+            assert!(pos_moved.pieces[&Piece::new(White, King)].has_square(king_to));
+            assert!(pos_moved.pieces[&Piece::new(White, Rook)].has_square(rook_to));
+            assert_eq!(pos_moved.castling, Castling::NONE);
+            pos_moved.undo_move(move_info, cache);
+            assert_eq!(pos, pos_moved);
+        }
+    }
+
     #[test]
     fn king_checks() {
         let check1_1 = Position::parse_fen("8/8/8/8/3K3r/8/8/8 w - - 0 1").unwrap();
@@ -946,15 +1892,136 @@ mod tests {
         assert_eq!(check5_2.num_active_king_checks(), 5);
     }
 
+    #[test]
+    fn gives_check_kinds() {
+        let direct = Position::parse_fen("4k3/8/8/8/8/8/8/R3K1N1 w - - 0 1").unwrap();
+        assert!(direct.gives_check(Move::new(A1, A8, None)));
+        assert!(direct.gives_check(Move::new(G1, F6, None)));
+        assert!(!direct.gives_check(Move::new(A1, A7, None)));
+        assert!(!direct.gives_check(Move::new(E1, E2, None)));
+
+        // Moving the bishop uncovers the rook, and moving the knight off the diagonal does not.
+        let discovered = Position::parse_fen("4k3/8/8/8/4B3/8/8/1N2R1K1 w - - 0 1").unwrap();
+        assert!(discovered.gives_check(Move::new(E4, D3, None)));
+        assert!(!discovered.gives_check(Move::new(B1, C3, None)));
+
+        let castling = Position::parse_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(castling.gives_check(Move::new(E1, G1, None)));
+        let castling = Position::parse_fen("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert!(castling.gives_check(Move::new(E1, C1, None)));
+
+        // Both pawns leave the rank between the rook and king.
+        let en_passant = Position::parse_fen("8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(en_passant.gives_check(Move::new(E5, D6, None)));
+        let en_passant = Position::parse_fen("8/8/2k5/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(!en_passant.gives_check(Move::new(E5, D6, None)));
+
+        let promotion = Position::parse_fen("k7/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(promotion.gives_check(Move::new(E7, E8, Some(Queen))));
+        assert!(promotion.gives_check(Move::new(E7, E8, Some(Rook))));
+        assert!(!promotion.gives_check(Move::new(E7, E8, Some(Bishop))));
+        assert!(!promotion.gives_check(Move::new(E7, E8, Some(Knight))));
+
+        // Agrees with making each legal move.
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        for fen in fens {
+            let position = Position::parse_fen(fen).unwrap();
+            for move_ in position.get_legal_moves() {
+                let expected = position.make_move(move_).is_in_check();
+                assert_eq!(position.gives_check(move_), expected, "{} {}", fen, move_);
+            }
+        }
+    }
+
+    #[test]
+    fn legal_single_check_moves_match_pseudo_legal_filter() {
+        // Every pseudo-legal move which does not leave the king attacked.
+        fn check_evasions(position: &Position) -> Vec<String> {
+            let player = *position.player();
+            let pieces = position.pieces();
+            let us = pieces.color_occupied(player);
+            let them = pieces.color_occupied(!player);
+            let occupied = pieces.occupied();
+
+            let mut moves = MoveList::new();
+            let king = pieces[(player, King)];
+            let king_square = king.get_lowest_square().unwrap();
+            for to in mg::king_attacks(king) & !us {
+                moves.push(Move::new(king_square, to, None));
+            }
+            mg::queen_pseudo_moves(&mut moves, pieces[(player, Queen)], occupied, us);
+            mg::rook_pseudo_moves(&mut moves, pieces[(player, Rook)], occupied, us);
+            mg::bishop_pseudo_moves(&mut moves, pieces[(player, Bishop)], occupied, us);
+            mg::knight_pseudo_moves(&mut moves, pieces[(player, Knight)], us);
+            let pawns = pieces[(player, Pawn)];
+            let en_passant = *position.en_passant();
+            mg::pawn_pseudo_moves(&mut moves, pawns, player, occupied, them, en_passant);
+
+            let mut evasions: Vec<String> = moves
+                .into_iter()
+                .filter(|move_| {
+                    let position = position.make_move(*move_);
+                    let king_square = position.pieces[(player, King)].get_lowest_square();
+                    !position.is_attacked_by(king_square.unwrap(), !player)
+                })
+                .map(|move_| move_.to_string())
+                .collect();
+            evasions.sort();
+            evasions
+        }
+
+        // Compare at every position in single check reached within `ply` moves.
+        fn compare_tree(position: &Position, ply: u32) -> usize {
+            let mut compared = 0;
+            if position.active_king_checks() == (true, false) {
+                let moves = position.generate_legal_single_check_moves::<MoveList>(GenKind::All);
+                let mut moves: Vec<String> = moves.iter().map(Move::to_string).collect();
+                moves.sort();
+                assert_eq!(moves, check_evasions(position), "{}", position.to_fen());
+                compared += 1;
+            }
+            if ply > 0 {
+                for move_ in position.get_legal_moves() {
+                    compared += compare_tree(&position.make_move(move_), ply - 1);
+                }
+            }
+            compared
+        }
+
+        let check_heavy_cases = [
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                3,
+            ),
+            ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 4),
+            ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 4),
+            ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 4),
+            ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 3),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                3,
+            ),
+        ];
+        for (fen, ply) in check_heavy_cases {
+            let position = Position::parse_fen(fen).unwrap();
+            assert!(compare_tree(&position, ply) > 0, "{}", fen);
+        }
+    }
+
     #[test]
     fn legal_double_check_moves() {
         let pos0_1 = Position::parse_fen("4R2k/7p/6p1/8/8/2B5/8/1K6 b - - 0 1").unwrap();
         let pos1_1 = Position::parse_fen("8/5K2/8/3Qk3/4R3/8/8/8 b - - 0 1").unwrap();
         let pos3_1 = Position::parse_fen("8/2k5/8/8/4Kr2/4r3/8/8 w - - 0 1").unwrap();
 
-        let moves0_1 = pos0_1.generate_legal_double_check_moves();
-        let moves1_1 = pos1_1.generate_legal_double_check_moves();
-        let moves3_1 = pos3_1.generate_legal_double_check_moves();
+        let moves0_1 = pos0_1.generate_legal_double_check_moves::<MoveList>(GenKind::All);
+        let moves1_1 = pos1_1.generate_legal_double_check_moves::<MoveList>(GenKind::All);
+        let moves3_1 = pos3_1.generate_legal_double_check_moves::<MoveList>(GenKind::All);
         assert_eq!(moves0_1.len(), 0);
         assert_eq!(moves1_1.len(), 1);
         assert_eq!(moves3_1.len(), 3);
@@ -993,6 +2060,74 @@ mod tests {
         assert_eq!(moves1.len(), 0);
     }
 
+    #[test]
+    fn insufficient_material() {
+        let drawn = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",    // K vs K
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",  // K+B vs K
+            "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1",  // K+N vs K
+            "4kn2/8/8/8/8/8/8/4K3 w - - 0 1",   // K vs K+N
+            "2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1", // K+B vs K+B, both on light squares
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", // K+B vs K+B, both on dark squares
+        ];
+        let not_drawn = [
+            "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1",  // K+2N vs K
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", // K+B vs K+B, opposite colored
+            "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",  // K+2B vs K
+            "4k3/8/8/8/8/8/8/2B1KN2 w - - 0 1",  // K+B+N vs K
+            "4kn2/8/8/8/8/8/8/2B1K3 w - - 0 1",  // K+B vs K+N
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",   // K+P vs K
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",    // K+R vs K
+        ];
+
+        for fen in drawn {
+            let pos = Position::parse_fen(fen).unwrap();
+            assert!(pos.is_insufficient_material(), "{}", fen);
+            assert!(pos.color_flip().is_insufficient_material(), "{}", fen);
+        }
+        for fen in not_drawn {
+            let pos = Position::parse_fen(fen).unwrap();
+            assert!(!pos.is_insufficient_material(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn accumulators_consistent_random_games() {
+        use rand::prelude::*;
+        let mut rng = StdRng::seed_from_u64(0xB1D);
+
+        for _ in 0..20 {
+            let mut position = Position::start_position();
+            let mut played = Vec::new();
+
+            // Play a random game, checking accumulators after each move.
+            for _ in 0..200 {
+                let legal_moves = position.get_legal_moves();
+                let move_ = match legal_moves.choose(&mut rng) {
+                    Some(move_) => *move_,
+                    None => break,
+                };
+                let cache = position.cache();
+                let move_info = position.do_move(move_);
+                played.push((move_info, cache));
+
+                let mut recomputed = position;
+                recomputed.recompute_accumulators();
+                assert_eq!(position, recomputed);
+            }
+
+            // Undo the whole game, checking accumulators return to start.
+            while let Some((move_info, cache)) = played.pop() {
+                position.undo_move(move_info, cache);
+
+                let mut recomputed = position;
+                recomputed.recompute_accumulators();
+                assert_eq!(position, recomputed);
+            }
+            assert_eq!(position, Position::start_position());
+        }
+    }
+
     #[test]
     fn color_flipped_eq() {
         // Manually check flipped positions.
@@ -1031,4 +2166,175 @@ mod tests {
             assert_eq!(pos.moves_played(), moves_played as MoveCount + 1);
         }
     }
+
+    #[test]
+    fn en_passant_discovered_check_is_illegal() {
+        // Capturing en-passant removes both pawns between the king and queen on the fifth rank.
+        let pos = Position::parse_fen("8/8/8/K2pP2q/8/8/8/k7 w - d6 0 1").unwrap();
+        let en_passant = Move::new(E5, D6, None);
+        let legal_moves = pos.get_legal_moves();
+        assert!(!legal_moves.contains(&en_passant));
+        assert!(!pos.is_legal_move(en_passant));
+        assert_eq!(legal_moves.len(), 6);
+
+        // Same position without the queen allows en-passant.
+        let pos = Position::parse_fen("8/8/8/K2pP3/8/8/8/k7 w - d6 0 1").unwrap();
+        assert!(pos.get_legal_moves().contains(&en_passant));
+        assert!(pos.is_legal_move(en_passant));
+
+        // Same position mirrored for Black, with the king to the right of the pawns.
+        let pos = Position::parse_fen("K7/8/8/8/Q2Pp2k/8/8/8 b - d3 0 1").unwrap();
+        assert!(!pos.is_legal_move(Move::new(E4, D3, None)));
+    }
+
+    #[test]
+    fn game_threefold_repetition() {
+        let shuffle = [
+            Move::new(G1, F3, None),
+            Move::new(G8, F6, None),
+            Move::new(F3, G1, None),
+            Move::new(F6, G8, None),
+        ];
+        let mut moves = MoveHistory::new();
+
+        // Start position occurs twice after one shuffle, and three times after two.
+        moves.extend(shuffle);
+        let game = Game::new(Position::start_position(), moves.clone()).unwrap();
+        assert!(!game.is_threefold_repetition());
+
+        moves.extend(shuffle);
+        let game = Game::new(Position::start_position(), moves.clone()).unwrap();
+        assert!(game.is_threefold_repetition());
+
+        // Repeating the position after a pawn move starts counting again.
+        moves.push(Move::new(E2, E4, None));
+        moves.push(Move::new(E7, E5, None));
+        moves.extend(shuffle);
+        let game = Game::new(Position::start_position(), moves.clone()).unwrap();
+        assert!(!game.is_threefold_repetition());
+        assert!(!Game::start_position().is_threefold_repetition());
+
+        // A move history which is not legal is not a repetition.
+        let mut game = Game::start_position();
+        game.moves.extend(shuffle);
+        game.moves.extend(shuffle);
+        game.moves.push(Move::new(E2, E5, None));
+        assert!(!game.is_threefold_repetition());
+    }
+
+    #[test]
+    fn game_push_and_pop_moves() {
+        let moves = [
+            Move::new(E2, E4, None),
+            Move::new(C7, C5, None),
+            Move::new(E4, E5, None),
+            Move::new(D7, D5, None),
+            Move::new(E5, D6, None),
+            Move::new(E8, D7, None),
+        ];
+        let mut game = Game::start_position();
+        for (idx, move_) in moves.iter().enumerate() {
+            game.push_move(*move_).unwrap();
+            let replayed = Game::new(game.base_position, game.moves.clone()).unwrap();
+            assert_eq!(game, replayed);
+            assert_eq!(game.moves[..], moves[..=idx]);
+        }
+
+        // Illegal moves leave the game unchanged.
+        let before = game.clone();
+        assert!(game.push_move(Move::new(A2, A5, None)).is_err());
+        assert_eq!(game, before);
+
+        for (idx, move_) in moves.iter().enumerate().rev() {
+            assert_eq!(game.pop_move(), Some(*move_));
+            let replayed = Game::new(game.base_position, game.moves.clone()).unwrap();
+            assert_eq!(game, replayed);
+            assert_eq!(game.moves[..], moves[..idx]);
+        }
+        assert_eq!(game.pop_move(), None);
+        assert_eq!(game, Game::start_position());
+    }
+
+    #[test]
+    fn game_uci_position_round_trip() {
+        let game = Game::from_uci_position_str("startpos").unwrap();
+        assert_eq!(game, Game::start_position());
+        assert_eq!(game.to_uci_position_str(), "startpos");
+
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let position_str = format!("fen {} moves e1g1 h3g2 d5e6 g2f1q", fen);
+        let game = Game::from_uci_position_str(&position_str).unwrap();
+        assert_eq!(game.base_position, Position::parse_fen(fen).unwrap());
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(game.moves[3], Move::new(G2, F1, Some(Queen)));
+        assert_eq!(game.to_uci_position_str(), position_str);
+        assert_eq!(game.to_string(), position_str);
+        assert_eq!(Game::try_from(position_str.as_str()).unwrap(), game);
+        assert_eq!(
+            Game::from_uci_position_str(&game.to_string()).unwrap(),
+            game
+        );
+
+        assert!(Game::from_uci_position_str("").is_err());
+        assert!(Game::from_uci_position_str("fen 8/8/8").is_err());
+        assert!(Game::from_uci_position_str("startpos moves e2e5").is_err());
+    }
+
+    #[test]
+    fn game_record_move_times() {
+        let mut game = Game::start_position();
+        assert!(game.record_move_time(0, Duration::from_secs(1)).is_err());
+
+        game.push_move(Move::new(E2, E4, None)).unwrap();
+        game.push_move(Move::new(E7, E5, None)).unwrap();
+        game.push_move(Move::new(G1, F3, None)).unwrap();
+        game.record_move_time(0, Duration::from_millis(1500))
+            .unwrap();
+        game.record_move_time(2, Duration::from_secs(3)).unwrap();
+        assert!(game.record_move_time(3, Duration::from_secs(1)).is_err());
+
+        assert_eq!(game.move_time(0), Some(Duration::from_millis(1500)));
+        assert_eq!(game.move_time(1), None);
+        assert_eq!(game.move_time(2), Some(Duration::from_secs(3)));
+        assert_eq!(game.move_time(3), None);
+
+        // Popping a move removes its time, and a replacement move starts without one.
+        game.pop_move().unwrap();
+        game.push_move(Move::new(B1, C3, None)).unwrap();
+        assert_eq!(game.move_time(0), Some(Duration::from_millis(1500)));
+        assert_eq!(game.move_time(2), None);
+    }
+
+    #[test]
+    fn game_fifty_move_draw() {
+        let position = Position::parse_fen("8/8/4k3/8/8/4K3/8/7R w - - 99 80").unwrap();
+        assert!(!Game::from(position).is_fifty_move_draw());
+
+        let mut moves = MoveHistory::new();
+        moves.push(Move::new(H1, H2, None));
+        let game = Game::new(position, moves).unwrap();
+        assert!(game.is_fifty_move_draw());
+
+        // Checkmate on the hundredth halfmove is not a draw.
+        let position = Position::parse_fen("6k1/8/6K1/8/8/8/8/R7 w - - 99 80").unwrap();
+        let mut moves = MoveHistory::new();
+        moves.push(Move::new(A1, A8, None));
+        let game = Game::new(position, moves).unwrap();
+        assert!(!game.is_fifty_move_draw());
+    }
+
+    #[test]
+    fn piece_on_square() {
+        let position = Position::start_position();
+        assert_eq!(position.piece_on(E1), Some(Piece::new(White, King)));
+        assert_eq!(position.piece_on(D8), Some(Piece::new(Black, Queen)));
+        assert_eq!(position.piece_on(B1), Some(Piece::new(White, Knight)));
+        assert_eq!(position.piece_on(H7), Some(Piece::new(Black, Pawn)));
+        assert_eq!(position.piece_on(E4), None);
+        assert_eq!(position.piece_on(A3), None);
+
+        let position = position.make_move(Move::new(E2, E4, None));
+        assert_eq!(position.piece_on(E4), Some(Piece::new(White, Pawn)));
+        assert_eq!(position.piece_on(E2), None);
+    }
 }
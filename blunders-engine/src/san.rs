@@ -0,0 +1,300 @@
+//! Standard Algebraic Notation, the human readable notation for chess moves used by PGN.
+//!
+//! [Chess Programming SAN](https://www.chessprogramming.org/Algebraic_Chess_Notation#Standard_Algebraic_Notation_.28SAN.29)
+//!
+//! Examples: `e4`, `Nf3`, `exd5`, `Rad1`, `N5xf3`, `e8=Q+`, `O-O-O#`
+
+use std::convert::TryFrom;
+
+use crate::coretypes::{Color, File, Move, MoveKind, Piece, PieceKind, Rank, Square};
+use crate::error::{self, ErrorKind};
+use crate::movelist::Line;
+use crate::position::Position;
+
+/// Parse a SAN move string into the legal Move it describes for a position.
+/// Check, checkmate, and annotation suffixes (`+#!?`) are accepted and ignored.
+/// Castling may be written with either letter O or digit 0.
+pub fn parse_san(position: &Position, san: &str) -> error::Result<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = position.get_legal_moves();
+
+    // Castling moves are found by the direction the king moves towards its rook.
+    if let Some(kingside) = match san {
+        "O-O" | "0-0" => Some(true),
+        "O-O-O" | "0-0-0" => Some(false),
+        _ => None,
+    } {
+        return legal_moves
+            .into_iter()
+            .find(|move_| {
+                position.move_info(*move_).move_kind == MoveKind::Castle
+                    && (move_.to.file() > move_.from.file()) == kingside
+            })
+            .ok_or_else(|| (ErrorKind::SanIllegalMove, san).into());
+    }
+
+    let malformed = || error::Error::from((ErrorKind::ParseSanMalformed, san));
+    let mut chars: Vec<char> = san.chars().filter(|ch| *ch != 'x').collect();
+
+    // Leading piece letter, where pawns have none.
+    let piece_kind = match chars.first() {
+        Some(ch) if ch.is_ascii_uppercase() => {
+            let piece_kind = Piece::try_from(chars.remove(0))?.piece_kind;
+            if piece_kind == PieceKind::Pawn {
+                return Err(malformed());
+            }
+            piece_kind
+        }
+        _ => PieceKind::Pawn,
+    };
+
+    // Trailing promotion piece, with or without `=`.
+    let promotion = match chars.last() {
+        Some(ch) if ch.is_ascii_uppercase() => {
+            let promotion = Piece::try_from(chars.pop().unwrap())?.piece_kind;
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+            Some(promotion)
+        }
+        _ => None,
+    };
+
+    // Target square is always last, and anything before it disambiguates the origin.
+    if chars.len() < 2 || chars.len() > 4 {
+        return Err(malformed());
+    }
+    let to_str: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let to: Square = to_str.parse()?;
+    let mut from_file = None;
+    let mut from_rank = None;
+    for ch in chars {
+        match ch {
+            'a'..='h' if from_file.is_none() => from_file = Some(File::try_from(ch)?),
+            '1'..='8' if from_rank.is_none() => from_rank = Some(Rank::try_from(ch)?),
+            _ => return Err(malformed()),
+        }
+    }
+
+    let mut candidates = legal_moves.into_iter().filter(|move_| {
+        move_.to == to
+            && move_.promotion == promotion
+            && position.move_info(*move_).piece_kind == piece_kind
+            && position.move_info(*move_).move_kind != MoveKind::Castle
+            && from_file.is_none_or(|file| move_.from.file() == file)
+            && from_rank.is_none_or(|rank| move_.from.rank() == rank)
+    });
+
+    match (candidates.next(), candidates.next()) {
+        (Some(move_), None) => Ok(move_),
+        (Some(_), Some(_)) => Err((ErrorKind::SanAmbiguousMove, san).into()),
+        (None, _) => Err((ErrorKind::SanIllegalMove, san).into()),
+    }
+}
+
+/// Format a legal move of a position as a SAN move string.
+/// The origin square is only given as needed to disambiguate between identical pieces,
+/// and a check or checkmate suffix is appended when the move gives check.
+pub fn to_san(position: &Position, move_: Move) -> String {
+    let move_info = position.move_info(move_);
+    let mut san = String::new();
+
+    if move_info.move_kind == MoveKind::Castle {
+        match move_.to.file() > move_.from.file() {
+            true => san.push_str("O-O"),
+            false => san.push_str("O-O-O"),
+        }
+    } else {
+        let is_capture = matches!(
+            move_info.move_kind,
+            MoveKind::Capture(_) | MoveKind::EnPassant
+        );
+
+        if move_info.piece_kind == PieceKind::Pawn {
+            if is_capture {
+                san.push(char::from(move_.from.file()));
+            }
+        } else {
+            san.push(char::from(move_info.piece_kind));
+
+            // Other pieces of the same kind which may move to the same square.
+            let others: Vec<Square> = position
+                .get_legal_moves()
+                .into_iter()
+                .filter(|other| {
+                    other.to == move_.to
+                        && other.from != move_.from
+                        && position.move_info(*other).piece_kind == move_info.piece_kind
+                })
+                .map(|other| other.from)
+                .collect();
+
+            if !others.is_empty() {
+                let shares_file = others.iter().any(|sq| sq.file() == move_.from.file());
+                let shares_rank = others.iter().any(|sq| sq.rank() == move_.from.rank());
+                if !shares_file {
+                    san.push(char::from(move_.from.file()));
+                } else if !shares_rank {
+                    san.push(char::from(move_.from.rank()));
+                } else {
+                    san.push_str(&move_.from.to_string());
+                }
+            }
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&move_.to.to_string());
+        if let Some(promotion) = move_.promotion {
+            san.push('=');
+            san.push(char::from(promotion));
+        }
+    }
+
+    let position = position.make_move(move_);
+    if position.is_checkmate() {
+        san.push('#');
+    } else if position.is_in_check() {
+        san.push('+');
+    }
+
+    san
+}
+
+impl Position {
+    /// Format a line of moves played from this position as SAN with move numbers,
+    /// such as `1. e4 e5 2. Nf3`. A line beginning with Black's move starts with `1...`.
+    /// Rendering stops before the first move which is illegal where it is played.
+    pub fn line_to_san(&self, line: &Line) -> String {
+        let mut tokens = Vec::new();
+        let mut position = *self;
+        for (idx, move_) in line.iter().enumerate() {
+            if !position.is_legal_move(*move_) {
+                break;
+            }
+            match (*position.player(), idx) {
+                (Color::White, _) => tokens.push(format!("{}.", position.fullmoves())),
+                (Color::Black, 0) => tokens.push(format!("{}...", position.fullmoves())),
+                _ => (),
+            }
+            tokens.push(to_san(&position, *move_));
+            position.do_move(*move_);
+        }
+        tokens.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coretypes::Square::*;
+    use crate::fen::Fen;
+
+    #[test]
+    fn parse_san_start_position() {
+        let pos = Position::start_position();
+        assert_eq!(parse_san(&pos, "e4").unwrap(), Move::new(E2, E4, None));
+        assert_eq!(parse_san(&pos, "Nf3").unwrap(), Move::new(G1, F3, None));
+        assert_eq!(parse_san(&pos, "Nc3!?").unwrap(), Move::new(B1, C3, None));
+        assert!(parse_san(&pos, "e5").is_err());
+        assert!(parse_san(&pos, "Nd2").is_err());
+        assert!(parse_san(&pos, "O-O").is_err());
+        assert!(parse_san(&pos, "Pe4").is_err());
+        assert!(parse_san(&pos, "").is_err());
+    }
+
+    #[test]
+    fn parse_san_disambiguation_and_promotion() {
+        let pos = Position::parse_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(parse_san(&pos, "Rad1").unwrap(), Move::new(A1, D1, None));
+        assert_eq!(parse_san(&pos, "Rhf1").unwrap(), Move::new(H1, F1, None));
+        assert_eq!(parse_san(&pos, "Rxa8+").unwrap(), Move::new(A1, A8, None));
+        assert_eq!(
+            parse_san(&pos, "b8=Q+").unwrap(),
+            Move::new(B7, B8, Some(PieceKind::Queen))
+        );
+        assert_eq!(
+            parse_san(&pos, "bxa8N").unwrap(),
+            Move::new(B7, A8, Some(PieceKind::Knight))
+        );
+        assert_eq!(parse_san(&pos, "O-O").unwrap(), Move::new(E1, G1, None));
+        assert_eq!(parse_san(&pos, "0-0-0").unwrap(), Move::new(E1, C1, None));
+        assert!(parse_san(&pos, "b8").is_err());
+
+        let pos = Position::parse_fen("4k3/8/8/8/8/8/8/N1N1K3 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&pos, "Nab3").unwrap(), Move::new(A1, B3, None));
+        assert!(parse_san(&pos, "N1xb3").is_err());
+        assert!(parse_san(&pos, "Nb3").is_err());
+    }
+
+    #[test]
+    fn to_san_round_trip() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/8/8/8/8/8/8/N1N1K3 w - - 0 1",
+        ];
+        for fen in fens {
+            let pos = Position::parse_fen(fen).unwrap();
+            for move_ in pos.get_legal_moves() {
+                let san = to_san(&pos, move_);
+                assert_eq!(parse_san(&pos, &san).unwrap(), move_, "{} {}", fen, san);
+            }
+        }
+    }
+
+    #[test]
+    fn to_san_formatting() {
+        let pos = Position::parse_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(to_san(&pos, Move::new(A1, D1, None)), "Rd1");
+        assert_eq!(to_san(&pos, Move::new(A1, A8, None)), "Rxa8+");
+        assert_eq!(
+            to_san(&pos, Move::new(B7, A8, Some(PieceKind::Queen))),
+            "bxa8=Q+"
+        );
+        assert_eq!(to_san(&pos, Move::new(E1, G1, None)), "O-O");
+        assert_eq!(to_san(&pos, Move::new(E1, C1, None)), "O-O-O");
+
+        let pos = Position::parse_fen("4k3/8/8/N7/8/8/8/N1N1K3 w - - 0 1").unwrap();
+        assert_eq!(to_san(&pos, Move::new(A1, B3, None)), "Na1b3");
+        assert_eq!(to_san(&pos, Move::new(C1, B3, None)), "Ncb3");
+        assert_eq!(to_san(&pos, Move::new(A5, C6, None)), "Nc6");
+
+        let pos = Position::parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(to_san(&pos, Move::new(A1, A8, None)), "Ra8#");
+    }
+
+    #[test]
+    fn line_to_san_move_numbers() {
+        let pos = Position::start_position();
+        let mut line = Line::new();
+        for move_ in [
+            Move::new(E2, E4, None),
+            Move::new(E7, E5, None),
+            Move::new(G1, F3, None),
+            Move::new(B8, C6, None),
+            Move::new(F1, B5, None),
+        ] {
+            line.push(move_);
+        }
+        assert_eq!(pos.line_to_san(&line), "1. e4 e5 2. Nf3 Nc6 3. Bb5");
+        assert_eq!(pos.line_to_san(&Line::new()), "");
+
+        // Black to move begins with an ellipsis, and illegal moves end the line.
+        let pos = pos.make_move(Move::new(E2, E4, None));
+        let mut line = Line::new();
+        for move_ in [
+            Move::new(E7, E5, None),
+            Move::new(D1, H5, None),
+            Move::new(E8, E7, None),
+            Move::new(H5, E5, None),
+            Move::new(E7, E6, None),
+        ] {
+            line.push(move_);
+        }
+        assert_eq!(pos.line_to_san(&line), "1... e5 2. Qh5 Ke7 3. Qxe5#");
+    }
+}
@@ -1,12 +1,15 @@
 //! History structure used within search.
 
 use crate::arrayvec::ArrayVec;
-use crate::coretypes::MAX_HISTORY;
+use crate::coretypes::{MAX_DEPTH, MAX_HISTORY};
 use crate::position::Game;
 use crate::zobrist::{HashKind, ZobristTable};
 
-type HashHistory = ArrayVec<HashKind, MAX_HISTORY>;
-type Unrepeatables = ArrayVec<usize, MAX_HISTORY>;
+// A search pushes up to MAX_DEPTH positions onto the history of a full length game.
+const HISTORY_CAPACITY: usize = MAX_HISTORY + MAX_DEPTH as usize;
+
+type HashHistory = ArrayVec<HashKind, HISTORY_CAPACITY>;
+type Unrepeatables = ArrayVec<usize, HISTORY_CAPACITY>;
 
 /// History primary use is for tracking repeated moves to prevent threefold repetition.
 /// It is stateful, in that functions assume the next interaction comes from the next
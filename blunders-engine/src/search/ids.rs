@@ -1,33 +1,40 @@
 //! Iterative Deepening Search.
 
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::arrayvec::display;
 use crate::coretypes::MAX_DEPTH;
+use crate::movelist::Line;
+use crate::moveorder::pick_best_move;
 use crate::search;
 use crate::search::History;
-use crate::search::SearchResult;
-use crate::timeman::Mode;
-use crate::transposition::{Entry, NodeKind, TranspositionTable};
+use crate::search::{ProgressSender, SearchContext, SearchProgress, SearchResult};
+use crate::timeman::{Mode, EASY_MOVE_MARGIN};
+use crate::transposition::{score_to_tt, Entry, NodeKind, TranspositionTable};
 use crate::Position;
 
+/// Number of iterations the best move must stay the same to be an easy move.
+const EASY_MOVE_ITERATIONS: u32 = 3;
+
 /// Run Iterative Deepening search on a root position to depth "ply" using
 /// a persistent transposition table.
 /// It returns the best move and score for the position in the search tree.
-/// TODO: Bug fix, returns invalid result in case where stopper was set too quickly.
-pub fn ids(
+/// Progress is sent after each completed iteration, and periodically during each iteration.
+/// If an iteration is stopped before its first root move is fully searched, the result
+/// of the last completed iteration is returned instead, flagged as stopped.
+/// If no iteration completes, the best move is only a guess by move ordering.
+/// The evaluation cache and history table of `context` are shared by every iteration.
+pub fn ids<P: ProgressSender>(
     position: Position,
     mode: Mode,
     history: History,
     tt: &TranspositionTable,
-    stopper: Arc<AtomicBool>,
-    debug: bool,
+    context: &mut SearchContext<P>,
 ) -> SearchResult {
     let hash = tt.generate_hash(&position);
     let instant = Instant::now();
-    let age = position.age();
+    let age = tt.new_generation();
 
     // Invalid default values, will be overwritten after each loop.
     let mut search_result = SearchResult {
@@ -39,19 +46,49 @@ pub fn ids(
     // Run a search for each ply from 1 to target ply.
     // After each search, ensure that the principal variation from the previous
     // iteration is in the tt.
+    let mut last_iteration = Duration::ZERO;
+    let mut best_move_iterations = 0;
     for ply in 1..=MAX_DEPTH {
         // Check if we need to stop before the current iteration.
-        if mode.stop(position.player, ply) {
+        if context.stopper.load(Ordering::Acquire) {
+            search_result.stopped = true;
+            break;
+        }
+        if mode.stop_iteration(position.player, ply, search_result.nodes, last_iteration) {
             break;
         }
+        let iteration_instant = Instant::now();
 
-        let stopper = Arc::clone(&stopper);
         let history = history.clone();
-        let maybe_result = search::iterative_negamax(position, ply, mode, history, tt, stopper);
+        let iteration_progress = IterationProgress {
+            sender: context.progress,
+            nodes: search_result.nodes,
+            instant,
+        };
+        let mut iteration_context = SearchContext {
+            eval_cache: &mut *context.eval_cache,
+            history_table: &mut *context.history_table,
+            q_params: context.q_params,
+            pruning: context.pruning,
+            stopper: Arc::clone(&context.stopper),
+            progress: &iteration_progress,
+        };
+        let maybe_result = search::iterative_negamax(
+            position,
+            ply,
+            mode.after_nodes(search_result.nodes),
+            history,
+            tt,
+            &mut iteration_context,
+        );
 
         // Update search_result from deeper iteration, and return early if it's flagged as stop.
         // Need to update nodes, q_nodes, and q_elapsed to get running total.
         if let Some(mut result) = maybe_result {
+            best_move_iterations = match result.best_move == search_result.best_move {
+                true => best_move_iterations + 1,
+                false => 1,
+            };
             result.add_metrics(search_result);
             search_result = result;
 
@@ -59,24 +96,34 @@ pub fn ids(
                 break;
             }
         } else {
+            // Iteration was stopped before any root move was fully searched,
+            // so the last completed iteration is the best result available.
+            search_result.stopped = true;
             break;
         }
 
-        if debug && !search_result.stopped {
-            // Print UCI info for this completed search result.
-            println!(
-                "info depth {} score cp {} time {} nodes {} nps {} pv {}",
-                search_result.depth,
-                search_result.relative_score(),
-                search_result.elapsed.as_millis(),
-                search_result.nodes,
-                search_result.nps(),
-                display(&search_result.pv),
-            );
-        }
+        // Send progress for this completed search result.
+        let mut completed = SearchProgress::from(&search_result);
+        completed.elapsed = instant.elapsed();
+        context.progress.send_progress(completed);
+        last_iteration = iteration_instant.elapsed();
 
         // Check if this completed search result contains a checkmate, to return early.
-        if search_result.score.is_mate() && !search_result.stopped {
+        // Mates found by quiescence past the horizon are not played out by the pv,
+        // so search continues until the full line to mate is found.
+        if search_result.score.is_mate()
+            && !search_result.stopped
+            && pv_ends_in_mate(&position, &search_result.pv)
+        {
+            break;
+        }
+
+        // Check if the best move is easy, being stable over several iterations and much
+        // better than every other move, to return early if the mode allows it.
+        let is_easy_move = best_move_iterations >= EASY_MOVE_ITERATIONS
+            && search_result.second_score.saturating_add(EASY_MOVE_MARGIN)
+                <= search_result.relative_score();
+        if is_easy_move && mode.stop_easy_move(position.player, search_result.nodes) {
             break;
         }
 
@@ -94,7 +141,8 @@ pub fn ids(
         let mut relative_pv_score = search_result.relative_score();
 
         for &pv_move in search_result.pv.iter().take(move_ply as usize) {
-            let pv_entry = Entry::new(hash, pv_move, relative_pv_score, move_ply, NodeKind::Pv);
+            let score = score_to_tt(relative_pv_score, ply - move_ply);
+            let pv_entry = Entry::new(hash, pv_move, score, move_ply, NodeKind::Pv);
             tt.replace(pv_entry, age);
 
             let cache = position.cache();
@@ -106,8 +154,298 @@ pub fn ids(
         // TODO: Handle part of PV that is longer than depth searched.
     }
 
+    // A search stopped before its first iteration completed has no best move.
+    // The best guess of move ordering is used so that a legal move is always returned.
+    if search_result.depth == 0 {
+        let key_move = tt.get(hash).map(|entry| entry.key_move);
+        if let Some(move_info) = pick_best_move(&mut position.get_legal_move_infos(), key_move) {
+            search_result.best_move = move_info.move_();
+            search_result.pv.clear();
+            search_result.pv.push(move_info.move_());
+        }
+    }
+
+    // The root is never scored as a draw, but a caller may claim one.
+    search_result.repetition = history.is_threefold_repetition(hash);
+    search_result.fifty_move = position.fifty_move_rule(position.get_legal_moves().len());
+    search_result.insufficient_material = position.is_insufficient_material();
+
     // Update values with those tracked in top level.
     search_result.elapsed = instant.elapsed();
 
     search_result
 }
+
+/// Returns true if playing out the pv from position ends in checkmate.
+fn pv_ends_in_mate(position: &Position, pv: &Line) -> bool {
+    let mut position = *position;
+    for &pv_move in pv.iter() {
+        position.do_move(pv_move);
+    }
+    position.is_checkmate()
+}
+
+/// IterationProgress forwards progress from a single iteration of iterative deepening,
+/// adding the totals of all previous iterations so progress reflects the entire search.
+struct IterationProgress<'a, P> {
+    sender: &'a P,
+    nodes: u64,
+    instant: Instant,
+}
+
+impl<P: ProgressSender> ProgressSender for IterationProgress<'_, P> {
+    fn send_progress(&self, mut progress: SearchProgress) {
+        progress.nodes += self.nodes;
+        progress.elapsed = self.instant.elapsed();
+        self.sender.send_progress(progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coretypes::{Move, Square::*};
+    use crate::fen::Fen;
+    use crate::search::{with_new_context, PruningParams, QuiescenceParams};
+    use crate::zobrist::ZobristTable;
+    use crate::Game;
+
+    /// Run a fresh iterative deepening search of a position.
+    /// Hashes are seeded so that repeated searches visit the same number of nodes.
+    fn search(position: Position, mode: Mode) -> SearchResult {
+        search_with(position, mode, PruningParams::DEFAULT)
+    }
+
+    /// Run a fresh iterative deepening search of a position with the given kinds of pruning.
+    fn search_with(position: Position, mode: Mode, pruning: PruningParams) -> SearchResult {
+        let ztable = ZobristTable::with_seed(0);
+        let tt = TranspositionTable::with_capacity_and_zobrist(100_000, ztable);
+        let history = History::new(&position.into(), tt.zobrist_table());
+        with_new_context(&tt, 1, |context| {
+            context.pruning = pruning;
+            ids(position, mode, history, &tt, context)
+        })
+    }
+
+    #[test]
+    fn easy_move_stops_early() {
+        // Black's queen is free to capture.
+        let position =
+            Position::parse_fen("rnb1kbnr/pppp1ppp/8/4q3/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 3")
+                .unwrap();
+        // A node budget stops both searches at the same point on any machine.
+        let mode = || Mode::nodes(40_000, None);
+
+        let full = search(position, mode());
+        let easy = search(position, mode().with_easy_move(true));
+
+        assert_eq!(full.best_move, Move::new(D4, E5, None));
+        assert_eq!(easy.best_move, Move::new(D4, E5, None));
+        assert!(easy.second_score + EASY_MOVE_MARGIN <= easy.relative_score());
+        assert!(easy.depth < full.depth, "{} {}", easy.depth, full.depth);
+        assert!(easy.nodes < full.nodes);
+    }
+
+    #[test]
+    fn nodes_mode_stops_mid_iteration() {
+        let nodes = 20_000;
+        let result = search(Position::start_position(), Mode::nodes(nodes, None));
+
+        // Nodes of the interrupted iteration are not counted, so only the upper bound is known.
+        assert!(result.stopped);
+        assert!(result.nodes < nodes + 4096, "{}", result.nodes);
+        assert_ne!(result.best_move, Move::illegal());
+    }
+
+    #[test]
+    fn movetime_mode_stops_mid_iteration() {
+        // The deadline has passed before the iteration begins, so it stops at its first
+        // stop check after 2048 nodes regardless of how fast the build searches.
+        let position = Position::start_position();
+        let tt = TranspositionTable::with_capacity_and_zobrist(100_000, ZobristTable::with_seed(0));
+        let history = History::new(&position.into(), tt.zobrist_table());
+        let result = with_new_context(&tt, 1, |context| {
+            search::iterative_negamax(position, 4, Mode::movetime(0, None), history, &tt, context)
+        })
+        .unwrap();
+
+        assert!(result.stopped);
+        assert!(result.nodes >= 2048);
+        assert!(result.nodes < 4096, "{}", result.nodes);
+        assert!(position.get_legal_moves().contains(&result.best_move));
+    }
+
+    #[test]
+    fn combined_limits_stop_at_first_reached() {
+        let position = Position::parse_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+
+        // Depth 99 is never reached, so movetime stops the search.
+        let instant = Instant::now();
+        let result = search(position, Mode::limits(Some(99), None, Some(50), None));
+        assert!(result.depth < 99);
+        assert!(instant.elapsed() < Duration::from_millis(1000));
+        assert_ne!(result.best_move, Move::illegal());
+
+        // The node limit is never reached, so depth stops the search.
+        let result = search(
+            position,
+            Mode::limits(Some(4), Some(1_000_000_000), None, None),
+        );
+        assert!(!result.stopped);
+        assert_eq!(result.depth, 4);
+        assert!(result.nodes < 1_000_000_000);
+    }
+
+    #[test]
+    fn stopped_before_first_iteration_returns_legal_move() {
+        let position = Position::start_position();
+        let tt = TranspositionTable::with_capacity(1_000);
+        let history = History::new(&position.into(), tt.zobrist_table());
+        let result = with_new_context(&tt, 1, |context| {
+            context.stopper.store(true, Ordering::Release);
+            ids(position, Mode::infinite(), history, &tt, context)
+        });
+
+        assert!(result.stopped);
+        assert_eq!(result.depth, 0);
+        assert!(position.get_legal_moves().contains(&result.best_move));
+        assert_eq!(result.pv[..], [result.best_move]);
+    }
+
+    #[test]
+    fn threefold_repetition_reports_draw() {
+        let mut game = Game::from(Position::start_position());
+        let knight_moves = [(G1, F3), (G8, F6), (F3, G1), (F6, G8)];
+        for _ in 0..2 {
+            for (from, to) in knight_moves {
+                game.push_move(Move::new(from, to, None)).unwrap();
+            }
+        }
+        assert_eq!(game.position.pieces(), Position::start_position().pieces());
+
+        let tt = TranspositionTable::with_capacity(100_000);
+        let history = History::new(&game, tt.zobrist_table());
+        let result = with_new_context(&tt, 1, |context| {
+            ids(game.position, Mode::depth(2, None), history, &tt, context)
+        });
+
+        assert!(result.repetition);
+        assert!(!result.fifty_move);
+        assert!(!result.insufficient_material);
+        assert!(result.is_drawn());
+        assert!(game.position.get_legal_moves().contains(&result.best_move));
+
+        let result = search(Position::start_position(), Mode::depth(2, None));
+        assert!(!result.is_drawn());
+    }
+
+    #[test]
+    fn stopped_iteration_returns_last_completed() {
+        let position = Position::start_position();
+        let completed = search(position, Mode::depth(4, None));
+        assert!(!completed.stopped);
+
+        // Only 1 node is allowed in the next iteration, which stops at its first stop check.
+        let stopped = search(position, Mode::nodes(completed.nodes + 1, None));
+        assert!(stopped.stopped);
+        assert_eq!(stopped.depth, completed.depth);
+        assert_eq!(stopped.best_move, completed.best_move);
+        assert_eq!(stopped.score, completed.score);
+        assert_eq!(stopped.pv, completed.pv);
+    }
+
+    #[test]
+    fn quiescence_depth_changes_q_nodes() {
+        // Many exchanges are available on e5 and d5.
+        let position = Position::parse_fen(
+            "r1bqkb1r/ppp2ppp/2n2n2/3pp3/3PP3/2N2N2/PPP2PPP/R1BQKB1R w KQkq - 0 5",
+        )
+        .unwrap();
+        let q_search = |depth| {
+            let tt =
+                TranspositionTable::with_capacity_and_zobrist(100_000, ZobristTable::with_seed(0));
+            let history = History::new(&position.into(), tt.zobrist_table());
+            let q_params = QuiescenceParams {
+                depth,
+                ..QuiescenceParams::DEFAULT
+            };
+            with_new_context(&tt, 1, |context| {
+                context.q_params = q_params;
+                ids(position, Mode::depth(3, None), history, &tt, context)
+            })
+        };
+
+        let stand_pat = q_search(0);
+        let shallow = q_search(1);
+        let deep = q_search(10);
+        assert_eq!(stand_pat.q_nodes, 0);
+        assert!(stand_pat.q_nodes < shallow.q_nodes);
+        assert!(shallow.q_nodes < deep.q_nodes);
+    }
+
+    #[test]
+    fn pruning_reduces_nodes() {
+        let kiwipete = Position::parse_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let disabled = [
+            PruningParams {
+                futility: false,
+                ..PruningParams::DEFAULT
+            },
+            PruningParams {
+                reverse_futility: false,
+                ..PruningParams::DEFAULT
+            },
+            PruningParams {
+                razoring: false,
+                ..PruningParams::DEFAULT
+            },
+        ];
+        let pruned = search(kiwipete, Mode::depth(4, None));
+        for pruning in disabled {
+            let full = search_with(kiwipete, Mode::depth(4, None), pruning);
+            assert!(
+                pruned.nodes < full.nodes,
+                "{:?} {} {}",
+                pruning,
+                pruned.nodes,
+                full.nodes
+            );
+        }
+
+        // Futility: White is far behind, but Rxa8+ captures the queen with check.
+        let position = Position::parse_fen("q6k/8/1r6/8/8/8/7P/R3K3 w - - 0 1").unwrap();
+        let result = search(position, Mode::depth(4, None));
+        assert_eq!(result.best_move, Move::new(A1, A8, None));
+
+        // Reverse futility: White is far ahead, and still finds a back rank mate.
+        let position = Position::parse_fen("6k1/5ppp/8/8/8/8/5PPP/2RQ2K1 w - - 0 1").unwrap();
+        let result = search(position, Mode::depth(4, None));
+        assert!(position.make_move(result.best_move).is_checkmate());
+
+        // Razoring: White is down material, but wins the queen by a capture found by quiescence.
+        let position = Position::parse_fen("6k1/5ppp/8/8/8/8/2q2PPP/2R3K1 w - - 0 1").unwrap();
+        let result = search(position, Mode::depth(4, None));
+        assert_eq!(result.best_move, Move::new(C1, C2, None));
+    }
+
+    #[test]
+    fn probe_pv_agrees_with_search_pv() {
+        let position =
+            Position::parse_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let tt = TranspositionTable::with_capacity_and_zobrist(100_000, ZobristTable::with_seed(0));
+        let history = History::new(&position.into(), tt.zobrist_table());
+        let mode = Mode::depth(4, None);
+        let result = with_new_context(&tt, 1, |context| ids(position, mode, history, &tt, context));
+
+        let probed = tt.probe_pv(&position, result.pv.len());
+        assert!(probed.len() >= 3, "{:?} {:?}", probed, result.pv);
+        assert_eq!(probed[..3], result.pv[..3]);
+    }
+}
@@ -15,19 +15,28 @@ pub use negamax::*;
 pub use quiescence::*;
 
 use std::fmt::{self, Display};
-use std::sync::{atomic::AtomicBool, mpsc, Arc};
+use std::sync::{atomic::AtomicBool, mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 use crate::arrayvec::display;
 use crate::coretypes::{Color, Cp, Move, PlyKind};
+use crate::eval::{EvalCache, EvalParams};
 use crate::movelist::Line;
+use crate::moveorder::HistoryTable;
 use crate::timeman::Mode;
 use crate::transposition::TranspositionTable;
 use crate::{Game, Position};
 
+/// Default size of the evaluation cache of a search in Megabytes.
+pub(crate) const EVAL_CACHE_MB: usize = 1;
+
 /// The results found from running a search on some root position.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SearchResult {
     /// The best move to make for a position discovered from search.
     pub best_move: Move,
@@ -49,6 +58,15 @@ pub struct SearchResult {
     pub q_elapsed: Duration,
     /// Flag that indicates this search was aborted.
     pub stopped: bool,
+    /// Upper bound of the relative score of the best root move other than `best_move`.
+    /// This is Cp::MIN if there is no other root move.
+    pub second_score: Cp,
+    /// Flag that the root position is drawn by threefold repetition of its game.
+    pub repetition: bool,
+    /// Flag that the root position is drawn by the fifty-move rule.
+    pub fifty_move: bool,
+    /// Flag that the root position is drawn by insufficient material.
+    pub insufficient_material: bool,
 
     /// Number of nodes where a beta-cutoff was performed.
     pub cut_nodes: u64,
@@ -114,6 +132,12 @@ impl SearchResult {
         self.score
     }
 
+    /// Returns true if the root position is drawn, and a draw may be claimed or adjudicated.
+    /// Search still finds a best move for a drawn root position.
+    pub fn is_drawn(&self) -> bool {
+        self.repetition || self.fifty_move || self.insufficient_material
+    }
+
     /// Returns the color who is leading in the search of the root position, or None if drawn.
     pub fn leading(&self) -> Option<Color> {
         match self.absolute_score().signum() {
@@ -124,6 +148,114 @@ impl SearchResult {
     }
 }
 
+/// An intermediate snapshot of a search which is still running.
+/// Progress is sent at the end of each completed iteration and periodically during an iteration,
+/// so that long searches can report their current best line before they finish.
+#[derive(Debug, Clone)]
+pub struct SearchProgress {
+    /// The best move found so far.
+    pub best_move: Move,
+    /// The centipawn score of the best move found so far, with absolute Cp (+White, -Black).
+    pub score: Cp,
+    /// The principal variation found so far.
+    pub pv: Line,
+    /// The player to move for the root position being searched.
+    pub player: Color,
+    /// Depth of the iteration this snapshot was taken from.
+    pub depth: PlyKind,
+    /// Total number of nodes visited so far.
+    pub nodes: u64,
+    /// Total time elapsed from the start of the search.
+    pub elapsed: Duration,
+    /// Flag that indicates the iteration at `depth` was searched to completion.
+    pub completed: bool,
+}
+
+impl SearchProgress {
+    /// Get average nodes per second of search so far.
+    pub fn nps(&self) -> f64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64()).round()
+    }
+
+    /// Converts the score of the progress into one that is relative to search's root player.
+    pub fn relative_score(&self) -> Cp {
+        self.score * self.player.sign()
+    }
+}
+
+/// Progress of a completed iteration, taken from that iteration's result.
+impl From<&SearchResult> for SearchProgress {
+    fn from(result: &SearchResult) -> Self {
+        Self {
+            best_move: result.best_move,
+            score: result.score,
+            pv: result.pv.clone(),
+            player: result.player,
+            depth: result.depth,
+            nodes: result.nodes,
+            elapsed: result.elapsed,
+            completed: !result.stopped,
+        }
+    }
+}
+
+/// ProgressSender is implemented by anything that can receive progress updates from a running search.
+pub trait ProgressSender {
+    /// Receive a single progress update. Sending progress must never fail a search.
+    fn send_progress(&self, progress: SearchProgress);
+}
+
+/// A unit ProgressSender discards all progress, for searches that do not report.
+impl ProgressSender for () {
+    fn send_progress(&self, _progress: SearchProgress) {}
+}
+
+/// Progress is sent over a channel using the same pattern as SearchResult.
+/// If the receiver has hung up, progress is dropped silently.
+impl<T: From<SearchProgress>> ProgressSender for mpsc::Sender<T> {
+    fn send_progress(&self, progress: SearchProgress) {
+        let _ = self.send(progress.into());
+    }
+}
+
+/// SearchContext holds the inputs of a single search which persist across its nodes
+/// and iterations, so they are passed to each part of the search together.
+///
+/// Positions are evaluated with the evaluation parameters of `eval_cache`.
+pub struct SearchContext<'a, P> {
+    /// Cache of static evaluations, made with the search's evaluation parameters.
+    pub eval_cache: &'a mut EvalCache,
+    /// History heuristic scores used to order quiet moves, updated by the search.
+    pub history_table: &'a mut HistoryTable,
+    /// Quiescence search parameters used at leaf nodes.
+    pub q_params: QuiescenceParams,
+    /// Kinds of pruning done near the horizon.
+    pub pruning: PruningParams,
+    /// Tells the search to stop early from an external source.
+    pub stopper: Arc<AtomicBool>,
+    /// Receives progress updates from the search.
+    pub progress: &'a P,
+}
+
+/// SharedContext is the owned form of a SearchContext, for a search run on another thread.
+/// Shared tables are locked for the duration of the search.
+#[derive(Debug, Clone)]
+pub struct SharedContext {
+    /// History heuristic table, shared by the searches of a game.
+    pub history_table: Arc<Mutex<HistoryTable>>,
+    /// Evaluation cache, shared by the searches of an engine. It must hash with the
+    /// zobrist table of the search's transposition table.
+    pub eval_cache: Arc<Mutex<EvalCache>>,
+    /// Evaluation parameters used by the search, which invalidate the cache if changed.
+    pub params: EvalParams,
+    /// Quiescence search parameters used by the search.
+    pub q_params: QuiescenceParams,
+    /// Kinds of pruning done by the search.
+    pub pruning: PruningParams,
+    /// Tells the search to stop early from an external source.
+    pub stopper: Arc<AtomicBool>,
+}
+
 /// Note that this default is technically illegal and does not represent any actual search.
 impl Default for SearchResult {
     fn default() -> Self {
@@ -138,6 +270,10 @@ impl Default for SearchResult {
             elapsed: Duration::ZERO,
             q_elapsed: Duration::ZERO,
             stopped: false,
+            second_score: Cp::MIN,
+            repetition: false,
+            fifty_move: false,
+            insufficient_material: false,
             cut_nodes: 0,
             pv_nodes: 0,
             all_nodes: 0,
@@ -165,6 +301,7 @@ impl Display for SearchResult {
         ));
         displayed.push_str(&format!("    q_ratio  : {:.2}\n", self.quiescence_ratio()));
         displayed.push_str(&format!("    stopped  : {}\n", self.stopped));
+        displayed.push_str(&format!("    drawn    : {}\n", self.is_drawn()));
         displayed.push_str(&format!("    pv_nodes : {}\n", self.pv_nodes));
         displayed.push_str(&format!("    cut_nodes: {}\n", self.cut_nodes));
         displayed.push_str(&format!("    all_nodes: {}\n", self.all_nodes));
@@ -182,17 +319,33 @@ pub fn search(position: Position, ply: PlyKind, tt: &TranspositionTable) -> Sear
     assert_ne!(ply, 0);
     let mode = Mode::depth(ply, None);
     let history = History::new(&position.into(), tt.zobrist_table());
-    ids(
-        position,
-        mode,
-        history,
-        tt,
-        Arc::new(AtomicBool::new(false)),
-        true,
-    )
+    with_new_context(tt, EVAL_CACHE_MB, |context| {
+        ids(position, mode, history, tt, context)
+    })
+}
+
+/// Runs `f` with a new SearchContext for searches using `tt`.
+/// The context has a new evaluation cache of `eval_cache_mb` megabytes and history table,
+/// default parameters, and a stopper which is not set.
+pub(crate) fn with_new_context<R>(
+    tt: &TranspositionTable,
+    eval_cache_mb: usize,
+    f: impl FnOnce(&mut SearchContext<()>) -> R,
+) -> R {
+    let mut eval_cache = EvalCache::with_mb_and_zobrist(eval_cache_mb, tt.zobrist_table().clone());
+    let mut history_table = HistoryTable::new();
+    f(&mut SearchContext {
+        eval_cache: &mut eval_cache,
+        history_table: &mut history_table,
+        q_params: QuiescenceParams::DEFAULT,
+        pruning: PruningParams::DEFAULT,
+        stopper: Arc::new(AtomicBool::new(false)),
+        progress: &(),
+    })
 }
 
 /// Blunders Engine non-blocking search function. This runs the search on a separate thread.
+/// While the search runs, progress updates are sent over the given Sender.
 /// When the search has been completed, it returns the value by sending it over the given Sender.
 ///
 /// # Arguments
@@ -200,19 +353,17 @@ pub fn search(position: Position, ply: PlyKind, tt: &TranspositionTable) -> Sear
 /// * `game`: State of the current active game
 /// * `mode`: Mode of search determines when the search stops and how deep it searches
 /// * `tt`: Shared Transposition table. This may or may not lock the table for the duration of the search
-/// * `stopper`: Tell search to stop early from an external source
-/// * `debug`: When true prints extra debugging information
-/// * `sender`: Channel to send search result over
+/// * `context`: Tables, parameters, and stopper used by the search
+/// * `sender`: Channel to send search progress and result over
 pub fn search_nonblocking<P, T>(
     game: P,
     mode: Mode,
     tt: Arc<TranspositionTable>,
-    stopper: Arc<AtomicBool>,
-    debug: bool,
+    context: SharedContext,
     sender: mpsc::Sender<T>,
 ) -> thread::JoinHandle<()>
 where
-    T: 'static + Send + From<SearchResult>,
+    T: 'static + Send + From<SearchResult> + From<SearchProgress>,
     P: Into<Game>,
 {
     let game: Game = game.into();
@@ -220,7 +371,24 @@ where
     let history = History::new(&game, tt.zobrist_table());
 
     thread::spawn(move || {
-        let search_result = ids(position, mode, history, &tt, stopper, debug);
-        sender.send(search_result.into()).unwrap();
+        let mut history_table = context.history_table.lock().unwrap();
+        let mut eval_cache = context.eval_cache.lock().unwrap();
+        eval_cache.set_params(&context.params);
+        let search_result = ids(
+            position,
+            mode,
+            history,
+            &tt,
+            &mut SearchContext {
+                eval_cache: &mut eval_cache,
+                history_table: &mut history_table,
+                q_params: context.q_params,
+                pruning: context.pruning,
+                stopper: context.stopper,
+                progress: &sender,
+            },
+        );
+        // The result is discarded if nothing is listening for it.
+        let _ = sender.send(search_result.into());
     })
 }
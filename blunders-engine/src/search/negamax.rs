@@ -2,46 +2,90 @@
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::arrayvec::{self, ArrayVec};
 use crate::coretypes::{Cp, Move, MoveInfo, MoveKind, PieceKind, PlyKind, MAX_DEPTH};
-use crate::eval::{draw, terminal};
-use crate::movelist::{Line, MoveInfoList};
-use crate::moveorder::order_all_moves;
+use crate::eval::{draw, terminal, EvalCache};
+use crate::movelist::Line;
+use crate::moveorder::HistoryTable;
+use crate::movepick::MovePicker;
 use crate::position::{Cache, Position};
-use crate::search::{quiescence, History, SearchResult};
-use crate::timeman::Mode;
-use crate::transposition::{Entry, NodeKind, TranspositionTable};
+use crate::search::{quiescence_from, QuiescenceParams, EVAL_CACHE_MB};
+use crate::search::{History, ProgressSender, SearchContext, SearchProgress, SearchResult};
+use crate::timeman::{Mode, EASY_MOVE_MARGIN};
+use crate::transposition::{score_from_tt, score_to_tt, Entry, NodeKind, TranspositionTable};
 use crate::zobrist::HashKind;
 
+/// Futility margins indexed by remaining ply, the most a quiet move is expected to raise
+/// the static evaluation of a frontier or pre-frontier node.
+const FUTILITY_MARGINS: [Cp; 3] = [Cp(0), Cp(200), Cp(500)];
+
+/// Reverse futility margins indexed by remaining ply, the most the opponent is expected to
+/// lower the static evaluation of a node within that many plies.
+const REVERSE_FUTILITY_MARGINS: [Cp; 4] = [Cp(0), Cp(120), Cp(240), Cp(360)];
+
+/// Razor margins indexed by remaining ply. When the static evaluation of a node plus its
+/// margin is below alpha, the node is checked with a quiescence search before searching it.
+pub const RAZOR_MARGINS: [Cp; 3] = [Cp(0), Cp(300), Cp(550)];
+
+/// PruningParams enable each kind of pruning done near the horizon of a negamax search.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PruningParams {
+    /// Skip quiet moves which cannot raise alpha by their futility margin.
+    pub futility: bool,
+    /// Return early from nodes whose static evaluation beats beta by its reverse futility margin.
+    pub reverse_futility: bool,
+    /// Drop nodes whose static evaluation is below alpha by their razor margin into quiescence.
+    pub razoring: bool,
+}
+
+impl PruningParams {
+    /// Built in pruning parameters, which enable every kind of pruning.
+    pub const DEFAULT: PruningParams = PruningParams {
+        futility: true,
+        reverse_futility: true,
+        razoring: true,
+    };
+}
+
+impl Default for PruningParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Negamax implementation of Minimax with alpha-beta pruning.
 /// Negamax searches to a given depth and returns the best move found.
 /// Internally, Negamax treats the active player as the maxing player,
 /// however the final centipawn score of the position returned is
 /// absolute with White as maxing and Black as minning.
-pub fn negamax(mut position: Position, ply: PlyKind, tt: &TranspositionTable) -> SearchResult {
-    assert!(0 < ply && ply < MAX_DEPTH);
+pub fn negamax(position: Position, ply: PlyKind, tt: &TranspositionTable) -> SearchResult {
+    assert_ne!(ply, 0);
 
     let root_player = *position.player();
     let hash = tt.generate_hash(&position);
     let instant = Instant::now();
-    let age = position.age();
 
     let mut pv = Line::new();
-    let mut nodes = 0;
-
-    let best_score = negamax_impl(
-        &mut position,
+    let mut eval_cache = EvalCache::with_mb_and_zobrist(EVAL_CACHE_MB, tt.zobrist_table().clone());
+    let mut history_table = HistoryTable::new();
+    let mut search = Negamax {
+        position,
         tt,
-        hash,
-        &mut pv,
-        &mut nodes,
-        ply,
-        Cp::MIN,
-        Cp::MAX,
-        age,
-    );
+        context: SearchContext {
+            eval_cache: &mut eval_cache,
+            history_table: &mut history_table,
+            q_params: QuiescenceParams::DEFAULT,
+            pruning: PruningParams::DEFAULT,
+            stopper: Arc::new(AtomicBool::new(false)),
+            progress: &(),
+        },
+        nodes: 0,
+        age: tt.new_generation(),
+    };
+
+    let best_score = negamax_impl(&mut search, hash, &mut pv, ply, Cp::MIN, Cp::MAX, 0);
 
     SearchResult {
         player: root_player,
@@ -49,12 +93,26 @@ pub fn negamax(mut position: Position, ply: PlyKind, tt: &TranspositionTable) ->
         best_move: *pv.get(0).unwrap(),
         score: best_score * root_player.sign(),
         pv,
-        nodes,
+        nodes: search.nodes,
         elapsed: instant.elapsed(),
         ..Default::default()
     }
 }
 
+/// Negamax holds the state shared by every node of a recursive negamax search.
+struct Negamax<'a> {
+    /// Current position to search, which is returned to the root after each node.
+    position: Position,
+    /// Transposition Table used for recalling search history.
+    tt: &'a TranspositionTable,
+    /// Evaluation cache, quiescence, and pruning parameters of the search.
+    context: SearchContext<'a, ()>,
+    /// Counter for number of nodes visited in search.
+    nodes: u64,
+    /// Generation of the search, used as the age of its tt entries.
+    age: u8,
+}
+
 /// The player whose turn it is to move for a position is always treated as the maxing player.
 /// negamax_impl returns the max possible score of the current maxing player.
 /// Therefore, when interpreting the score of a child node, the score needs to be negated.
@@ -63,104 +121,157 @@ pub fn negamax(mut position: Position, ply: PlyKind, tt: &TranspositionTable) ->
 ///
 /// Parameters:
 ///
-/// position: current position to search.
-/// tt: Transposition Table used for recalling search history.
-/// hash: Incrementally updatable hash of provided position.
+/// search: State of the search, including the current position to search.
+/// hash: Incrementally updatable hash of the current position.
 /// pv: Line of moves in principal variation.
-/// nodes: Counter for number of nodes visited in search.
 /// ply: remaining depth to search to.
 /// alpha: Best (greatest) guaranteed value for current player.
 /// beta: Best (lowest) guaranteed value for opposite player.
+/// distance: number of plies from the root position, which must return a best move even when drawn.
 fn negamax_impl(
-    position: &mut Position,
-    tt: &TranspositionTable,
+    search: &mut Negamax,
     hash: HashKind,
     pv: &mut Line,
-    nodes: &mut u64,
     ply: PlyKind,
     mut alpha: Cp,
     beta: Cp,
-    age: u8,
+    distance: PlyKind,
 ) -> Cp {
-    *nodes += 1;
-
-    let legal_moves = position.get_legal_moves();
-    let num_moves = legal_moves.len();
+    search.nodes += 1;
+    let tt = search.tt;
+    let age = search.age;
+
+    // Nodes at the maximum depth are leaf nodes, regardless of their remaining ply.
+    let is_leaf = ply == 0 || distance >= MAX_DEPTH;
+
+    // Leaf nodes, and nodes which may be drawn by the fifty-move rule, need to know if any
+    // legal moves exist up front. All other nodes generate their moves lazily with a MovePicker.
+    let num_moves = match is_leaf || *search.position.halfmoves() >= 100 {
+        true => Some(search.position.get_legal_moves().len()),
+        false => None,
+    };
 
     // Save tt lookup from nested if.
     let mut hash_move = None;
 
     // Search can return when any of the following are encountered:
     // * Checkmate / Stalemate (terminal node)
+    // * Draw by fifty-move rule or insufficient material
     // * Tt move evaluated at equal or greater depth than searching depth
     // * depth 0 reached (leaf node)
     //
     // An eval is returned with respect to the current player.
     // (+Cp good, -Cp bad)
     // Terminal and leaf nodes have no following moves so pv of parent is cleared.
-    if num_moves == 0 {
+    if num_moves == Some(0) {
+        pv.clear();
+        return terminal(&search.position, distance);
+    }
+    // Checked after terminal because mate presides over the 50-move rule.
+    // Negamax has no contempt, so a draw is scored as even.
+    else if distance != 0
+        && (num_moves.is_some_and(|num_moves| search.position.fifty_move_rule(num_moves))
+            || search.position.is_insufficient_material())
+    {
         pv.clear();
-        return terminal(&position);
+        return draw(true, Cp(0));
     }
     // Check if current move exists in tt. If so, we might be able to return that value
-    // right away if has a greater or equal depth than we are considering.
+    // right away if has a greater or equal depth than we are considering,
+    // and its score is usable within the current alpha-beta window.
     // Check that the tt key_move is a legal move, as extra (but not complete)
     // protection against Key collisions.
-    // TODO: Verify that this is bug free. It is possible this may cut the Pv line,
-    //       or that returning early is incorrect.
-    else if let Some(entry) = tt.get(hash) {
-        if entry.ply >= ply && legal_moves.contains(&entry.key_move) {
+    else if let Some(mut entry) = tt.get(hash) {
+        entry.score = score_from_tt(entry.score, distance);
+        if entry.ply >= ply
+            && is_tt_cutoff(&entry, alpha, beta)
+            && search.position.is_legal_move(entry.key_move)
+        {
             pv.clear();
             pv.push(entry.key_move);
             return entry.score;
         }
         hash_move = Some(entry.key_move);
+    }
 
     // Run a Quiescence Search for non-terminal leaf nodes to find a more stable
     // evaluation than a static evaluation.
     // The parent of this node receives an empty pv,
     // because this leaf node has no best move, and is not in history.
-    } else if ply == 0 {
+    if is_leaf {
+        pv.clear();
+        return quiescence_from(
+            &mut search.position,
+            hash,
+            alpha,
+            beta,
+            distance,
+            &mut search.nodes,
+            &mut search.context,
+        );
+    }
+
+    // Nodes near the horizon may return early or skip their quiet moves.
+    let static_eval = pruning_eval(&search.position, hash, ply, distance, &mut search.context);
+    if let Some(score) = reverse_futility(static_eval, beta, ply, &search.context.pruning) {
         pv.clear();
-        let q_ply = 10;
-        return quiescence(position, alpha, beta, q_ply, nodes);
+        return score;
     }
+    if should_razor(static_eval, alpha, ply, &search.context.pruning) {
+        if let Some(score) = razor(
+            &mut search.position,
+            hash,
+            alpha,
+            distance,
+            &mut search.nodes,
+            &mut search.context,
+        ) {
+            pv.clear();
+            return score;
+        }
+    }
+    let futility_score = futility_score(static_eval, alpha, ply, &search.context.pruning);
 
     // Move Ordering
-    // Sort legal moves with estimated best move first.
-    let legal_moves = legal_moves
-        .into_iter()
-        .map(|move_| position.move_info(move_))
-        .collect();
-    let ordered_legal_moves = order_all_moves(legal_moves, hash_move);
-    debug_assert_eq!(num_moves, ordered_legal_moves.len());
+    // Legal moves are picked in stages with estimated best moves first.
+    // Negamax does not track killer moves.
+    let move_picker = MovePicker::new(&search.position, hash_move, [None, None]);
 
     // Placeholder best_move, is guaranteed to be overwritten as there is at
     // lest one legal move, and the score of that move is better than worst
     // possible score.
-    let cache = position.cache();
+    let cache = search.position.cache();
     let mut best_move = Move::illegal();
     let mut local_pv = Line::new();
     let mut best_score = Cp::MIN;
     let mut alpha_raised = false;
+    let mut any_pruned = false;
 
     // For each child of current position, recursively find maxing move.
-    for legal_move_info in ordered_legal_moves.into_iter().rev() {
+    for legal_move_info in move_picker {
+        // Futile quiet moves are scored at the futility score without being searched.
+        if let Some(futility_score) = futility_score {
+            if is_futile(&search.position, legal_move_info.move_()) {
+                best_score = best_score.max(futility_score);
+                any_pruned = true;
+                continue;
+            }
+        }
+
         // Get value of a move relative to active player.
-        position.do_move_info(legal_move_info);
-        let move_hash = tt.update_from_hash(hash, &position, legal_move_info, cache);
+        search.position.do_move_info(legal_move_info);
+        let move_hash = tt.update_from_hash(hash, &search.position, legal_move_info, cache);
+        tt.prefetch(move_hash);
         let move_score = -negamax_impl(
-            position,
-            tt,
+            search,
             move_hash,
             &mut local_pv,
-            nodes,
             ply - 1,
-            -beta,
-            -alpha,
-            age,
+            beta.saturating_neg(),
+            alpha.saturating_neg(),
+            distance + 1,
         );
-        position.undo_move(legal_move_info, cache);
+        search.position.undo_move(legal_move_info, cache);
 
         // Update best_* trackers if this move is best of all seen so far.
         if move_score > best_score {
@@ -171,9 +282,11 @@ fn negamax_impl(
         // Cut-off has occurred, no further children of this position need to be searched.
         // This branch will not be taken further up the tree as there is a better move.
         // Push this cut-node into the tt, with a score relative to this node's active player.
+        // The remaining children may score higher, so move_score is a lower bound.
         if move_score >= beta {
             let cut_move = legal_move_info.move_();
-            let entry = Entry::new(hash, cut_move, move_score, ply, NodeKind::Cut);
+            let score = score_to_tt(move_score, distance);
+            let entry = Entry::new(hash, cut_move, score, ply, NodeKind::Cut);
             tt.replace_by(entry, age, replace_scheme);
             return move_score;
         }
@@ -184,16 +297,30 @@ fn negamax_impl(
             alpha = best_score;
             pv.clear();
             pv.push(best_move);
-            arrayvec::append(pv, local_pv.clone());
+            // A pv longer than a Line is truncated, as only its first moves are played.
+            arrayvec::try_append(pv, local_pv.clone());
         }
     }
 
+    // No legal moves were picked, so this is a terminal node.
+    // If every move was futile then this node fails low without a best move.
+    if best_move == Move::illegal() {
+        pv.clear();
+        return match any_pruned {
+            true => best_score,
+            false => terminal(&search.position, distance),
+        };
+    }
+
     // Every move for this node has been evaluated, and best_score did not exceed beta.
+    // If alpha was raised, best_score is exact. Otherwise every child failed low,
+    // so best_score is an upper bound.
     let node_kind = match alpha_raised {
         true => NodeKind::Pv,
         false => NodeKind::All,
     };
-    let entry = Entry::new(hash, best_move, best_score, ply, node_kind);
+    let score = score_to_tt(best_score, distance);
+    let entry = Entry::new(hash, best_move, score, ply, node_kind);
 
     // Always replace with a PV node, otherwise replace conditionally.
     if node_kind == NodeKind::Pv {
@@ -205,6 +332,101 @@ fn negamax_impl(
     best_score
 }
 
+/// Static evaluation of a node with `ply` remaining plies, used to decide on pruning.
+/// Nodes beyond the pruning horizon, the root, and nodes in check where the evaluation
+/// is unreliable are not evaluated and are never pruned.
+fn pruning_eval<P>(
+    position: &Position,
+    hash: HashKind,
+    ply: PlyKind,
+    distance: PlyKind,
+    context: &mut SearchContext<P>,
+) -> Option<Cp> {
+    match (ply as usize) < REVERSE_FUTILITY_MARGINS.len()
+        && distance != 0
+        && !position.is_in_check()
+    {
+        true => Some(context.eval_cache.evaluate(position, hash)),
+        false => None,
+    }
+}
+
+/// Reverse Futility Pruning (Static Null Move Pruning)
+/// Near the horizon, when the static evaluation minus a margin still beats beta,
+/// the opponent is unlikely to recover in time so the node returns its static evaluation early.
+/// This is not done when beta is a mate score.
+fn reverse_futility(
+    static_eval: Option<Cp>,
+    beta: Cp,
+    ply: PlyKind,
+    pruning: &PruningParams,
+) -> Option<Cp> {
+    static_eval
+        .filter(|_| pruning.reverse_futility && !beta.is_mate())
+        .filter(|static_eval| {
+            static_eval.saturating_sub(REVERSE_FUTILITY_MARGINS[ply as usize]) >= beta
+        })
+}
+
+/// Razoring
+/// Near the horizon, when the static evaluation plus a margin is far below alpha,
+/// the node is likely hopeless. It is verified with `razor` before returning early.
+/// This is not done when alpha is a mate score.
+fn should_razor(static_eval: Option<Cp>, alpha: Cp, ply: PlyKind, pruning: &PruningParams) -> bool {
+    static_eval
+        .filter(|_| pruning.razoring && (ply as usize) < RAZOR_MARGINS.len() && !alpha.is_mate())
+        .is_some_and(|static_eval| static_eval.saturating_add(RAZOR_MARGINS[ply as usize]) < alpha)
+}
+
+/// Futility Pruning
+/// Near the horizon, when the static evaluation plus a margin cannot reach alpha,
+/// quiet moves are unlikely to raise alpha and are skipped, scored at the returned
+/// futility score. Captures, promotions, and checks are still searched.
+/// This is not done when alpha is a mate score.
+fn futility_score(
+    static_eval: Option<Cp>,
+    alpha: Cp,
+    ply: PlyKind,
+    pruning: &PruningParams,
+) -> Option<Cp> {
+    static_eval
+        .filter(|_| pruning.futility && (ply as usize) < FUTILITY_MARGINS.len() && !alpha.is_mate())
+        .map(|static_eval| static_eval.saturating_add(FUTILITY_MARGINS[ply as usize]))
+        .filter(|futility_score| *futility_score <= alpha)
+}
+
+/// Returns true if a move may be skipped by futility pruning.
+fn is_futile(position: &Position, move_: Move) -> bool {
+    position.is_quiet(move_) && !position.gives_check(move_)
+}
+
+/// Quiescence search with a null window at alpha, which verifies that razoring a node
+/// `distance` plies from the root does not miss a tactic that reaches alpha.
+/// Checking moves are also searched at its first ply, so that quiet mates are not razored.
+/// Returns the quiescence score if no tactic reaches alpha, so the node can return it.
+fn razor<P>(
+    position: &mut Position,
+    hash: HashKind,
+    alpha: Cp,
+    distance: PlyKind,
+    nodes: &mut u64,
+    context: &mut SearchContext<P>,
+) -> Option<Cp> {
+    let q_params = context.q_params;
+    context.q_params.check_plies = q_params.check_plies.max(1);
+    let score = quiescence_from(
+        position,
+        hash,
+        alpha.saturating_sub(Cp(1)),
+        alpha,
+        distance,
+        nodes,
+        context,
+    );
+    context.q_params = q_params;
+    (score < alpha).then_some(score)
+}
+
 /// Label represents what stage of processing a node is in.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 enum Label {
@@ -219,7 +441,8 @@ enum Label {
 struct Frame {
     pub label: Label,
     pub local_pv: Line,
-    pub legal_moves: MoveInfoList,
+    pub move_picker: Option<MovePicker>,
+    pub killers: [Option<Move>; 2],
     pub alpha: Cp,
     pub beta: Cp,
     pub best_score: Cp,
@@ -228,6 +451,8 @@ struct Frame {
     pub move_info: MoveInfo,
     pub cache: Cache,
     pub alpha_raised: bool,
+    pub futility_score: Option<Cp>,
+    pub any_pruned: bool,
 }
 /// A frame defaults with junk data, however this is acceptable
 /// because nodes set appropriate data before using.
@@ -237,7 +462,8 @@ impl Default for Frame {
         Self {
             label: Label::Initialize,
             local_pv: Line::new(),
-            legal_moves: MoveInfoList::new(),
+            move_picker: None,
+            killers: [None; 2],
             alpha: Cp::MIN,
             beta: Cp::MAX,
             best_score: Cp::MIN,
@@ -252,6 +478,8 @@ impl Default for Frame {
             },
             cache: Cache::illegal(),
             alpha_raised: false,
+            futility_score: None,
+            any_pruned: false,
         }
     }
 }
@@ -292,16 +520,40 @@ fn curr_ply(frame_idx: usize) -> PlyKind {
     (frame_idx - 1) as PlyKind
 }
 
+/// Returns true if a tt entry's score may be returned in place of searching a node
+/// with the given alpha-beta window. Only Pv entries have exact scores.
+/// Cut entries have a lower bound score, usable only if it causes a beta-cutoff.
+/// All entries have an upper bound score, usable only if it fails to raise alpha.
+#[inline]
+fn is_tt_cutoff(entry: &Entry, alpha: Cp, beta: Cp) -> bool {
+    match entry.node_kind {
+        NodeKind::Pv => true,
+        NodeKind::Cut => entry.score >= beta,
+        NodeKind::All => entry.score <= alpha,
+    }
+}
+
 /// TT entry replacement scheme, assuming PV node are unconditionally replaced elsewhere.
 /// This is a replacement scheme assuming new_entry is All or Cut.
 /// Goals:
-/// * Always replace entries from previous searches.
+/// * Always replace stale entries, whose age is from a previous search generation.
 /// * Prioritize deeper searched nodes.
 #[inline]
-fn replace_scheme(new_entry: &Entry, new_age: u8, existing: &Entry, existing_age: u8) -> bool {
+pub(crate) fn replace_scheme(
+    new_entry: &Entry,
+    new_age: u8,
+    existing: &Entry,
+    existing_age: u8,
+) -> bool {
     new_age != existing_age || (existing.node_kind != NodeKind::Pv && new_entry.ply >= existing.ply)
 }
 
+/// Minimum amount of time between progress updates sent during a single search.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Number of nodes, including quiescence nodes, searched between checks of the stop conditions.
+const NODES_PER_STOP_CHECK: u64 = 2048;
+
 /// Iterative fail-soft Negamax implementation with alpha-beta pruning and transposition table lookup.
 ///
 /// In fail-soft, the return value of a call can exceed its given bounds alpha and beta (score < alpha, score > beta).
@@ -311,16 +563,20 @@ fn replace_scheme(new_entry: &Entry, new_age: u8, existing: &Entry, existing_age
 /// This is hard to do from a recursive search without changing/checking return value.
 /// * Makes it easier to tell how far a node is from root.
 /// * Easy to stop without risk of corrupting transposition table entries.
-pub fn iterative_negamax(
+///
+/// While searching, the best root move found so far is periodically sent to the progress
+/// sender of `context`.
+pub fn iterative_negamax<P: ProgressSender>(
     mut position: Position,
     ply: PlyKind,
     mode: Mode,
     mut history: History,
     tt: &TranspositionTable,
-    stopper: Arc<AtomicBool>,
+    context: &mut SearchContext<P>,
 ) -> Option<SearchResult> {
     // Guard: must have a valid searchable ply, and root position must not be terminal.
-    assert!(0 < ply && ply <= MAX_DEPTH);
+    // Plies beyond MAX_DEPTH are not searched, as nodes at MAX_DEPTH are leaf nodes.
+    assert_ne!(ply, 0);
     assert_ne!(position.get_legal_moves().len(), 0);
 
     // Meta Search variables
@@ -328,15 +584,22 @@ pub fn iterative_negamax(
     let root_position = position.clone(); // For assertions
     let root_hash = tt.generate_hash(&position); // Keep copy of root hash for assertions
     let root_history = history.clone();
-    let age = position.age();
+    let age = tt.generation(); // Generation is started by the caller, such as ids.
 
     // Early Stop variables
-    let nodes_per_stop_check = 2000; // Number of nodes between updates to stopped flag
     let mut stopped = false; // Indicates if search was stopped
-    let mut stop_check_counter = nodes_per_stop_check; // When this hits 0, update stopped and reset
+    let mut next_stop_check = NODES_PER_STOP_CHECK; // Node count at which to update stopped flag
+    let mut progress_instant = Instant::now(); // Time since last progress update was sent
 
     // A score assigned to draws to lean engine away from drawing (Cp 0) when slightly behind.
-    let contempt = Cp(50);
+    let contempt = context.eval_cache.params().contempt_cp;
+
+    // Root moves are searched with alpha lowered by this margin, so that when searching for an
+    // easy move, every root move not within the margin of the best move fails low against it.
+    let root_margin = match mode.easy_move() {
+        true => EASY_MOVE_MARGIN,
+        false => Cp(0),
+    };
 
     // Update Metrics in SearchResult.
     let mut metrics = SearchResult::default();
@@ -345,10 +608,11 @@ pub fn iterative_negamax(
     metrics.stopped = false;
 
     // Stack holds frame data, where each ply gets one frame.
-    // Size is +1 because the 0th index holds the PV so far for root position.
+    // Size is +3 because the 0th index holds the PV so far for root position,
+    // the root and leaf plies both get a frame, and a leaf frame has an unused child frame.
     const BASE_IDX: usize = 0; // Root passes PV to this parent frame
     const ROOT_IDX: usize = 1; // Root position data frame
-    let mut stack: ArrayVec<Frame, { (MAX_DEPTH + 1) as usize }> = ArrayVec::new();
+    let mut stack: ArrayVec<Frame, { MAX_DEPTH as usize + 3 }> = ArrayVec::new();
     // Fill stack with default values to navigate, opposed to pushing and popping.
     while !stack.is_full() {
         stack.push(Default::default());
@@ -364,6 +628,25 @@ pub fn iterative_negamax(
 
     // MAIN ITERATIVE LOOP
     while frame_idx > 0 {
+        // Progress Check: Alongside the stop check, send the best root move found so far
+        // if enough time has passed since the last update.
+        // The root PV is only non-empty once at least one root move has been searched.
+        if metrics.nodes >= next_stop_check && progress_instant.elapsed() >= PROGRESS_INTERVAL {
+            progress_instant = Instant::now();
+            if !stack[BASE_IDX].local_pv.is_empty() {
+                context.progress.send_progress(SearchProgress {
+                    best_move: stack[ROOT_IDX].best_move,
+                    score: stack[ROOT_IDX].best_score * root_position.player.sign(),
+                    pv: stack[BASE_IDX].local_pv.clone(),
+                    player: root_position.player,
+                    depth: ply,
+                    nodes: metrics.nodes,
+                    elapsed: instant.elapsed(),
+                    completed: false,
+                });
+            }
+        }
+
         // Take a mut sliding window view into the stack.
         let (parent, us, child) = split_window_frames(&mut stack, frame_idx);
         // How many ply left to target depth.
@@ -372,10 +655,11 @@ pub fn iterative_negamax(
 
         // Stop Check: Before processing, check if search has been told to stop.
         // It is safe to stop at anytime outside of the processing modes below.
-        if label == Label::Initialize && stop_check_counter <= 0 {
-            stop_check_counter = nodes_per_stop_check;
-            stopped |= stopper.load(Ordering::Acquire);
-            stopped |= mode.stop(root_position.player, ply);
+        // Node and time limits of the mode are checked here, so they interrupt an iteration.
+        if label == Label::Initialize && metrics.nodes >= next_stop_check {
+            next_stop_check = metrics.nodes + NODES_PER_STOP_CHECK;
+            stopped |= context.stopper.load(Ordering::Acquire);
+            stopped |= mode.stop(root_position.player, ply, metrics.nodes);
         }
 
         // If stopped flag is ever set, breaking ends search early.
@@ -391,32 +675,40 @@ pub fn iterative_negamax(
         //
         // Flow: Return eval to parent || set self to search mode
         if Label::Initialize == label {
-            stop_check_counter -= 1;
             metrics.nodes += 1;
 
-            let legal_moves = position.get_legal_moves();
-            let num_moves = legal_moves.len();
+            // Leaf nodes, and nodes which may be drawn by the fifty-move rule, need to know if
+            // any legal moves exist up front. Other nodes pick their moves lazily while searching,
+            // and find that they are terminal if they have none.
+            let is_leaf = remaining_ply == 0 || curr_ply(frame_idx) >= MAX_DEPTH;
+            let num_moves = match is_leaf || *position.halfmoves() >= 100 {
+                true => Some(position.get_legal_moves().len()),
+                false => None,
+            };
 
             // Save TT lookup to avoid re-locking.
             let mut hash_move = None;
 
             // This position has no best move.
             // Store its evaluation and tell parent to retrieve value.
-            if num_moves == 0 {
+            if num_moves == Some(0) {
                 parent.label = Label::Retrieve;
                 parent.local_pv.clear();
-                us.best_score = terminal(&position);
+                us.best_score = terminal(&position, curr_ply(frame_idx));
 
                 frame_idx = parent_idx(frame_idx);
                 continue;
             }
-            // Check for draw by repetition or fifty-move rule.
+            // Check for draw by repetition, fifty-move rule, or insufficient material.
+            // The root is not checked because it must always find a best move.
             // After terminal because terminal can't be repeated, mate presides over 50-move rule.
             // Before tt lookup because a repeated position has a different score than when previously visited.
             // TODO:
             // Change to twofold_repetition but avoid error where root is in history.
-            else if position.fifty_move_rule(num_moves)
-                || history.is_threefold_repetition(us.hash)
+            else if frame_idx != ROOT_IDX
+                && (num_moves.is_some_and(|num_moves| position.fifty_move_rule(num_moves))
+                    || position.is_insufficient_material()
+                    || history.is_threefold_repetition(us.hash))
             {
                 parent.label = Label::Retrieve;
                 parent.local_pv.clear();
@@ -427,9 +719,13 @@ pub fn iterative_negamax(
             }
             // Check if this position exists in tt and has been searched to/beyond our ply.
             // If so the score is usable, store this value and return to parent.
-            else if let Some(entry) = tt.get(us.hash) {
+            else if let Some(mut entry) = tt.get(us.hash) {
                 metrics.tt_hits += 1;
-                if entry.ply >= remaining_ply && legal_moves.contains(&entry.key_move) {
+                entry.score = score_from_tt(entry.score, curr_ply(frame_idx));
+                if entry.ply >= remaining_ply
+                    && is_tt_cutoff(&entry, us.alpha, us.beta)
+                    && position.is_legal_move(entry.key_move)
+                {
                     metrics.tt_cuts += 1;
                     parent.label = Label::Retrieve;
                     parent.local_pv.clear();
@@ -443,15 +739,23 @@ pub fn iterative_negamax(
                 }
                 hash_move = Some(entry.key_move);
             }
+
             // Max depth (leaf node) reached. Statically evaluate position and return value.
-            else if remaining_ply == 0 {
+            if is_leaf {
                 parent.label = Label::Retrieve;
                 parent.local_pv.clear();
 
-                let q_ply = 10;
                 let q_instant = Instant::now();
                 let mut q_nodes = 0;
-                us.best_score = quiescence(&mut position, us.alpha, us.beta, q_ply, &mut q_nodes);
+                us.best_score = quiescence_from(
+                    &mut position,
+                    us.hash,
+                    us.alpha,
+                    us.beta,
+                    curr_ply(frame_idx),
+                    &mut q_nodes,
+                    context,
+                );
                 metrics.q_elapsed += q_instant.elapsed();
                 metrics.nodes += q_nodes;
                 metrics.q_nodes += q_nodes;
@@ -460,43 +764,106 @@ pub fn iterative_negamax(
                 continue;
             }
 
-            // This node has not returned early, so it has moves to search.
-            // Order all of this node's legal moves, and set it to search mode.
-            // Optional: Either Sort all moves first, or pick best each time.
-            let legal_moves: MoveInfoList = legal_moves
-                .into_iter()
-                .map(|move_| position.move_info(move_))
-                .collect();
+            // Nodes near the horizon may return early or skip their quiet moves.
+            let distance = curr_ply(frame_idx);
+            let static_eval = pruning_eval(&position, us.hash, remaining_ply, distance, context);
+            let mut early_score =
+                reverse_futility(static_eval, us.beta, remaining_ply, &context.pruning);
+            if early_score.is_none()
+                && should_razor(static_eval, us.alpha, remaining_ply, &context.pruning)
+            {
+                let q_instant = Instant::now();
+                let mut q_nodes = 0;
+                early_score = razor(
+                    &mut position,
+                    us.hash,
+                    us.alpha,
+                    distance,
+                    &mut q_nodes,
+                    context,
+                );
+                metrics.q_elapsed += q_instant.elapsed();
+                metrics.nodes += q_nodes;
+                metrics.q_nodes += q_nodes;
+            }
+            if let Some(score) = early_score {
+                parent.label = Label::Retrieve;
+                parent.local_pv.clear();
+                us.best_score = score;
 
-            us.legal_moves = order_all_moves(legal_moves, hash_move);
+                frame_idx = parent_idx(frame_idx);
+                continue;
+            }
+            us.futility_score =
+                futility_score(static_eval, us.alpha, remaining_ply, &context.pruning);
+            us.any_pruned = false;
+
+            // This node has not returned early, so it may have moves to search.
+            // Its moves are picked in stages with estimated best moves first,
+            // using the killers of its siblings. Then it is set to search mode.
+            us.move_picker = Some(MovePicker::new(&position, hash_move, us.killers));
             us.cache = position.cache();
             us.label = Label::Search;
 
         // SEARCH MODE
-        // If a node ever enters search mode, it is guaranteed to have had a legal move to search.
         // Each search either pushes a child node onto the stack during which it waits
         // to be set to RETRIEVE, or it sees that it has evaluated all of its children and returns
-        // its own score to its parent.
+        // its own score to its parent. A node which never had a move to search is terminal.
         //
         // Flow: (Moves to search) ? recurse to child : return eval to parent
         } else if Label::Search == label {
+            // Futile quiet moves are scored at the futility score without being searched.
+            let mut next_move = None;
+            if let Some(move_picker) = us.move_picker.as_mut() {
+                while let Some(move_info) = move_picker.next_with_history(context.history_table) {
+                    let move_ = move_info.move_();
+                    match us.futility_score {
+                        Some(futility_score) if is_futile(&position, move_) => {
+                            us.best_score = us.best_score.max(futility_score);
+                            us.any_pruned = true;
+                        }
+                        _ => {
+                            next_move = Some(move_info);
+                            break;
+                        }
+                    }
+                }
+            }
+
             // This position has a child position to search, initialize its frame.
-            if let Some(legal_move) = us.legal_moves.pop() {
+            if let Some(legal_move) = next_move {
                 us.move_info = legal_move;
                 position.do_move_info(legal_move);
                 history.push(us.hash, us.move_info.is_unrepeatable());
 
                 let child_hash = tt.update_from_hash(us.hash, &position, us.move_info, us.cache);
+                tt.prefetch(child_hash);
                 child.label = Label::Initialize;
                 child.hash = child_hash;
-                child.alpha = -us.beta;
-                child.beta = -us.alpha;
+                child.alpha = us.beta.saturating_neg();
+                child.beta = us.alpha.saturating_neg();
+                if frame_idx == ROOT_IDX {
+                    child.beta = us.alpha.saturating_sub(root_margin).saturating_neg();
+                }
                 child.best_score = Cp::MIN;
+                child.best_move = Move::illegal();
                 child.alpha_raised = false;
 
                 frame_idx = child_idx(frame_idx);
 
+            // No move was searched to be the best move. If moves were futile this node fails
+            // low at its best score, otherwise it has no legal moves and is terminal.
+            } else if us.best_move == Move::illegal() {
+                parent.label = Label::Retrieve;
+                parent.local_pv.clear();
+                if !us.any_pruned {
+                    us.best_score = terminal(&position, curr_ply(frame_idx));
+                }
+
+                frame_idx = parent_idx(frame_idx);
+
             // Every move for this node has been evaluated, so its complete score is returned.
+            // If alpha was raised the score is exact, otherwise it is an upper bound.
             } else {
                 let node_kind = match us.alpha_raised {
                     true => {
@@ -512,7 +879,7 @@ pub fn iterative_negamax(
                 let entry = Entry::new(
                     us.hash,
                     us.best_move,
-                    us.best_score,
+                    score_to_tt(us.best_score, curr_ply(frame_idx)),
                     remaining_ply,
                     node_kind,
                 );
@@ -540,6 +907,16 @@ pub fn iterative_negamax(
             // Negate child's best score so it's relative to this node.
             let move_score = -child.best_score;
 
+            // Track the score of the best root move other than the best move.
+            // Scores of moves that failed low are upper bounds, so this is also an upper bound.
+            if frame_idx == ROOT_IDX {
+                let second_score = match move_score > us.best_score {
+                    true => us.best_score,
+                    false => move_score,
+                };
+                metrics.second_score = metrics.second_score.max(second_score);
+            }
+
             // Update our best_* trackers if this move is best seen so far.
             if move_score > us.best_score {
                 us.best_score = move_score;
@@ -548,17 +925,29 @@ pub fn iterative_negamax(
 
             // Cut-off has occurred, no further children of this position need to be searched.
             // This branch will not be taken further up the tree as there is a better move.
+            // The remaining children may score higher, so the score is a lower bound.
             if us.best_score >= us.beta {
                 metrics.cut_nodes += 1;
                 let entry = Entry::new(
                     us.hash,
                     us.best_move,
-                    us.best_score,
+                    score_to_tt(us.best_score, curr_ply(frame_idx)),
                     remaining_ply,
                     NodeKind::Cut,
                 );
                 tt.replace_by(entry, age, replace_scheme);
 
+                // Quiet moves which cause cutoffs are tried earlier in other nodes,
+                // and first after noisy moves in sibling nodes.
+                let cut_move = us.move_info.move_();
+                if position.is_quiet(cut_move) {
+                    context.history_table.update(cut_move, remaining_ply);
+                    if us.killers[0] != Some(cut_move) {
+                        us.killers[1] = us.killers[0];
+                        us.killers[0] = Some(cut_move);
+                    }
+                }
+
                 // Early return.
                 parent.label = Label::Retrieve;
                 frame_idx = parent_idx(frame_idx);
@@ -572,9 +961,10 @@ pub fn iterative_negamax(
                 us.alpha = us.best_score;
 
                 // Give parent updated PV by appending child PV to our best move.
+                // A PV longer than a Line is truncated instead of overflowing.
                 parent.local_pv.clear();
                 parent.local_pv.push(us.best_move);
-                arrayvec::append(&mut parent.local_pv, us.local_pv.clone());
+                arrayvec::try_append(&mut parent.local_pv, us.local_pv.clone());
             }
 
             // Default action is to attempt to continue searching this node.
@@ -619,7 +1009,9 @@ pub fn iterative_negamax(
 mod tests {
     use super::*;
     use crate::coretypes::{Color, Move, Square::*};
+    use crate::eval::evaluate;
     use crate::fen::Fen;
+    use crate::search::with_new_context;
 
     #[test]
     #[ignore]
@@ -635,6 +1027,35 @@ mod tests {
         println!("{:?}", result.pv);
     }
 
+    #[test]
+    fn depth_beyond_max_depth_is_capped() {
+        // Pawns are locked and each king is confined to its back rank, so the tree is narrow.
+        let fen = "k7/p1p1p1p1/P1P1P1P1/8/8/1p1p1p1p/1P1P1P1P/K7 w - - 0 1";
+        let position = Position::parse_fen(fen).unwrap();
+        let legal_moves = position.get_legal_moves();
+
+        let tt = TranspositionTable::with_capacity(100_000);
+        let result = negamax(position, MAX_DEPTH + 8, &tt);
+        assert!(legal_moves.contains(&result.best_move));
+        assert!(result.pv.len() <= MAX_DEPTH as usize);
+
+        let tt = TranspositionTable::with_capacity(100_000);
+        let history = History::new(&position.into(), tt.zobrist_table());
+        let result = with_new_context(&tt, 1, |context| {
+            iterative_negamax(
+                position,
+                MAX_DEPTH + 8,
+                Mode::infinite(),
+                history,
+                &tt,
+                context,
+            )
+        })
+        .unwrap();
+        assert!(legal_moves.contains(&result.best_move));
+        assert!(result.pv.len() <= MAX_DEPTH as usize);
+    }
+
     #[test]
     fn color_sign() {
         let cp = Cp(40); // Absolute score.
@@ -652,4 +1073,264 @@ mod tests {
         assert!(NodeKind::Pv > NodeKind::All);
         assert!(NodeKind::Pv > NodeKind::Cut);
     }
+
+    #[test]
+    fn replace_scheme_evicts_stale_generation() {
+        let tt = TranspositionTable::with_capacity(2);
+        assert_eq!(tt.bucket_capacity(), 1); // All hashes index same bucket.
+        let best_move = Move::new(E2, E4, None);
+        let deep_entry = Entry::new(1, best_move, Cp(10), 8, NodeKind::Cut);
+        let shallow_entry = Entry::new(2, best_move, Cp(20), 2, NodeKind::Cut);
+        let other_entry = Entry::new(3, best_move, Cp(30), 1, NodeKind::All);
+
+        let old_generation = tt.new_generation();
+        tt.replace_by(deep_entry, old_generation, replace_scheme);
+
+        // Within the same generation, a shallower entry does not replace the deeper entry.
+        tt.replace_by(shallow_entry, old_generation, replace_scheme);
+        assert_eq!(tt.get(deep_entry.hash), Some(deep_entry));
+
+        // In a new generation, the deeper entry is stale and is replaced by a shallower entry.
+        let new_generation = tt.new_generation();
+        assert_ne!(old_generation, new_generation);
+        assert_eq!(tt.generation(), new_generation);
+        tt.replace_by(other_entry, new_generation, replace_scheme);
+        assert_eq!(tt.get(other_entry.hash), Some(other_entry));
+        assert_eq!(tt.get(deep_entry.hash), None);
+    }
+
+    /// Search a position with negamax_impl in the current generation of a tt,
+    /// returning the searched score and number of nodes visited.
+    fn run_negamax_impl(
+        position: &Position,
+        tt: &TranspositionTable,
+        ply: PlyKind,
+        alpha: Cp,
+        beta: Cp,
+        distance: PlyKind,
+    ) -> (Cp, u64) {
+        let hash = tt.generate_hash(position);
+        let mut pv = Line::new();
+        let mut eval_cache = EvalCache::with_mb_and_zobrist(1, tt.zobrist_table().clone());
+        let mut history_table = HistoryTable::new();
+        let mut search = Negamax {
+            position: *position,
+            tt,
+            context: SearchContext {
+                eval_cache: &mut eval_cache,
+                history_table: &mut history_table,
+                q_params: QuiescenceParams::DEFAULT,
+                pruning: PruningParams::DEFAULT,
+                stopper: Arc::new(AtomicBool::new(false)),
+                progress: &(),
+            },
+            nodes: 0,
+            age: tt.generation(),
+        };
+        let score = negamax_impl(&mut search, hash, &mut pv, ply, alpha, beta, distance);
+        (score, search.nodes)
+    }
+
+    /// Search a crafted tt entry for the start position with negamax_impl, returning
+    /// the searched score and number of nodes visited.
+    fn search_crafted_entry(
+        node_kind: NodeKind,
+        score: Cp,
+        ply: PlyKind,
+        window: (Cp, Cp),
+    ) -> (Cp, u64) {
+        let position = Position::start_position();
+        let tt = TranspositionTable::with_capacity(1000);
+        let hash = tt.generate_hash(&position);
+        let entry = Entry::new(hash, Move::new(E2, E4, None), score, 10, node_kind);
+        tt.replace(entry, tt.generation());
+
+        let (alpha, beta) = window;
+        run_negamax_impl(&position, &tt, ply, alpha, beta, 1)
+    }
+
+    #[test]
+    fn tt_cutoff_respects_bounds() {
+        let window = (Cp(-100), Cp(100));
+
+        // Exact scores are always usable.
+        assert_eq!(
+            search_crafted_entry(NodeKind::Pv, Cp(50), 2, window),
+            (Cp(50), 1)
+        );
+        assert_eq!(
+            search_crafted_entry(NodeKind::Pv, Cp(500), 2, window),
+            (Cp(500), 1)
+        );
+
+        // Lower bounds are only usable at or above beta.
+        assert_eq!(
+            search_crafted_entry(NodeKind::Cut, Cp(100), 2, window),
+            (Cp(100), 1)
+        );
+        let (score, nodes) = search_crafted_entry(NodeKind::Cut, Cp(99), 2, window);
+        assert_ne!(score, Cp(99));
+        assert!(nodes > 1);
+
+        // Upper bounds are only usable at or below alpha.
+        assert_eq!(
+            search_crafted_entry(NodeKind::All, Cp(-100), 2, window),
+            (Cp(-100), 1)
+        );
+        let (score, nodes) = search_crafted_entry(NodeKind::All, Cp(-99), 2, window);
+        assert_ne!(score, Cp(-99));
+        assert!(nodes > 1);
+
+        // Unusable bounds at leaf nodes fall through to quiescence search.
+        let (score, _) = search_crafted_entry(NodeKind::Cut, Cp(99), 0, window);
+        assert_ne!(score, Cp(99));
+    }
+
+    /// Returns the score and node count of a search of a position with an empty tt.
+    fn search_window(position: &Position, ply: PlyKind, alpha: Cp, beta: Cp) -> (Cp, u64) {
+        let tt = TranspositionTable::with_capacity(1_000);
+        run_negamax_impl(position, &tt, ply, alpha, beta, 1)
+    }
+
+    #[test]
+    fn futility_pruning_skips_quiet_moves() {
+        // White is far behind, but Rxa8+ captures the queen with check.
+        let position = Position::parse_fen("q6k/8/1r6/8/8/8/7P/R3K3 w - - 0 1").unwrap();
+        let futile_alpha = evaluate(&position) + FUTILITY_MARGINS[1];
+
+        // Only the capture is searched when alpha is out of reach of quiet moves.
+        let (full_score, full_nodes) = search_window(&position, 1, Cp::MIN, Cp::MAX);
+        let (score, nodes) = search_window(&position, 1, futile_alpha, Cp::MAX);
+        assert!(nodes < full_nodes);
+        assert!(score > futile_alpha);
+        assert_eq!(score, full_score);
+
+        // A quiet position far below alpha has every move pruned, and fails low.
+        let position = Position::parse_fen("7k/8/8/8/8/8/1r5P/4K3 w - - 0 1").unwrap();
+        let futile_alpha = evaluate(&position) + FUTILITY_MARGINS[1];
+        let (score, nodes) = search_window(&position, 1, futile_alpha, Cp::MAX);
+        assert_eq!((score, nodes), (futile_alpha, 1));
+    }
+
+    #[test]
+    fn reverse_futility_pruning_returns_early() {
+        // White is up a queen and a rook in a quiet position.
+        let position = Position::parse_fen("6k1/5ppp/8/8/8/8/5PPP/2RQ2K1 w - - 0 1").unwrap();
+        let static_eval = evaluate(&position);
+        let beta = static_eval - REVERSE_FUTILITY_MARGINS[2];
+        assert_eq!(
+            search_window(&position, 2, beta - Cp(1), beta),
+            (static_eval, 1)
+        );
+
+        // Beta just out of reach of the margin is searched.
+        let (_, nodes) = search_window(&position, 2, beta, beta + Cp(1));
+        assert!(nodes > 1);
+
+        // Search near a mate score is never pruned.
+        let (_, nodes) = search_window(&position, 2, Cp::MIN, -Cp::CHECKMATE);
+        assert!(nodes > 1);
+        let (_, nodes) = search_window(&position, 2, Cp::CHECKMATE - Cp(1), Cp::CHECKMATE);
+        assert!(nodes > 1);
+
+        // White is far ahead, but in check on the back rank.
+        let position = Position::parse_fen("6k1/5ppp/8/8/Q6R/8/5PPP/3r2K1 w - - 0 1").unwrap();
+        assert!(position.is_in_check());
+        let beta = evaluate(&position) - REVERSE_FUTILITY_MARGINS[2];
+        let (_, nodes) = search_window(&position, 2, beta - Cp(1), beta);
+        assert!(nodes > 1);
+    }
+
+    #[test]
+    fn razoring_drops_into_quiescence() {
+        // White is down a rook with no tactics, so a search far above the evaluation is razored.
+        let position = Position::parse_fen("6k1/5ppp/8/8/8/8/3r1PPP/6K1 w - - 0 1").unwrap();
+        let static_eval = evaluate(&position);
+        let alpha = static_eval + RAZOR_MARGINS[2] + Cp(1);
+        let (score, nodes) = search_window(&position, 2, alpha, alpha + Cp(1));
+        assert_eq!((score, nodes), (static_eval, 1));
+
+        let (_, nodes) = search_window(&position, 2, Cp::MIN, Cp::MAX);
+        assert!(nodes > 1);
+
+        // White is down material, but wins the queen by a capture which is found by quiescence.
+        let position = Position::parse_fen("6k1/5ppp/8/8/8/8/2q2PPP/2R3K1 w - - 0 1").unwrap();
+        let static_eval = evaluate(&position);
+        let alpha = static_eval + RAZOR_MARGINS[2] + Cp(1);
+        let (score, nodes) = search_window(&position, 2, alpha, alpha + Cp(1));
+        assert!(score > alpha);
+        assert!(nodes > 1);
+
+        let tt = TranspositionTable::with_capacity(10_000);
+        let result = negamax(position, 3, &tt);
+        assert_eq!(result.best_move, Move::new(C1, C2, None));
+
+        // White is down material, but has a quiet back rank mate, so it is never razored.
+        let position = Position::parse_fen("6k1/5ppp/8/7q/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let alpha = evaluate(&position) + RAZOR_MARGINS[1] + Cp(1);
+        let (score, _) = search_window(&position, 1, alpha, alpha + Cp(1));
+        assert!(score.is_mate());
+        assert!(score > alpha);
+    }
+
+    /// Returns the score of a full window search with an empty tt.
+    fn exact_score(position: &Position, ply: PlyKind, distance: PlyKind) -> Cp {
+        let tt = TranspositionTable::with_capacity(1_000);
+        run_negamax_impl(position, &tt, ply, Cp::MIN, Cp::MAX, distance).0
+    }
+
+    #[test]
+    fn tt_bounds_are_valid_in_search_tree() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        let windows = [
+            (Cp::MIN, Cp::MAX),
+            (Cp(-50), Cp(50)),
+            (Cp(0), Cp(1)),
+            (Cp(-300), Cp(-200)),
+            (Cp(200), Cp(300)),
+        ];
+
+        for fen in fens {
+            let root = Position::parse_fen(fen).unwrap();
+            let tt = TranspositionTable::with_capacity(100_000);
+            negamax(root, 3, &tt);
+
+            // Replay the stored search tree, checking each entry against an exact search.
+            let mut positions = vec![(root, 0)];
+            while let Some((position, distance)) = positions.pop() {
+                if let Some(mut entry) = tt.get(tt.generate_hash(&position)) {
+                    entry.score = score_from_tt(entry.score, distance);
+                    let exact = exact_score(&position, entry.ply, distance);
+                    let score = entry.score;
+                    match entry.node_kind {
+                        NodeKind::Pv => assert_eq!(exact, score, "{}", position),
+                        NodeKind::Cut => assert!(exact >= score, "{}", position),
+                        NodeKind::All => assert!(exact <= score, "{}", position),
+                    }
+
+                    // A bound is only used where it gives the same result as the exact score.
+                    for (alpha, beta) in windows {
+                        if is_tt_cutoff(&entry, alpha, beta) {
+                            assert!(
+                                score == exact
+                                    || (score <= alpha && exact <= alpha)
+                                    || (score >= beta && exact >= beta),
+                                "{}",
+                                position
+                            );
+                        }
+                    }
+                }
+
+                if distance < 2 {
+                    for move_ in position.get_legal_moves() {
+                        positions.push((position.make_move(move_), distance + 1));
+                    }
+                }
+            }
+        }
+    }
 }
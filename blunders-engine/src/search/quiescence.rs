@@ -15,19 +15,48 @@
 //! Quiescence search searches a small sub-tree of the leaf node to evaluate
 //! quiet position, so the evaluation of the original leaf node is more stable.
 
-use crate::coretypes::{Cp, PlyKind};
-use crate::eval::evaluate;
+use crate::coretypes::{Cp, MoveInfo, PlyKind, MAX_DEPTH};
+use crate::eval::EvalCache;
 use crate::movelist::MoveInfoList;
 use crate::moveorder::pick_best_move;
+use crate::position::Cache;
+use crate::search::SearchContext;
+use crate::transposition::{score_from_tt, score_to_tt};
+use crate::zobrist::HashKind;
 use crate::Position;
 use std::cmp::max;
 
+/// QuiescenceParams limit which moves are searched by quiescence search, and how deep.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct QuiescenceParams {
+    /// Maximum depth of a quiescence search in plies.
+    pub depth: PlyKind,
+    /// Number of plies at the start of a quiescence search which also search checking moves.
+    pub check_plies: PlyKind,
+}
+
+impl QuiescenceParams {
+    /// Built in quiescence parameters, which search captures only.
+    pub const DEFAULT: QuiescenceParams = QuiescenceParams {
+        depth: 10,
+        check_plies: 0,
+    };
+}
+
+impl Default for QuiescenceParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// notes:
 /// Quiescence search returns a score relative to active player.
 /// It can be given any max depth to limit its search.
 /// A depth of 0 is the same as the stand pat evaluation.
 /// Quiescence is guaranteed to have a short runtime because it only evaluates captures,
 /// and there are a limited number of captures to be had for any position.
+/// The exception is a position in check, where all legal evasions are searched instead.
+/// Checking moves are also searched for the first `check_plies` plies, to see short mates.
 ///
 /// Quiescence is implemented as a fail-soft negamax.
 ///
@@ -40,17 +69,75 @@ use std::cmp::max;
 /// Quiescence needs pruning. Can aggressive pruning cause inaccurate scores?
 ///
 ///
+/// Mate scores count plies from the node quiescence is called on, so any window with a mate
+/// score must also count from that node. Use `quiescence_from` to search a node of a negamax
+/// search, whose mate scores count plies from the root.
+///
+/// `hash` is the hash of `position` made with the zobrist table of `eval_cache`,
+/// and `q_params` holds the remaining depth and checking plies of this search.
+///
 /// Initial Call to Quiescence:
 /// Negamax:
-///     if node is leaf and non-terminal, return quiescence(position, alpha, beta)
+///     if node is leaf and non-terminal, return quiescence(position, hash, alpha, beta)
 pub fn quiescence(
     position: &mut Position,
+    hash: HashKind,
     mut alpha: Cp,
     beta: Cp,
-    ply: PlyKind,
+    q_params: QuiescenceParams,
     nodes: &mut u64,
+    eval_cache: &mut EvalCache,
 ) -> Cp {
-    let mut best_score = evaluate(position);
+    let cache = position.cache();
+    let ply = q_params.depth;
+    let check_plies = q_params.check_plies;
+    let child_params = QuiescenceParams {
+        depth: ply.saturating_sub(1),
+        check_plies: check_plies.saturating_sub(1),
+    };
+
+    // When in check, every legal evasion is searched, not only captures.
+    // There is no stand pat, because the active player is not allowed to ignore the check.
+    if position.is_in_check() {
+        let mut evasions = position.get_legal_move_infos();
+
+        if evasions.is_empty() {
+            return -Cp::CHECKMATE;
+        } else if ply == 0 {
+            return eval_cache.evaluate(position, hash);
+        }
+
+        let mut best_score = Cp::MIN;
+        while let Some(evasion) = pick_best_move(&mut evasions, None) {
+            *nodes += 1;
+            position.do_move_info(evasion);
+            let child_hash = child_hash(eval_cache, hash, position, evasion, cache);
+            let child_score = quiescence(
+                position,
+                child_hash,
+                -score_to_tt(beta, 1),
+                -score_to_tt(alpha, 1),
+                child_params,
+                nodes,
+                eval_cache,
+            );
+            let score = score_from_tt(-child_score, 1);
+            position.undo_move(evasion, cache);
+
+            best_score = max(best_score, score);
+
+            if best_score >= beta {
+                return best_score;
+            }
+            if best_score > alpha {
+                alpha = best_score;
+            }
+        }
+
+        return best_score;
+    }
+
+    let mut best_score = eval_cache.evaluate(position, hash);
 
     // Depth limited search.
     if ply == 0 {
@@ -65,19 +152,29 @@ pub fn quiescence(
         alpha = best_score;
     }
 
-    let cache = position.cache();
-    let mut legal_captures: MoveInfoList = position
-        .get_legal_moves()
+    let mut legal_moves: MoveInfoList = position
+        .get_legal_move_infos()
         .into_iter()
-        .map(|move_| position.move_info(move_))
-        .filter(|move_info| move_info.is_capture())
+        .filter(|move_info| {
+            move_info.is_capture() || (check_plies > 0 && position.gives_check(move_info.move_()))
+        })
         .collect();
 
-    while let Some(capture) = pick_best_move(&mut legal_captures, None) {
+    while let Some(move_info) = pick_best_move(&mut legal_moves, None) {
         *nodes += 1;
-        position.do_move_info(capture);
-        let score = -quiescence(position, -beta, -alpha, ply - 1, nodes);
-        position.undo_move(capture, cache);
+        position.do_move_info(move_info);
+        let child_hash = child_hash(eval_cache, hash, position, move_info, cache);
+        let child_score = quiescence(
+            position,
+            child_hash,
+            -score_to_tt(beta, 1),
+            -score_to_tt(alpha, 1),
+            child_params,
+            nodes,
+            eval_cache,
+        );
+        let score = score_from_tt(-child_score, 1);
+        position.undo_move(move_info, cache);
 
         best_score = max(best_score, score);
 
@@ -92,3 +189,130 @@ pub fn quiescence(
 
     return best_score;
 }
+
+/// Returns the hash of a child position, after `move_info` has been applied to its parent.
+#[inline]
+fn child_hash(
+    eval_cache: &EvalCache,
+    mut hash: HashKind,
+    position: &Position,
+    move_info: MoveInfo,
+    cache: Cache,
+) -> HashKind {
+    eval_cache
+        .zobrist_table()
+        .update_hash(&mut hash, position.into(), move_info, cache);
+    hash
+}
+
+/// Quiescence search of a leaf node `distance` plies from the root of a negamax search,
+/// with a window and returned score that count mates in plies from the root.
+/// The search does not go past MAX_DEPTH plies from the root, so mate scores stay in range.
+/// Positions are evaluated and quiescence parameters are taken from `context`.
+pub fn quiescence_from<P>(
+    position: &mut Position,
+    hash: HashKind,
+    alpha: Cp,
+    beta: Cp,
+    distance: PlyKind,
+    nodes: &mut u64,
+    context: &mut SearchContext<P>,
+) -> Cp {
+    let q_params = QuiescenceParams {
+        depth: context
+            .q_params
+            .depth
+            .min(MAX_DEPTH.saturating_sub(distance)),
+        ..context.q_params
+    };
+    let score = quiescence(
+        position,
+        hash,
+        score_to_tt(alpha, distance),
+        score_to_tt(beta, distance),
+        q_params,
+        nodes,
+        context.eval_cache,
+    );
+    score_from_tt(score, distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coretypes::Move;
+    use crate::coretypes::Square::*;
+    use crate::eval::{evaluate_with, EvalParams};
+    use crate::fen::Fen;
+
+    /// Quiescence search of a position with a full window.
+    fn full_quiescence(
+        position: &mut Position,
+        depth: PlyKind,
+        check_plies: PlyKind,
+        nodes: &mut u64,
+        eval_cache: &mut EvalCache,
+    ) -> Cp {
+        let hash = eval_cache
+            .zobrist_table()
+            .generate_hash((&*position).into());
+        let q_params = QuiescenceParams { depth, check_plies };
+        quiescence(
+            position,
+            hash,
+            Cp::MIN,
+            Cp::MAX,
+            q_params,
+            nodes,
+            eval_cache,
+        )
+    }
+
+    #[test]
+    fn quiescence_checkmate() {
+        // Static evaluation only sees black down a rook, but black is checkmated.
+        let mut position = Position::parse_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        let mut nodes = 0;
+        let mut eval_cache = EvalCache::with_mb(1);
+        let score = full_quiescence(&mut position, 10, 0, &mut nodes, &mut eval_cache);
+        assert_eq!(score, -Cp::CHECKMATE);
+    }
+
+    #[test]
+    fn quiescence_searches_quiet_evasion() {
+        // White is in check with a single quiet evasion, after which black captures the knight.
+        let fen = "7k/4b3/8/8/7N/8/6PP/r5K1 w - - 0 1";
+        let mut position = Position::parse_fen(fen).unwrap();
+        let escape = Move::new(G1, F2, None);
+        assert_eq!(position.get_legal_moves().len(), 1);
+        assert!(position.is_legal_move(escape));
+
+        let mut eval_cache = EvalCache::with_mb(1);
+        let mut nodes = 0;
+        let score = full_quiescence(&mut position, 10, 0, &mut nodes, &mut eval_cache);
+        assert_eq!(position, Position::parse_fen(fen).unwrap());
+        assert!(nodes > 0);
+
+        let mut child = position.make_move(escape);
+        let mut child_nodes = 0;
+        let child_score = full_quiescence(&mut child, 9, 0, &mut child_nodes, &mut eval_cache);
+        assert_eq!(score, -child_score);
+        assert!(score < evaluate_with(&position, &EvalParams::DEFAULT));
+    }
+
+    #[test]
+    fn quiescence_checks_find_mate() {
+        // Back rank mate is a quiet move, so it is only found when checks are searched.
+        let mut position = Position::parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut eval_cache = EvalCache::with_mb(1);
+        let mut nodes = 0;
+        let score = full_quiescence(&mut position, 10, 0, &mut nodes, &mut eval_cache);
+        assert!(!score.is_mate());
+        assert_eq!(nodes, 0);
+
+        let mut nodes = 0;
+        let score = full_quiescence(&mut position, 10, 1, &mut nodes, &mut eval_cache);
+        assert_eq!(score, Cp::CHECKMATE - Cp(1));
+        assert!(nodes > 0);
+    }
+}
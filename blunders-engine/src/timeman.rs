@@ -1,36 +1,231 @@
 //! Time Management
 
 use std::convert::TryFrom;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::coretypes::{Color, PlyKind};
+use crate::coretypes::{Color, Cp, PlyKind};
 use crate::error::{self, ErrorKind};
 use crate::uci::SearchControls;
 
-const TIME_RATIO: u32 = 15; // Use 1/15th of remaining time per timed move.
-const OVERHEAD: u128 = 10; // Expected amount of time loss in ms.
+const MOVES_TO_GO: u32 = 30; // Expected number of moves remaining in a sudden death game.
+const SAFETY_BUFFER: u128 = 50; // Remaining time in ms which is never allocated.
+const HARD_RATIO: u128 = 3; // Hard limit is this many times the soft limit.
+const ITERATION_GROWTH: u32 = 2; // Expected time ratio of an iteration to the previous iteration.
+const EASY_MOVE_RATIO: u128 = 4; // An easy move may stop after 1/4th of the soft or node limit.
 
-/// There are 4 supported search modes currently, Infinite, Standard, Depth, and MoveTime.  
+/// Default expected amount of time lost communicating each move.
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(10);
+
+/// Amount an easy move must score above every other root move.
+pub(crate) const EASY_MOVE_MARGIN: Cp = Cp(200);
+
+/// Amount of time allocated to a single search, in milliseconds.
+/// A search may stop after the soft limit, and must stop by the hard limit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TimeAllocation {
+    pub soft_ms: u128,
+    pub hard_ms: u128,
+}
+
+/// Allocate time for a single search in a game with time controls.
+///
+/// The soft limit is an even share of the remaining time over the moves to go,
+/// plus most of the increment. The hard limit is a multiple of the soft limit,
+/// but never more than 3/4ths of the remaining time.
+/// A safety buffer of time and the move overhead are never allocated,
+/// so with very low time both limits are 0.
+///
+/// # Arguments
+///
+/// * `remaining_ms`: Time remaining on the clock of the player to move, which may be negative.
+/// * `increment_ms`: Time gained by the player to move after each move.
+/// * `moves_to_go`: Number of moves until the next time control, or None for sudden death.
+/// * `move_overhead`: Expected time lost communicating the move.
+pub fn allocate_time(
+    remaining_ms: i32,
+    increment_ms: Option<u32>,
+    moves_to_go: Option<u32>,
+    move_overhead: Duration,
+) -> TimeAllocation {
+    let remaining = remaining_ms.max(0) as u128;
+    let increment = increment_ms.unwrap_or(0) as u128;
+    let moves_to_go = moves_to_go.unwrap_or(MOVES_TO_GO).clamp(1, MOVES_TO_GO) as u128;
+
+    let available = remaining
+        .saturating_sub(SAFETY_BUFFER)
+        .saturating_sub(move_overhead.as_millis());
+    let hard_ms = (available / moves_to_go + increment * 4 / 5) * HARD_RATIO;
+    let hard_ms = hard_ms.min(available * 3 / 4);
+    let soft_ms = (available / moves_to_go + increment * 4 / 5).min(hard_ms);
+
+    TimeAllocation { soft_ms, hard_ms }
+}
+
+/// There are 7 supported search modes currently, Infinite, Standard, Depth, MoveTime, Nodes, Mate,
+/// and Limits.  
 /// Infinite mode: do not stop searching. Search must be signaled externally to stop.  
 /// Standard mode: standard chess time controls with time per side.  
 /// Depth mode: search to a given depth.  
-/// MoveTime mode: search for a specified time per move.
+/// MoveTime mode: search for a specified time per move.  
+/// Nodes mode: search a specified number of nodes.  
+/// Mate mode: search for a forced checkmate within a number of moves.  
+/// Limits mode: search until the first of several depth, nodes, movetime, and mate limits is reached.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Mode {
     Infinite,           // Search until told to stop. Requires `infinite`.
     Standard(Standard), // Each player has a time limit. Requires `wtime`, `btime`.
     Depth(Depth),       // Search to a given depth. Requires `depth`.
     MoveTime(MoveTime), // Search for a specified amount of time. Requires `movetime`.
+    Nodes(Nodes),       // Search for a specified number of nodes. Requires `nodes`.
+    Mate(Mate),         // Search for a mate in a specified number of moves. Requires `mate`.
+    Limits(Limits),     // Search until any of several limits is reached. Requires 2 or more limits.
 }
 
 impl Mode {
-    /// Returns true if a search should be stopped.
-    pub fn stop(&self, root_player: Color, ply: PlyKind) -> bool {
+    /// Returns true if a new iteration of iterative deepening to `ply` should not be started.
+    /// Timed modes also stop if the new iteration is not expected to complete in time,
+    /// estimated from the duration of the last iteration.
+    pub fn stop_iteration(
+        &self,
+        root_player: Color,
+        ply: PlyKind,
+        nodes: u64,
+        last_iteration: Duration,
+    ) -> bool {
+        match self {
+            Mode::Standard(standard_mode) => {
+                standard_mode.stop_iteration(root_player, ply, last_iteration)
+            }
+            _ => self.stop(root_player, ply, nodes),
+        }
+    }
+
+    /// Returns true if a search which has found an easy move after searching `nodes` nodes
+    /// may stop early. Only Standard and Nodes modes with easy moves enabled stop early.
+    pub fn stop_easy_move(&self, root_player: Color, nodes: u64) -> bool {
+        match self {
+            Mode::Standard(standard_mode) => standard_mode.stop_easy_move(root_player),
+            Mode::Nodes(nodes_mode) => nodes_mode.stop_easy_move(nodes),
+            _ => false,
+        }
+    }
+
+    /// Returns true if this mode may stop early for an easy move.
+    pub fn easy_move(&self) -> bool {
+        match self {
+            Mode::Standard(standard_mode) => standard_mode.easy_move,
+            Mode::Nodes(nodes_mode) => nodes_mode.easy_move,
+            _ => false,
+        }
+    }
+
+    /// Returns this mode with early stopping for easy moves enabled or disabled.
+    /// This only affects Standard mode, where an easy move saves clock time,
+    /// and Nodes mode, where it saves part of the node budget.
+    pub fn with_easy_move(self, easy_move: bool) -> Self {
+        match self {
+            Mode::Standard(standard_mode) => Mode::Standard(Standard {
+                easy_move,
+                ..standard_mode
+            }),
+            Mode::Nodes(nodes_mode) => Mode::Nodes(Nodes {
+                easy_move,
+                ..nodes_mode
+            }),
+            mode => mode,
+        }
+    }
+
+    /// Returns this mode with the expected time lost communicating a move set to `move_overhead`.
+    /// Timed modes stop early by this amount so a move is received before time runs out.
+    pub fn with_move_overhead(self, move_overhead: Duration) -> Self {
+        match self {
+            Mode::Depth(depth_mode) => Mode::Depth(Depth {
+                move_overhead,
+                ..depth_mode
+            }),
+            Mode::MoveTime(movetime_mode) => Mode::MoveTime(MoveTime {
+                move_overhead,
+                ..movetime_mode
+            }),
+            Mode::Standard(standard_mode) => Mode::Standard(Standard {
+                move_overhead,
+                ..standard_mode
+            }),
+            Mode::Limits(limits_mode) => Mode::Limits(Limits {
+                move_overhead,
+                ..limits_mode
+            }),
+            mode => mode,
+        }
+    }
+
+    /// Returns this mode without any time based cutoffs, so that it stops only by depth or nodes.
+    /// Timed modes with a depth limit become Depth mode, and Infinite mode is unchanged as it
+    /// only stops when signaled. Timed modes without a depth limit cannot be made deterministic.
+    /// Limits mode drops its movetime, and must have another limit remaining.
+    pub fn deterministic(self) -> error::Result<Self> {
+        let depth = match self {
+            Mode::Limits(limits_mode) => {
+                let Limits {
+                    depth, nodes, mate, ..
+                } = limits_mode;
+                return match (depth, nodes, mate) {
+                    (None, None, None) => {
+                        Err((ErrorKind::ModeNotDeterministic, format!("{:?}", self)).into())
+                    }
+                    _ => Ok(Mode::Limits(Limits {
+                        movetime: None,
+                        ..limits_mode
+                    })),
+                };
+            }
+            Mode::Depth(depth_mode) => Some(depth_mode.depth),
+            Mode::MoveTime(movetime_mode) => movetime_mode.depth,
+            Mode::Standard(standard_mode) => standard_mode.depth,
+            mode => return Ok(mode),
+        };
+
+        depth
+            .map(|ply| Mode::depth(ply, None))
+            .ok_or_else(|| (ErrorKind::ModeNotDeterministic, format!("{:?}", self)).into())
+    }
+
+    /// Returns this mode with `nodes` already searched counted against its node limit.
+    /// Modes without a node limit are unchanged.
+    pub fn after_nodes(self, nodes: u64) -> Self {
+        match self {
+            Mode::Nodes(nodes_mode) => Mode::Nodes(Nodes {
+                nodes: nodes_mode.nodes.saturating_sub(nodes),
+                ..nodes_mode
+            }),
+            Mode::Limits(limits_mode) => Mode::Limits(Limits {
+                nodes: limits_mode.nodes.map(|limit| limit.saturating_sub(nodes)),
+                ..limits_mode
+            }),
+            mode => mode,
+        }
+    }
+
+    /// Returns the number of moves a mate is searched for in, if this mode has a mate limit.
+    pub fn mate_moves(&self) -> Option<u32> {
+        match self {
+            Mode::Mate(mate_mode) => Some(mate_mode.moves),
+            Mode::Limits(limits_mode) => limits_mode.mate,
+            _ => None,
+        }
+    }
+
+    /// Returns true if a search which has searched `nodes` nodes should be stopped.
+    pub fn stop(&self, root_player: Color, ply: PlyKind, nodes: u64) -> bool {
         match self {
             Mode::Infinite => Infinite::stop(),
             Mode::Depth(depth_mode) => depth_mode.stop(ply),
             Mode::MoveTime(movetime_mode) => movetime_mode.stop(ply),
             Mode::Standard(standard_mode) => standard_mode.stop(root_player, ply),
+            Mode::Nodes(nodes_mode) => nodes_mode.stop(ply, nodes),
+            Mode::Mate(mate_mode) => mate_mode.stop(ply),
+            Mode::Limits(limits_mode) => limits_mode.stop(ply, nodes),
         }
     }
 
@@ -45,6 +240,7 @@ impl Mode {
             depth: ply,
             instant: Instant::now(),
             movetime,
+            move_overhead: MOVE_OVERHEAD,
         })
     }
 
@@ -54,9 +250,46 @@ impl Mode {
             movetime,
             instant: Instant::now(),
             depth: ply,
+            move_overhead: MOVE_OVERHEAD,
         })
     }
 
+    /// Returns a new Nodes mode.
+    pub fn nodes(nodes: u64, ply: Option<PlyKind>) -> Self {
+        Self::Nodes(Nodes {
+            nodes,
+            depth: ply,
+            easy_move: false,
+        })
+    }
+
+    /// Returns a new Mate mode, searching for a mate in `moves` moves.
+    pub fn mate(moves: u32) -> Self {
+        Self::Mate(Mate { moves })
+    }
+
+    /// Returns a new Limits mode, which stops at whichever given limit is reached first.
+    pub fn limits(
+        ply: Option<PlyKind>,
+        nodes: Option<u64>,
+        movetime: Option<u32>,
+        mate: Option<u32>,
+    ) -> Self {
+        Self::Limits(Limits {
+            depth: ply,
+            nodes,
+            movetime,
+            mate,
+            instant: Instant::now(),
+            move_overhead: MOVE_OVERHEAD,
+        })
+    }
+
+    /// Returns a new Limits mode, which stops once either `nodes` or `movetime` is reached.
+    pub fn node_and_time(nodes: u64, movetime: u32) -> Self {
+        Self::limits(None, Some(nodes), Some(movetime), None)
+    }
+
     pub fn standard(
         wtime: i32,
         btime: i32,
@@ -72,6 +305,8 @@ impl Mode {
             binc,
             moves_to_go,
             depth: ply,
+            easy_move: false,
+            move_overhead: MOVE_OVERHEAD,
             instant: Instant::now(),
         })
     }
@@ -91,12 +326,30 @@ impl TryFrom<SearchControls> for Mode {
                 controls.moves_to_go,
                 controls.depth,
             ))
+        } else if Limits::satisfied(&controls) {
+            Ok(Mode::limits(
+                controls.depth,
+                controls.nodes,
+                controls.move_time,
+                controls.mate,
+            ))
         } else if MoveTime::satisfied(&controls) {
             Ok(Mode::movetime(controls.move_time.unwrap(), controls.depth))
+        } else if Nodes::satisfied(&controls) {
+            Ok(Mode::nodes(controls.nodes.unwrap(), controls.depth))
+        } else if Mate::satisfied(&controls) {
+            Ok(Mode::mate(controls.mate.unwrap()))
         } else if Depth::satisfied(&controls) {
             Ok(Mode::depth(controls.depth.unwrap(), controls.move_time))
+        } else if controls.wtime.is_none() && controls.btime.is_none() {
+            // A go without any search limit searches until it is told to stop.
+            Ok(Mode::infinite())
         } else {
-            Err(ErrorKind::ModeNotSatisfied.into())
+            let message = match controls.wtime {
+                Some(_) => "wtime given without btime",
+                None => "btime given without wtime",
+            };
+            Err((ErrorKind::SearchModeInvalid, message).into())
         }
     }
 }
@@ -119,6 +372,7 @@ pub struct Depth {
     pub depth: PlyKind,
     instant: Instant,
     movetime: Option<u32>,
+    move_overhead: Duration,
 }
 
 impl Depth {
@@ -130,7 +384,7 @@ impl Depth {
 
         if let Some(movetime) = self.movetime {
             let elapsed_ms = self.instant.elapsed().as_millis();
-            if elapsed_ms >= (movetime as u128).saturating_sub(OVERHEAD) {
+            if elapsed_ms >= (movetime as u128).saturating_sub(self.move_overhead.as_millis()) {
                 return true;
             }
         }
@@ -149,13 +403,14 @@ pub struct MoveTime {
     movetime: u32,
     instant: Instant,
     depth: Option<PlyKind>,
+    move_overhead: Duration,
 }
 
 impl MoveTime {
     /// MoveTime mode stops after a given time has passed, or optionally if its depth is passed.
     fn stop(&self, ply: PlyKind) -> bool {
         let elapsed_ms = self.instant.elapsed().as_millis();
-        if elapsed_ms >= (self.movetime as u128).saturating_sub(OVERHEAD) {
+        if elapsed_ms >= (self.movetime as u128).saturating_sub(self.move_overhead.as_millis()) {
             return true;
         }
 
@@ -174,6 +429,99 @@ impl MoveTime {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Nodes {
+    nodes: u64,
+    depth: Option<PlyKind>,
+    easy_move: bool,
+}
+
+impl Nodes {
+    /// Nodes mode stops once its node limit is reached, or optionally if its depth is passed.
+    /// The first iteration is always allowed to complete so a best move is found.
+    fn stop(&self, ply: PlyKind, nodes: u64) -> bool {
+        if let Some(depth) = self.depth {
+            if ply > depth {
+                return true;
+            }
+        }
+
+        ply > 1 && nodes >= self.nodes
+    }
+
+    /// Nodes stops for an easy move if enabled, once a fraction of its node limit is used.
+    fn stop_easy_move(&self, nodes: u64) -> bool {
+        self.easy_move && nodes as u128 >= self.nodes as u128 / EASY_MOVE_RATIO
+    }
+
+    /// Returns true if search controls has all required fields for Nodes mode.
+    fn satisfied(search_controls: &SearchControls) -> bool {
+        search_controls.nodes.is_some()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Mate {
+    pub moves: u32,
+}
+
+impl Mate {
+    /// Mate mode stops once its depth is passed, where a mate in `moves` moves
+    /// is found by a search of `2 * moves - 1` plies.
+    /// Search returns early as soon as any mate is found.
+    /// The first iteration is always allowed to complete so a best move is found.
+    fn stop(&self, ply: PlyKind) -> bool {
+        ply as u32 > self.depth()
+    }
+
+    /// Returns the number of plies needed to find a mate in `moves` moves.
+    fn depth(&self) -> u32 {
+        self.moves.saturating_mul(2).saturating_sub(1).max(1)
+    }
+
+    /// Returns true if search controls has all required fields for Mate mode.
+    fn satisfied(search_controls: &SearchControls) -> bool {
+        search_controls.mate.is_some()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Limits {
+    depth: Option<PlyKind>,
+    nodes: Option<u64>,
+    movetime: Option<u32>,
+    mate: Option<u32>,
+    instant: Instant,
+    move_overhead: Duration,
+}
+
+impl Limits {
+    /// Limits mode stops once any of its limits is reached, each checked as in its own mode.
+    /// The first iteration is allowed to complete unless stopped by movetime.
+    fn stop(&self, ply: PlyKind, nodes: u64) -> bool {
+        let depth_stop = self.depth.is_some_and(|depth| ply > depth);
+        let nodes_stop = self.nodes.is_some_and(|limit| ply > 1 && nodes >= limit);
+        let mate_stop = self.mate.is_some_and(|moves| Mate { moves }.stop(ply));
+        let movetime_stop = self.movetime.is_some_and(|movetime| {
+            let elapsed_ms = self.instant.elapsed().as_millis();
+            elapsed_ms >= (movetime as u128).saturating_sub(self.move_overhead.as_millis())
+        });
+
+        depth_stop || nodes_stop || mate_stop || movetime_stop
+    }
+
+    /// Returns true if search controls has more than one of depth, nodes, movetime, and mate.
+    fn satisfied(search_controls: &SearchControls) -> bool {
+        let limits = [
+            search_controls.depth.is_some(),
+            search_controls.nodes.is_some(),
+            search_controls.move_time.is_some(),
+            search_controls.mate.is_some(),
+        ];
+        limits.iter().filter(|&&limit| limit).count() > 1
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Standard {
     instant: Instant,
@@ -183,19 +531,15 @@ pub struct Standard {
     binc: Option<u32>,
     moves_to_go: Option<u32>,
     depth: Option<PlyKind>,
+    easy_move: bool,
+    move_overhead: Duration,
 }
 
 impl Standard {
-    /// Standard stops after using some heuristic to determine how much of remaining time to use.
+    /// Standard stops once the hard limit of its time allocation is reached.
+    /// The first iteration is always allowed to complete so a best move is found.
     /// Optionally, stops when a depth is passed.
     fn stop(&self, root_player: Color, ply: PlyKind) -> bool {
-        let target_elapsed = self.target_elapsed_ms(root_player);
-        let elapsed_ms = self.instant.elapsed().as_millis();
-
-        if elapsed_ms >= target_elapsed {
-            return true;
-        }
-
         // Optional depth
         if let Some(depth) = self.depth {
             if ply > depth {
@@ -203,23 +547,38 @@ impl Standard {
             }
         }
 
-        false
+        let hard_ms = self.allocation(root_player).hard_ms;
+        ply > 1 && self.instant.elapsed().as_millis() >= hard_ms
     }
 
-    fn target_elapsed_ms(&self, root_player: Color) -> u128 {
-        let remaining_time = match root_player {
-            Color::White => self.wtime,
-            Color::Black => self.btime,
-        };
+    /// Standard does not start a new iteration once the soft limit of its time allocation
+    /// is reached, or if the new iteration is expected to pass the hard limit.
+    fn stop_iteration(&self, root_player: Color, ply: PlyKind, last_iteration: Duration) -> bool {
+        if self.stop(root_player, ply) {
+            return true;
+        }
 
-        // Clamp to lower bound of 0.
-        let remaining_time: u128 = if remaining_time.is_negative() {
-            0
-        } else {
-            remaining_time as u128
-        };
+        let allocation = self.allocation(root_player);
+        let elapsed_ms = self.instant.elapsed().as_millis();
+        let next_iteration_ms = (last_iteration * ITERATION_GROWTH).as_millis();
+        ply > 1
+            && (elapsed_ms >= allocation.soft_ms
+                || elapsed_ms + next_iteration_ms >= allocation.hard_ms)
+    }
 
-        (remaining_time / TIME_RATIO as u128).saturating_sub(OVERHEAD)
+    /// Standard stops for an easy move if enabled, once a fraction of the soft limit is used.
+    fn stop_easy_move(&self, root_player: Color) -> bool {
+        let soft_ms = self.allocation(root_player).soft_ms;
+        self.easy_move && self.instant.elapsed().as_millis() >= soft_ms / EASY_MOVE_RATIO
+    }
+
+    /// Returns the time allocated to this search for the root player.
+    fn allocation(&self, root_player: Color) -> TimeAllocation {
+        let (remaining, increment) = match root_player {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        allocate_time(remaining, increment, self.moves_to_go, self.move_overhead)
     }
 
     /// Returns true if search controls has all required fields for Standard Mode.
@@ -244,4 +603,209 @@ mod tests {
         let mode = mode.unwrap();
         assert!(matches!(mode, Mode::Standard(_)));
     }
+
+    #[test]
+    fn invalid_controls_are_search_mode_invalid() {
+        let mut controls = SearchControls::default();
+        controls.wtime = Some(5000);
+        controls.winc = Some(100);
+
+        let error = Mode::try_from(controls).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::SearchModeInvalid);
+        assert!(error.to_string().contains("btime"), "{}", error);
+    }
+
+    #[test]
+    fn no_search_limit_is_infinite() {
+        let controls = SearchControls::default();
+        assert_eq!(Mode::try_from(controls).unwrap(), Mode::infinite());
+
+        // Increments alone do not limit a search.
+        let mut controls = SearchControls::default();
+        controls.winc = Some(100);
+        controls.binc = Some(100);
+        assert_eq!(Mode::try_from(controls).unwrap(), Mode::infinite());
+    }
+
+    #[test]
+    fn combined_limits_stop_at_first_reached() {
+        let mut controls = SearchControls::default();
+        controls.depth = Some(4);
+        controls.nodes = Some(1_000_000_000);
+        let mode = Mode::try_from(controls).unwrap();
+        assert!(matches!(mode, Mode::Limits(_)));
+        assert!(!mode.stop(Color::White, 4, 1000));
+        assert!(mode.stop(Color::White, 5, 1000));
+        assert!(mode.after_nodes(999_999_000).stop(Color::White, 2, 1000));
+
+        let mode = Mode::node_and_time(1000, 60_000);
+        assert!(!mode.stop(Color::White, 1, 5000));
+        assert!(mode.stop(Color::White, 2, 1000));
+        assert!(Mode::node_and_time(1000, 0).stop(Color::White, 1, 0));
+
+        let mode = Mode::limits(Some(99), None, None, Some(2));
+        assert_eq!(mode.mate_moves(), Some(2));
+        assert!(!mode.stop(Color::White, 3, 0));
+        assert!(mode.stop(Color::White, 4, 0));
+
+        // Dropping movetime leaves the other limits.
+        let mode = Mode::limits(Some(3), None, Some(0), None);
+        let mode = mode.deterministic().unwrap();
+        assert!(matches!(
+            mode,
+            Mode::Limits(Limits {
+                depth: Some(3),
+                movetime: None,
+                ..
+            })
+        ));
+        assert!(!mode.stop(Color::White, 3, 0));
+        assert!(Mode::limits(None, None, Some(1000), None)
+            .deterministic()
+            .is_err());
+    }
+
+    #[test]
+    fn move_overhead_stops_earlier() {
+        let mode = Mode::movetime(1000, None);
+        assert!(!mode.stop(Color::White, 2, 0));
+        let mode = mode.with_move_overhead(Duration::from_millis(1000));
+        assert!(mode.stop(Color::White, 2, 0));
+
+        // Modes which are not timed are unchanged.
+        let mode = Mode::nodes(100, None);
+        assert_eq!(mode.with_move_overhead(Duration::from_secs(1)), mode);
+    }
+
+    #[test]
+    fn mate_mode_depth() {
+        let mut controls = SearchControls::new();
+        controls.mate = Some(2);
+        let mode = Mode::try_from(controls).unwrap();
+        assert_eq!(mode, Mode::mate(2));
+        assert!(!mode.stop(Color::White, 3, 0));
+        assert!(mode.stop(Color::White, 4, 0));
+
+        // Mate in 0 still searches the first ply.
+        assert!(!Mode::mate(0).stop(Color::White, 1, 0));
+        assert!(Mode::mate(0).stop(Color::White, 2, 0));
+    }
+
+    #[test]
+    fn allocate_time_sudden_death() {
+        let allocation = allocate_time(60_050, None, None, Duration::ZERO);
+        assert_eq!(allocation.soft_ms, 2000);
+        assert_eq!(allocation.hard_ms, 6000);
+
+        // Increments add most of their value.
+        let allocation = allocate_time(60_050, Some(1000), None, Duration::ZERO);
+        assert_eq!(allocation.soft_ms, 2800);
+        assert_eq!(allocation.hard_ms, 8400);
+    }
+
+    #[test]
+    fn allocate_time_moves_to_go() {
+        let allocation = allocate_time(10_050, None, Some(10), Duration::ZERO);
+        assert_eq!(allocation.soft_ms, 1000);
+        assert_eq!(allocation.hard_ms, 3000);
+
+        // Last move before time control can use most, but not all remaining time.
+        let allocation = allocate_time(10_050, Some(500), Some(1), Duration::ZERO);
+        assert_eq!(allocation.hard_ms, 7500);
+        assert_eq!(allocation.soft_ms, 7500);
+    }
+
+    #[test]
+    fn allocate_time_low_time() {
+        // Time within the safety buffer is never allocated, even with an increment.
+        assert_eq!(
+            allocate_time(40, Some(1000), None, Duration::ZERO).hard_ms,
+            0
+        );
+        assert_eq!(
+            allocate_time(-100, None, Some(5), Duration::ZERO).soft_ms,
+            0
+        );
+
+        // Allocation never passes the remaining time.
+        for remaining in [60, 100, 500, 1000] {
+            for moves_to_go in [None, Some(1), Some(2)] {
+                let allocation = allocate_time(remaining, Some(2000), moves_to_go, Duration::ZERO);
+                assert!(allocation.soft_ms <= allocation.hard_ms);
+                assert!(allocation.hard_ms < remaining as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn allocate_time_move_overhead() {
+        // Overhead is never allocated, reducing both limits.
+        let allocation = allocate_time(1000, None, Some(1), Duration::ZERO);
+        let reduced = allocate_time(1000, None, Some(1), Duration::from_millis(500));
+        assert_eq!(allocation.hard_ms, 712);
+        assert_eq!(reduced.hard_ms, 337);
+        assert!(reduced.soft_ms <= reduced.hard_ms);
+        assert!(reduced.hard_ms + 500 < 1000);
+        let allocation = allocate_time(1000, None, None, Duration::from_millis(1000));
+        assert_eq!(allocation.hard_ms, 0);
+    }
+
+    #[test]
+    fn standard_stop_limits() {
+        // Out of time still completes the first iteration.
+        let mode = Mode::standard(0, 0, None, None, None, None);
+        assert!(!mode.stop(Color::White, 1, 0));
+        assert!(!mode.stop_iteration(Color::White, 1, 0, Duration::ZERO));
+        assert!(mode.stop(Color::White, 2, 0));
+        assert!(mode.stop_iteration(Color::White, 2, 0, Duration::ZERO));
+
+        // Next iteration is not started if it is not expected to complete.
+        let mode = Mode::standard(60_050, 60_050, None, None, None, None);
+        assert!(!mode.stop(Color::Black, 5, 0));
+        assert!(!mode.stop_iteration(Color::Black, 5, 0, Duration::from_millis(10)));
+        assert!(mode.stop_iteration(Color::Black, 5, 0, Duration::from_millis(3000)));
+    }
+
+    #[test]
+    fn nodes_stop_limits() {
+        let mut controls = SearchControls::default();
+        controls.nodes = Some(1000);
+        let mode = Mode::try_from(controls).unwrap();
+        assert_eq!(mode, Mode::nodes(1000, None));
+
+        // Out of nodes still completes the first iteration.
+        assert!(!mode.stop(Color::White, 1, 5000));
+        assert!(!mode.stop(Color::White, 2, 999));
+        assert!(mode.stop(Color::White, 2, 1000));
+        assert!(mode.stop_iteration(Color::White, 2, 1000, Duration::ZERO));
+
+        // Nodes searched in earlier iterations count against the limit.
+        let mode = mode.after_nodes(600);
+        assert!(!mode.stop(Color::White, 3, 399));
+        assert!(mode.stop(Color::White, 3, 400));
+        assert!(Mode::nodes(1000, Some(3)).stop(Color::White, 4, 0));
+    }
+
+    #[test]
+    fn deterministic_removes_time_limits() {
+        let mode = Mode::depth(5, Some(0)).deterministic().unwrap();
+        assert!(!mode.stop(Color::White, 5, 0));
+        assert!(mode.stop(Color::White, 6, 0));
+
+        let mode = Mode::movetime(0, Some(4)).deterministic().unwrap();
+        assert!(matches!(mode, Mode::Depth(Depth { depth: 4, .. })));
+        let mode = Mode::standard(0, 0, None, None, None, Some(3));
+        assert!(!mode.deterministic().unwrap().stop(Color::White, 3, 0));
+
+        // Modes which do not stop by time are unchanged.
+        for mode in [Mode::nodes(100, None), Mode::mate(2), Mode::infinite()] {
+            assert_eq!(mode.deterministic().unwrap(), mode);
+        }
+
+        // Timed modes must have a depth limit.
+        assert!(Mode::movetime(1000, None).deterministic().is_err());
+        assert!(Mode::standard(1000, 1000, None, None, None, None)
+            .deterministic()
+            .is_err());
+    }
 }
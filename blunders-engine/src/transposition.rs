@@ -4,10 +4,11 @@ use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 use std::mem;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Mutex;
 
-use crate::coretypes::{Cp, Move, MoveInfo, PieceKind::*, PlyKind, Square};
+use crate::coretypes::{Cp, CpKind, Move, MoveInfo, PieceKind::*, PlyKind, Square};
+use crate::movelist::Line;
 use crate::position::{Cache, Position};
 use crate::zobrist::{HashKind, ZobristTable};
 
@@ -16,11 +17,14 @@ use crate::zobrist::{HashKind, ZobristTable};
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 #[repr(u8)]
 pub enum NodeKind {
-    /// An All node has had all of its children searched.
+    /// An All node has had all of its children searched without raising alpha.
+    /// Its score is an upper bound.
     All,
     /// A Cut node, or a node that was pruned because it caused a beta-cutoff.
+    /// Its score is a lower bound.
     Cut,
     /// A principal variation node from a previous search.
+    /// Its score is exact.
     Pv,
 }
 
@@ -40,6 +44,25 @@ impl TryFrom<u8> for NodeKind {
     }
 }
 
+/// Convert a score found at `distance` plies from the root into a score to store in an Entry.
+/// Mate scores count plies from the root, so they are stored as counting from the entry's
+/// position instead, which remains correct when reached by paths of a different length.
+pub fn score_to_tt(score: Cp, distance: PlyKind) -> Cp {
+    match score.is_mate() {
+        true => score + Cp(score.signum() * distance as CpKind),
+        false => score,
+    }
+}
+
+/// Convert a score stored in an Entry into a score at `distance` plies from the root.
+/// This is the inverse of `score_to_tt`.
+pub fn score_from_tt(score: Cp, distance: PlyKind) -> Cp {
+    match score.is_mate() {
+        true => score - Cp(score.signum() * distance as CpKind),
+        false => score,
+    }
+}
+
 /// Entry contains information about a single previously searched position.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Entry {
@@ -83,6 +106,11 @@ impl Entry {
             node_kind: NodeKind::All,
         }
     }
+
+    /// Returns true if this entry holds a searched position, or false if it is illegal.
+    pub fn is_occupied(&self) -> bool {
+        self.key_move != Move::illegal()
+    }
 }
 
 impl Hash for Entry {
@@ -112,6 +140,9 @@ pub trait TwoBucket: Debug + Default + Sync {
     /// Returns true if this bucket has any entry which contains the given hash.
     fn contains(&self, hash: HashKind) -> bool;
 
+    /// Returns the number of entries in this bucket which hold a searched position.
+    fn occupied(&self) -> usize;
+
     /// Unconditionally store the entry in the general slot, without updating age.
     fn store(&self, general_entry: Entry);
 
@@ -170,6 +201,9 @@ impl TwoBucket for DummyBucket {
     fn contains(&self, _hash: HashKind) -> bool {
         false
     }
+    fn occupied(&self) -> usize {
+        0
+    }
     fn store(&self, _general_entry: Entry) {}
     fn replace(&self, _priority_entry: Entry, _age: u8) {}
     fn swap_replace(&self, _priority_entry: Entry, _age: u8) {}
@@ -272,6 +306,15 @@ impl TwoBucket for LockBucket {
         priority_hash == hash || general_hash == hash
     }
 
+    #[inline]
+    fn occupied(&self) -> usize {
+        let lock = self.mu.lock().unwrap();
+        [lock.priority, lock.general]
+            .iter()
+            .filter(|entry| entry.is_occupied())
+            .count()
+    }
+
     #[inline]
     fn store(&self, general_entry: Entry) {
         let mut lock = self.mu.lock().unwrap();
@@ -580,6 +623,16 @@ impl TwoBucket for AtomicBucket {
         hash == loaded_priority.hash() || hash == loaded_general.hash()
     }
 
+    /// Returns the number of entries in this bucket which hold a searched position.
+    fn occupied(&self) -> usize {
+        let loaded_priority = self.priority.load(Ordering::Acquire);
+        let loaded_general = self.general.load(Ordering::Acquire);
+        [loaded_priority, loaded_general]
+            .iter()
+            .filter(|loaded| loaded.entry().is_occupied())
+            .count()
+    }
+
     /// Unconditionally store the entry in the general slot, without updating age.
     fn store(&self, general_entry: Entry) {
         self.general.store(general_entry.into(), Ordering::Release);
@@ -697,6 +750,10 @@ pub struct TranspositionTable<Bucket: TwoBucket = AtomicBucket> {
     ztable: ZobristTable,
     /// Bucketed vector of transpositions.
     transpositions: Vec<Bucket>,
+    /// Generation of the current search, used as the age of stored entries.
+    generation: AtomicU8,
+    /// Number of occupied entries, counted as entries are written.
+    len: AtomicUsize,
 }
 
 /// Transposition Table functions that use the default generic parameter bucket.
@@ -748,6 +805,12 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
         (mb * 1_000_000) / mem::size_of::<Bucket>()
     }
 
+    /// Converts a capacity in entries to a capacity of inner vector,
+    /// rounding up to guarantee the minimum entry capacity.
+    fn entry_to_bucket_capacity(entry_capacity: usize) -> usize {
+        entry_capacity.div_ceil(Bucket::len())
+    }
+
     fn mb_to_entry_capacity(mb: usize) -> usize {
         assert!(mb > 0, "mb cannot be 0");
         let bucket_capacity = Self::mb_to_bucket_capacity(mb);
@@ -797,8 +860,7 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
     /// Returns a new TranspositionTable with provided ZobristTable
     /// and capacity in entries pre-allocated.
     pub fn with_capacity_and_zobrist_in(entry_capacity: usize, ztable: ZobristTable) -> Self {
-        // Add Bucket::len - 1 to guarantee minimum capacity due to integer division floor.
-        let bucket_capacity = (entry_capacity + Bucket::len() - 1) / Bucket::len();
+        let bucket_capacity = Self::entry_to_bucket_capacity(entry_capacity);
 
         let mut transpositions = Vec::with_capacity(bucket_capacity);
         fill_with_default(&mut transpositions);
@@ -809,21 +871,59 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
             bucket_capacity,
             ztable,
             transpositions,
+            generation: AtomicU8::new(0),
+            len: AtomicUsize::new(0),
         }
     }
 
     /// Returns the capacity of entries of the TranspositionTable.
     pub fn capacity(&self) -> usize {
-        assert_eq!(self.bucket_capacity, self.transpositions.capacity());
-        self.transpositions.capacity() * Bucket::len()
+        assert_eq!(self.bucket_capacity, self.transpositions.len());
+        self.bucket_capacity * Bucket::len()
     }
 
     /// Returns the capacity of buckets in this TranspositionTable.
     pub fn bucket_capacity(&self) -> usize {
-        assert_eq!(self.bucket_capacity, self.transpositions.capacity());
+        assert_eq!(self.bucket_capacity, self.transpositions.len());
         self.bucket_capacity
     }
 
+    /// Returns the number of occupied entries in the TranspositionTable.
+    /// Entries written concurrently to the same slot may rarely be counted twice,
+    /// so this is an estimate which never exceeds the capacity.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed).min(self.capacity())
+    }
+
+    /// Returns true if no entries are occupied.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the ratio of occupied entries to capacity, from 0.0 to 1.0.
+    pub fn fill_ratio(&self) -> f64 {
+        self.len() as f64 / self.capacity() as f64
+    }
+
+    /// Returns the number of occupied entries per thousand, as sent in UCI `hashfull`.
+    pub fn hashfull(&self) -> u32 {
+        (self.len() * 1000 / self.capacity()) as u32
+    }
+
+    /// Returns the generation of the current search.
+    /// Entries stored with an age other than the current generation are stale.
+    pub fn generation(&self) -> AgeKind {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Begin a new search generation, and return it.
+    /// The generation wraps around after reaching the maximum AgeKind.
+    pub fn new_generation(&self) -> AgeKind {
+        self.generation
+            .fetch_add(1, Ordering::AcqRel)
+            .wrapping_add(1)
+    }
+
     /// Removes all items from TranspositionTable.
     /// Since the TT uniquely holds its inner vector, this operation is safely guarded
     /// by its signature `&mut self`, as it cannot be held by any other thread.
@@ -831,17 +931,44 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
         for bucket in &mut self.transpositions {
             *bucket = Bucket::default();
         }
-        debug_assert_eq!(self.bucket_capacity, self.transpositions.capacity());
+        *self.len.get_mut() = 0;
         debug_assert_eq!(self.bucket_capacity, self.transpositions.len());
     }
 
-    /// Drops original table and allocates a new table of size `new_mb`.
-    /// Entries in the original table are not preserved.
-    /// Returns the table's new entry capacity.
+    /// Alias of `resize_mb`.
     pub fn set_mb(&mut self, new_mb: usize) -> usize {
+        self.resize_mb(new_mb)
+    }
+
+    /// Resize the table to fill `new_mb` Megabytes, and return the table's new entry capacity.
+    /// The existing allocation is reused when shrinking, and only grown when needed.
+    /// Entries in the original table are not preserved, but its ZobristTable is.
+    pub fn resize_mb(&mut self, new_mb: usize) -> usize {
         let entry_capacity = Self::mb_to_entry_capacity(new_mb);
-        let ztable = self.ztable.clone();
-        *self = Self::with_capacity_and_zobrist_in(entry_capacity, ztable);
+        self.resize(entry_capacity)
+    }
+
+    /// Resize the table to hold `entry_capacity` entries, and return the table's new entry capacity.
+    /// The existing allocation is reused when shrinking, and only grown when needed.
+    /// Entries in the original table are not preserved, but its ZobristTable is.
+    pub fn resize(&mut self, entry_capacity: usize) -> usize {
+        let bucket_capacity = Self::entry_to_bucket_capacity(entry_capacity);
+
+        // Entries are indexed by table size, so existing entries are invalid after resize.
+        self.transpositions.truncate(bucket_capacity);
+        for bucket in &mut self.transpositions {
+            *bucket = Bucket::default();
+        }
+        *self.len.get_mut() = 0;
+
+        let len = self.transpositions.len();
+        if bucket_capacity > len {
+            self.transpositions.reserve_exact(bucket_capacity - len);
+            self.transpositions
+                .resize_with(bucket_capacity, Bucket::default);
+        }
+        self.bucket_capacity = bucket_capacity;
+
         self.capacity()
     }
 
@@ -853,6 +980,7 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
     }
 
     /// Update hash for the application of a Move on Position.
+    /// In debug builds, the updated hash is verified against a hash generated from scratch.
     pub fn update_hash(
         &self,
         hash: &mut HashKind,
@@ -862,9 +990,12 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
     ) {
         self.ztable
             .update_hash(hash, position.into(), move_info, cache);
+        #[cfg(debug_assertions)]
+        self.ztable.debug_verify_hash(*hash, position.into());
     }
 
     /// Generate a new hash from a Move applied to an existing Hash and Position.
+    /// In debug builds, the new hash is verified against a hash generated from scratch.
     pub fn update_from_hash(
         &self,
         mut hash: HashKind,
@@ -874,6 +1005,8 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
     ) -> HashKind {
         self.ztable
             .update_hash(&mut hash, position.into(), move_info, cache);
+        #[cfg(debug_assertions)]
+        self.ztable.debug_verify_hash(hash, position.into());
         hash
     }
 
@@ -882,6 +1015,24 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
         (hash % self.bucket_capacity as HashKind) as usize
     }
 
+    /// Hint to the CPU that the bucket for a hash will be probed soon, so it may be loaded into
+    /// cache while other work is done. This never changes the contents of the table, and
+    /// is a no-op on architectures without a prefetch intrinsic.
+    #[inline(always)]
+    pub fn prefetch(&self, hash: HashKind) {
+        let index = self.hash_to_index(hash);
+        let bucket: *const Bucket = &self.transpositions[index];
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            // Safety: Prefetching is only a hint and cannot fault, and bucket is a valid reference.
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(bucket as *const i8) };
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = bucket;
+    }
+
     /// Returns true if a TranspositionTable bucket contains an entry with the given hash.
     /// Key collisions are expected to be rare but possible,
     /// so care should be taken with the return value.
@@ -896,27 +1047,70 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
         self.transpositions[index].get(hash)
     }
 
+    /// Reconstruct the principal variation of a root position from the table,
+    /// by following the key move of each position's entry.
+    /// The line ends when a position has no entry, its key move is illegal, a position repeats,
+    /// or the line reaches `max_len` moves or the capacity of a Line.
+    pub fn probe_pv(&self, root: &Position, max_len: usize) -> Line {
+        let mut pv = Line::new();
+        let mut position = *root;
+        let mut hash = self.generate_hash(&position);
+        let mut visited = vec![hash];
+        let max_len = max_len.min(pv.capacity());
+
+        while pv.len() < max_len {
+            let key_move = match self.get(hash) {
+                Some(entry) if position.is_legal_move(entry.key_move) => entry.key_move,
+                _ => break,
+            };
+
+            let cache = position.cache();
+            let move_info = position.do_move(key_move);
+            self.update_hash(&mut hash, &position, move_info, cache);
+            pv.push(key_move);
+
+            if visited.contains(&hash) {
+                break;
+            }
+            visited.push(hash);
+        }
+
+        pv
+    }
+
     /// Unconditionally replace an existing item in the TranspositionTable
     /// where replace_by true would place it.
     /// Capacity of the table remains unchanged.
     pub fn replace(&self, priority_entry: Entry, age: AgeKind) {
-        let index = self.hash_to_index(priority_entry.hash);
-        self.transpositions[index].replace(priority_entry, age);
-
-        debug_assert_eq!(self.bucket_capacity, self.transpositions.capacity());
+        self.write_bucket(priority_entry.hash, |bucket| {
+            bucket.replace(priority_entry, age)
+        });
         debug_assert_eq!(self.bucket_capacity, self.transpositions.len());
     }
 
     /// Move entry in priority slot to general slot then place priority_entry into priority slot.
     pub fn swap_replace(&self, priority_entry: Entry, age: AgeKind) {
-        let index = self.hash_to_index(priority_entry.hash);
-        self.transpositions[index].swap_replace(priority_entry, age);
+        self.write_bucket(priority_entry.hash, |bucket| {
+            bucket.swap_replace(priority_entry, age)
+        });
     }
 
     /// Store the entry into the index bucket's general slot, without changing age or scheme slot.
     pub fn store(&self, general_entry: Entry) {
-        let index = self.hash_to_index(general_entry.hash);
-        self.transpositions[index].store(general_entry);
+        self.write_bucket(general_entry.hash, |bucket| bucket.store(general_entry));
+    }
+
+    /// Apply a write to the bucket indexed by hash, counting any newly occupied entries.
+    /// Entries are never emptied by a write, so the count only grows until the table is cleared.
+    #[inline]
+    fn write_bucket<F: FnOnce(&Bucket)>(&self, hash: HashKind, write: F) {
+        let bucket = &self.transpositions[self.hash_to_index(hash)];
+        let occupied = bucket.occupied();
+        write(bucket);
+        if occupied < Bucket::len() {
+            let newly_occupied = bucket.occupied().saturating_sub(occupied);
+            self.len.fetch_add(newly_occupied, Ordering::Relaxed);
+        }
     }
 
     /// Attempt to insert an item into the tt depending on a replacement scheme.
@@ -970,8 +1164,9 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
     where
         F: FnOnce(&Entry, u8, &Entry, u8) -> bool,
     {
-        let index = self.hash_to_index(entry.hash);
-        self.transpositions[index].replace_by(entry, age, should_replace);
+        self.write_bucket(entry.hash, |bucket| {
+            bucket.replace_by(entry, age, should_replace)
+        });
     }
 
     /// If entry passes the should_replace test, then the existing entry in the priority slot
@@ -981,8 +1176,9 @@ impl<Bucket: TwoBucket> TranspositionTable<Bucket> {
     where
         F: FnOnce(&Entry, u8, &Entry, u8) -> bool,
     {
-        let index = self.hash_to_index(entry.hash);
-        self.transpositions[index].swap_replace_by(entry, age, should_replace)
+        self.write_bucket(entry.hash, |bucket| {
+            bucket.swap_replace_by(entry, age, should_replace)
+        });
     }
 }
 
@@ -1133,4 +1329,150 @@ mod tests {
         assert!(tt.contains(hash));
         assert_eq!(tt.get(hash), Some(tt_entry));
     }
+
+    #[test]
+    fn prefetch_does_not_change_table() {
+        let tt = TranspositionTable::with_capacity(1000);
+        let best_move = Move::new(E2, E4, None);
+        let entries: Vec<Entry> = (0..100)
+            .map(|hash| Entry::new(hash * 7919, best_move, Cp(hash as i16), 3, NodeKind::Pv))
+            .collect();
+        for entry in &entries {
+            tt.replace(*entry, 0);
+        }
+        let before: Vec<Option<Entry>> = (0..1000).map(|hash| tt.get(hash)).collect();
+
+        for hash in (0..1000).chain([HashKind::MAX, 7919 * 99]) {
+            tt.prefetch(hash);
+        }
+
+        let after: Vec<Option<Entry>> = (0..1000).map(|hash| tt.get(hash)).collect();
+        assert_eq!(before, after);
+        for entry in entries {
+            tt.prefetch(entry.hash);
+            assert_eq!(tt.get(entry.hash), Some(entry));
+        }
+    }
+
+    #[test]
+    fn resize_preserves_zobrist_table() {
+        let mut tt = TranspositionTable::with_mb(4);
+        let ztable = tt.zobrist_table().clone();
+        let capacity = tt.capacity();
+        let hash = tt.generate_hash(&Position::start_position());
+        tt.replace(
+            Entry::new(hash, Move::new(D2, D4, None), Cp(3), 5, NodeKind::Pv),
+            0,
+        );
+
+        // Shrinking keeps the original allocation.
+        let small = tt.resize_mb(1);
+        assert_eq!(small, TranspositionTable::with_mb(1).capacity());
+        assert!(small < capacity);
+        assert!(tt.transpositions.capacity() * 2 >= capacity);
+        assert_eq!(tt.zobrist_table(), &ztable);
+        assert_eq!(tt.get(hash), None);
+        assert!(tt.is_empty());
+
+        let large = tt.resize_mb(4);
+        assert_eq!(large, capacity);
+        assert_eq!(tt.zobrist_table(), &ztable);
+        assert_eq!(tt.generate_hash(&Position::start_position()), hash);
+
+        assert_eq!(tt.set_mb(2), TranspositionTable::with_mb(2).capacity());
+        assert_eq!(tt.zobrist_table(), &ztable);
+    }
+
+    #[test]
+    fn len_counts_occupied_entries() {
+        let mut tt = TranspositionTable::with_capacity(1000);
+        let best_move = Move::new(E2, E4, None);
+        assert_eq!(tt.len(), 0);
+        assert_eq!(tt.hashfull(), 0);
+
+        // Replacing an entry in the same slot does not change the count.
+        let entry = Entry::new(7, best_move, Cp(1), 3, NodeKind::Pv);
+        tt.replace(entry, 0);
+        tt.replace(entry, 1);
+        assert_eq!(tt.len(), 1);
+        tt.swap_replace(entry, 1);
+        assert_eq!(tt.len(), 2);
+
+        for hash in 0..500 {
+            tt.store(Entry::new(hash, best_move, Cp(1), 3, NodeKind::Pv));
+        }
+        assert_eq!(tt.len(), 501);
+        assert_eq!(tt.hashfull(), 501);
+        assert!((tt.fill_ratio() - 0.501).abs() < 1e-9);
+
+        tt.clear();
+        assert_eq!(tt.len(), 0);
+    }
+
+    #[test]
+    fn probe_pv_follows_key_moves() {
+        // Seeded so that positions of the line never share a bucket.
+        let tt = TranspositionTable::with_capacity_and_zobrist(1000, ZobristTable::with_seed(0));
+        let mut position = Position::start_position();
+        let line = [
+            Move::new(E2, E4, None),
+            Move::new(E7, E5, None),
+            Move::new(G1, F3, None),
+        ];
+        for move_ in line {
+            let hash = tt.generate_hash(&position);
+            tt.replace(Entry::new(hash, move_, Cp(0), 3, NodeKind::Pv), 0);
+            position.do_move(move_);
+        }
+        let root = Position::start_position();
+        assert_eq!(tt.probe_pv(&root, 10).as_slice(), &line);
+        assert_eq!(tt.probe_pv(&root, 2).as_slice(), &line[..2]);
+
+        // An illegal key move ends the line.
+        let hash = tt.generate_hash(&position);
+        tt.replace(
+            Entry::new(hash, Move::new(E2, E4, None), Cp(0), 3, NodeKind::Pv),
+            0,
+        );
+        assert_eq!(tt.probe_pv(&root, 10).as_slice(), &line);
+    }
+
+    #[test]
+    fn probe_pv_stops_at_repetition() {
+        // Seeded so that positions of the line never share a bucket.
+        let tt = TranspositionTable::with_capacity_and_zobrist(1000, ZobristTable::with_seed(0));
+        let mut position = Position::start_position();
+        let cycle = [
+            Move::new(G1, F3, None),
+            Move::new(G8, F6, None),
+            Move::new(F3, G1, None),
+            Move::new(F6, G8, None),
+        ];
+        for move_ in cycle {
+            let hash = tt.generate_hash(&position);
+            tt.replace(Entry::new(hash, move_, Cp(0), 3, NodeKind::Pv), 0);
+            position.do_move(move_);
+        }
+        let pv = tt.probe_pv(&Position::start_position(), 20);
+        assert_eq!(pv.as_slice(), &cycle);
+    }
+
+    #[test]
+    fn score_to_and_from_tt() {
+        let mate = Cp::CHECKMATE - Cp(5);
+        assert_eq!(score_to_tt(mate, 3), Cp::CHECKMATE - Cp(2));
+        assert_eq!(score_to_tt(-mate, 3), -Cp::CHECKMATE + Cp(2));
+        for score in [
+            mate,
+            -mate,
+            Cp(0),
+            Cp(250),
+            Cp(-250),
+            Cp::SCORE_MAX,
+            Cp::SCORE_MIN,
+        ] {
+            assert_eq!(score_from_tt(score_to_tt(score, 7), 7), score);
+        }
+        assert_eq!(score_to_tt(Cp(250), 7), Cp(250));
+    }
 }
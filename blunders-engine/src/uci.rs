@@ -4,17 +4,21 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fmt::{self, Display, Write};
+use std::fs::{File, OpenOptions};
 use std::hash::{Hash, Hasher};
 use std::io;
 use std::ops::Deref;
 use std::ops::{Index, IndexMut};
 use std::str::{FromStr, SplitWhitespace};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::coretypes::{Move, PlyKind};
+use crate::bench::BENCH_DEPTH;
+use crate::coretypes::{Cp, Move, PlyKind, MAX_DEPTH};
 use crate::error::{self, ErrorKind};
-use crate::fen::Fen;
-use crate::movelist::MoveHistory;
-use crate::position::{Game, Position};
+use crate::movelist::Line;
+use crate::position::Game;
+use crate::search::{SearchProgress, SearchResult};
 
 /// UciCommands commands from an external program sent to this chess engine.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -29,11 +33,23 @@ pub enum UciCommand {
     Stop,
     PonderHit,
     Quit,
+    /// Non-standard command to print the static evaluation of the position by term.
+    Eval,
+    /// Non-standard command to count the leaf nodes of each root move to a depth.
+    Perft(u32),
+    /// Non-standard command to search the benchmark suite to a depth and report total nodes.
+    Bench(PlyKind),
 }
 
 impl UciCommand {
     /// Parse a single input line into a UciCommand if possible.
     pub fn parse_command(input_str: &str) -> error::Result<Self> {
+        Self::parse_command_chess960(input_str, false)
+    }
+
+    /// Parse a single input line into a UciCommand if possible.
+    /// If `chess960` is set, positions describe castling moves as the king moving onto its rook.
+    pub fn parse_command_chess960(input_str: &str, chess960: bool) -> error::Result<Self> {
         let mut input = input_str.split_whitespace();
         let head = input.next().ok_or(ErrorKind::UciNoCommand)?;
 
@@ -43,11 +59,14 @@ impl UciCommand {
             "isready" => Ok(UciCommand::IsReady),
             "setoption" => Self::parse_setoption(input),
             "ucinewgame" => Ok(UciCommand::UciNewGame),
-            "position" => Self::parse_pos(input),
+            "position" => Self::parse_pos(input, chess960),
             "go" => Self::parse_go(input),
             "stop" => Ok(UciCommand::Stop),
             "ponderhit" => Ok(UciCommand::PonderHit),
             "quit" => Ok(UciCommand::Quit),
+            "eval" => Ok(UciCommand::Eval),
+            "perft" => Self::parse_perft(input),
+            "bench" => Self::parse_bench(input),
             _ => Err((ErrorKind::UciUnknownCommand, head).into()),
         }
     }
@@ -64,6 +83,38 @@ impl UciCommand {
         }
     }
 
+    /// Extract a `perft` command if possible. Depth must be at least 1 and fit in a PlyKind.
+    /// command: `perft depth`
+    fn parse_perft(mut input: SplitWhitespace) -> error::Result<Self> {
+        let depth: u32 = input
+            .next()
+            .ok_or((ErrorKind::UciNoArgument, "perft missing depth"))?
+            .parse()
+            .map_err(|err| (ErrorKind::UciCannotParseInt, err))?;
+
+        match depth > 0 && PlyKind::try_from(depth).is_ok() {
+            true => Ok(Self::Perft(depth)),
+            false => Err((ErrorKind::UciPerftIllegalDepth, depth.to_string()).into()),
+        }
+    }
+
+    /// Extract a `bench` command if possible. Depth defaults to BENCH_DEPTH if not given,
+    /// and must be in range [1, MAX_DEPTH].
+    /// command: `bench (depth)`
+    fn parse_bench(mut input: SplitWhitespace) -> error::Result<Self> {
+        let depth: u32 = match input.next() {
+            Some(depth_str) => depth_str
+                .parse()
+                .map_err(|err| (ErrorKind::UciCannotParseInt, err))?,
+            None => BENCH_DEPTH.into(),
+        };
+
+        match depth > 0 && depth <= MAX_DEPTH.into() {
+            true => Ok(Self::Bench(depth as PlyKind)),
+            false => Err((ErrorKind::UciBenchIllegalDepth, depth.to_string()).into()),
+        }
+    }
+
     /// Extract a `setoption` command if possible.
     ///command: `setoption name [id] (value x)`
     fn parse_setoption(mut input: SplitWhitespace) -> error::Result<Self> {
@@ -113,36 +164,8 @@ impl UciCommand {
 
     /// Extract a `position` command if possible.
     /// command: `position [fen fen_str | startpos] (moves move_list ...)`
-    fn parse_pos(mut input: SplitWhitespace) -> error::Result<Self> {
-        let position_input = input.next().ok_or((
-            ErrorKind::UciNoArgument,
-            "position missing description [fen | startpos]",
-        ))?;
-
-        // Parse a valid position from startpos or FEN, or return an Err(_).
-        let base_position = match position_input {
-            "startpos" => Ok(Position::start_position()),
-            "fen" => {
-                let mut fen_str = String::new();
-                for _ in 0..6 {
-                    fen_str.push_str(input.next().ok_or(ErrorKind::UciPositionMalformed)?);
-                    fen_str.push(' ');
-                }
-                Position::parse_fen(&fen_str)
-            }
-            _ => return Err(ErrorKind::UciPositionMalformed.into()),
-        }?;
-
-        let mut moves = MoveHistory::new();
-
-        // Check if there is a sequence of moves to apply to the position.
-        if let Some("moves") = input.next() {
-            for move_str in input {
-                moves.push(Move::from_str(move_str)?);
-            }
-        }
-
-        Game::new(base_position, moves).map(|game| UciCommand::Pos(game))
+    fn parse_pos(input: SplitWhitespace, chess960: bool) -> error::Result<Self> {
+        Game::parse_uci_position(input, chess960).map(UciCommand::Pos)
     }
 
     /// Extract a `go` command if possible.
@@ -176,34 +199,11 @@ impl UciCommand {
                     .map_err(|err| (ErrorKind::UciCannotParseInt, err))?;
 
                 match input_str {
-                    "wtime" => {
-                        controls.wtime = Some(
-                            argument
-                                .try_into()
-                                .map_err(|err| (ErrorKind::UciCannotParseInt, err))?,
-                        )
-                    }
-                    "btime" => {
-                        controls.btime = Some(
-                            argument
-                                .try_into()
-                                .map_err(|err| (ErrorKind::UciCannotParseInt, err))?,
-                        )
-                    }
-                    "winc" => {
-                        controls.winc = Some(
-                            argument
-                                .try_into()
-                                .map_err(|err| (ErrorKind::UciCannotParseInt, err))?,
-                        )
-                    }
-                    "binc" => {
-                        controls.binc = Some(
-                            argument
-                                .try_into()
-                                .map_err(|err| (ErrorKind::UciCannotParseInt, err))?,
-                        )
-                    }
+                    // Clocks may be negative or zero near flag-fall, which means no time remains.
+                    "wtime" => controls.wtime = Some(clamp_clock(argument)),
+                    "btime" => controls.btime = Some(clamp_clock(argument)),
+                    "winc" => controls.winc = Some(clamp_increment(argument)),
+                    "binc" => controls.binc = Some(clamp_increment(argument)),
                     "depth" => {
                         controls.depth = Some(
                             argument
@@ -254,6 +254,16 @@ impl UciCommand {
     }
 }
 
+/// Clamp a clock time in milliseconds to be non-negative.
+fn clamp_clock(time_ms: i64) -> i32 {
+    time_ms.clamp(0, i32::MAX as i64) as i32
+}
+
+/// Clamp a clock increment in milliseconds to be non-negative.
+fn clamp_increment(increment_ms: i64) -> u32 {
+    increment_ms.clamp(0, u32::MAX as i64) as u32
+}
+
 impl FromStr for UciCommand {
     type Err = error::Error;
     fn from_str(s: &str) -> error::Result<Self> {
@@ -268,8 +278,9 @@ pub enum UciResponse {
     UciOk,
     ReadyOk,
     Opt(UciOption),
-    BestMove(Move),
-    Info(UciInfo),
+    /// Best move, and optionally the expected reply to ponder on.
+    BestMove(Move, Option<Move>),
+    Info(Box<UciInfo>),
 }
 
 impl UciResponse {
@@ -281,21 +292,23 @@ impl UciResponse {
         Self::Opt(uci_opt)
     }
 
-    pub fn new_best_move(move_: Move) -> Self {
-        Self::BestMove(move_)
+    pub fn new_best_move(move_: Move, ponder: Option<Move>) -> Self {
+        Self::BestMove(move_, ponder)
     }
 
     pub fn new_info(uci_info: UciInfo) -> Self {
-        Self::Info(uci_info)
+        Self::Info(Box::new(uci_info))
+    }
+
+    /// Write this UciResponse to a writer, flushing it so the response is sent immediately.
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.to_string().as_bytes())?;
+        writer.flush()
     }
 
     /// Send this UciResponse over stdout.
-    /// TODO: Allow for writing to files or stdout.
     pub fn send(&self) -> io::Result<()> {
-        let stdout = io::stdout();
-        let mut handle = stdout.lock();
-        <io::StdoutLock as io::Write>::write_all(&mut handle, self.to_string().as_ref())?;
-        <io::StdoutLock as io::Write>::flush(&mut handle)
+        self.write_to(&mut io::stdout().lock())
     }
 }
 
@@ -312,18 +325,19 @@ impl Display for UciResponse {
             }
             Self::UciOk => f.write_str("uciok\n"),
             Self::ReadyOk => f.write_str("readyok\n"),
-            Self::BestMove(move_) => {
+            Self::BestMove(move_, ponder) => {
                 f.write_str("bestmove ")?;
                 move_.fmt(f)?;
+                if let Some(ponder) = ponder {
+                    f.write_str(" ponder ")?;
+                    ponder.fmt(f)?;
+                }
                 f.write_char('\n')
             }
             Self::Opt(uci_opt) => {
                 write!(f, "{}\n", uci_opt)
             }
-            Self::Info(_info) => {
-                // TODO
-                f.write_str("info string todo\n")
-            }
+            Self::Info(info) => writeln!(f, "{}", info),
         }
     }
 }
@@ -331,15 +345,14 @@ impl Display for UciResponse {
 /// Send a debug info string over UCI.
 /// TODO: This is a temporary function until UciInfo and UciResponse are worked out.
 pub fn debug(can_debug: bool, s: &str) -> io::Result<()> {
+    debug_to(&mut io::stdout().lock(), can_debug, s)
+}
+
+/// Write a debug info string to a writer, if debugging is enabled.
+pub fn debug_to<W: io::Write>(writer: &mut W, can_debug: bool, s: &str) -> io::Result<()> {
     if can_debug {
-        let mut debug_str = String::from("info string debug ");
-        debug_str.push_str(s);
-        debug_str.push('\n');
-
-        let stdout = io::stdout();
-        let mut handle = stdout.lock();
-        <io::StdoutLock as io::Write>::write_all(&mut handle, debug_str.as_ref())?;
-        <io::StdoutLock as io::Write>::flush(&mut handle)
+        writer.write_all(format!("info string debug {}\n", s).as_bytes())?;
+        writer.flush()
     } else {
         Ok(())
     }
@@ -348,18 +361,296 @@ pub fn debug(can_debug: bool, s: &str) -> io::Result<()> {
 /// Send an error info string over UCI.
 /// TODO: This is a temporary function until UciInfo and UciResponse are worked out.
 pub fn error(s: &str) -> io::Result<()> {
-    let mut error_str = String::from("info string error ");
-    error_str.push_str(s);
-    error_str.push('\n');
+    error_to(&mut io::stdout().lock(), s)
+}
 
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-    <io::StdoutLock as io::Write>::write_all(&mut handle, error_str.as_ref())?;
-    <io::StdoutLock as io::Write>::flush(&mut handle)
+/// Write an error info string to a writer.
+pub fn error_to<W: io::Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_all(format!("info string error {}\n", s).as_bytes())?;
+    writer.flush()
 }
 
-#[derive(Debug, Clone)]
-pub struct UciInfo {}
+/// Value of a string option which is unset.
+pub const EMPTY_STRING_OPTION: &str = "<empty>";
+
+/// UciLog mirrors UCI protocol traffic to a log file, with each line prefixed by a timestamp
+/// in seconds since the Unix epoch and its direction, `>>` for received and `<<` for sent.
+/// Clones of a UciLog share the same file, so traffic from every thread is logged together.
+/// Logging is best effort, failures to write the log are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct UciLog {
+    file: Arc<Mutex<Option<File>>>,
+}
+
+impl UciLog {
+    /// Create a new UciLog which does not log until a file is set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log to a file, appending to it if it exists. An empty path disables logging.
+    pub fn set_file(&self, path: &str) -> io::Result<()> {
+        let file = match path.trim() {
+            "" | EMPTY_STRING_OPTION => None,
+            path => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        };
+        *self.file.lock().unwrap() = file;
+        Ok(())
+    }
+
+    /// Returns true if traffic is being logged to a file.
+    pub fn is_enabled(&self) -> bool {
+        self.file.lock().unwrap().is_some()
+    }
+
+    /// Log a line of input received by the engine.
+    pub fn received(&self, line: &str) {
+        self.log(">>", line);
+    }
+
+    /// Log output sent by the engine, one log line per line of output.
+    pub fn sent(&self, output: &str) {
+        for line in output.lines() {
+            self.log("<<", line);
+        }
+    }
+
+    fn log(&self, direction: &str, line: &str) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let line = format!(
+                "{}.{:03} {} {}\n",
+                timestamp.as_secs(),
+                timestamp.subsec_millis(),
+                direction,
+                line.trim_end()
+            );
+            let _ = io::Write::write_all(file, line.as_bytes());
+        }
+    }
+}
+
+/// UciWriter writes engine output to an inner writer, mirroring each complete line to a UciLog.
+#[derive(Debug)]
+pub struct UciWriter<W: io::Write> {
+    inner: W,
+    log: UciLog,
+    pending: Vec<u8>,
+}
+
+impl<W: io::Write> UciWriter<W> {
+    /// Create a new UciWriter over a writer, such as stdout.
+    pub fn new(inner: W, log: UciLog) -> Self {
+        Self {
+            inner,
+            log,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Consume this UciWriter, returning its inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> io::Write for UciWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.pending.extend_from_slice(&buf[..written]);
+
+        // Only complete lines are logged, so that a line written in parts is logged once.
+        if let Some(end) = self.pending.iter().rposition(|byte| *byte == b'\n') {
+            let lines: Vec<u8> = self.pending.drain(..=end).collect();
+            self.log.sent(&String::from_utf8_lossy(&lines));
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Score of an `info` line, relative to the engine.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UciScore {
+    /// Score in centipawns.
+    Cp(Cp),
+    /// Mate in a number of moves, which is negative if the engine is getting mated.
+    Mate(i32),
+}
+
+impl UciScore {
+    /// Returns a UciScore for a relative search score and the principal variation leading to it.
+    /// Checkmate scores are counted in moves from the length of the principal variation.
+    pub fn new(score: Cp, pv: &Line) -> Self {
+        match score.is_mate() {
+            true => Self::Mate(score.signum() as i32 * ((pv.len() as i32 + 1) / 2)),
+            false => Self::Cp(score),
+        }
+    }
+}
+
+impl Display for UciScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cp(cp) => write!(f, "cp {}", cp.0),
+            Self::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+/// Search information sent to the GUI in an `info` line.
+/// Only fields which are set are sent, in the order they are declared.
+/// The principal variation is sent after other fields, followed only by any string.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct UciInfo {
+    /// Search depth in plies.
+    pub depth: Option<PlyKind>,
+    /// Selective search depth in plies.
+    pub seldepth: Option<PlyKind>,
+    /// Time searched in milliseconds.
+    pub time: Option<u128>,
+    /// Number of nodes searched.
+    pub nodes: Option<u64>,
+    /// Score of the principal variation.
+    pub score: Option<UciScore>,
+    /// Nodes searched per second.
+    pub nps: Option<u64>,
+    /// Permille of the transposition table which is full.
+    pub hashfull: Option<u32>,
+    /// Number of positions found in endgame tablebases.
+    pub tbhits: Option<u64>,
+    /// Principal variation, the best line found.
+    pub pv: Option<Line>,
+    /// Any string to display, which is always last in the line.
+    pub string: Option<String>,
+}
+
+impl UciInfo {
+    /// Returns a new UciInfo with no fields set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_depth(mut self, depth: PlyKind) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_seldepth(mut self, seldepth: PlyKind) -> Self {
+        self.seldepth = Some(seldepth);
+        self
+    }
+
+    pub fn with_time(mut self, time: u128) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn with_score(mut self, score: UciScore) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    pub fn with_nps(mut self, nps: u64) -> Self {
+        self.nps = Some(nps);
+        self
+    }
+
+    pub fn with_hashfull(mut self, hashfull: u32) -> Self {
+        self.hashfull = Some(hashfull);
+        self
+    }
+
+    pub fn with_tbhits(mut self, tbhits: u64) -> Self {
+        self.tbhits = Some(tbhits);
+        self
+    }
+
+    pub fn with_pv(mut self, pv: Line) -> Self {
+        self.pv = Some(pv);
+        self
+    }
+
+    pub fn with_string(mut self, string: String) -> Self {
+        self.string = Some(string);
+        self
+    }
+}
+
+impl From<&SearchProgress> for UciInfo {
+    fn from(progress: &SearchProgress) -> Self {
+        Self::new()
+            .with_depth(progress.depth)
+            .with_time(progress.elapsed.as_millis())
+            .with_nodes(progress.nodes)
+            .with_score(UciScore::new(progress.relative_score(), &progress.pv))
+            .with_nps(progress.nps() as u64)
+            .with_pv(progress.pv.clone())
+    }
+}
+
+impl From<&SearchResult> for UciInfo {
+    fn from(result: &SearchResult) -> Self {
+        Self::new()
+            .with_depth(result.depth)
+            .with_time(result.elapsed.as_millis())
+            .with_nodes(result.nodes)
+            .with_score(UciScore::new(result.relative_score(), &result.pv))
+            .with_nps(result.nps() as u64)
+            .with_pv(result.pv.clone())
+    }
+}
+
+/// Formats an `info` line without a trailing newline.
+impl Display for UciInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("info")?;
+        if let Some(depth) = self.depth {
+            write!(f, " depth {}", depth)?;
+        }
+        if let Some(seldepth) = self.seldepth {
+            write!(f, " seldepth {}", seldepth)?;
+        }
+        if let Some(time) = self.time {
+            write!(f, " time {}", time)?;
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {}", nodes)?;
+        }
+        if let Some(score) = self.score {
+            write!(f, " score {}", score)?;
+        }
+        if let Some(nps) = self.nps {
+            write!(f, " nps {}", nps)?;
+        }
+        if let Some(hashfull) = self.hashfull {
+            write!(f, " hashfull {}", hashfull)?;
+        }
+        if let Some(tbhits) = self.tbhits {
+            write!(f, " tbhits {}", tbhits)?;
+        }
+        if let Some(pv) = &self.pv {
+            f.write_str(" pv")?;
+            for move_ in pv {
+                write!(f, " {}", move_)?;
+            }
+        }
+        if let Some(string) = &self.string {
+            write!(f, " string {}", string)?;
+        }
+        Ok(())
+    }
+}
 
 /// Type parsed from a Uci `setoption` command.
 /// The value is stringly typed, because it can be a string, bool, integer, or nothing.
@@ -369,6 +660,18 @@ pub struct RawOption {
     value: String,
 }
 
+impl RawOption {
+    /// Returns the name of the option to set.
+    pub fn name(&self) -> &CaselessString {
+        &self.name
+    }
+
+    /// Returns the unparsed value of the option, which is empty if no value was given.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Check {
     pub value: bool,
@@ -815,8 +1118,12 @@ impl Default for SearchControls {
 mod tests {
     use super::*;
     use crate::coretypes::Square::*;
+    use crate::fen::Fen;
+    use crate::movelist::MoveHistory;
+    use crate::timeman::Mode;
+    use crate::Position;
 
-    /// Tests commands: uci, isready, ucinewgame, stop, ponderhit, quit
+    /// Tests commands: uci, isready, ucinewgame, stop, ponderhit, quit, eval
     #[test]
     fn parse_command_singles() {
         {
@@ -849,6 +1156,43 @@ mod tests {
             let command = UciCommand::parse_command(&input);
             assert_eq!(UciCommand::Quit, command.unwrap());
         }
+        {
+            let input = "eval";
+            let command = UciCommand::parse_command(&input);
+            assert_eq!(UciCommand::Eval, command.unwrap());
+        }
+    }
+
+    #[test]
+    fn parse_command_perft() {
+        let command = UciCommand::parse_command("perft 5\n");
+        assert_eq!(command.unwrap(), UciCommand::Perft(5));
+
+        let perft_error = |input: &str| match UciCommand::parse_command(input) {
+            Err(error::Error::Message(kind, _)) => kind,
+            other => panic!("{} parsed as {:?}", input, other),
+        };
+        assert_eq!(perft_error("perft"), ErrorKind::UciNoArgument);
+        assert_eq!(perft_error("perft five"), ErrorKind::UciCannotParseInt);
+        assert_eq!(perft_error("perft 0"), ErrorKind::UciPerftIllegalDepth);
+        assert_eq!(perft_error("perft 256"), ErrorKind::UciPerftIllegalDepth);
+    }
+
+    #[test]
+    fn parse_command_bench() {
+        let command = UciCommand::parse_command("bench\n");
+        assert_eq!(command.unwrap(), UciCommand::Bench(BENCH_DEPTH));
+        let command = UciCommand::parse_command("bench 3");
+        assert_eq!(command.unwrap(), UciCommand::Bench(3));
+
+        let bench_error = |input: &str| match UciCommand::parse_command(input) {
+            Err(error::Error::Message(kind, _)) => kind,
+            other => panic!("{} parsed as {:?}", input, other),
+        };
+        assert_eq!(bench_error("bench three"), ErrorKind::UciCannotParseInt);
+        assert_eq!(bench_error("bench 0"), ErrorKind::UciBenchIllegalDepth);
+        let too_deep = format!("bench {}", MAX_DEPTH as u32 + 1);
+        assert_eq!(bench_error(&too_deep), ErrorKind::UciBenchIllegalDepth);
     }
 
     #[test]
@@ -964,6 +1308,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_command_pos_illegal_fen() {
+        let fen_error = |input: &str| match UciCommand::parse_command(input) {
+            Err(error::Error::Message(kind, _)) => kind,
+            result => panic!("expected fen error, got {:?}", result),
+        };
+        assert_eq!(
+            fen_error("position fen 8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            ErrorKind::FenKingCount
+        );
+        assert_eq!(
+            fen_error("position fen 4k3/8/8/8/8/8/8/4K3 b K - 0 1 moves e8d8"),
+            ErrorKind::FenIllegalCastling
+        );
+    }
+
     #[test]
     fn parse_command_go() {
         {
@@ -974,6 +1334,40 @@ mod tests {
             search_ctrl.wtime = Some(40000);
             assert_eq!(UciCommand::Go(search_ctrl), command);
         }
+        {
+            // Negative and zero clocks are clamped instead of rejected.
+            let input = "go wtime -5 btime 0 winc 100";
+            let command = UciCommand::parse_command(input).unwrap();
+            let mut search_ctrl = SearchControls::new();
+            search_ctrl.wtime = Some(0);
+            search_ctrl.btime = Some(0);
+            search_ctrl.winc = Some(100);
+            assert_eq!(UciCommand::Go(search_ctrl), command);
+            let mode = Mode::try_from(search_ctrl).unwrap();
+            assert!(matches!(mode, Mode::Standard(_)));
+        }
+        {
+            let input = "go wtime -99999999999 btime 1000 winc -10 binc -10";
+            let command = UciCommand::parse_command(input).unwrap();
+            let mut search_ctrl = SearchControls::new();
+            search_ctrl.wtime = Some(0);
+            search_ctrl.btime = Some(1000);
+            search_ctrl.winc = Some(0);
+            search_ctrl.binc = Some(0);
+            assert_eq!(UciCommand::Go(search_ctrl), command);
+        }
+        assert!(UciCommand::parse_command("go wtime").is_err());
+        assert!(UciCommand::parse_command("go wtime fast").is_err());
+
+        // A bare go and go infinite both search until stopped.
+        for input in ["go", "go infinite"] {
+            match UciCommand::parse_command(input).unwrap() {
+                UciCommand::Go(search_ctrl) => {
+                    assert_eq!(Mode::try_from(search_ctrl).unwrap(), Mode::infinite())
+                }
+                command => panic!("{} parsed as {:?}", input, command),
+            }
+        }
     }
 
     #[test]
@@ -1010,4 +1404,180 @@ mod tests {
         assert_eq!(option_threads, *uci_options.get(&"threads".into()).unwrap());
         assert_ne!(option_hash, *uci_options.get(&"hash".into()).unwrap());
     }
+
+    #[test]
+    fn best_move_ponder() {
+        let best_move = UciResponse::new_best_move(Move::new(E2, E4, None), None);
+        assert_eq!(best_move.to_string(), "bestmove e2e4\n");
+
+        // Ponder move is the second move of a search's principal variation.
+        let tt = crate::TranspositionTable::with_capacity(10_000);
+        let result = crate::search::search(crate::Position::start_position(), 3, &tt);
+        assert!(result.pv.len() >= 2);
+        let ponder = result.pv.get(1).copied();
+        let best_move = UciResponse::new_best_move(result.best_move, ponder);
+        assert_eq!(
+            best_move.to_string(),
+            format!("bestmove {} ponder {}\n", result.best_move, result.pv[1])
+        );
+    }
+
+    #[test]
+    fn responses_write_to_writer() {
+        let mut output = Vec::new();
+        let best_move = UciResponse::new_best_move(
+            Move::new(E7, E8, Some(crate::coretypes::PieceKind::Queen)),
+            None,
+        );
+        best_move.write_to(&mut output).unwrap();
+
+        let mut pv = Line::new();
+        pv.push(Move::new(E2, E4, None));
+        let info = UciInfo::new()
+            .with_depth(1)
+            .with_score(UciScore::Cp(Cp(20)))
+            .with_pv(pv);
+        UciResponse::new_info(info).write_to(&mut output).unwrap();
+
+        debug_to(&mut output, false, "hidden").unwrap();
+        debug_to(&mut output, true, "shown").unwrap();
+        error_to(&mut output, "failed").unwrap();
+
+        assert_eq!(
+            output,
+            b"bestmove e7e8q\ninfo depth 1 score cp 20 pv e2e4\n\
+              info string debug shown\ninfo string error failed\n"
+        );
+    }
+
+    #[test]
+    fn log_file_mirrors_traffic() {
+        let path =
+            std::env::temp_dir().join(format!("blunders_uci_log_{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let log = UciLog::new();
+        let mut writer = UciWriter::new(Vec::new(), log.clone());
+        log.received("isready");
+        UciResponse::ReadyOk.write_to(&mut writer).unwrap();
+        assert!(!log.is_enabled());
+        assert!(!path.exists());
+
+        log.set_file(path.to_str().unwrap()).unwrap();
+        assert!(log.is_enabled());
+        log.received("isready");
+        UciResponse::ReadyOk.write_to(&mut writer).unwrap();
+        debug_to(&mut writer, true, "two\nlines").unwrap();
+        log.set_file(EMPTY_STRING_OPTION).unwrap();
+        assert!(!log.is_enabled());
+        log.received("quit");
+
+        // Output is unchanged by logging.
+        assert_eq!(
+            writer.into_inner(),
+            b"readyok\nreadyok\ninfo string debug two\nlines\n"
+        );
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(" >> isready"));
+        assert!(lines[1].ends_with(" << readyok"));
+        assert!(lines[2].ends_with(" << info string debug two"));
+        assert!(lines[3].ends_with(" << lines"));
+        let timestamp = lines[0].split(' ').next().unwrap();
+        assert!(timestamp.parse::<f64>().unwrap() > 0.0);
+    }
+
+    /// Parse the key value pairs of an info line. A pv takes moves up to any string,
+    /// and a string takes the rest of the line.
+    fn parse_info(line: &str) -> HashMap<String, String> {
+        let mut tokens = line.trim_end().split(' ').peekable();
+        assert_eq!(tokens.next(), Some("info"));
+        let mut pairs = HashMap::new();
+        while let Some(key) = tokens.next() {
+            let value: Vec<&str> = match key {
+                "score" => tokens.by_ref().take(2).collect(),
+                "pv" => std::iter::from_fn(|| tokens.next_if(|token| *token != "string")).collect(),
+                "string" => tokens.by_ref().collect(),
+                _ => tokens.next().into_iter().collect(),
+            };
+            pairs.insert(key.to_string(), value.join(" "));
+        }
+        pairs
+    }
+
+    #[test]
+    fn info_display() {
+        let mut pv = Line::new();
+        pv.push(Move::new(E2, E4, None));
+        pv.push(Move::new(E7, E5, None));
+        let info = UciInfo::new()
+            .with_depth(5)
+            .with_seldepth(9)
+            .with_score(UciScore::Cp(Cp(-35)))
+            .with_nodes(12345)
+            .with_nps(6789)
+            .with_time(1820)
+            .with_hashfull(250)
+            .with_tbhits(0)
+            .with_pv(pv.clone())
+            .with_string("extra stats".to_string());
+        let line = UciResponse::new_info(info).to_string();
+        assert_eq!(
+            line,
+            "info depth 5 seldepth 9 time 1820 nodes 12345 score cp -35 nps 6789 hashfull 250 \
+             tbhits 0 pv e2e4 e7e5 string extra stats\n"
+        );
+
+        let pairs = parse_info(&line);
+        assert_eq!(pairs.len(), 10);
+        assert_eq!(pairs["depth"], "5");
+        assert_eq!(pairs["seldepth"], "9");
+        assert_eq!(pairs["score"], "cp -35");
+        assert_eq!(pairs["nodes"], "12345");
+        assert_eq!(pairs["nps"], "6789");
+        assert_eq!(pairs["time"], "1820");
+        assert_eq!(pairs["hashfull"], "250");
+        assert_eq!(pairs["tbhits"], "0");
+        assert_eq!(pairs["pv"], "e2e4 e7e5");
+        assert_eq!(pairs["string"], "extra stats");
+
+        // Unset fields are not sent.
+        assert_eq!(UciInfo::default().to_string(), "info");
+
+        let mate = UciInfo::new()
+            .with_depth(3)
+            .with_score(UciScore::Mate(2))
+            .with_pv(pv);
+        assert_eq!(
+            UciResponse::new_info(mate).to_string(),
+            "info depth 3 score mate 2 pv e2e4 e7e5\n"
+        );
+        let mated = UciInfo::new().with_score(UciScore::Mate(-1));
+        assert_eq!(mated.to_string(), "info score mate -1");
+        let string = UciInfo::new().with_string("hello world".to_string());
+        assert_eq!(string.to_string(), "info string hello world");
+    }
+
+    #[test]
+    fn info_from_search_result() {
+        let tt = crate::TranspositionTable::with_capacity(10_000);
+        let result = crate::search::search(crate::Position::start_position(), 2, &tt);
+        let pairs = parse_info(&UciInfo::from(&result).to_string());
+        assert_eq!(pairs["depth"], "2");
+        assert_eq!(pairs["score"], format!("cp {}", result.relative_score().0));
+        assert_eq!(pairs["nodes"], result.nodes.to_string());
+        assert_eq!(pairs["pv"].split(' ').count(), result.pv.len());
+        assert!(!pairs.contains_key("string"));
+
+        // Mate scores are sent as the number of moves to mate.
+        let mut pv = Line::new();
+        pv.push(Move::new(D8, H4, None));
+        assert_eq!(UciScore::new(Cp::CHECKMATE, &pv), UciScore::Mate(1));
+        pv.push(Move::new(A2, A3, None));
+        assert_eq!(UciScore::new(-Cp::CHECKMATE, &pv), UciScore::Mate(-1));
+        assert_eq!(UciScore::Mate(-1).to_string(), "mate -1");
+    }
 }
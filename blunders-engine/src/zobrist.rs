@@ -2,20 +2,38 @@
 
 use std::collections::HashSet;
 use std::ops::Index;
+use std::sync::OnceLock;
 
 use rand::prelude::*;
 
 use crate::boardrepr::PieceSets;
-use crate::coretypes::{Castling, Color, File, Piece, PieceKind, Rank, Square, SquareIndexable};
-use crate::coretypes::{MoveInfo, MoveKind, Square::*};
+use crate::coretypes::{Castling, CastlingFiles, Color, File, Piece, PieceKind, Rank, Square};
+use crate::coretypes::{MoveInfo, MoveKind, SquareIndexable};
 use crate::coretypes::{NUM_FILES, NUM_PIECE_KINDS, NUM_SQUARES};
 use crate::position::{Cache, Position};
 
 /// HashKind is an alias for the underlying type of a Zobrist Hash.
 pub type HashKind = u64;
 
+/// Seed of the pawn table, so that pawn hashes are the same for every run.
+const PAWN_TABLE_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Returns the ZobristTable used for the pawn hash each Position keeps.
+/// This table is shared, so pawn hashes of any two positions may be compared.
+pub fn pawn_table() -> &'static ZobristTable {
+    static TABLE: OnceLock<ZobristTable> = OnceLock::new();
+    TABLE.get_or_init(|| ZobristTable::with_seed(PAWN_TABLE_SEED))
+}
+
 /// Key contains all data needed to generate a hash.
-pub type Key<'a> = (&'a PieceSets, &'a Color, &'a Castling, &'a Option<Square>);
+/// Castling files are not hashed, but are needed to update a hash after castling.
+pub type Key<'a> = (
+    &'a PieceSets,
+    &'a Color,
+    &'a Castling,
+    &'a Option<Square>,
+    &'a CastlingFiles,
+);
 
 /// Convert a Position reference into a Key.
 impl<'a> From<&'a Position> for Key<'a> {
@@ -25,6 +43,7 @@ impl<'a> From<&'a Position> for Key<'a> {
             pos_ref.player(),
             pos_ref.castling(),
             pos_ref.en_passant(),
+            pos_ref.castling_files(),
         )
     }
 }
@@ -147,6 +166,33 @@ impl ZobristTable {
         hash
     }
 
+    /// Debug check that an incrementally updated hash equals the hash generated from scratch.
+    /// A mismatch would silently poison any table the hash is used with.
+    #[cfg(debug_assertions)]
+    pub fn debug_verify_hash(&self, hash: HashKind, key: Key) {
+        let generated_hash = self.generate_hash(key);
+        assert_eq!(
+            hash, generated_hash,
+            "incrementally updated hash differs from generated hash"
+        );
+    }
+
+    /// Returns the key of a pawn of a color on a square, used for pawn structure hashes.
+    pub fn pawn_key(&self, color: Color, square: Square) -> HashKind {
+        self[(Piece::new(color, PieceKind::Pawn), square)]
+    }
+
+    /// Generate a pawn structure hash, which only hashes the placement of pawns.
+    pub fn generate_pawn_hash(&self, pieces: &PieceSets) -> HashKind {
+        let mut hash = HashKind::default();
+        for color in Color::iter() {
+            for square in pieces[(color, PieceKind::Pawn)] {
+                hash ^= self.pawn_key(color, square);
+            }
+        }
+        hash
+    }
+
     /// Update a hash from a Position and its MoveInfo. The move that resulted in MoveInfo
     /// must already be applied to the position.
     /// update_hash works both directions, it can apply and remove a move from a position's hash.
@@ -206,14 +252,13 @@ impl ZobristTable {
             }
 
             // Toggle both castling squares for rook.
+            // In Chess960 the king's "to" square is its rook, so the king is moved to its real square.
             MoveKind::Castle => {
-                let (rook_from, rook_to) = match move_info.to {
-                    G1 => (H1, F1),
-                    C1 => (A1, D1),
-                    G8 => (H8, F8),
-                    C8 => (A8, D8),
-                    _ => panic!("Processing Castling Move, but to square was not valid."),
-                };
+                let (king_to, rook_from, rook_to) =
+                    key.4.castling_squares(move_info.from, move_info.to);
+                *hash ^= self[(moved_piece, move_info.to)];
+                *hash ^= self[(moved_piece, king_to)];
+
                 let castled_rook = Piece::new(moved_player, PieceKind::Rook);
                 *hash ^= self[(castled_rook, rook_from)];
                 *hash ^= self[(castled_rook, rook_to)];
@@ -223,6 +268,23 @@ impl ZobristTable {
             MoveKind::Quiet => (),
         };
     }
+
+    /// Update a hash for a null move, which only toggles the player and en-passant.
+    /// Like update_hash, this works both directions when given the same arguments.
+    ///
+    /// # Arguments
+    /// `hash`: The hash value to directly update.
+    /// `key`: A key taken from the Position after the null move.
+    /// `cache`: The original cache of the Position, before the null move.
+    pub fn update_null_hash(&self, hash: &mut HashKind, key: Key, cache: Cache) {
+        *hash ^= self.player_hash;
+        if let Some(ep_square) = cache.en_passant {
+            *hash ^= self[ep_square.file()];
+        }
+        if let Some(ep_square) = key.3 {
+            *hash ^= self[ep_square.file()];
+        }
+    }
 }
 
 /// Default for ZobristTable is a table with a random seed.
@@ -300,7 +362,7 @@ impl Piece {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::coretypes::Move;
+    use crate::coretypes::{Move, Square::*};
     use crate::fen::Fen;
     use crate::Position;
 
@@ -342,6 +404,19 @@ mod tests {
         assert_eq!(hash, hash_after);
     }
 
+    #[test]
+    fn seeded_tables_are_reproducible() {
+        let position = Position::start_position();
+        let key = Key::from(&position);
+        let hash = ZobristTable::with_seed(0x2088).generate_hash(key);
+        assert_eq!(
+            ZobristTable::with_seed(0x2088),
+            ZobristTable::with_seed(0x2088)
+        );
+        assert_eq!(ZobristTable::with_seed(0x2088).generate_hash(key), hash);
+        assert_ne!(ZobristTable::with_seed(0x2089).generate_hash(key), hash);
+    }
+
     #[test]
     fn hash_start_position() {
         let table = ZobristTable::new();
@@ -380,4 +455,78 @@ mod tests {
 
         test_before_and_after(table, pos_before, pos_after, legal_move);
     }
+
+    #[test]
+    fn pawn_hash_only_hashes_pawns() {
+        let pos1 =
+            Position::parse_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let pos2 =
+            Position::parse_fen("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 3 3")
+                .unwrap();
+        let table = pawn_table();
+
+        // Identical pawns with different pieces and player share a pawn hash.
+        assert_eq!(pos1.pawn_hash(), pos2.pawn_hash());
+        assert_eq!(*pos1.pawn_hash(), table.generate_pawn_hash(pos1.pieces()));
+        assert_ne!(*pos1.pawn_hash(), Position::start_position().pawn_hash);
+
+        // Piece moves keep pawn hash, while pawn moves and captures change it.
+        let mut pos = pos1;
+        let cache = pos.cache();
+        let knight_move = pos.do_move(Move::new(F3, G5, None));
+        assert_eq!(pos.pawn_hash(), pos1.pawn_hash());
+        pos.undo_move(knight_move, cache);
+
+        let pawn_move = pos.do_move(Move::new(D2, D4, None));
+        assert_ne!(pos.pawn_hash(), pos1.pawn_hash());
+        assert_eq!(*pos.pawn_hash(), table.generate_pawn_hash(pos.pieces()));
+        let pawn_move_cache = pos.cache();
+        let capture = pos.do_move(Move::new(E5, D4, None));
+        assert_eq!(*pos.pawn_hash(), table.generate_pawn_hash(pos.pieces()));
+
+        pos.undo_move(capture, pawn_move_cache);
+        pos.undo_move(pawn_move, cache);
+        assert_eq!(pos, pos1);
+    }
+
+    #[test]
+    fn update_hash_random_games() {
+        let mut rng = StdRng::seed_from_u64(0x2088);
+        let table = ZobristTable::with_seed(0x2088);
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+
+        for game in 0..1000 {
+            let start = Position::parse_fen(fens[game % fens.len()]).unwrap();
+            let mut position = start;
+            let mut hash = table.generate_hash(Key::from(&position));
+            let mut played = Vec::new();
+
+            // Play a random game, checking the incremental hash after each move.
+            for _ in 0..100 {
+                let legal_moves = position.get_legal_moves();
+                let move_ = match legal_moves.choose(&mut rng) {
+                    Some(move_) => *move_,
+                    None => break,
+                };
+                let cache = position.cache();
+                let move_info = position.do_move(move_);
+                table.update_hash(&mut hash, Key::from(&position), move_info, cache);
+                assert_eq!(hash, table.generate_hash(Key::from(&position)));
+                played.push((move_info, cache));
+            }
+
+            // Undo the whole game, where updating with the same move removes it from the hash.
+            while let Some((move_info, cache)) = played.pop() {
+                table.update_hash(&mut hash, Key::from(&position), move_info, cache);
+                position.undo_move(move_info, cache);
+                assert_eq!(hash, table.generate_hash(Key::from(&position)));
+            }
+            assert_eq!(position, start);
+        }
+    }
 }
@@ -3,7 +3,7 @@
 //! Tests to ensure threefold repetition and 50-move rule draws
 //! are correctly evaluated.
 
-use blunders_engine::coretypes::{Color::*, Move, Square::*};
+use blunders_engine::coretypes::{Color::*, Cp, Move, PieceKind, Square::*};
 use blunders_engine::fen::Fen;
 use blunders_engine::movelist::MoveHistory;
 use blunders_engine::*;
@@ -43,3 +43,44 @@ fn threefold_repetition_perpetual_check_1() {
         // assert_eq!(search_result.leading(), None); How to assess draw with contempt?
     }
 }
+
+#[test]
+fn fifty_move_rule_one_ply_from_draw() {
+    // White is a rook ahead, but every move that is not a mate draws by the 50-move rule.
+    let fresh = Position::parse_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 80").unwrap();
+    let almost_drawn = Position::parse_fen("8/8/8/4k3/8/8/8/R3K3 w - - 99 80").unwrap();
+    let mut tt = TranspositionTable::new();
+
+    let fresh_result = search::negamax(fresh, 3, &tt);
+    assert_eq!(fresh_result.leading(), Some(White));
+
+    tt.clear();
+    let drawn_result = search::negamax(almost_drawn, 3, &tt);
+    assert_eq!(drawn_result.score, Cp::STALEMATE);
+    assert_eq!(drawn_result.leading(), None);
+
+    // Iterative search scores draws with contempt, which is far below a rook advantage.
+    let mut engine = Engine::new();
+    engine.set_game(almost_drawn);
+    let search_result = engine.search_sync(Mode::depth(3, None));
+    assert!(search_result.score.abs() < PieceKind::Pawn.centipawns());
+}
+
+#[test]
+fn insufficient_material_draw() {
+    // White can capture the last pawn, leaving an insufficient material draw.
+    let pos = Position::parse_fen("8/8/8/4k3/8/8/3p4/2N1K3 w - - 0 1").unwrap();
+    let mut engine = Engine::new();
+    engine.set_game(pos);
+    let search_result = engine.search_sync(Mode::depth(4, None));
+    assert!(search_result.score.abs() < PieceKind::Pawn.centipawns());
+
+    // Searching an already drawn position reports a draw.
+    let pos = Position::parse_fen("8/8/8/4k3/8/8/8/2N1K3 w - - 0 1").unwrap();
+    let mut tt = TranspositionTable::new();
+    let search_result = search::negamax(pos, 3, &mut tt);
+    assert_eq!(search_result.score, Cp::STALEMATE);
+    engine.set_game(pos);
+    let search_result = engine.search_sync(Mode::depth(3, None));
+    assert!(search_result.score.abs() < PieceKind::Pawn.centipawns());
+}
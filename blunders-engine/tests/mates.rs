@@ -3,9 +3,12 @@
 //! Tests to ensure engine finds forced checkmates.
 //! They should find the best move with a small depth.
 
+use std::convert::TryFrom;
+
 use blunders_engine::coretypes::{Color::*, Move, PieceKind::*, Square::*};
 use blunders_engine::fen::Fen;
 use blunders_engine::search::search;
+use blunders_engine::uci::{UciCommand, UciInfo, UciScore};
 use blunders_engine::*;
 
 #[test]
@@ -102,3 +105,25 @@ fn mate_in_3_force_king_moves_with_bishop_rook() {
     assert_eq!(result.leading(), Some(Black));
     assert_eq!(bm, result.best_move);
 }
+
+#[test]
+fn go_mate_finds_mate_in_2() {
+    let pos = Position::parse_fen("6k1/5ppp/4p3/4P2q/3P1P2/2r4P/4R1QK/8 w - - 0 3").unwrap();
+    let bm = Move::new(G2, A8, None);
+    let controls = match UciCommand::parse_command("go mate 2").unwrap() {
+        UciCommand::Go(controls) => controls,
+        command => panic!("expected go command, got {:?}", command),
+    };
+    let mode = Mode::try_from(controls).unwrap();
+    assert_eq!(mode, Mode::mate(2));
+
+    let mut engine = EngineBuilder::new().position(pos).build();
+    let result = engine.search_sync(mode);
+    assert_eq!(bm, result.best_move);
+    assert_eq!(
+        UciInfo::from(&result).score,
+        Some(UciScore::Mate(2)),
+        "{}",
+        result
+    );
+}
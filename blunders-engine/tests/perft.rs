@@ -1,268 +1,433 @@
 //! Performance Test (perft)
 //!
 //! Tests to ensure engine passes Perft test by checking against pre-determined results.
+//! Each position is a row of a data table, so adding a case is a single entry.
 //! [Perft Results](https://www.chessprogramming.org/Perft_Results)
 
 use num_cpus;
 
+use blunders_engine::coretypes::PlyKind;
 use blunders_engine::fen::Fen;
 use blunders_engine::perft::*;
 use blunders_engine::*;
 
 const ONE_THREAD: usize = 1;
 
+/// Largest expected node count that is run outside of expensive tests.
+const CHEAP_NODE_LIMIT: u64 = 1_500_000;
+
+const START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// https://www.chessprogramming.org/Perft_Results#Position_2
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+// https://www.chessprogramming.org/Perft_Results#Position_3
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+// https://www.chessprogramming.org/Perft_Results#Position_4
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+// https://www.chessprogramming.org/Perft_Results#Position_5
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+// https://www.chessprogramming.org/Perft_Results#Position_6
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
 fn cpu_threads() -> usize {
     num_cpus::get()
 }
 
-#[test]
-fn perft_starting_position() {
-    let position = Position::start_position();
-    let ply0 = perft(position, 0, ONE_THREAD);
-    let ply1 = perft(position, 1, ONE_THREAD);
-    let ply2 = perft(position, 2, ONE_THREAD);
-    let ply3 = perft(position, 3, ONE_THREAD);
-    let ply4 = perft(position, 4, ONE_THREAD);
-
-    println!("perft(0): {:?}", ply0);
-    println!("perft(1): {:?}", ply1);
-    println!("perft(2): {:?}", ply2);
-    println!("perft(3): {:?}", ply3);
-    println!("perft(4): {:?}", ply4);
-
-    assert_eq!(ply0.nodes, 1);
-    assert_eq!(ply1.nodes, 20);
-    assert_eq!(ply2.nodes, 400);
-    assert_eq!(ply3.nodes, 8_902);
-    assert_eq!(ply4.nodes, 197_281);
-
-    let threaded_ply2 = perft(position, 2, cpu_threads());
-    let threaded_ply3 = perft(position, 3, cpu_threads());
-    let threaded_ply4 = perft(position, 4, cpu_threads());
-    assert_eq!(threaded_ply2, ply2);
-    assert_eq!(threaded_ply3, ply3);
-    assert_eq!(threaded_ply4, ply4);
+fn parse(fen: &str) -> Position {
+    Position::parse_fen(fen).unwrap()
 }
 
-#[test]
-#[ignore]
-fn perft_starting_position_expensive() {
-    let position = Position::start_position();
-    let ply5 = perft(position, 5, ONE_THREAD);
-    let ply6 = perft(position, 6, ONE_THREAD);
-
-    println!("perft(5): {:?}", ply5);
-    println!("perft(6): {:?}", ply6);
+/// A perft test position, with expected node counts for each ply starting at 1.
+struct PerftCase {
+    name: &'static str,
+    fen: &'static str,
+    nodes: &'static [u64],
+}
 
-    assert_eq!(ply5.nodes, 4_865_609);
-    assert_eq!(ply6.nodes, 119_060_324);
+const PERFT_SUITE: [PerftCase; 7] = [
+    PerftCase {
+        name: "start position",
+        fen: START_POSITION,
+        nodes: &[20, 400, 8_902, 197_281, 4_865_609, 119_060_324],
+    },
+    PerftCase {
+        name: "kiwipete",
+        fen: KIWIPETE,
+        nodes: &[48, 2_039, 97_862, 4_085_603, 193_690_690],
+    },
+    PerftCase {
+        name: "position 3",
+        fen: POSITION_3,
+        nodes: &[14, 191, 2_812, 43_238, 674_624, 11_030_083],
+    },
+    PerftCase {
+        name: "position 4",
+        fen: POSITION_4,
+        nodes: &[6, 264, 9_467, 422_333, 15_833_292],
+    },
+    PerftCase {
+        name: "position 4 mirrored",
+        fen: "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        nodes: &[6, 264, 9_467, 422_333, 15_833_292],
+    },
+    PerftCase {
+        name: "position 5",
+        fen: POSITION_5,
+        nodes: &[44, 1_486, 62_379, 2_103_487, 89_941_194],
+    },
+    PerftCase {
+        name: "position 6",
+        fen: POSITION_6,
+        nodes: &[46, 2_079, 89_890, 3_894_594, 164_075_551],
+    },
+];
+
+/// A position which tests a single tricky rule of move generation, with the expected node count
+/// at one ply. These cover pins, en-passant discovered checks, castling, promotions,
+/// and evading check.
+/// [Perft Tests by Martin Sedlak](http://www.talkchess.com/forum/viewtopic.php?t=47318)
+struct PerftRuleCase {
+    name: &'static str,
+    fen: &'static str,
+    ply: PlyKind,
+    nodes: u64,
 }
 
-fn kiwipete_position() -> Position {
-    // https://www.chessprogramming.org/Perft_Results#Position_2
-    Position::parse_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
-        .unwrap()
+const PERFT_RULE_SUITE: [PerftRuleCase; 18] = [
+    PerftRuleCase {
+        name: "illegal en-passant, pinned on rank",
+        fen: "3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1",
+        ply: 6,
+        nodes: 1_134_888,
+    },
+    PerftRuleCase {
+        name: "illegal en-passant, pinned on diagonal",
+        fen: "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1",
+        ply: 6,
+        nodes: 1_015_133,
+    },
+    PerftRuleCase {
+        name: "en-passant while pinned on diagonal",
+        fen: "8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1",
+        ply: 6,
+        nodes: 824_064,
+    },
+    PerftRuleCase {
+        name: "en-passant gives check",
+        fen: "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        ply: 6,
+        nodes: 1_440_467,
+    },
+    PerftRuleCase {
+        name: "short castling gives check",
+        fen: "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        ply: 6,
+        nodes: 661_072,
+    },
+    PerftRuleCase {
+        name: "long castling gives check",
+        fen: "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1",
+        ply: 6,
+        nodes: 803_711,
+    },
+    PerftRuleCase {
+        name: "castling rights lost",
+        fen: "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1",
+        ply: 4,
+        nodes: 1_274_206,
+    },
+    PerftRuleCase {
+        name: "castling prevented",
+        fen: "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1",
+        ply: 4,
+        nodes: 1_720_476,
+    },
+    PerftRuleCase {
+        name: "promote out of check",
+        fen: "2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1",
+        ply: 6,
+        nodes: 3_821_001,
+    },
+    PerftRuleCase {
+        name: "discovered check",
+        fen: "8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1",
+        ply: 5,
+        nodes: 1_004_658,
+    },
+    PerftRuleCase {
+        name: "promote to give check",
+        fen: "4k3/1P6/8/8/8/8/K7/8 w - - 0 1",
+        ply: 6,
+        nodes: 217_342,
+    },
+    PerftRuleCase {
+        name: "underpromote to check",
+        fen: "8/P1k5/K7/8/8/8/8/8 w - - 0 1",
+        ply: 6,
+        nodes: 92_683,
+    },
+    PerftRuleCase {
+        name: "self stalemate",
+        fen: "K1k5/8/P7/8/8/8/8/8 w - - 0 1",
+        ply: 6,
+        nodes: 2_217,
+    },
+    PerftRuleCase {
+        name: "stalemate and checkmate with pawn",
+        fen: "8/k1P5/8/1K6/8/8/8/8 w - - 0 1",
+        ply: 7,
+        nodes: 567_584,
+    },
+    PerftRuleCase {
+        name: "stalemate and checkmate with queen",
+        fen: "8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1",
+        ply: 4,
+        nodes: 23_527,
+    },
+    PerftRuleCase {
+        name: "evade bishop check with castling rights",
+        fen: "r6r/1b2k1bq/8/8/7B/8/8/R3K2R b KQ - 3 2",
+        ply: 1,
+        nodes: 8,
+    },
+    PerftRuleCase {
+        name: "en-passant captures checking pawn",
+        fen: "8/8/8/2k5/2pP4/8/B7/4K3 b - d3 0 3",
+        ply: 1,
+        nodes: 8,
+    },
+    PerftRuleCase {
+        name: "evade queen check by blocking",
+        fen: "r3k2r/p1pp1pb1/bn2Qnp1/2qPN3/1p2P3/2N5/PPPBBPPP/R3K2R b KQkq - 3 2",
+        ply: 1,
+        nodes: 5,
+    },
+];
+
+/// Run perft for each case of the suite at every ply whose expected count is within node_limit.
+fn run_perft_suite(node_limit: u64) {
+    for case in PERFT_SUITE.iter() {
+        let position = parse(case.fen);
+        assert_eq!(perft(position, 0, ONE_THREAD).nodes, 1);
+        for (ply, expected) in (1..).zip(case.nodes) {
+            if *expected > node_limit {
+                break;
+            }
+            let result = perft(position, ply, cpu_threads());
+            println!("{}: perft({}): {:?}", case.name, ply, result);
+            assert_eq!(result.nodes, *expected, "{} at ply {}", case.name, ply);
+
+            // Threaded and single threaded perft agree where it is affordable.
+            if *expected <= CHEAP_NODE_LIMIT {
+                assert_eq!(perft(position, ply, ONE_THREAD), result);
+            }
+        }
+    }
 }
 
 #[test]
-fn perft_kiwipete_position() {
-    // https://www.chessprogramming.org/Perft_Results#Position_2
-    let position = kiwipete_position();
-    let ply0 = perft(position, 0, ONE_THREAD);
-    let ply1 = perft(position, 1, ONE_THREAD);
-    let ply2 = perft(position, 2, ONE_THREAD);
-    let ply3 = perft(position, 3, ONE_THREAD);
-
-    println!("perft(0): {:?}", ply0);
-    println!("perft(1): {:?}", ply1);
-    println!("perft(2): {:?}", ply2);
-    println!("perft(3): {:?}", ply3);
-
-    // Perft results used found in link above.
-    assert_eq!(ply0.nodes, 1);
-    assert_eq!(ply1.nodes, 48);
-    assert_eq!(ply2.nodes, 2_039);
-    assert_eq!(ply3.nodes, 97_862);
-
-    let threaded_ply2 = perft(position, 2, cpu_threads());
-    let threaded_ply3 = perft(position, 3, cpu_threads());
-    assert_eq!(threaded_ply2, ply2);
-    assert_eq!(threaded_ply3, ply3);
+fn perft_suite() {
+    run_perft_suite(CHEAP_NODE_LIMIT);
 }
 
 #[test]
 #[ignore]
-fn perft_kiwipete_position_expensive() {
-    let position = kiwipete_position();
-
-    let ply4 = perft(position, 4, ONE_THREAD);
-    println!("perft(4): {:?}", ply4);
-    assert_eq!(ply4.nodes, 4_085_603);
+fn perft_suite_expensive() {
+    run_perft_suite(u64::MAX);
 }
 
-fn position_3() -> Position {
-    // https://www.chessprogramming.org/Perft_Results#Position_3
-    Position::parse_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap()
+/// Run perft for each rule case whose expected count is within node_limit.
+fn run_perft_rule_suite(node_limit: u64) {
+    for case in PERFT_RULE_SUITE
+        .iter()
+        .filter(|case| case.nodes <= node_limit)
+    {
+        let position = parse(case.fen);
+        let result = perft(position, case.ply, cpu_threads());
+        println!("{}: perft({}): {:?}", case.name, case.ply, result);
+        assert_eq!(result.nodes, case.nodes, "{}", case.name);
+    }
 }
 
 #[test]
-fn perft_test_position_3() {
-    // https://www.chessprogramming.org/Perft_Results#Position_3
-    let position = position_3();
-    let ply0 = perft(position, 0, ONE_THREAD);
-    let ply1 = perft(position, 1, ONE_THREAD);
-    let ply2 = perft(position, 2, ONE_THREAD);
-    let ply3 = perft(position, 3, ONE_THREAD);
-    let ply4 = perft(position, 4, ONE_THREAD);
-
-    println!("perft(0): {:?}", ply0);
-    println!("perft(1): {:?}", ply1);
-    println!("perft(2): {:?}", ply2);
-    println!("perft(3): {:?}", ply3);
-    println!("perft(4): {:?}", ply4);
-
-    // Perft results used found in link above.
-    assert_eq!(ply0.nodes, 1);
-    assert_eq!(ply1.nodes, 14);
-    assert_eq!(ply2.nodes, 191);
-    assert_eq!(ply3.nodes, 2_812);
-    assert_eq!(ply4.nodes, 43_238);
-
-    let threaded_ply2 = perft(position, 2, cpu_threads());
-    let threaded_ply3 = perft(position, 3, cpu_threads());
-    let threaded_ply4 = perft(position, 4, cpu_threads());
-    assert_eq!(threaded_ply2, ply2);
-    assert_eq!(threaded_ply3, ply3);
-    assert_eq!(threaded_ply4, ply4);
+fn perft_rule_suite() {
+    run_perft_rule_suite(CHEAP_NODE_LIMIT);
 }
 
 #[test]
 #[ignore]
-fn perft_test_position_3_expensive() {
-    let position = position_3();
-
-    let ply5 = perft(position, 5, ONE_THREAD);
-    let ply6 = perft(position, 6, ONE_THREAD);
-    println!("perft(5): {:?}", ply5);
-    println!("perft(6): {:?}", ply6);
-    assert_eq!(ply5.nodes, 674_624);
-    assert_eq!(ply6.nodes, 11_030_083);
-}
-
-fn position_4() -> Position {
-    // https://www.chessprogramming.org/Perft_Results#Position_4
-    Position::parse_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap()
+fn perft_rule_suite_expensive() {
+    run_perft_rule_suite(u64::MAX);
 }
 
 #[test]
-fn perft_test_position_4() {
-    // https://www.chessprogramming.org/Perft_Results#Position_4
-    let position = position_4();
-    let ply0 = perft(position, 0, ONE_THREAD);
-    let ply1 = perft(position, 1, ONE_THREAD);
-    let ply2 = perft(position, 2, ONE_THREAD);
-    let ply3 = perft(position, 3, ONE_THREAD);
-    let ply4 = perft(position, 4, ONE_THREAD);
-
-    println!("perft(0): {:?}", ply0);
-    println!("perft(1): {:?}", ply1);
-    println!("perft(2): {:?}", ply2);
-    println!("perft(3): {:?}", ply3);
-    println!("perft(4): {:?}", ply4);
-
-    // Perft results used found in link above.
-    assert_eq!(ply0.nodes, 1);
-    assert_eq!(ply1.nodes, 6);
-    assert_eq!(ply2.nodes, 264);
-    assert_eq!(ply3.nodes, 9_467);
-    assert_eq!(ply4.nodes, 422_333);
-
-    let threaded_ply2 = perft(position, 2, cpu_threads());
-    let threaded_ply3 = perft(position, 3, cpu_threads());
-    let threaded_ply4 = perft(position, 4, cpu_threads());
-    assert_eq!(threaded_ply2, ply2);
-    assert_eq!(threaded_ply3, ply3);
-    assert_eq!(threaded_ply4, ply4);
-}
-
-fn position_5() -> Position {
-    // https://www.chessprogramming.org/Perft_Results#Position_5
-    Position::parse_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap()
+fn perft_divide_starting_position() {
+    use blunders_engine::coretypes::Square::*;
+    // Reference per-move counts of start position at ply 3.
+    let reference = [
+        (A2, A3, 380),
+        (B2, B3, 420),
+        (C2, C3, 420),
+        (D2, D3, 539),
+        (E2, E3, 599),
+        (F2, F3, 380),
+        (G2, G3, 420),
+        (H2, H3, 380),
+        (A2, A4, 420),
+        (B2, B4, 421),
+        (C2, C4, 441),
+        (D2, D4, 560),
+        (E2, E4, 600),
+        (F2, F4, 401),
+        (G2, G4, 421),
+        (H2, H4, 420),
+        (B1, A3, 400),
+        (B1, C3, 440),
+        (G1, F3, 440),
+        (G1, H3, 400),
+    ];
+    let position = Position::start_position();
+    let (divided, total) = divide(&position, 3);
+
+    assert_eq!(divided.len(), reference.len());
+    for (from, to, nodes) in reference {
+        let move_ = Move::new(from, to, None);
+        let found = divided
+            .iter()
+            .find(|(divide_move, _)| *divide_move == move_);
+        assert_eq!(found, Some(&(move_, nodes)), "{}", move_);
+    }
+
+    assert_eq!(total, 8_902);
+    assert_eq!(total, perft(position, 3, ONE_THREAD).nodes);
+
+    // Divide at ply 1 counts each move once.
+    let (divided, total) = divide(&position, 1);
+    assert!(divided.iter().all(|(_, nodes)| *nodes == 1));
+    assert_eq!(total, 20);
 }
 
 #[test]
-fn perft_test_position_5() {
-    // https://www.chessprogramming.org/Perft_Results#Position_5
-    let position = position_5();
-    let ply0 = perft(position, 0, ONE_THREAD);
-    let ply1 = perft(position, 1, ONE_THREAD);
-    let ply2 = perft(position, 2, ONE_THREAD);
-    let ply3 = perft(position, 3, ONE_THREAD);
-
-    println!("perft(0): {:?}", ply0);
-    println!("perft(1): {:?}", ply1);
-    println!("perft(2): {:?}", ply2);
-    println!("perft(3): {:?}", ply3);
-
-    // Perft results used found in link above.
-    assert_eq!(ply0.nodes, 1);
-    assert_eq!(ply1.nodes, 44);
-    assert_eq!(ply2.nodes, 1_486);
-    assert_eq!(ply3.nodes, 62_379);
-
-    let threaded_ply2 = perft(position, 2, cpu_threads());
-    let threaded_ply3 = perft(position, 3, cpu_threads());
-    assert_eq!(threaded_ply2, ply2);
-    assert_eq!(threaded_ply3, ply3);
+fn perft_parallel_equals_perft() {
+    let positions = [
+        Position::start_position(),
+        parse(KIWIPETE),
+        parse(POSITION_3),
+        parse(POSITION_4),
+        parse(POSITION_5),
+    ];
+    for position in positions {
+        for ply in 0..=3 {
+            let expected = perft(position, ply, ONE_THREAD).nodes;
+            assert_eq!(perft_parallel(&position, ply, cpu_threads()), expected);
+        }
+    }
 }
 
 #[test]
 #[ignore]
-fn perft_test_position_5_expensive() {
-    let position = position_5();
-    let ply4 = perft(position, 4, ONE_THREAD);
-    println!("perft(4): {:?}", ply4);
-    assert_eq!(ply4.nodes, 2_103_487);
+fn perft_parallel_equals_perft_expensive() {
+    let start_position = Position::start_position();
+    assert_eq!(perft_parallel(&start_position, 5, cpu_threads()), 4_865_609);
+    assert_eq!(
+        perft_parallel(&start_position, 5, cpu_threads()),
+        perft(start_position, 5, ONE_THREAD).nodes
+    );
+
+    for position in [parse(KIWIPETE), parse(POSITION_4)] {
+        let expected = perft(position, 4, ONE_THREAD).nodes;
+        assert_eq!(perft_parallel(&position, 4, cpu_threads()), expected);
+    }
 }
 
-fn position_6() -> Position {
-    // https://www.chessprogramming.org/Perft_Results#Position_6
-    Position::parse_fen("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10")
-        .unwrap()
+#[test]
+fn perft_tt_equals_perft() {
+    let positions = [
+        Position::start_position(),
+        parse(KIWIPETE),
+        parse(POSITION_3),
+        parse(POSITION_4),
+        parse(POSITION_5),
+        parse(POSITION_6),
+        chess960_positions()[0].0,
+    ];
+    // The same table is reused for every ply to check that counts of different plies are kept apart.
+    let mut tt = PerftTable::with_mb(4);
+    let mut tiny_tt = PerftTable::with_capacity(1);
+    for position in positions {
+        for ply in 0..=3 {
+            let expected = perft(position, ply, ONE_THREAD);
+            assert_eq!(perft_tt(position, ply, &mut tt), expected, "{}", position);
+            assert_eq!(perft_tt(position, ply, &mut tiny_tt), expected);
+        }
+    }
 }
 
 #[test]
-fn perft_test_position_6() {
-    // https://www.chessprogramming.org/Perft_Results#Position_6
-    let position = position_6();
-    let ply0 = perft(position, 0, ONE_THREAD);
-    let ply1 = perft(position, 1, ONE_THREAD);
-    let ply2 = perft(position, 2, ONE_THREAD);
-    let ply3 = perft(position, 3, ONE_THREAD);
-
-    println!("perft(0): {:?}", ply0);
-    println!("perft(1): {:?}", ply1);
-    println!("perft(2): {:?}", ply2);
-    println!("perft(3): {:?}", ply3);
-
-    // Perft results used found in link above.
-    assert_eq!(ply0.nodes, 1);
-    assert_eq!(ply1.nodes, 46);
-    assert_eq!(ply2.nodes, 2_079);
-    assert_eq!(ply3.nodes, 89_890);
-
-    let threaded_ply2 = perft(position, 2, cpu_threads());
-    let threaded_ply3 = perft(position, 3, cpu_threads());
-    assert_eq!(threaded_ply2, ply2);
-    assert_eq!(threaded_ply3, ply3);
+fn perft_tt_kiwipete_position() {
+    let position = parse(KIWIPETE);
+    let mut tt = PerftTable::with_mb(16);
+    assert_eq!(perft_tt(position, 4, &mut tt).nodes, 4_085_603);
 }
 
 #[test]
 #[ignore]
-fn perft_test_position_6_expensive() {
-    let position = position_6();
+fn perft_tt_kiwipete_position_expensive() {
+    let position = parse(KIWIPETE);
+    let mut tt = PerftTable::with_mb(64);
+    let ply5 = perft_tt(position, 5, &mut tt);
+    println!("perft_tt(5): {:?}", ply5);
+    assert_eq!(ply5.nodes, 193_690_690);
+
+    // Plain perft agrees at every depth where it is affordable.
+    for ply in 0..=4 {
+        assert_eq!(
+            perft_tt(position, ply, &mut tt),
+            perft(position, ply, ONE_THREAD)
+        );
+    }
+}
+
+fn chess960_positions() -> Vec<(Position, [u64; 4])> {
+    // https://www.chessprogramming.org/Chess960_Perft_Results
+    [
+        (
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            [21, 528, 12_189, 326_672],
+        ),
+        (
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            [21, 807, 18_002, 667_366],
+        ),
+        (
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            [20, 479, 10_471, 273_318],
+        ),
+        (
+            "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            [22, 593, 13_440, 382_958],
+        ),
+        (
+            "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+            [28, 1_120, 31_058, 1_171_749],
+        ),
+        (
+            "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+            [29, 899, 26_578, 824_055],
+        ),
+    ]
+    .iter()
+    .map(|(fen, nodes)| (Position::parse_fen(fen).unwrap(), *nodes))
+    .collect()
+}
 
-    let ply4 = perft(position, 4, ONE_THREAD);
-    println!("perft(4): {:?}", ply4);
-    assert_eq!(ply4.nodes, 3_894_594);
+#[test]
+fn perft_chess960_positions() {
+    for (position, expected) in chess960_positions() {
+        assert!(position.chess960());
+        for (ply, expected_nodes) in (1..).zip(expected.iter()) {
+            let result = perft(position, ply, ONE_THREAD);
+            println!("{}: perft({}): {:?}", position, ply, result);
+            assert_eq!(result.nodes, *expected_nodes);
+        }
+    }
 }
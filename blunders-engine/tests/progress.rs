@@ -0,0 +1,59 @@
+//! Progress
+//!
+//! Tests to ensure a running search reports its progress before it completes.
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use blunders_engine::*;
+
+/// Message type received from a search, similar to what a UCI application uses.
+enum Message {
+    Result(SearchResult),
+    Progress(SearchProgress),
+}
+
+impl From<SearchResult> for Message {
+    fn from(search_result: SearchResult) -> Self {
+        Message::Result(search_result)
+    }
+}
+
+impl From<SearchProgress> for Message {
+    fn from(search_progress: SearchProgress) -> Self {
+        Message::Progress(search_progress)
+    }
+}
+
+#[test]
+fn infinite_search_sends_progress() {
+    let min_progress = 4;
+    let timeout = Duration::from_secs(30);
+    let mut engine = EngineBuilder::new().debug(false).build();
+    let (sender, receiver) = mpsc::channel::<Message>();
+
+    engine.search(Mode::infinite(), sender).unwrap();
+
+    // Infinite search only completes when stopped, so all messages until then are progress.
+    let mut progress_count = 0;
+    let mut last_depth = 0;
+    while progress_count < min_progress {
+        match receiver.recv_timeout(timeout).unwrap() {
+            Message::Progress(progress) => {
+                assert!(progress.depth >= last_depth);
+                assert!(progress.pv.len() > 0);
+                assert_eq!(progress.best_move, progress.pv[0]);
+                last_depth = progress.depth;
+                progress_count += 1;
+            }
+            Message::Result(_) => panic!("infinite search completed before being stopped"),
+        }
+    }
+
+    engine.stop();
+    engine.wait();
+
+    // After stopping, the final search result is sent last.
+    let last_message = receiver.iter().last().unwrap();
+    assert!(matches!(last_message, Message::Result(_)));
+}
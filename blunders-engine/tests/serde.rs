@@ -0,0 +1,57 @@
+//! Serde
+//!
+//! Tests to ensure core types round trip through serialization, when the `serde` feature is enabled.
+#![cfg(feature = "serde")]
+
+use blunders_engine::coretypes::{Castling, Color, PieceKind, Square};
+use blunders_engine::fen::Fen;
+use blunders_engine::*;
+
+#[test]
+fn position_round_trip() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let position = Position::parse_fen(fen).unwrap();
+
+    let json = serde_json::to_string(&position).unwrap();
+    assert_eq!(json, format!("\"{}\"", fen));
+    let deserialized: Position = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, position);
+
+    assert!(serde_json::from_str::<Position>("\"not a fen\"").is_err());
+}
+
+#[test]
+fn move_round_trip() {
+    let move_ = Move::new(Square::A7, Square::A8, Some(PieceKind::Queen));
+    let json = serde_json::to_string(&move_).unwrap();
+    assert_eq!(json, "\"a7a8q\"");
+    assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), move_);
+}
+
+#[test]
+fn core_types_round_trip() {
+    let json = serde_json::to_string(&(Square::E4, Color::Black, PieceKind::Knight)).unwrap();
+    let deserialized: (Square, Color, PieceKind) = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, (Square::E4, Color::Black, PieceKind::Knight));
+
+    let castling = Castling::W_KING | Castling::B_QUEEN;
+    let json = serde_json::to_string(&castling).unwrap();
+    assert_eq!(serde_json::from_str::<Castling>(&json).unwrap(), castling);
+}
+
+#[test]
+fn search_result_round_trip() {
+    let tt = TranspositionTable::new();
+    let position = Position::start_position();
+    let result = search::search(position, 3, &tt);
+
+    let json = serde_json::to_string(&result).unwrap();
+    let deserialized: SearchResult = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.best_move, result.best_move);
+    assert_eq!(deserialized.score, result.score);
+    assert_eq!(deserialized.pv, result.pv);
+    assert_eq!(deserialized.player, result.player);
+    assert_eq!(deserialized.depth, result.depth);
+    assert_eq!(deserialized.nodes, result.nodes);
+    assert_eq!(deserialized.elapsed, result.elapsed);
+}
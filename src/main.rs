@@ -1,17 +1,25 @@
 //! Blunders Chess Engine UCI compatible CLI application.
 
+use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::env;
 use std::io;
 use std::panic;
 use std::process;
 use std::str::FromStr;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Instant;
-
-use blunders_engine::arrayvec::display;
-use blunders_engine::uci::{self, UciCommand, UciOption, UciOptions, UciResponse};
-use blunders_engine::{EngineBuilder, Fen, Game, Mode, SearchResult};
+use std::time::{Duration, Instant};
+
+use blunders_engine::bench;
+use blunders_engine::coretypes::{Cp, Move, PlyKind};
+use blunders_engine::eval::EvalParams;
+use blunders_engine::timeman::MOVE_OVERHEAD;
+use blunders_engine::uci::{
+    self, UciCommand, UciInfo, UciLog, UciOption, UciOptions, UciResponse, UciWriter,
+    EMPTY_STRING_OPTION,
+};
+use blunders_engine::{EngineBuilder, Fen, Game, Mode, Position, SearchProgress, SearchResult};
 
 /// App uses message passing over channels for communication between the
 /// main, input, and search threads.
@@ -19,6 +27,7 @@ use blunders_engine::{EngineBuilder, Fen, Game, Mode, SearchResult};
 enum Message {
     Command(UciCommand),
     Search(SearchResult),
+    Progress(SearchProgress),
 }
 
 impl From<UciCommand> for Message {
@@ -33,18 +42,92 @@ impl From<SearchResult> for Message {
     }
 }
 
+impl From<SearchProgress> for Message {
+    fn from(search_progress: SearchProgress) -> Self {
+        Message::Progress(search_progress)
+    }
+}
+
+/// A search started by `go` whose result has not been received yet.
+/// Its result is reported with the mode and root position it was started with.
+#[derive(Debug, Clone)]
+struct PendingSearch {
+    mode: Mode,
+    position: Position,
+    // Set when a later `position` or `go` stops this search, so it is not reported.
+    replaced: bool,
+}
+
+/// Format a perft divide as one `move: nodes` line per root move, followed by the total.
+fn divide_lines(divide: &[(Move, u64)], total: u64) -> Vec<String> {
+    divide
+        .iter()
+        .map(|(move_, nodes)| format!("{}: {}", move_, nodes))
+        .chain(std::iter::once(format!("nodes {}", total)))
+        .collect()
+}
+
+/// Report the result of a finished search over UCI, ending with its best move.
+/// The principal variation is described from the root position of the search.
+fn report_search_result<W: io::Write>(
+    out: &mut W,
+    debug: bool,
+    hashfull: u32,
+    search: &PendingSearch,
+    search_result: &SearchResult,
+) -> io::Result<()> {
+    uci::debug_to(out, debug, "search_result begin")?;
+    let extras = format!(
+        "q_nodes {} q_nps {} q_ratio {:.2} tt_cuts {} tt_hits {} cut_ratio {:.2} pv_nodes {} cut_nodes {} all_nodes {}",
+        search_result.q_nodes,
+        search_result.q_nps(),
+        search_result.quiescence_ratio(),
+        search_result.tt_cuts,
+        search_result.tt_hits,
+        search_result.tt_cut_ratio(),
+        search_result.pv_nodes,
+        search_result.cut_nodes,
+        search_result.all_nodes,
+    );
+    let info = UciInfo::from(search_result)
+        .with_hashfull(hashfull)
+        .with_string(extras);
+    UciResponse::new_info(info).write_to(out)?;
+    if let Some(moves) = search.mode.mate_moves() {
+        if !search_result.score.is_mate() {
+            let no_mate = format!("no mate found in {} moves", moves);
+            UciResponse::new_info(UciInfo::new().with_string(no_mate)).write_to(out)?;
+        }
+    }
+    let pv_san = search.position.line_to_san(&search_result.pv);
+    uci::debug_to(out, debug, &format!("pv san {}", pv_san))?;
+    let ponder = search_result.pv.get(1).copied();
+    UciResponse::new_best_move(search_result.best_move, ponder).write_to(out)
+}
+
 /// Input is parsed in a separate thread from main so Blunders CLI can receive and
 /// process both input and search results in an asynchronous fashion.
-fn input_handler(sender: mpsc::Sender<Message>) {
+fn input_handler(sender: mpsc::Sender<Message>, log: UciLog) {
+    // Positions are parsed here, so the castling notation of the variant is tracked here.
+    let mut chess960 = false;
+    let mut out = UciWriter::new(io::stdout(), log.clone());
+
     loop {
         // Wait to receive a line of input.
         let mut buffer = String::new();
         io::stdin().read_line(&mut buffer).unwrap();
+        log.received(&buffer);
 
         // Try to parse into valid input.
-        match UciCommand::from_str(&buffer) {
+        match UciCommand::parse_command_chess960(&buffer, chess960) {
             // On success, send to main thread. If command was quit, exit.
             Ok(command) => {
+                if let UciCommand::SetOption(ref raw_opt) = command {
+                    if *raw_opt.name() == "UCI_Chess960" {
+                        chess960 = bool::from_str(raw_opt.value()).unwrap_or(chess960);
+                    }
+                }
+
                 let is_quit = command == UciCommand::Quit;
                 let send_result = sender.send(command.into());
 
@@ -57,7 +140,7 @@ fn input_handler(sender: mpsc::Sender<Message>) {
             // On error reporting, exit.
             Err(err) => {
                 let err_str = format!("{} could not be parsed, {}", buffer.escape_debug(), err);
-                if let Err(err) = uci::error(&err_str) {
+                if let Err(err) = uci::error_to(&mut out, &err_str) {
                     panic!("{}", err);
                 }
             }
@@ -99,18 +182,52 @@ fn main() -> io::Result<()> {
     // Hook to print errors to STDOUT on panic.
     panic_hook();
 
+    // Command line `bench (depth)` runs the benchmark and exits without entering the uci loop.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        match UciCommand::parse_command(&args.join(" ")) {
+            Ok(UciCommand::Bench(depth)) => println!("{}", bench::bench(depth)),
+            Ok(_) => unreachable!(),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     // Engine Internal parameters
     // option name Hash type spin default 1 min 1 max 16000
     // option name Clear Hash type button
+    // option name Clear Hash On New Game type check default true
     // option name Ponder type check default false
     // option name Threads type spin default 1 min 1 max 32
     // option name Debug type check default true
+    // option name UCI_Chess960 type check default false
+    // option name Contempt type spin default 50 min -500 max 500
+    // option name Move Overhead type spin default 10 min 0 max 5000
+    // option name Debug Log File type string default <empty>
     let mut uci_options = UciOptions::new();
     uci_options.insert(UciOption::new_spin("Hash", 1, 1, 16000));
     uci_options.insert(UciOption::new_button("Clear Hash", false));
+    uci_options.insert(UciOption::new_check("Clear Hash On New Game", true));
     uci_options.insert(UciOption::new_check("Ponder", false));
     uci_options.insert(UciOption::new_spin("Threads", 1, 1, 32));
     uci_options.insert(UciOption::new_check("Debug", true));
+    uci_options.insert(UciOption::new_check("UCI_Chess960", false));
+    uci_options.insert(UciOption::new_spin(
+        "Contempt",
+        EvalParams::DEFAULT.contempt_cp.0.into(),
+        -500,
+        500,
+    ));
+    uci_options.insert(UciOption::new_spin(
+        "Move Overhead",
+        MOVE_OVERHEAD.as_millis() as i64,
+        0,
+        5000,
+    ));
+    uci_options.insert(UciOption::new_string("Debug Log File", EMPTY_STRING_OPTION));
 
     // Current chess game with move history.
     let mut game = Game::start_position();
@@ -118,18 +235,33 @@ fn main() -> io::Result<()> {
     // If set to true, allow debugging strings to be printed.
     let mut debug = uci_options["Debug"].check().value;
 
+    // Started searches which have not yet sent their results, oldest first.
+    // Searches send their results in the order they were started, as each is
+    // stopped and waited on before the next one starts.
+    let mut pending_searches: VecDeque<PendingSearch> = VecDeque::new();
+
+    // Protocol traffic of the input and main threads is mirrored to the same log.
+    let log = UciLog::new();
+    let mut out = UciWriter::new(io::stdout(), log.clone());
+
     // Communications between input, search, and main threads.
     let (sender, receiver) = mpsc::channel::<Message>();
 
     // Create input thread.
     let input_sender = sender.clone();
-    let input_thread_handle = thread::spawn(move || input_handler(input_sender));
+    let input_log = log.clone();
+    let input_thread_handle = thread::spawn(move || input_handler(input_sender, input_log));
 
     // Main Engine instance.
     let mut engine = EngineBuilder::new()
         .transpositions_mb(uci_options["Hash"].spin().value())
         .threads(uci_options["Threads"].spin().value())
         .debug(debug)
+        .clear_on_new_game(uci_options["Clear Hash On New Game"].check().value)
+        .contempt(Cp(uci_options["Contempt"].spin().value()))
+        .move_overhead(Duration::from_millis(
+            uci_options["Move Overhead"].spin().value(),
+        ))
         .game(game.clone())
         .build();
 
@@ -141,29 +273,32 @@ fn main() -> io::Result<()> {
                 // GUI is telling engine to use UCI protocol.
                 // It requires a response of Id, available options, and an acknowledgement.
                 UciCommand::Uci => {
-                    UciResponse::new_id(name_version, author).send()?;
+                    UciResponse::new_id(name_version, author).write_to(&mut out)?;
                     for uci_opt in uci_options.values() {
-                        UciResponse::new_option(uci_opt.clone()).send()?;
+                        UciResponse::new_option(uci_opt.clone()).write_to(&mut out)?;
                     }
-                    UciResponse::UciOk.send()?;
+                    UciResponse::UciOk.write_to(&mut out)?;
                 }
 
                 // Command used to sync GUI with engine. Requires acknowledgement response.
                 UciCommand::IsReady => {
-                    UciResponse::ReadyOk.send()?;
+                    UciResponse::ReadyOk.write_to(&mut out)?;
                 }
 
                 // The next search will be from a different game.
-                // Clearing the transposition table of all entries allows engine
-                // to enter new game without prior information.
+                // Entries of the previous game are aged so they are replaced first, and the
+                // transposition table is cleared unless disabled to enter the new game faster.
                 UciCommand::UciNewGame => match engine.new_game() {
-                    Ok(()) => uci::debug(debug, "transposition table cleared")?,
-                    Err(err) => uci::error(&err.to_string())?,
+                    Ok(()) if engine.clear_on_new_game() => {
+                        uci::debug_to(&mut out, debug, "transposition table cleared")?
+                    }
+                    Ok(()) => uci::debug_to(&mut out, debug, "transposition table aged")?,
+                    Err(err) => uci::error_to(&mut out, &err.to_string())?,
                 },
 
                 // GUI commands engine to immediately stop any active search.
                 UciCommand::Stop => {
-                    uci::debug(debug, "stopping...")?;
+                    uci::debug_to(&mut out, debug, "stopping...")?;
                     engine.stop();
                 }
 
@@ -174,10 +309,41 @@ fn main() -> io::Result<()> {
                 // Shutdown engine.
                 UciCommand::Quit => break,
 
+                // Non-standard debugging command, prints the static evaluation by term.
+                UciCommand::Eval => {
+                    for line in engine.eval_trace().lines() {
+                        UciResponse::new_info(UciInfo::new().with_string(line.to_string()))
+                            .write_to(&mut out)?;
+                    }
+                }
+
+                // Non-standard debugging command, prints the perft of each root move.
+                // Depth is checked to fit in a PlyKind when parsed.
+                UciCommand::Perft(depth) => {
+                    let instant = Instant::now();
+                    let (divide, total) = engine.perft(depth as PlyKind);
+                    let elapsed = instant.elapsed();
+                    for line in divide_lines(&divide, total) {
+                        UciResponse::new_info(UciInfo::new().with_string(line))
+                            .write_to(&mut out)?;
+                    }
+                    let time_str = format!("time {}", elapsed.as_millis());
+                    UciResponse::new_info(UciInfo::new().with_string(time_str))
+                        .write_to(&mut out)?;
+                }
+
+                // Non-standard command, searches the benchmark suite and prints a summary line.
+                UciCommand::Bench(depth) => {
+                    let bench_str = bench::bench(depth).to_string();
+                    UciResponse::new_info(UciInfo::new().with_string(bench_str))
+                        .write_to(&mut out)?;
+                }
+
                 // Tells engine to send extra `info string` to the GUI.
                 // Command can be sent anytime.
                 UciCommand::Debug(new_debug_value) => {
-                    uci::debug(
+                    uci::debug_to(
+                        &mut out,
                         debug | new_debug_value,
                         &format!("set debug {}", new_debug_value),
                     )?;
@@ -199,9 +365,9 @@ fn main() -> io::Result<()> {
                             match engine.try_set_transpositions_mb(mb) {
                                 Ok(capacity) => {
                                     let s = format!("tt mb: {}, capacity: {}", mb, capacity);
-                                    uci::debug(debug, &s)?;
+                                    uci::debug_to(&mut out, debug, &s)?;
                                 }
-                                Err(err) => uci::error(&err.to_string())?,
+                                Err(err) => uci::error_to(&mut out, &err.to_string())?,
                             };
 
                         // Button was pressed to clear the hash table.
@@ -209,38 +375,90 @@ fn main() -> io::Result<()> {
                             option.button_mut().pressed = false;
 
                             match engine.try_clear_transpositions() {
-                                Ok(()) => uci::debug(debug, "hash table cleared")?,
-                                Err(err) => uci::error(&err.to_string())?,
+                                Ok(()) => uci::debug_to(&mut out, debug, "hash table cleared")?,
+                                Err(err) => uci::error_to(&mut out, &err.to_string())?,
                             };
 
+                        // Engine was told whether to clear the hash table for a new game.
+                        } else if option.name == "Clear Hash On New Game" {
+                            let clear = option.check().value;
+                            let response = format!("setoption Clear Hash On New Game: {}", clear);
+                            uci::debug_to(&mut out, debug, &response)?;
+                            engine.set_clear_on_new_game(clear);
+
                         // Engine was informed if pondering is possible or not.
                         } else if option.name == "Ponder" {
                             let response = format!("setoption Ponder: {}", option.check().value);
-                            uci::debug(debug, &response)?;
+                            uci::debug_to(&mut out, debug, &response)?;
 
                         // Engine was given the number of threads it can use.
                         } else if option.name == "Threads" {
                             let response = format!("setoption Threads: {}", option.spin().value);
-                            uci::debug(debug, &response)?;
+                            uci::debug_to(&mut out, debug, &response)?;
 
                         // Engine debug mode was set.
                         } else if option.name == "Debug" {
                             let new_debug_value = option.check().value;
                             let response = format!("setoption Debug {}", new_debug_value);
-                            uci::debug(debug | new_debug_value, &response)?;
+                            uci::debug_to(&mut out, debug | new_debug_value, &response)?;
 
                             debug = new_debug_value;
                             engine.set_debug(new_debug_value);
+
+                        // Engine was told to play Chess960. Positions are parsed with
+                        // Chess960 castling from the next `position` command onwards.
+                        } else if option.name == "UCI_Chess960" {
+                            let response =
+                                format!("setoption UCI_Chess960: {}", option.check().value);
+                            uci::debug_to(&mut out, debug, &response)?;
+
+                        // Engine was told how much to avoid draws.
+                        } else if option.name == "Contempt" {
+                            let contempt = option.spin().value();
+                            let response = format!("setoption Contempt: {}", contempt);
+                            uci::debug_to(&mut out, debug, &response)?;
+                            engine.set_contempt(Cp(contempt));
+
+                        // Engine was told how much time is lost communicating each move.
+                        } else if option.name == "Move Overhead" {
+                            let overhead = option.spin().value();
+                            let response = format!("setoption Move Overhead: {}", overhead);
+                            uci::debug_to(&mut out, debug, &response)?;
+                            engine.set_move_overhead(Duration::from_millis(overhead));
+
+                        // Engine was given a file to log protocol traffic to.
+                        } else if option.name == "Debug Log File" {
+                            let path = &option.string().value;
+                            match log.set_file(path) {
+                                Ok(()) => {
+                                    let response = format!("setoption Debug Log File: {}", path);
+                                    uci::debug_to(&mut out, debug, &response)?;
+                                }
+                                Err(err) => uci::error_to(&mut out, &err.to_string())?,
+                            };
                         }
                     }
-                    Err(err) => uci::error(&err.to_string())?,
+                    Err(err) => uci::error_to(&mut out, &err.to_string())?,
                 },
 
-                // Set the current position.
+                // Set the current position. Any active search is of an outdated position,
+                // so it is stopped, and its result is not reported.
                 UciCommand::Pos(new_game) => {
+                    if !engine.ready() {
+                        uci::debug_to(&mut out, debug, "position stopping active search...")?;
+                        engine.stop();
+                        engine.wait();
+                    }
+                    pending_searches
+                        .iter_mut()
+                        .for_each(|pending| pending.replaced = true);
                     game = new_game;
                     engine.set_game(game.clone());
-                    uci::debug(debug, &format!("set position {}", game.position.to_fen()))?;
+                    uci::debug_to(
+                        &mut out,
+                        debug,
+                        &format!("set position {}", game.position.to_fen()),
+                    )?;
                 }
 
                 // Begin a search with provided parameters. Only search if are no other active searches.
@@ -248,51 +466,70 @@ fn main() -> io::Result<()> {
                     let mode = match Mode::try_from(search_ctrl) {
                         Ok(mode) => mode,
                         Err(err) => {
-                            uci::error(&err.to_string())?;
-                            uci::error("falling back to depth search")?;
+                            uci::error_to(&mut out, &err.to_string())?;
+                            uci::error_to(&mut out, "falling back to depth search")?;
                             Mode::depth(6, None)
                         }
                     };
 
-                    // TODO: consider stopping any active search to ensure new search can always start.
-                    match engine.search(mode, sender.clone()) {
-                        Ok(()) => uci::debug(debug, "go starting search...")?,
-                        Err(err) => uci::error(&err.to_string())?,
+                    // Any active search is stopped so that the new search always starts.
+                    // Only the result of the new search is reported.
+                    if !engine.ready() {
+                        uci::debug_to(&mut out, debug, "go stopping active search...")?;
+                    }
+                    pending_searches
+                        .iter_mut()
+                        .for_each(|pending| pending.replaced = true);
+                    match engine.search_now(mode, sender.clone()) {
+                        Ok(()) => {
+                            pending_searches.push_back(PendingSearch {
+                                mode,
+                                position: engine.game().position,
+                                replaced: false,
+                            });
+                            uci::debug_to(&mut out, debug, "go starting search...")?
+                        }
+                        Err(err) => uci::error_to(&mut out, &err.to_string())?,
                     };
                 }
             },
 
+            // A search in progress has sent an update of its current best line.
+            // Updates of a replaced search are outdated and are not reported.
+            Message::Progress(progress) => {
+                if pending_searches
+                    .front()
+                    .is_none_or(|pending| pending.replaced)
+                {
+                    continue;
+                }
+                let hashfull = engine.transposition_table().hashfull();
+                UciResponse::new_info(UciInfo::from(&progress).with_hashfull(hashfull))
+                    .write_to(&mut out)?;
+            }
+
             // A search has finished and the results have been returned.
+            // The result of a replaced search is not reported, so it cannot be
+            // mistaken for the best move of the search which replaced it.
             Message::Search(search_result) => {
-                uci::debug(debug, "search_result begin")?;
-                let extras = format!(
-                    "string q_nodes {} q_nps {} q_ratio {:.2} tt_cuts {} tt_hits {} cut_ratio {:.2} pv_nodes {}",
-                    search_result.q_nodes,
-                    search_result.q_nps(),
-                    search_result.quiescence_ratio(),
-                    search_result.tt_cuts,
-                    search_result.tt_hits,
-                    search_result.tt_cut_ratio(),
-                    search_result.pv_nodes,
-                );
-                println!(
-                    "info depth {} score cp {} time {} nodes {} nps {} pv {} {}",
-                    search_result.depth,
-                    search_result.relative_score(),
-                    search_result.elapsed.as_millis(),
-                    search_result.nodes,
-                    search_result.nps(),
-                    display(&search_result.pv),
-                    extras
-                );
-                UciResponse::new_best_move(search_result.best_move).send()?;
-
-                // Wait for engine to clean up.
-                uci::debug(debug, "engine waiting...")?;
-                let instant = Instant::now();
-                engine.wait();
-                let time_str = format!("engine wait time: {:?}", instant.elapsed());
-                uci::debug(debug, &time_str)?;
+                let pending = pending_searches
+                    .pop_front()
+                    .expect("search result without a pending search");
+                if pending.replaced {
+                    uci::debug_to(&mut out, debug, "replaced search_result ignored")?;
+                } else {
+                    let hashfull = engine.transposition_table().hashfull();
+                    report_search_result(&mut out, debug, hashfull, &pending, &search_result)?;
+                }
+
+                // Wait for engine to clean up, unless a newer search has already started.
+                if pending_searches.is_empty() {
+                    uci::debug_to(&mut out, debug, "engine waiting...")?;
+                    let instant = Instant::now();
+                    engine.wait();
+                    let time_str = format!("engine wait time: {:?}", instant.elapsed());
+                    uci::debug_to(&mut out, debug, &time_str)?;
+                }
             }
         };
     }
@@ -304,3 +541,19 @@ fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perft_start_position() {
+        let engine = EngineBuilder::new().build();
+        let (divide, total) = engine.perft(3);
+        let lines = divide_lines(&divide, total);
+        assert_eq!(lines.len(), 21);
+        assert!(lines.contains(&"e2e4: 600".to_string()));
+        assert!(lines.contains(&"g1f3: 440".to_string()));
+        assert_eq!(lines.last().unwrap(), "nodes 8902");
+    }
+}